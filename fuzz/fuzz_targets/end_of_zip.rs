@@ -0,0 +1,14 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+#![no_main]
+
+use jump::internals::end_of_zip;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u16, &[u8])| {
+    let (maximum_trailer_size, data) = input;
+    if let Ok(end) = end_of_zip(data, maximum_trailer_size as usize) {
+        assert!(end <= data.len());
+    }
+});
@@ -0,0 +1,17 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+#![no_main]
+
+use std::path::Path;
+
+use jump::internals::load_jump;
+use jump::ScieFooter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = load_jump(data, Path::new("fuzz"));
+    if let Ok(Some(footer)) = ScieFooter::parse(data) {
+        assert!(footer.payload().end <= data.len());
+    }
+});
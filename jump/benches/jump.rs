@@ -0,0 +1,162 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::io::Write;
+use std::path::Path;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use jump::config::{Config, UnknownFields};
+use jump::internals;
+use tempfile::TempDir;
+
+const TREE_SIZE: usize = 10_000;
+
+const LIFT_MANIFEST: &[u8] = include_bytes!("../../examples/java/lift.linux-x86_64.json");
+
+// A synthetic interpreter distribution: many small files spread across a modest number of
+// directories.
+fn create_tree(root: &Path) {
+    for index in 0..TREE_SIZE {
+        let dir = root.join(format!("lib/package{}", index % 100));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(format!("module{index}.py")),
+            format!("# Module {index}\nVALUE = {index}\n").repeat(10),
+        )
+        .unwrap();
+    }
+}
+
+fn create_tar(root: &Path) -> Vec<u8> {
+    let mut tar = tar::Builder::new(vec![]);
+    tar.append_dir_all(".", root).unwrap();
+    tar.into_inner().unwrap()
+}
+
+// A zip with a large prefix standing in for the scie-jump and file payloads and a JSON trailer
+// standing in for the lift manifest.
+fn create_scie() -> Vec<u8> {
+    let mut scie = vec![0; 10 * 1024 * 1024];
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+    zip.start_file("file", Default::default()).unwrap();
+    zip.write_all(b"contents").unwrap();
+    scie.extend(zip.finish().unwrap().into_inner());
+    scie.extend(LIFT_MANIFEST);
+    scie
+}
+
+fn eocd_scan(c: &mut Criterion) {
+    let scie = create_scie();
+    c.bench_function("eocd_scan", |b| {
+        b.iter(|| internals::end_of_zip(black_box(&scie), LIFT_MANIFEST.len()).unwrap())
+    });
+}
+
+fn config_parse(c: &mut Criterion) {
+    c.bench_function("config_parse", |b| {
+        b.iter(|| Config::parse(black_box(LIFT_MANIFEST), UnknownFields::Warn).unwrap())
+    });
+}
+
+fn placeholder_parse(c: &mut Criterion) {
+    let text = "{scie.bindings.configure:PYTHON}/bin/{python}:{scie.env.PATH}:{{literal}}:\
+        {scie.files.cpython}:{scie.platform}/{scie.base}/{scie.lift}";
+    c.bench_function("placeholder_parse", |b| {
+        b.iter(|| internals::parse_placeholders(black_box(text)).unwrap())
+    });
+}
+
+fn zip_pack(c: &mut Criterion) {
+    let tree = TempDir::new().unwrap();
+    create_tree(&tree.path().join("tree"));
+    let mut group = c.benchmark_group("pack");
+    group.sample_size(10);
+    group.bench_function("zip_10k_files", |b| {
+        b.iter_batched(
+            || {
+                let _ = std::fs::remove_file(tree.path().join("tree.zip"));
+            },
+            |_| internals::create_zip(tree.path(), "tree").unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+fn extraction(c: &mut Criterion) {
+    let tree = TempDir::new().unwrap();
+    create_tree(&tree.path().join("tree"));
+    let zip = std::fs::read(internals::create_zip(tree.path(), "tree").unwrap()).unwrap();
+    let tar = create_tar(&tree.path().join("tree"));
+
+    let mut group = c.benchmark_group("extract");
+    group.sample_size(10);
+    group.bench_function("zip_10k_files", |b| {
+        b.iter_batched(
+            || TempDir::new().unwrap(),
+            |dst| {
+                internals::extract_zip(&zip, dst.path()).unwrap();
+                // N.B.: Returning the destination defers its clean up until after timing.
+                dst
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("tar_10k_files", |b| {
+        b.iter_batched(
+            || TempDir::new().unwrap(),
+            |dst| {
+                internals::extract_tar(&tar, dst.path()).unwrap();
+                // N.B.: Returning the destination defers its clean up until after timing.
+                dst
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+// A scie payload of dozens of files like a Python distribution split into per-package archives.
+fn verification(c: &mut Criterion) {
+    let payloads = (0..48_u8)
+        .map(|index| vec![index; 2 * 1024 * 1024])
+        .collect::<Vec<_>>();
+    let blobs = payloads
+        .iter()
+        .map(|payload| (payload.len(), jump::fingerprint::digest(payload)))
+        .collect::<Vec<_>>();
+    let payload = payloads.concat();
+    let dst = TempDir::new().unwrap();
+
+    let mut group = c.benchmark_group("verify");
+    group.sample_size(10);
+    // N.B.: This is how each file was verified just before it was extracted before verification
+    // was done up front and concurrently.
+    group.bench_function("sequential_48_files", |b| {
+        b.iter(|| {
+            let mut offset = 0;
+            for (size, hash) in &blobs {
+                assert_eq!(
+                    hash,
+                    &jump::fingerprint::digest(black_box(&payload[offset..offset + size]))
+                );
+                offset += size;
+            }
+        })
+    });
+    group.bench_function("concurrent_48_files", |b| {
+        b.iter(|| internals::verify_blobs(black_box(&payload), &blobs, dst.path()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    eocd_scan,
+    config_parse,
+    placeholder_parse,
+    zip_pack,
+    extraction,
+    verification
+);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+/* Copyright 2023 Science project contributors.
+ * Licensed under the Apache License, Version 2.0 (see LICENSE). */
+
+#ifndef JUMP_H
+#define JUMP_H
+
+/* Generated by cbindgen from jump/src/capi.rs; do not edit. */
+
+#include <stdbool.h>
+
+/**
+ * Returns the lift manifest of the scie at the given path as a single line of JSON.
+ *
+ * # Safety
+ *
+ * `scie` must be a valid pointer to a nul-terminated string.
+ */
+char *jump_load_config(const char *scie);
+
+/**
+ * Packs the lift manifest at the given path into a scie written to `dest_dir` and returns a JSON
+ * report describing it; just like `SCIE=boot-pack --json`.
+ *
+ * The `manifest` can be a lift manifest file or a directory containing a `lift.json`. The
+ * `scie_jump` must be a scie-jump binary and not a scie.
+ *
+ * # Safety
+ *
+ * `manifest`, `scie_jump` and `dest_dir` must be valid pointers to nul-terminated strings.
+ */
+char *jump_pack(const char *manifest,
+                const char *scie_jump,
+                const char *dest_dir,
+                bool single_line,
+                bool align);
+
+/**
+ * Returns a description of the last failure on this thread or null if the last call succeeded.
+ *
+ * The returned string is owned by the library and is only valid until the next call on this
+ * thread; it must not be passed to [`jump_string_free`].
+ */
+const char *jump_last_error(void);
+
+/**
+ * Releases a string returned by this library.
+ *
+ * # Safety
+ *
+ * `value` must be null or a string returned by this library that has not already been released.
+ */
+void jump_string_free(char *value);
+
+#endif /* JUMP_H */
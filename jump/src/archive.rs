@@ -1,42 +1,216 @@
 // Copyright 2022 Science project contributors.
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::fs::Metadata;
+use std::collections::HashMap;
+use std::fs::{Metadata, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
 use std::path::{Path, PathBuf};
 
-use log::debug;
+use log::{debug, warn};
 use logging_timer::time;
 use walkdir::WalkDir;
 use zip::write::FileOptions;
 
+use crate::config::{ArchiveType, Compression, FileType, UnicodeNormalization, Zips};
+use crate::error::JumpError;
+use crate::fingerprint;
+use crate::placeholders::{self, Item, Placeholder};
+
 #[cfg(not(target_family = "unix"))]
-pub fn create_options(_metadata: &Metadata) -> Result<FileOptions, String> {
+pub fn create_options(_metadata: &Metadata) -> Result<FileOptions, JumpError> {
     Ok(FileOptions::default())
 }
 
 #[cfg(target_family = "unix")]
-pub fn create_options(metadata: &Metadata) -> Result<FileOptions, String> {
+pub fn create_options(metadata: &Metadata) -> Result<FileOptions, JumpError> {
     use std::os::unix::fs::PermissionsExt;
     let perms = metadata.permissions();
     Ok(FileOptions::default().unix_permissions(perms.mode()))
 }
 
-fn create_zip(dir: &Path) -> Result<PathBuf, String> {
-    let zip_path = dir.with_extension("zip");
-    let mut zip = zip::ZipWriter::new(
-        std::fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&zip_path)
-            .map_err(|e| {
-                format!(
-                    "Failed to open {zip} for packing {dir} into: {e}",
-                    zip = zip_path.display(),
-                    dir = dir.display()
-                )
-            })?,
-    );
-    for entry in WalkDir::new(dir).contents_first(false).follow_links(true) {
+// N.B.: These names refer to devices on Windows; with or without an extension.
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Windows programs that are not long path aware, which includes Python unless long paths are
+// enabled in the registry, cannot open paths longer than this.
+const WINDOWS_MAX_PATH: usize = 260;
+
+// An estimate of the length of the directory an archive is extracted to on Windows; i.e.:
+// `C:\Users\<user>\AppData\Local\nce\<hash>\`.
+const WINDOWS_NCE_DIR_LEN: usize = r"C:\Users\username\AppData\Local\nce\".len() + 64 + 1;
+
+/// Returns a description of why an archive entry path cannot be extracted on Windows, if it
+/// cannot.
+pub(crate) fn windows_path_problem(path: &Path) -> Option<String> {
+    for component in path.iter() {
+        let component = component.to_string_lossy();
+        let stem = component
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(' ');
+        if WINDOWS_RESERVED_NAMES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(stem))
+        {
+            return Some(format!("{component} is a reserved device name on Windows"));
+        }
+        if component.ends_with('.') || component.ends_with(' ') {
+            return Some(format!(
+                "{component} ends with a dot or space, which Windows strips from file names"
+            ));
+        }
+        if let Some(char) = component
+            .chars()
+            .find(|char| char.is_control() || "<>:\"|?*".contains(*char))
+        {
+            return Some(format!(
+                "{component} contains {char:?}, which Windows does not allow in file names"
+            ));
+        }
+    }
+    None
+}
+
+// N.B.: These are left behind by file managers and editors rather than being part of the tree
+// being packed; so dropping them keeps the zip, and its hash, the same across machines.
+fn is_junk(name: &str) -> bool {
+    matches!(name, ".DS_Store" | "Thumbs.db" | "__MACOSX")
+        // Vim swap files.
+        || (name.starts_with('.') && (name.ends_with(".swp") || name.ends_with(".swo")))
+        // Emacs lock and auto-save files.
+        || name.starts_with(".#")
+        || (name.len() > 1 && name.starts_with('#') && name.ends_with('#'))
+        // Editor backup files.
+        || name.ends_with('~')
+}
+
+/// Renders the file name of a zip from the template given by the lift "zips" "name".
+///
+/// Returns `None` if the name includes the hash of the zip but no `hash` is given.
+pub(crate) fn zip_name(
+    template: &str,
+    name: &str,
+    hash: Option<&str>,
+) -> Result<Option<String>, String> {
+    let parsed = placeholders::parse(template)
+        .map_err(|e| format!("The zip name {template} is invalid: {e}"))?;
+    let mut zip_name = String::new();
+    for item in parsed.items {
+        match item {
+            Item::Text(text) => zip_name.push_str(text),
+            Item::LeftBrace => zip_name.push('{'),
+            Item::Placeholder(Placeholder::FileName("name")) => zip_name.push_str(name),
+            Item::Placeholder(Placeholder::FileName("hash")) => match hash {
+                Some(hash) => zip_name.push_str(hash),
+                None => return Ok(None),
+            },
+            _ => {
+                return Err(format!(
+                    "The zip name {template} can only use the {{name}} and {{hash}} \
+                    placeholders."
+                ))
+            }
+        }
+    }
+    if zip_name.is_empty() || zip_name.contains(['/', '\\']) || zip_name == "." || zip_name == ".."
+    {
+        return Err(format!(
+            "The zip name {template} must be a file name; it cannot name a directory."
+        ));
+    }
+    Ok(Some(zip_name))
+}
+
+fn zips_dir(resolve_base: &Path, directory: &Path, zips: &Zips) -> PathBuf {
+    match zips.dir.as_deref() {
+        Some(dir) => resolve_base.join(dir),
+        None => directory
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    }
+}
+
+fn canonical_directory(resolve_base: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = resolve_base.join(name);
+    let directory = path.canonicalize().map_err(|e| {
+        format!(
+            "Cannot create a zip archive from {path}: Directory does not exist: {e}",
+            path = path.display()
+        )
+    })?;
+    if !directory.is_dir() {
+        return Err(format!(
+            "Cannot create a zip archive from {name}: {directory} is a file.",
+            directory = directory.display()
+        ));
+    }
+    Ok(directory)
+}
+
+/// The path the boot-pack writes the zip of the directory `name` to.
+///
+/// Returns `None` if the zip is named after its hash but no `hash` is given.
+pub(crate) fn zip_path(
+    resolve_base: &Path,
+    name: &str,
+    zips: &Zips,
+    hash: Option<&str>,
+) -> Result<Option<PathBuf>, String> {
+    let directory = canonical_directory(resolve_base, name)?;
+    let zip_name = match zips.name.as_deref() {
+        Some(template) => {
+            let Some(name) = directory.file_name().and_then(|name| name.to_str()) else {
+                return Err(format!(
+                    "Cannot name the zip of {directory} after it since its name is not valid \
+                    UTF-8.",
+                    directory = directory.display()
+                ));
+            };
+            match zip_name(template, name, hash)? {
+                Some(zip_name) => PathBuf::from(zip_name),
+                None => return Ok(None),
+            }
+        }
+        None => directory
+            .with_extension("zip")
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_default(),
+    };
+    Ok(Some(
+        zips_dir(resolve_base, &directory, zips).join(zip_name),
+    ))
+}
+
+fn create_zip<W: Write + Seek>(
+    dir: &Path,
+    out: W,
+    zip_path: &Path,
+    unicode_normalization: UnicodeNormalization,
+    keep_junk: bool,
+) -> Result<(), String> {
+    let mut zip = zip::ZipWriter::new(out);
+    let mut longest_entry_name = String::new();
+    let mut entry_names = HashMap::new();
+    for entry in WalkDir::new(dir)
+        .contents_first(false)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| {
+            let junk = !keep_junk
+                && entry.depth() > 0
+                && entry.file_name().to_str().map(is_junk).unwrap_or(false);
+            if junk {
+                debug!("Skipping junk {path}", path = entry.path().display());
+            }
+            !junk
+        })
+    {
         let entry = entry.map_err(|e| {
             format!(
                 "Walk failed while trying to create a zip of {dir}: {e}",
@@ -60,6 +234,25 @@ fn create_zip(dir: &Path) -> Result<PathBuf, String> {
             .collect::<Result<Vec<_>, _>>()?
             // N.B.: Zip archive entry names always use / as the directory separator.
             .join("/");
+        let entry_name = unicode_normalization.normalize(&entry_name).into_owned();
+        if let Some(other) = entry_names.insert(entry_name.clone(), rel_path.to_path_buf()) {
+            return Err(format!(
+                "Cannot create a zip of {dir}: {other} and {path} are both named {entry_name} \
+                once their names are Unicode normalized. Rename one of them.",
+                dir = dir.display(),
+                other = other.display(),
+                path = rel_path.display()
+            ));
+        }
+        if let Some(problem) = windows_path_problem(rel_path) {
+            warn!(
+                "Adding {entry_name} to {zip}: {problem}; so it cannot be extracted there.",
+                zip = zip_path.display()
+            );
+        }
+        if longest_entry_name.len() < entry_name.len() {
+            longest_entry_name = entry_name.clone();
+        }
         let options = create_options(&entry.metadata().map_err(|e| {
             format!(
                 "Failed to read metadata for {path}: {e}",
@@ -81,29 +274,844 @@ fn create_zip(dir: &Path) -> Result<PathBuf, String> {
             std::io::copy(&mut file, &mut zip).map_err(|e| format!("{e}"))?;
         }
     }
-    zip.finish().map_err(|e| {
-        format!(
-            "Failed to finalize zip {zip}: {e}",
+    zip.finish()
+        .and_then(|mut out| out.flush().map_err(zip::result::ZipError::Io))
+        .map_err(|e| {
+            format!(
+                "Failed to finalize zip {zip}: {e}",
+                zip = zip_path.display()
+            )
+        })?;
+    let dir_name_len = dir
+        .file_name()
+        .map(|name| name.len() + 1)
+        .unwrap_or_default();
+    if WINDOWS_NCE_DIR_LEN + dir_name_len + longest_entry_name.len() > WINDOWS_MAX_PATH {
+        warn!(
+            "The zip {zip} has entries with long paths, like {longest_entry_name}. When extracted \
+            on Windows these will likely exceed {WINDOWS_MAX_PATH} characters, which programs \
+            that are not long path aware, like Python without long paths enabled, cannot open.",
             zip = zip_path.display()
-        )
-    })?;
-    Ok(zip_path)
+        );
+    }
+    Ok(())
 }
 
+/// Zips up the directory `name` as directed by the lift "zips".
+///
+/// Returns the path of the zip and, if the zip is named after its hash, its size and hash.
 #[time("debug", "archive::{}")]
-pub(crate) fn create(dir: &Path, name: &str) -> Result<PathBuf, String> {
-    let path = dir.join(name);
-    let directory = path.canonicalize().map_err(|e| {
+pub(crate) fn create(
+    resolve_base: &Path,
+    name: &str,
+    unicode_normalization: UnicodeNormalization,
+    keep_junk: bool,
+    zips: &Zips,
+) -> Result<(PathBuf, Option<(usize, String)>), String> {
+    let directory = canonical_directory(resolve_base, name)?;
+    let zips_dir = zips_dir(resolve_base, &directory, zips);
+    std::fs::create_dir_all(&zips_dir).map_err(|e| {
         format!(
-            "Cannot create a zip archive from {path}: Directory does not exist: {e}",
-            path = path.display()
+            "Failed to create the directory {zips_dir} to write the zip of {directory} to: {e}",
+            zips_dir = zips_dir.display(),
+            directory = directory.display()
         )
     })?;
-    if !directory.is_dir() {
+    if zips_dir
+        .canonicalize()
+        .map_or(false, |zips_dir| zips_dir.starts_with(&directory))
+    {
         return Err(format!(
-            "Cannot create a zip archive from {name}: {directory} is a file.",
-            directory = directory.display()
+            "Cannot write the zip of {directory} to {zips_dir} since that is inside the directory \
+            being zipped.",
+            directory = directory.display(),
+            zips_dir = zips_dir.display()
         ));
     }
-    create_zip(&directory)
+    let exists = |path: &Path| {
+        format!(
+            "Failed to create {zip} for packing {directory} into: it already exists. Set \
+            \"overwrite\": true in the lift \"zips\" to replace it.",
+            zip = path.display(),
+            directory = directory.display()
+        )
+    };
+    let known_path = zip_path(resolve_base, name, zips, None)?;
+    if let Some(path) = known_path.as_deref() {
+        if !zips.overwrite && path.exists() {
+            return Err(exists(path));
+        }
+    }
+
+    // N.B.: We write the zip to a temporary file first since its name may include its hash.
+    let tmp = tempfile::Builder::new()
+        .prefix(".zip.")
+        .tempfile_in(&zips_dir)
+        .map_err(|e| {
+            format!(
+                "Failed to open a temporary file in {zips_dir} for packing {directory} into: {e}",
+                zips_dir = zips_dir.display(),
+                directory = directory.display()
+            )
+        })?;
+    create_zip(
+        &directory,
+        BufWriter::new(tmp.as_file()),
+        known_path.as_deref().unwrap_or(tmp.path()),
+        unicode_normalization,
+        keep_junk,
+    )?;
+    let (path, digest) = match known_path {
+        Some(path) => (path, None),
+        None => {
+            let (size, hash) = fingerprint::digest_file(tmp.path())?;
+            let path = zip_path(resolve_base, name, zips, Some(&hash))?
+                .expect("The hash of the zip is known.");
+            (path, Some((size, hash)))
+        }
+    };
+    // N.B.: A zip named after its hash that already exists holds the same contents; so it is
+    // always safe to replace.
+    if zips.overwrite || digest.is_some() {
+        tmp.persist(&path).map_err(|e| e.error)
+    } else {
+        tmp.persist_noclobber(&path).map_err(|e| e.error)
+    }
+    .map_err(|e| {
+        if e.kind() == std::io::ErrorKind::AlreadyExists {
+            exists(&path)
+        } else {
+            format!(
+                "Failed to write {zip} for packing {directory} into: {e}",
+                zip = path.display(),
+                directory = directory.display()
+            )
+        }
+    })?;
+    Ok((path, digest))
+}
+
+// Returns the tar stream held in a tarball or `None` if this build has no decompressor for it.
+fn tar_stream<'a, R: Read + 'a>(
+    archive_type: ArchiveType,
+    stream: R,
+) -> Result<Option<Box<dyn Read + 'a>>, String> {
+    Ok(Some(match archive_type {
+        ArchiveType::Tar => Box::new(stream),
+        #[cfg(feature = "bzip2")]
+        ArchiveType::CompressedTar(Compression::Bzip2) => {
+            Box::new(bzip2::read::BzDecoder::new(stream))
+        }
+        ArchiveType::CompressedTar(Compression::Gzip) => {
+            Box::new(flate2::read::GzDecoder::new(stream))
+        }
+        #[cfg(feature = "xz")]
+        ArchiveType::CompressedTar(Compression::Xz) => Box::new(xz2::read::XzDecoder::new(stream)),
+        ArchiveType::CompressedTar(Compression::Zlib) => {
+            Box::new(flate2::read::ZlibDecoder::new(stream))
+        }
+        #[cfg(feature = "zstd")]
+        ArchiveType::CompressedTar(Compression::Zstd) => Box::new(
+            zstd::stream::Decoder::new(stream)
+                .map_err(|e| format!("Failed to create a zstd decoder: {e}"))?,
+        ),
+        #[allow(unreachable_patterns)]
+        _ => return Ok(None),
+    }))
+}
+
+// Checks whether `block` starts with a tar header by validating the header checksum; which also
+// covers old v7 tars that lack the ustar magic.
+fn is_tar_header(block: &[u8]) -> bool {
+    if block.len() < 512 {
+        return false;
+    }
+    let Ok(checksum) = tar::Header::from_byte_slice(&block[..512]).cksum() else {
+        return false;
+    };
+    // N.B.: The checksum is calculated with the checksum field itself filled with spaces.
+    let sum = block[..512]
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| {
+            if (148..156).contains(&index) {
+                u32::from(b' ')
+            } else {
+                u32::from(*byte)
+            }
+        })
+        .sum::<u32>();
+    checksum == sum
+}
+
+/// Identifies the type of the archive at `path` from its leading bytes, if it is one.
+///
+/// A compressed file is only identified as a tarball if it decompresses to a tar header; so
+/// compressed files this build has no decompressor for are not identified.
+pub(crate) fn sniff(path: &Path) -> Result<Option<ArchiveType>, String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))?;
+    let mut head = Vec::with_capacity(512);
+    (&mut file)
+        .take(512)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("Failed to read {path}: {e}", path = path.display()))?;
+    let compression = match head.as_slice() {
+        [b'P', b'K', 3, 4, ..] | [b'P', b'K', 5, 6, ..] | [b'P', b'K', 7, 8, ..] => {
+            return Ok(Some(ArchiveType::Zip))
+        }
+        [0x1f, 0x8b, ..] => Compression::Gzip,
+        [b'B', b'Z', b'h', ..] => Compression::Bzip2,
+        [0xfd, b'7', b'z', b'X', b'Z', 0, ..] => Compression::Xz,
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => Compression::Zstd,
+        [0x78, flags, ..] if (0x7800 | u16::from(*flags)) % 31 == 0 => Compression::Zlib,
+        _ => return Ok(is_tar_header(&head).then_some(ArchiveType::Tar)),
+    };
+    file.rewind()
+        .map_err(|e| format!("Failed to read {path}: {e}", path = path.display()))?;
+    let archive_type = ArchiveType::CompressedTar(compression);
+    let Ok(Some(tar_stream)) = tar_stream(archive_type, BufReader::new(file)) else {
+        return Ok(None);
+    };
+    let mut block = Vec::with_capacity(512);
+    if tar_stream.take(512).read_to_end(&mut block).is_err() {
+        return Ok(None);
+    }
+    Ok(is_tar_header(&block).then_some(archive_type))
+}
+
+fn tar_size<R: Read>(tar_stream: R) -> Result<usize, String> {
+    let mut size = 0;
+    for entry in tar::Archive::new(tar_stream)
+        .entries()
+        .map_err(|e| format!("Failed to read tar entries: {e}"))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read tar entry: {e}"))?;
+        size += entry.size() as usize;
+    }
+    Ok(size)
+}
+
+/// Calculates the total size of the files an archive unpacks to.
+///
+/// Returns `None` for blobs, whose extracted size is just their size, and for compressed tarballs
+/// this build has no decompressor for.
+#[time("debug", "archive::{}")]
+pub(crate) fn extracted_size(path: &Path, file_type: FileType) -> Result<Option<usize>, String> {
+    let archive_type = match file_type {
+        FileType::Blob => return Ok(None),
+        FileType::Directory => ArchiveType::Zip,
+        FileType::Archive(archive_type) => archive_type,
+    };
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))?;
+    let stream = BufReader::new(file);
+    let size = match archive_type {
+        ArchiveType::Zip => {
+            let mut zip = zip::ZipArchive::new(stream)
+                .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))?;
+            let mut size = 0;
+            for index in 0..zip.len() {
+                size += zip
+                    .by_index_raw(index)
+                    .map_err(|e| format!("Failed to read {path}: {e}", path = path.display()))?
+                    .size() as usize;
+            }
+            size
+        }
+        _ => match tar_stream(archive_type, stream)
+            .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))?
+        {
+            Some(tar_stream) => tar_size(tar_stream)?,
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(size))
+}
+
+// Writes the tar stream produced by `write_tar` to `out`, compressed as `archive_type` calls for.
+fn write_tarball(
+    path: &Path,
+    archive_type: ArchiveType,
+    out: std::fs::File,
+    write_tar: impl FnOnce(&mut dyn Write) -> Result<(), String>,
+) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("Failed to write {path}: {e}", path = path.display());
+    match archive_type {
+        ArchiveType::Tar => {
+            let mut out = BufWriter::new(out);
+            write_tar(&mut out)?;
+            out.flush().map_err(error)
+        }
+        #[cfg(feature = "bzip2")]
+        ArchiveType::CompressedTar(Compression::Bzip2) => {
+            let mut out = bzip2::write::BzEncoder::new(out, bzip2::Compression::default());
+            write_tar(&mut out)?;
+            out.finish().map(|_| ()).map_err(error)
+        }
+        ArchiveType::CompressedTar(Compression::Gzip) => {
+            let mut out = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+            write_tar(&mut out)?;
+            out.finish().map(|_| ()).map_err(error)
+        }
+        #[cfg(feature = "xz")]
+        ArchiveType::CompressedTar(Compression::Xz) => {
+            let mut out = xz2::write::XzEncoder::new(out, 6);
+            write_tar(&mut out)?;
+            out.finish().map(|_| ()).map_err(error)
+        }
+        ArchiveType::CompressedTar(Compression::Zlib) => {
+            let mut out = flate2::write::ZlibEncoder::new(out, flate2::Compression::default());
+            write_tar(&mut out)?;
+            out.finish().map(|_| ()).map_err(error)
+        }
+        #[cfg(feature = "zstd")]
+        ArchiveType::CompressedTar(Compression::Zstd) => {
+            let mut out = zstd::stream::Encoder::new(out, 0).map_err(error)?;
+            write_tar(&mut out)?;
+            out.finish().map(|_| ()).map_err(error)
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(format!(
+            "Cannot create {path} since this build has no compressor for {ext} archives.",
+            path = path.display(),
+            ext = archive_type.as_ext()
+        )),
+    }
+}
+
+// Converts the MS-DOS date and time zip entries record to seconds since the Unix epoch; taking the
+// time to be UTC since zips do not record a time zone.
+fn zip_mtime(datetime: zip::DateTime) -> u64 {
+    // See: https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let month = u64::from(datetime.month());
+    let year = u64::from(datetime.year()) - u64::from(month <= 2);
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + u64::from(datetime.day()) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    days * 86_400
+        + u64::from(datetime.hour()) * 3_600
+        + u64::from(datetime.minute()) * 60
+        + u64::from(datetime.second())
+}
+
+fn zip_to_tar<R: Read + Seek>(path: &Path, stream: R, out: &mut dyn Write) -> Result<(), String> {
+    let mut zip = zip::ZipArchive::new(stream)
+        .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))?;
+    let mut tar = tar::Builder::new(out);
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("Failed to read {path}: {e}", path = path.display()))?;
+        let name = entry
+            .enclosed_name()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| {
+                format!(
+                    "Cannot transcode {path} since its entry {name} has an unsafe path.",
+                    path = path.display(),
+                    name = entry.name()
+                )
+            })?;
+        let mode = entry.unix_mode();
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(zip_mtime(entry.last_modified()));
+        let result = if entry.is_dir() {
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(mode.unwrap_or(0o755) & 0o7777);
+            header.set_size(0);
+            tar.append_data(&mut header, &name, std::io::empty())
+        } else if mode.map(|mode| mode & 0o170000 == 0o120000) == Some(true) {
+            let mut target = String::new();
+            entry.read_to_string(&mut target).map_err(|e| {
+                format!(
+                    "Failed to read the target of the symlink {name} in {path}: {e}",
+                    name = name.display(),
+                    path = path.display()
+                )
+            })?;
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_mode(0o777);
+            header.set_size(0);
+            tar.append_link(&mut header, &name, target)
+        } else {
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_mode(mode.unwrap_or(0o644) & 0o7777);
+            header.set_size(entry.size());
+            tar.append_data(&mut header, &name, &mut entry)
+        };
+        result.map_err(|e| {
+            format!(
+                "Failed to transcode {name} from {path}: {e}",
+                name = name.display(),
+                path = path.display()
+            )
+        })?;
+    }
+    tar.finish()
+        .map_err(|e| format!("Failed to transcode {path}: {e}", path = path.display()))
+}
+
+fn tar_to_zip<R: Read>(
+    path: &Path,
+    tar_stream: R,
+    dst: &Path,
+    out: std::fs::File,
+) -> Result<(), String> {
+    let mut zip = zip::ZipWriter::new(BufWriter::new(out));
+    let mut tar = tar::Archive::new(tar_stream);
+    for entry in tar.entries().map_err(|e| {
+        format!(
+            "Failed to read tar entries from {path}: {e}",
+            path = path.display()
+        )
+    })? {
+        let mut entry = entry.map_err(|e| {
+            format!(
+                "Failed to read tar entry from {path}: {e}",
+                path = path.display()
+            )
+        })?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| format!("Failed to read tar entry path: {e}"))?
+            .into_owned();
+        // N.B.: Zip archive entry names always use / as the directory separator.
+        let name = entry_path
+            .iter()
+            .map(|component| {
+                component.to_str().ok_or_else(|| {
+                    format!("Failed to interpret tar entry path as utf8: {entry_path:?}")
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .join("/");
+        let entry_type = entry.header().entry_type();
+        if !matches!(
+            entry_type,
+            tar::EntryType::Directory
+                | tar::EntryType::Regular
+                | tar::EntryType::Continuous
+                | tar::EntryType::GNUSparse
+        ) {
+            return Err(format!(
+                "Cannot transcode {path} to a zip since {name} is a {entry_type:?} entry, which \
+                the zips the scie-jump extracts cannot hold. Use a tar type instead.",
+                path = path.display()
+            ));
+        }
+        #[cfg(not(target_os = "wasi"))]
+        if crate::extract::pax_sparse(&mut entry)?.is_some() {
+            return Err(format!(
+                "Cannot transcode {path} to a zip since {name} is a PAX sparse file.",
+                path = path.display()
+            ));
+        }
+        let options = FileOptions::default()
+            .unix_permissions(
+                entry
+                    .header()
+                    .mode()
+                    .map_err(|e| format!("Failed to read the mode of {name}: {e}"))?
+                    & 0o7777,
+            )
+            .large_file(entry.size() > u64::from(u32::MAX));
+        if entry_type.is_dir() {
+            zip.add_directory(&name, options)
+        } else {
+            zip.start_file(&name, options).and_then(|_| {
+                std::io::copy(&mut entry, &mut zip)
+                    .map(|_| ())
+                    .map_err(zip::result::ZipError::Io)
+            })
+        }
+        .map_err(|e| {
+            format!(
+                "Failed to transcode {name} from {path}: {e}",
+                path = path.display()
+            )
+        })?;
+    }
+    zip.finish()
+        .and_then(|mut out| out.flush().map_err(zip::result::ZipError::Io))
+        .map_err(|e| format!("Failed to finalize zip {dst}: {e}", dst = dst.display()))
+}
+
+/// The path the archive at `path` is transcoded to when packed as a different archive type.
+pub(crate) fn transcoded_path(path: &Path, archive_type: ArchiveType) -> PathBuf {
+    let mut transcoded_path = path.as_os_str().to_owned();
+    transcoded_path.push(".");
+    transcoded_path.push(archive_type.as_ext());
+    PathBuf::from(transcoded_path)
+}
+
+/// Converts the archive at `path` from one archive type to another without unpacking it to disk.
+///
+/// Between tarballs only the compression changes. Converting a tarball to a zip fails if it holds
+/// entries, like symlinks, that the zips the scie-jump extracts cannot hold.
+#[time("debug", "archive::{}")]
+pub(crate) fn transcode(
+    path: &Path,
+    from: ArchiveType,
+    to: ArchiveType,
+) -> Result<PathBuf, String> {
+    let stream = BufReader::new(
+        std::fs::File::open(path)
+            .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))?,
+    );
+    let dst = transcoded_path(path, to);
+    let out = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&dst)
+        .map_err(|e| {
+            format!(
+                "Failed to open {dst} for transcoding {path} into: {e}",
+                dst = dst.display(),
+                path = path.display()
+            )
+        })?;
+    let no_decompressor = || {
+        format!(
+            "Cannot transcode {path} since this build has no decompressor for {ext} archives.",
+            path = path.display(),
+            ext = from.as_ext()
+        )
+    };
+    let result = match (from, to) {
+        (ArchiveType::Zip, _) => write_tarball(&dst, to, out, |tar| zip_to_tar(path, stream, tar)),
+        _ => tar_stream(from, stream)
+            .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))
+            .and_then(|tar| tar.ok_or_else(no_decompressor))
+            .and_then(|mut tar| match to {
+                ArchiveType::Zip => tar_to_zip(path, tar, &dst, out),
+                _ => write_tarball(&dst, to, out, |out| {
+                    std::io::copy(&mut tar, out).map(|_| ()).map_err(|e| {
+                        format!("Failed to transcode {path}: {e}", path = path.display())
+                    })
+                }),
+            }),
+    };
+    if result.is_err() {
+        let _ = std::fs::remove_file(&dst);
+    }
+    result.map(|_| dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    use tempfile::tempdir;
+
+    use super::{windows_path_problem, zip_name};
+    use crate::config::{ArchiveType, Compression, FileType, UnicodeNormalization, Zips};
+    use crate::fingerprint;
+
+    fn zip_app(
+        dir: &Path,
+        unicode_normalization: UnicodeNormalization,
+        keep_junk: bool,
+    ) -> Result<PathBuf, String> {
+        super::create(
+            dir,
+            "app",
+            unicode_normalization,
+            keep_junk,
+            &Zips::default(),
+        )
+        .map(|(zip, _)| zip)
+    }
+
+    #[test]
+    fn windows_paths() {
+        assert_eq!(
+            None,
+            windows_path_problem(Path::new("site-packages/auxiliary/con_fig.py"))
+        );
+        assert_eq!(
+            Some("aux.py is a reserved device name on Windows".to_string()),
+            windows_path_problem(Path::new("lib/aux.py"))
+        );
+        assert_eq!(
+            Some("CON is a reserved device name on Windows".to_string()),
+            windows_path_problem(Path::new("CON/file"))
+        );
+        assert_eq!(
+            Some("lpt1 .txt is a reserved device name on Windows".to_string()),
+            windows_path_problem(Path::new("lpt1 .txt"))
+        );
+        assert_eq!(
+            Some("dir. ends with a dot or space, which Windows strips from file names".to_string()),
+            windows_path_problem(Path::new("dir./file"))
+        );
+        assert_eq!(
+            Some("what?.txt contains '?', which Windows does not allow in file names".to_string()),
+            windows_path_problem(Path::new("what?.txt"))
+        );
+    }
+
+    fn zip_names(zip: &Path) -> Vec<String> {
+        let mut names = zip::ZipArchive::new(std::fs::File::open(zip).unwrap())
+            .unwrap()
+            .file_names()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn unicode_normalization() {
+        let tmp = tempdir().unwrap();
+        let app = tmp.path().join("app");
+        std::fs::create_dir_all(app.join("cafe\u{301}")).unwrap();
+        std::fs::write(app.join("cafe\u{301}").join("menu"), "cr\u{e8}pes").unwrap();
+
+        let zip = zip_app(tmp.path(), UnicodeNormalization::Nfc, false).unwrap();
+        assert_eq!(vec!["caf\u{e9}/", "caf\u{e9}/menu"], zip_names(&zip));
+        std::fs::remove_file(&zip).unwrap();
+
+        let zip = zip_app(tmp.path(), UnicodeNormalization::Preserve, false).unwrap();
+        assert_eq!(vec!["cafe\u{301}/", "cafe\u{301}/menu"], zip_names(&zip));
+        std::fs::remove_file(&zip).unwrap();
+
+        std::fs::create_dir(app.join("caf\u{e9}")).unwrap();
+        let err = zip_app(tmp.path(), UnicodeNormalization::Nfc, false).unwrap_err();
+        assert!(err.contains("are both named caf\u{e9} once"), "{err}");
+    }
+
+    #[test]
+    fn junk() {
+        let tmp = tempdir().unwrap();
+        let app = tmp.path().join("app");
+        std::fs::create_dir_all(app.join("__MACOSX/src")).unwrap();
+        std::fs::create_dir_all(app.join("src")).unwrap();
+        for junk in [
+            ".DS_Store",
+            "Thumbs.db",
+            "__MACOSX/src/._main.py",
+            "src/.main.py.swp",
+            "src/.#main.py",
+            "src/#main.py#",
+            "src/main.py~",
+        ] {
+            std::fs::write(app.join(junk), "junk").unwrap();
+        }
+        std::fs::write(app.join("src/main.py"), "print('hi')").unwrap();
+        std::fs::write(app.join("src/#"), "").unwrap();
+
+        let zip = zip_app(tmp.path(), UnicodeNormalization::Nfc, false).unwrap();
+        assert_eq!(vec!["src/", "src/#", "src/main.py"], zip_names(&zip));
+        std::fs::remove_file(&zip).unwrap();
+
+        let zip = zip_app(tmp.path(), UnicodeNormalization::Nfc, true).unwrap();
+        assert_eq!(12, zip_names(&zip).len());
+    }
+
+    #[test]
+    fn zips() {
+        let tmp = tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("app.d")).unwrap();
+        std::fs::write(tmp.path().join("app.d").join("main.py"), "print('hi')").unwrap();
+        let create = |zips: &Zips| {
+            super::create(tmp.path(), "app.d", UnicodeNormalization::Nfc, false, zips)
+        };
+
+        let default = Zips::default();
+        let (zip, digest) = create(&default).unwrap();
+        assert_eq!(tmp.path().canonicalize().unwrap().join("app.zip"), zip);
+        assert_eq!(None, digest);
+        let err = create(&default).unwrap_err();
+        assert!(err.contains("it already exists"), "{err}");
+        let overwrite = Zips {
+            overwrite: true,
+            ..Default::default()
+        };
+        assert_eq!(zip, create(&overwrite).unwrap().0);
+
+        // Zips named after their hash are content addressed; so an existing zip is just replaced.
+        let hashed = Zips {
+            dir: Some("dist".to_string()),
+            name: Some("{name}-{hash}.zip".to_string()),
+            overwrite: false,
+        };
+        let (zip, digest) = create(&hashed).unwrap();
+        let (size, hash) = fingerprint::digest_file(&zip).unwrap();
+        assert_eq!(Some((size, hash.clone())), digest);
+        assert_eq!(
+            tmp.path().join("dist").join(format!("app.d-{hash}.zip")),
+            zip
+        );
+        assert_eq!(zip, create(&hashed).unwrap().0);
+        assert_eq!(
+            1,
+            std::fs::read_dir(tmp.path().join("dist")).unwrap().count()
+        );
+
+        let inside = Zips {
+            dir: Some("app.d/dist".to_string()),
+            ..Default::default()
+        };
+        let err = create(&inside).unwrap_err();
+        assert!(err.contains("inside the directory being zipped"), "{err}");
+
+        assert_eq!(
+            Some("app-abc.zip".to_string()),
+            zip_name("{name}-{hash}.zip", "app", Some("abc")).unwrap()
+        );
+        assert_eq!(
+            Some("{app}.zip".to_string()),
+            zip_name("{{{name}}.zip", "app", None).unwrap()
+        );
+        assert_eq!(None, zip_name("{hash}.zip", "app", None).unwrap());
+        assert!(zip_name("{version}.zip", "app", None).is_err());
+        assert!(zip_name("zips/{name}.zip", "app", None).is_err());
+        assert!(zip_name("..", "app", None).is_err());
+    }
+
+    #[test]
+    fn zip_mtime() {
+        assert_eq!(315_532_800, super::zip_mtime(zip::DateTime::default()));
+        assert_eq!(
+            1_709_210_096,
+            super::zip_mtime(zip::DateTime::from_date_and_time(2024, 2, 29, 12, 34, 56).unwrap())
+        );
+    }
+
+    #[test]
+    fn transcode() {
+        let tmp = tempdir().unwrap();
+        let app = tmp.path().join("app");
+        std::fs::create_dir_all(app.join("bin")).unwrap();
+        std::fs::write(app.join("bin/tool"), "#!/bin/sh").unwrap();
+        std::fs::write(app.join("README"), "Read me.").unwrap();
+        let zip = zip_app(tmp.path(), UnicodeNormalization::Nfc, false).unwrap();
+
+        let tgz = super::transcode(
+            &zip,
+            ArchiveType::Zip,
+            ArchiveType::CompressedTar(Compression::Gzip),
+        )
+        .unwrap();
+        assert_eq!(tmp.path().join("app.zip.tar.gz"), tgz);
+        let dst = tmp.path().join("tgz");
+        crate::extract::tar(
+            flate2::read::GzDecoder::new(std::fs::File::open(&tgz).unwrap()),
+            &dst,
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "#!/bin/sh",
+            std::fs::read_to_string(dst.join("bin/tool")).unwrap()
+        );
+        assert_eq!(
+            "Read me.",
+            std::fs::read_to_string(dst.join("README")).unwrap()
+        );
+
+        let tar = super::transcode(
+            &tgz,
+            ArchiveType::CompressedTar(Compression::Gzip),
+            ArchiveType::Tar,
+        )
+        .unwrap();
+        assert_eq!(
+            Some(17),
+            super::extracted_size(&tar, FileType::Archive(ArchiveType::Tar)).unwrap()
+        );
+
+        let zip = super::transcode(&tar, ArchiveType::Tar, ArchiveType::Zip).unwrap();
+        assert_eq!(vec!["README", "bin/", "bin/tool"], zip_names(&zip));
+
+        let err = super::transcode(&tar, ArchiveType::Tar, ArchiveType::Zip).unwrap_err();
+        assert!(err.contains("Failed to open"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn transcode_symlinks() {
+        let tmp = tempdir().unwrap();
+        let tar = tmp.path().join("links.tar");
+        let mut builder = tar::Builder::new(std::fs::File::create(&tar).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_mode(0o777);
+        header.set_size(0);
+        builder.append_link(&mut header, "link", "target").unwrap();
+        builder.finish().unwrap();
+
+        let err = super::transcode(&tar, ArchiveType::Tar, ArchiveType::Zip).unwrap_err();
+        assert!(err.contains("link is a Symlink entry"), "{err}");
+        assert!(!tmp.path().join("links.tar.zip").exists());
+    }
+
+    fn tar_bytes() -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_ustar();
+        header.set_mode(0o644);
+        header.set_size(5);
+        builder
+            .append_data(&mut header, "hello.txt", b"hello".as_slice())
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn sniff() {
+        let tmp = tempdir().unwrap();
+        let sniff = |name: &str, contents: &[u8]| {
+            let path = tmp.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            super::sniff(&path).unwrap()
+        };
+        let tar = tar_bytes();
+        assert_eq!(Some(ArchiveType::Tar), sniff("tar", &tar));
+
+        let mut v7_tar = tar.clone();
+        // Blank the ustar magic and version and re-checksum the header.
+        v7_tar[257..265].fill(0);
+        let mut header = tar::Header::from_byte_slice(&v7_tar[..512]).clone();
+        header.set_cksum();
+        v7_tar[..512].copy_from_slice(header.as_bytes());
+        assert_eq!(Some(ArchiveType::Tar), sniff("v7", &v7_tar));
+
+        let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gz.write_all(&tar).unwrap();
+        assert_eq!(
+            Some(ArchiveType::CompressedTar(Compression::Gzip)),
+            sniff("tgz", &gz.finish().unwrap())
+        );
+
+        let mut zlib = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        zlib.write_all(&tar).unwrap();
+        assert_eq!(
+            Some(ArchiveType::CompressedTar(Compression::Zlib)),
+            sniff("tar-z", &zlib.finish().unwrap())
+        );
+
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            Some(ArchiveType::CompressedTar(Compression::Zstd)),
+            sniff("tzst", &zstd::encode_all(tar.as_slice(), 0).unwrap())
+        );
+
+        let mut json_gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        json_gz.write_all(br#"{"not": "a tar"}"#).unwrap();
+        assert_eq!(None, sniff("json.gz", &json_gz.finish().unwrap()));
+
+        let app = tmp.path().join("app");
+        std::fs::create_dir(&app).unwrap();
+        std::fs::write(app.join("file"), "contents").unwrap();
+        let zip = zip_app(tmp.path(), UnicodeNormalization::Nfc, false).unwrap();
+        assert_eq!(Some(ArchiveType::Zip), super::sniff(&zip).unwrap());
+
+        assert_eq!(None, sniff("empty", b""));
+        assert_eq!(None, sniff("script", b"#!/bin/sh\necho hello\n"));
+        assert_eq!(None, sniff("zeros", &[0; 1024]));
+    }
 }
@@ -0,0 +1,144 @@
+// Copyright 2022 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::ffi::OsString;
+use std::sync::OnceLock;
+
+use crate::manual::ManualFormat;
+
+const PREFIX: &str = "--scie-";
+const END: &str = "--scie--";
+
+/// Reserved `--scie-*` flags consumed by the scie-jump before the remaining arguments are passed
+/// on to the selected command.
+///
+/// Only flags leading the command line are recognized; parsing stops at the first argument that is
+/// not a recognized reserved flag or at the `--scie--` terminator, which is itself consumed.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ScieArgs {
+    /// An override for the `nce` cache base (`--scie-base=<path>`).
+    pub base: Option<OsString>,
+    /// The name of the command to boot (`--scie-boot=<name>`).
+    pub boot: Option<String>,
+    /// A log filter in `RUST_LOG` syntax (`--scie-log=<filter>`).
+    pub log: Option<String>,
+    /// Print the selected command instead of executing it (`--scie-dry-run`).
+    pub dry_run: bool,
+    /// Print a manual for the scie's commands (`--scie-help` or `--scie-help=man`).
+    pub help: Option<ManualFormat>,
+    args: Vec<OsString>,
+}
+
+impl ScieArgs {
+    fn parse<I: IntoIterator<Item = OsString>>(argv: I) -> Self {
+        let mut scie_args = ScieArgs::default();
+        let mut argv = argv.into_iter();
+        scie_args.args.extend(argv.next());
+        let mut argv = argv.peekable();
+        while let Some(arg) = argv.peek().and_then(|arg| arg.to_str()) {
+            if !arg.starts_with(PREFIX) {
+                break;
+            }
+            if arg == END {
+                argv.next();
+                break;
+            } else if arg == "--scie-dry-run" {
+                scie_args.dry_run = true;
+            } else if arg == "--scie-help" {
+                scie_args.help = Some(ManualFormat::Text);
+            } else if arg == "--scie-help=man" {
+                scie_args.help = Some(ManualFormat::Man);
+            } else if let Some(base) = arg.strip_prefix("--scie-base=") {
+                scie_args.base = Some(OsString::from(base));
+            } else if let Some(boot) = arg.strip_prefix("--scie-boot=") {
+                scie_args.boot = Some(boot.to_string());
+            } else if let Some(log) = arg.strip_prefix("--scie-log=") {
+                scie_args.log = Some(log.to_string());
+            } else {
+                // N.B.: Unrecognized `--scie-*` arguments belong to the command; e.g.: the
+                // boot-pack `--scie-jump` flag.
+                break;
+            }
+            argv.next();
+        }
+        scie_args.args.extend(argv);
+        scie_args
+    }
+
+    /// The command line arguments with any reserved flags removed.
+    pub fn args_os(&self) -> impl Iterator<Item = &OsString> {
+        self.args.iter()
+    }
+
+    /// The command line arguments with any reserved flags removed, lossily converted to strings.
+    pub fn args(&self) -> impl Iterator<Item = String> + '_ {
+        self.args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+    }
+}
+
+static SCIE_ARGS: OnceLock<ScieArgs> = OnceLock::new();
+
+/// Returns the reserved flags parsed from the current process's command line.
+pub fn scie_args() -> &'static ScieArgs {
+    SCIE_ARGS.get_or_init(|| ScieArgs::parse(std::env::args_os()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::ScieArgs;
+    use crate::manual::ManualFormat;
+
+    fn parse(argv: &[&str]) -> ScieArgs {
+        ScieArgs::parse(argv.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn leading_flags() {
+        let scie_args = parse(&[
+            "scie",
+            "--scie-base=/opt/nce",
+            "--scie-boot=tool",
+            "--scie-log=debug",
+            "--scie-dry-run",
+            "--scie-help=man",
+            "--scie-boot",
+            "--scie-base=/not/reserved",
+        ]);
+        assert_eq!(Some(OsString::from("/opt/nce")), scie_args.base);
+        assert_eq!(Some("tool".to_string()), scie_args.boot);
+        assert_eq!(Some("debug".to_string()), scie_args.log);
+        assert!(scie_args.dry_run);
+        assert_eq!(Some(ManualFormat::Man), scie_args.help);
+        assert_eq!(
+            vec!["scie", "--scie-boot", "--scie-base=/not/reserved"],
+            scie_args.args().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn pass_through() {
+        let scie_args = parse(&["scie", "arg", "--scie-dry-run"]);
+        assert!(!scie_args.dry_run);
+        assert_eq!(
+            vec!["scie", "arg", "--scie-dry-run"],
+            scie_args.args().collect::<Vec<_>>()
+        );
+
+        let scie_args = parse(&["scie", "--scie-jump", "path"]);
+        assert_eq!(
+            vec!["scie", "--scie-jump", "path"],
+            scie_args.args().collect::<Vec<_>>()
+        );
+
+        let scie_args = parse(&["scie", "--scie-dry-run", "--scie--", "--scie-dry-run"]);
+        assert!(scie_args.dry_run);
+        assert_eq!(
+            vec!["scie", "--scie-dry-run"],
+            scie_args.args().collect::<Vec<_>>()
+        );
+    }
+}
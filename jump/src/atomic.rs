@@ -3,10 +3,15 @@
 
 use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use serde::Serializer;
 
+use crate::error::JumpError;
+use crate::fingerprint;
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub(crate) enum Target {
     Directory,
@@ -23,7 +28,7 @@ impl Display for Target {
 }
 
 impl Target {
-    fn check_exists(&self, target: &Path) -> Result<bool, String> {
+    fn check_exists(&self, target: &Path) -> Result<bool, JumpError> {
         match self {
             Target::Directory => {
                 if target.is_dir() {
@@ -40,24 +45,209 @@ impl Target {
                 }
             }
         }
-        Err(format!(
+        Err(JumpError::Other(format!(
             "The target path {target} exists but is not a {self}.",
             target = target.display()
-        ))
+        )))
     }
 }
 
-fn clean(path: &Path) -> Result<(), String> {
+fn clean(path: &Path) -> Result<(), JumpError> {
     if !path.exists() {
         return Ok(());
     }
 
+    // N.B.: A work directory left behind by an interrupted extraction may be partially populated;
+    // so we remove it wholesale.
     if path.is_dir() {
-        std::fs::remove_dir(path)
+        std::fs::remove_dir_all(path)
     } else {
         std::fs::remove_file(path)
     }
-    .map_err(|e| format!("Failed to remove path {path}: {e}", path = path.display()))
+    .map_err(|e| {
+        JumpError::io(
+            format!("Failed to remove path {path}", path = path.display()),
+            e,
+        )
+    })
+}
+
+pub(crate) fn marker_path(target: &Path) -> PathBuf {
+    let mut marker = target.as_os_str().to_os_string();
+    marker.push(".complete");
+    PathBuf::from(marker)
+}
+
+// Identifies the current state of a file such that a change to its contents is very likely to
+// change its signature.
+//
+// N.B.: Signatures are kept in the completion markers that every entry already has instead of in
+// an index for the whole nce cache. Each marker is replaced atomically by the one process that
+// verified its entry; so concurrent boots, even of different scies sharing a cache, never contend
+// for a lock or lose each other's updates. The cost is one small marker read and one stat per
+// entry checked on each boot, which the fingerprint check in the marker already required.
+fn signature(path: &Path) -> Option<String> {
+    let metadata = path.metadata().ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    #[cfg(unix)]
+    let inode = {
+        use std::os::unix::fs::MetadataExt;
+        metadata.ino()
+    };
+    #[cfg(not(unix))]
+    let inode = 0;
+    Some(format!("{inode}:{mtime}:{size}", size = metadata.len()))
+}
+
+fn marker_contents(target: &Path, target_type: Target, fingerprint: &str) -> String {
+    match (target_type, signature(target)) {
+        (Target::File, Some(signature)) => format!("{fingerprint}\n{signature}"),
+        _ => fingerprint.to_string(),
+    }
+}
+
+// N.B.: The marker is only advisory; so we do not fail if we cannot update it, as is the case for
+// a read-only shared nce cache.
+pub(crate) fn update_marker(target: &Path, target_type: Target, fingerprint: &str) {
+    let marker = marker_path(target);
+    let result = marker
+        .parent()
+        .ok_or_else(|| "The marker has no parent directory.".to_string())
+        .and_then(|parent| tempfile::NamedTempFile::new_in(parent).map_err(|e| format!("{e}")))
+        .and_then(|mut temp| {
+            temp.write_all(marker_contents(target, target_type, fingerprint).as_bytes())
+                .map_err(|e| format!("{e}"))?;
+            temp.persist(&marker).map_err(|e| format!("{e}"))
+        });
+    if let Err(e) = result {
+        debug!(
+            "Failed to update completion marker {marker}: {e}",
+            marker = marker.display()
+        );
+    }
+}
+
+// Refuses to trust a cache entry that another user could have planted; e.g.: in a cache shared
+// by the users of a machine. Entries owned by root are trusted since an administrator may populate
+// a shared cache ahead of time.
+#[cfg(unix)]
+fn check_owner(path: &Path) -> Result<(), JumpError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let owner = std::fs::symlink_metadata(path)
+        .map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to determine the owner of {path}",
+                    path = path.display()
+                ),
+                e,
+            )
+        })?
+        .uid();
+    let uid = nix::unistd::geteuid().as_raw();
+    if owner == uid || owner == 0 {
+        return Ok(());
+    }
+    Err(JumpError::Integrity(format!(
+        "Refusing to trust {path} since it is owned by uid {owner}; only files owned by you \
+        (uid {uid}) or root are trusted. Set SCIE_BASE to a directory only you can write to.",
+        path = path.display()
+    )))
+}
+
+#[cfg(not(unix))]
+fn check_owner(_path: &Path) -> Result<(), JumpError> {
+    Ok(())
+}
+
+/// The state of an atomic path.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Status {
+    /// The target was established and still matches its fingerprint.
+    Complete,
+    /// The target is missing or was not completely established.
+    Incomplete,
+    /// The target file was established but its contents no longer match its fingerprint.
+    Mutated,
+}
+
+/// Returns the status of the `target` given the `fingerprint` its completion marker should hold.
+///
+/// For files, the marker also records a signature of the file's inode, modification time and
+/// size when it was last verified. If that signature no longer matches, the file is re-hashed to
+/// verify it still matches the `fingerprint` and the marker is updated; so warm boots only pay for
+/// hashing when a file has changed.
+pub(crate) fn status(
+    target: &Path,
+    target_type: Target,
+    fingerprint: Option<&str>,
+) -> Result<Status, JumpError> {
+    if !target_type.check_exists(target)? {
+        return Ok(Status::Incomplete);
+    }
+    let Some(fingerprint) = fingerprint else {
+        return Ok(Status::Complete);
+    };
+    let marker = marker_path(target);
+    let Ok(recorded) = std::fs::read_to_string(&marker) else {
+        return Ok(Status::Incomplete);
+    };
+    check_owner(target)?;
+    check_owner(&marker)?;
+    let mut lines = recorded.lines();
+    if lines.next() != Some(fingerprint) {
+        return Ok(Status::Incomplete);
+    }
+    if Target::Directory == target_type {
+        return Ok(Status::Complete);
+    }
+    let recorded_signature = lines.next();
+    if recorded_signature.is_some() && signature(target).as_deref() == recorded_signature {
+        return Ok(Status::Complete);
+    }
+    match fingerprint::digest_file(target) {
+        Ok((_, hash)) if hash == fingerprint => {
+            debug!(
+                "The file {target} changed on disk but still has the expected hash.",
+                target = target.display()
+            );
+            update_marker(target, target_type, fingerprint);
+            Ok(Status::Complete)
+        }
+        _ => Ok(Status::Mutated),
+    }
+}
+
+/// Returns `true` if the `target` exists and its completion marker matches the `fingerprint`.
+///
+/// See [`status`].
+pub(crate) fn is_complete(
+    target: &Path,
+    target_type: Target,
+    fingerprint: Option<&str>,
+) -> Result<bool, JumpError> {
+    Ok(Status::Complete == status(target, target_type, fingerprint)?)
+}
+
+/// Removes the completion marker of the `target`; so it is re-established by the next
+/// [`atomic_path`] call for it.
+pub(crate) fn invalidate(target: &Path) -> Result<(), JumpError> {
+    let marker = marker_path(target);
+    std::fs::remove_file(&marker).map_err(|e| {
+        JumpError::io(
+            format!(
+                "Failed to remove the completion marker {marker}",
+                marker = marker.display()
+            ),
+            e,
+        )
+    })
 }
 
 /// Executes work to create the `target` path exactly once across threads and processes.
@@ -67,11 +257,16 @@ fn clean(path: &Path) -> Result<(), String> {
 /// renamed atomically to the `target` directory path. If the `target_type` is `Target::File` and
 /// the `target` file has not been created, then `work` is handed the path of a work file to create.
 /// That work file will not exist, but its parent directories will have been already created.
-pub(crate) fn atomic_path<E: Display, T, F>(
+///
+/// If a `fingerprint` is given, a `<target>.complete` marker file containing it is written once the
+/// `target` is established. A `target` found without a matching marker is treated as the remains
+/// of an interrupted or corrupted extraction; it is removed and the work is re-done.
+pub(crate) fn atomic_path<E: Into<JumpError>, T, F>(
     target: &Path,
     target_type: Target,
+    fingerprint: Option<&str>,
     work: F,
-) -> Result<Option<T>, String>
+) -> Result<Option<T>, JumpError>
 where
     F: FnOnce(&Path) -> Result<T, E>,
 {
@@ -79,7 +274,7 @@ where
     // path creation.
 
     // First check.
-    if target_type.check_exists(target)? {
+    if is_complete(target, target_type, fingerprint)? {
         debug!(
             "The atomic {target_type} at {path} has already been established.",
             path = target.display()
@@ -89,17 +284,20 @@ where
 
     // Lock.
     if !target.is_absolute() {
-        return Err(format!(
+        return Err(JumpError::Other(format!(
             "The target_dir must be an absolute path, given: {}",
             target.display()
-        ));
+        )));
     }
     let (work_path, lock_file) = {
         if let Some(parent) = target.parent() {
             std::fs::create_dir_all(parent).map_err(|e| {
-                format!(
-                    "Failed to establish parent directory of {target}: {e}",
-                    target = target.display()
+                JumpError::io(
+                    format!(
+                        "Failed to establish parent directory of {target}",
+                        target = target.display()
+                    ),
+                    e,
                 )
             })?;
         }
@@ -109,16 +307,19 @@ where
     };
 
     let lock_fd = File::create(&lock_file).map_err(|e| {
-        format!(
-            "Failed to open lock file {lock_file}: {e}",
-            lock_file = lock_file.display()
+        JumpError::io(
+            format!(
+                "Failed to open lock file {lock_file}",
+                lock_file = lock_file.display()
+            ),
+            e,
         )
     })?;
     let mut lock = fd_lock::RwLock::new(lock_fd);
     let _write_lock = lock.write();
 
     // Second check.
-    if target_type.check_exists(target)? {
+    if is_complete(target, target_type, fingerprint)? {
         debug!(
             "The atomic {target_type} at {path} has already been established \
             (lost double-check race).",
@@ -126,6 +327,27 @@ where
         );
         return Ok(None);
     }
+    let marker = marker_path(target);
+    if target.exists() {
+        info!(
+            "The atomic {target_type} at {path} has no completion marker; re-establishing it.",
+            path = target.display()
+        );
+        if target.is_dir() {
+            std::fs::remove_dir_all(target)
+        } else {
+            std::fs::remove_file(target)
+        }
+        .map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to remove incomplete {target_type} {path}",
+                    path = target.display()
+                ),
+                e,
+            )
+        })?;
+    }
 
     // Act.
 
@@ -137,26 +359,170 @@ where
 
     if Target::Directory == target_type {
         std::fs::create_dir(&work_path).map_err(|e| {
-            format!(
-                "Failed to prepare workdir {work_dir}: {e}",
-                work_dir = work_path.display()
+            JumpError::io(
+                format!(
+                    "Failed to prepare workdir {work_dir}",
+                    work_dir = work_path.display()
+                ),
+                e,
             )
         })?
     }
 
     let result = work(&work_path).map_err(|e| {
-        format!(
-            "Failed to establish atomic directory {target_dir}. Population of work directory \
-            failed: {e}",
-            target_dir = target.display()
-        )
+        e.into().context(|message| {
+            format!(
+                "Failed to establish atomic directory {target_dir}. Population of work \
+                directory failed: {message}",
+                target_dir = target.display()
+            )
+        })
     })?;
     std::fs::rename(work_path, target).map_err(|e| {
-        format!(
-            "Failed to establish atomic directory {target_dir}. Rename of work directory \
-            failed: {e}",
-            target_dir = target.display()
+        JumpError::io(
+            format!(
+                "Failed to establish atomic directory {target_dir}. Rename of work directory \
+                failed",
+                target_dir = target.display()
+            ),
+            e,
         )
     })?;
+    if let Some(fingerprint) = fingerprint {
+        std::fs::write(&marker, marker_contents(target, target_type, fingerprint)).map_err(
+            |e| {
+                JumpError::io(
+                    format!(
+                        "Failed to write completion marker {marker} for {target}",
+                        marker = marker.display(),
+                        target = target.display()
+                    ),
+                    e,
+                )
+            },
+        )?;
+    }
     Ok(Some(result))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::atomic::{atomic_path, is_complete, marker_path, status, Status, Target};
+    use crate::error::JumpError;
+
+    fn populate(work_dir: &Path) -> Result<(), JumpError> {
+        std::fs::write(work_dir.join("file"), "contents")
+            .map_err(|e| JumpError::io("Failed to populate", e))
+    }
+
+    #[test]
+    fn complete_marker() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("dir");
+
+        assert_eq!(
+            Some(()),
+            atomic_path(&target, Target::Directory, Some("abc"), populate).unwrap()
+        );
+        assert_eq!(
+            "abc",
+            std::fs::read_to_string(marker_path(&target)).unwrap()
+        );
+        assert_eq!(
+            None,
+            atomic_path(&target, Target::Directory, Some("abc"), populate).unwrap()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn untrusted_owner() {
+        use nix::unistd::{chown, geteuid, Uid};
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("dir");
+        atomic_path(&target, Target::Directory, Some("abc"), populate).unwrap();
+        assert!(is_complete(&target, Target::Directory, Some("abc")).unwrap());
+
+        // N.B.: Only root can give files away to another user.
+        if !geteuid().is_root() {
+            return;
+        }
+        chown(&marker_path(&target), Some(Uid::from_raw(12345)), None).unwrap();
+        let error = is_complete(&target, Target::Directory, Some("abc")).unwrap_err();
+        assert!(matches!(error, JumpError::Integrity(_)), "{error:?}");
+        assert!(
+            error.to_string().contains(
+                "since it is owned by uid 12345; only files owned by you (uid 0) or root"
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn verify_changed_file() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("file");
+        let hash = crate::fingerprint::digest(b"contents");
+        let write = |path: &Path| {
+            std::fs::write(path, "contents").map_err(|e| JumpError::io("Failed to write", e))
+        };
+
+        assert_eq!(
+            Some(()),
+            atomic_path(&target, Target::File, Some(&hash), write).unwrap()
+        );
+        let marker = std::fs::read_to_string(marker_path(&target)).unwrap();
+        assert!(marker.starts_with(&format!("{hash}\n")));
+
+        // Re-writing the same contents changes the file signature but verification by hash
+        // passes.
+        std::fs::remove_file(&target).unwrap();
+        std::fs::write(&target, "contents").unwrap();
+        assert!(is_complete(&target, Target::File, Some(&hash)).unwrap());
+        assert_ne!(
+            marker,
+            std::fs::read_to_string(marker_path(&target)).unwrap()
+        );
+
+        // Changing the contents fails verification and the file is re-established.
+        std::fs::write(&target, "tampered").unwrap();
+        assert_eq!(
+            Status::Mutated,
+            status(&target, Target::File, Some(&hash)).unwrap()
+        );
+        assert_eq!(
+            Some(()),
+            atomic_path(&target, Target::File, Some(&hash), write).unwrap()
+        );
+        assert_eq!("contents", std::fs::read_to_string(&target).unwrap());
+    }
+
+    #[test]
+    fn restart_incomplete() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("dir");
+
+        // A target with no marker, as if we were interrupted after the rename.
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("stale"), "").unwrap();
+        // A partially populated work dir, as if we were interrupted mid-extraction.
+        let work_dir = target.with_extension("work");
+        std::fs::create_dir(&work_dir).unwrap();
+        std::fs::write(work_dir.join("partial"), "").unwrap();
+
+        assert_eq!(
+            Some(()),
+            atomic_path(&target, Target::Directory, Some("abc"), populate).unwrap()
+        );
+        assert!(target.join("file").is_file());
+        assert!(!target.join("stale").exists());
+        assert!(!work_dir.exists());
+        assert_eq!(
+            "abc",
+            std::fs::read_to_string(marker_path(&target)).unwrap()
+        );
+    }
+}
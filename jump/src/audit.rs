@@ -0,0 +1,75 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! An opt-in log of the commands scies boot and the files they extract; see `SCIE_AUDIT`.
+//!
+//! Each boot appends one JSON record to `audit.jsonl` under the `nce` cache base; so operators of
+//! shared machines can tell which scies ran, when, and what they added to the cache.
+
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use serde::Serialize;
+
+use crate::installer::Extraction;
+
+const LOG_FILE: &str = "audit.jsonl";
+
+/// Returns `true` if `SCIE_AUDIT` asks for boots to be logged.
+pub(crate) fn enabled() -> bool {
+    std::env::var_os("SCIE_AUDIT").is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// The record of a single boot.
+#[derive(Debug, Serialize)]
+pub(crate) struct Boot<'a> {
+    pub(crate) timestamp_ms: u128,
+    pub(crate) scie: &'a Path,
+    pub(crate) lift: &'a str,
+    /// The fingerprint of the scie's lift manifest; which covers the hashes of all its files.
+    pub(crate) hash: &'a str,
+    pub(crate) command: &'a str,
+    pub(crate) duration_ms: u128,
+    pub(crate) extractions: &'a [Extraction],
+}
+
+/// Returns `time` in milliseconds since the Unix epoch.
+pub(crate) fn timestamp_ms(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_millis()
+}
+
+fn append(base: &Path, boot: &Boot) -> Result<(), String> {
+    let path = base.join(LOG_FILE);
+    let mut line = serde_json::to_vec(boot)
+        .map_err(|e| format!("Failed to serialize the audit record {boot:?}: {e}"))?;
+    line.push(b'\n');
+    std::fs::create_dir_all(base)
+        .and_then(|()| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+        })
+        // N.B.: A single append of a record this small is not interleaved with the appends of
+        // concurrent boots.
+        .and_then(|mut log| log.write_all(&line))
+        .map_err(|e| {
+            format!(
+                "Failed to append to the audit log {path}: {e}",
+                path = path.display()
+            )
+        })
+}
+
+/// Appends the record of a boot to the audit log under `base`.
+///
+/// Failing to do so is warned about but does not prevent the boot.
+pub(crate) fn record(base: &Path, boot: &Boot) {
+    if let Err(message) = append(base, boot) {
+        warn!("{message}");
+    }
+}
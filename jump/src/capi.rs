@@ -0,0 +1,243 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! A C API for introspecting and packing scies in-process.
+//!
+//! Strings returned by these functions are owned by the caller and must be released with
+//! [`jump_string_free`]. When a function fails it returns null and a description of the failure is
+//! available from [`jump_last_error`] until the next call on the same thread. The C header for this
+//! API is generated with `cbindgen`; see `include/jump.h`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use crate::config::{Fmt, UnknownFields};
+use crate::lift::PackCache;
+use crate::pack::{load_manifest, load_scie_jump, pack, PackOptions, PAGE_ALIGNMENT};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: String) {
+    // N.B.: Messages are built from paths and errors we format ourselves; but we guard against
+    // interior nuls so that an error is never lost.
+    let message = CString::new(message.replace('\0', "\\0"))
+        .expect("We replaced all interior nul bytes above.");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+unsafe fn to_path(name: &str, value: *const c_char) -> Result<PathBuf, String> {
+    if value.is_null() {
+        return Err(format!("The {name} path cannot be null."));
+    }
+    let value = CStr::from_ptr(value);
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Ok(PathBuf::from(std::ffi::OsStr::from_bytes(value.to_bytes())))
+    }
+    #[cfg(not(unix))]
+    {
+        value
+            .to_str()
+            .map(PathBuf::from)
+            .map_err(|e| format!("The {name} path is not valid UTF-8: {e}"))
+    }
+}
+
+fn call<F: FnOnce() -> Result<String, String>>(func: F) -> *mut c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow_mut().take());
+    let result = catch_unwind(AssertUnwindSafe(func))
+        .unwrap_or_else(|_| Err("An unexpected internal error occurred.".to_string()))
+        .and_then(|value| {
+            CString::new(value).map_err(|e| format!("Failed to convert result to a C string: {e}"))
+        });
+    match result {
+        Ok(value) => value.into_raw(),
+        Err(message) => {
+            set_last_error(message);
+            ptr::null_mut()
+        }
+    }
+}
+
+fn load_config(scie: &Path) -> Result<String, String> {
+    let mut config = vec![];
+    crate::inspect(scie)?.serialize(&mut config, Fmt::new())?;
+    String::from_utf8(config).map_err(|e| format!("The lift manifest is not valid UTF-8: {e}"))
+}
+
+fn pack_manifest(
+    manifest: &Path,
+    scie_jump: &Path,
+    dest_dir: &Path,
+    single_line: bool,
+    align: bool,
+) -> Result<String, String> {
+    let jump = load_scie_jump(scie_jump)?;
+    let (lift, manifest) = load_manifest(
+        manifest,
+        &jump,
+        UnknownFields::Warn.or_strict_from_env(),
+        &mut PackCache::default(),
+    )?;
+    let packed = pack(
+        lift,
+        &manifest,
+        &jump,
+        scie_jump,
+        dest_dir,
+        &PackOptions {
+            single_line,
+            alignment: align.then_some(PAGE_ALIGNMENT),
+            ..Default::default()
+        },
+    )?;
+    Ok(packed.to_json().to_string())
+}
+
+/// Returns the lift manifest of the scie at the given path as a single line of JSON.
+///
+/// # Safety
+///
+/// `scie` must be a valid pointer to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn jump_load_config(scie: *const c_char) -> *mut c_char {
+    call(|| load_config(&to_path("scie", scie)?))
+}
+
+/// Packs the lift manifest at the given path into a scie written to `dest_dir` and returns a JSON
+/// report describing it; just like `SCIE=boot-pack --json`.
+///
+/// The `manifest` can be a lift manifest file or a directory containing a `lift.json`. The
+/// `scie_jump` must be a scie-jump binary and not a scie.
+///
+/// # Safety
+///
+/// `manifest`, `scie_jump` and `dest_dir` must be valid pointers to nul-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn jump_pack(
+    manifest: *const c_char,
+    scie_jump: *const c_char,
+    dest_dir: *const c_char,
+    single_line: bool,
+    align: bool,
+) -> *mut c_char {
+    call(|| {
+        pack_manifest(
+            &to_path("manifest", manifest)?,
+            &to_path("scie-jump", scie_jump)?,
+            &to_path("destination directory", dest_dir)?,
+            single_line,
+            align,
+        )
+    })
+}
+
+/// Returns a description of the last failure on this thread or null if the last call succeeded.
+///
+/// The returned string is owned by the library and is only valid until the next call on this
+/// thread; it must not be passed to [`jump_string_free`].
+#[no_mangle]
+pub extern "C" fn jump_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map(|message| message.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+/// Releases a string returned by this library.
+///
+/// # Safety
+///
+/// `value` must be null or a string returned by this library that has not already been released.
+#[no_mangle]
+pub unsafe extern "C" fn jump_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::path::Path;
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::{jump_last_error, jump_load_config, jump_pack, jump_string_free};
+    use crate::EOF_MAGIC;
+
+    fn c_path(path: &Path) -> CString {
+        CString::new(path.to_str().unwrap()).unwrap()
+    }
+
+    unsafe fn take(value: *mut std::ffi::c_char) -> String {
+        assert!(
+            !value.is_null(),
+            "{}",
+            CStr::from_ptr(jump_last_error()).to_string_lossy()
+        );
+        let result = CStr::from_ptr(value).to_str().unwrap().to_string();
+        jump_string_free(value);
+        result
+    }
+
+    #[test]
+    fn pack_and_load_config() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let mut scie_jump = b"#!/bin/sh\n".to_vec();
+        let size = scie_jump.len() as u32 + 8;
+        scie_jump.write_u32::<LittleEndian>(size).unwrap();
+        scie_jump.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+        let scie_jump_path = tmp.path().join("scie-jump");
+        std::fs::write(&scie_jump_path, scie_jump).unwrap();
+
+        std::fs::write(tmp.path().join("data.txt"), "data").unwrap();
+        std::fs::write(
+            tmp.path().join("lift.json"),
+            r#"{"scie":{"lift":{"name":"example","files":[{"name":"data.txt"}],
+                "boot":{"commands":{"":{"exe":"{data.txt}"}}}}}}"#,
+        )
+        .unwrap();
+        let dest_dir = tmp.path().join("dist");
+        std::fs::create_dir(&dest_dir).unwrap();
+
+        let report = unsafe {
+            take(jump_pack(
+                c_path(tmp.path()).as_ptr(),
+                c_path(&scie_jump_path).as_ptr(),
+                c_path(&dest_dir).as_ptr(),
+                true,
+                false,
+            ))
+        };
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        assert_eq!(size as u64, report["jump"]["size"]);
+        assert_eq!("data.txt", report["files"][0]["name"]);
+
+        let config = unsafe { take(jump_load_config(c_path(&dest_dir.join("example")).as_ptr())) };
+        let config: serde_json::Value = serde_json::from_str(&config).unwrap();
+        assert_eq!("example", config["scie"]["lift"]["name"]);
+        assert_eq!("data.txt", config["scie"]["lift"]["files"][0]["name"]);
+        assert!(jump_last_error().is_null());
+
+        let error = unsafe { jump_load_config(c_path(&scie_jump_path).as_ptr()) };
+        assert!(error.is_null());
+        let message = unsafe { CStr::from_ptr(jump_last_error()) };
+        assert!(message
+            .to_str()
+            .unwrap()
+            .ends_with("is a scie-jump binary and not a scie."));
+
+        assert!(unsafe { jump_load_config(std::ptr::null()) }.is_null());
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Content-defined chunking of the large files a scie stores; see `boot-pack --chunk`.
+//!
+//! FastCDC cuts a file where its contents dictate; so an edit only changes the chunks it touches
+//! and the rest of the file cuts the same as before. The boot-pack stores each chunk on a page
+//! boundary; so block based dedupe and binary diffs between two releases of a scie line up on the
+//! chunks that did not change.
+
+use std::ops::Range;
+
+use crate::config::Chunk;
+
+const MIN_SIZE: u32 = 256 * 1024;
+const AVG_SIZE: u32 = 1024 * 1024;
+const MAX_SIZE: u32 = 4 * 1024 * 1024;
+
+/// Returns the ranges of the content-defined chunks of `data`, in order.
+pub(crate) fn cut(data: &[u8]) -> Vec<Range<usize>> {
+    fastcdc::v2020::FastCDC::new(data, MIN_SIZE, AVG_SIZE, MAX_SIZE)
+        .map(|chunk| chunk.offset..chunk.offset + chunk.length)
+        .collect()
+}
+
+/// Returns the bytes of each chunk of the file `name` given the scie `data` that starts at
+/// `data_offset` in the scie.
+pub(crate) fn slices<'a>(
+    data: &'a [u8],
+    data_offset: usize,
+    name: &str,
+    chunks: &[Chunk],
+) -> Result<Vec<&'a [u8]>, String> {
+    chunks
+        .iter()
+        .map(|chunk| {
+            chunk
+                .offset
+                .checked_sub(data_offset)
+                .and_then(|start| Some(start..start.checked_add(chunk.size)?))
+                .and_then(|range| data.get(range))
+                .ok_or_else(|| {
+                    format!(
+                        "The chunk of {name} of size {size} at offset {offset} lies outside the \
+                        scie.",
+                        size = chunk.size,
+                        offset = chunk.offset
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Generates `len` bytes of incompressible data that chunk like real archives do.
+#[cfg(test)]
+pub(crate) fn noise(len: usize, mut seed: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u8
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cut, noise, slices, MAX_SIZE, MIN_SIZE};
+    use crate::config::Chunk;
+    use crate::fingerprint;
+
+    fn hashes(data: &[u8]) -> Vec<String> {
+        cut(data)
+            .into_iter()
+            .map(|range| fingerprint::digest(&data[range]))
+            .collect()
+    }
+
+    #[test]
+    fn content_defined() {
+        let data = noise(16 * 1024 * 1024, 42);
+        let ranges = cut(&data);
+        assert!(ranges.len() > 4, "{ranges:?}");
+        assert_eq!(0, ranges[0].start);
+        assert_eq!(data.len(), ranges[ranges.len() - 1].end);
+        for (index, range) in ranges.iter().enumerate() {
+            if index > 0 {
+                assert_eq!(ranges[index - 1].end, range.start);
+            }
+            assert!(range.len() <= MAX_SIZE as usize);
+            if index < ranges.len() - 1 {
+                assert!(range.len() >= MIN_SIZE as usize);
+            }
+        }
+
+        // Inserting bytes near the start only changes the chunks around the insertion.
+        let mut edited = b"an edit".to_vec();
+        edited.extend_from_slice(&data);
+        let original = hashes(&data);
+        let edited = hashes(&edited);
+        let shared = edited.iter().filter(|hash| original.contains(hash)).count();
+        assert!(shared >= original.len() - 2, "{shared} of {original:?}");
+
+        assert!(cut(&data[..MIN_SIZE as usize]).len() <= 1);
+    }
+
+    #[test]
+    fn chunk_slices() {
+        let data = b"0123456789";
+        let chunk = |offset, size| Chunk {
+            offset,
+            size,
+            hash: String::new(),
+        };
+        assert_eq!(
+            vec![b"567".as_slice(), b"12".as_slice(), b"567".as_slice()],
+            slices(
+                data,
+                100,
+                "file",
+                &[chunk(105, 3), chunk(101, 2), chunk(105, 3)]
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Err("The chunk of file of size 3 at offset 99 lies outside the scie.".to_string()),
+            slices(data, 100, "file", &[chunk(99, 3)])
+        );
+        assert!(slices(data, 100, "file", &[chunk(108, 3)]).is_err());
+        assert!(slices(data, 100, "file", &[chunk(usize::MAX, 3)]).is_err());
+    }
+}
@@ -78,6 +78,9 @@ impl EnvParser {
                 Item::Placeholder(Placeholder::FileName(name)) => {
                     reified.push_str(&format!("{{scie.files.{name}}}"))
                 }
+                Item::Placeholder(Placeholder::FileSize(name)) => {
+                    reified.push_str(&format!("{{scie.files.{name}.size}}"))
+                }
                 Item::Placeholder(Placeholder::UserCacheDir(fallback)) => {
                     reified.push_str(&format!("{{scie.user.cache_dir={fallback}}}"))
                 }
@@ -90,7 +93,9 @@ impl EnvParser {
                 Item::Placeholder(Placeholder::ScieBindingEnv(ScieBindingEnv { binding, env })) => {
                     reified.push_str(&format!("{{scie.bindings.{binding}:{env}}}"))
                 }
+                Item::Placeholder(Placeholder::ScieLibc) => reified.push_str("{scie.libc}"),
                 Item::Placeholder(Placeholder::ScieLift) => reified.push_str("{scie.lift}"),
+                Item::Placeholder(Placeholder::ScieScript) => reified.push_str("{scie.script}"),
                 Item::Placeholder(Placeholder::SciePlatform) => reified.push_str("{scie.platform}"),
                 Item::Placeholder(Placeholder::SciePlatformArch) => {
                     reified.push_str("{scie.platform.arch}")
@@ -106,7 +111,26 @@ impl EnvParser {
     fn reify_env_var(&mut self, name: &str) -> Result<String, String> {
         let reified_env_name = self.reify_env(name)?;
         let parsed_env = parse_scie_env_placeholder(&reified_env_name)?;
-        let env_val = if self.key_stack.contains(&parsed_env.name) {
+        let env_val = if let Some(index) = self
+            .key_stack
+            .iter()
+            .position(|key| key == &parsed_env.name)
+        {
+            // A reference back to the env var we started calculating or to the env var currently
+            // being calculated reads the ambient value. Any other reference back into the stack is
+            // a cycle amongst the intermediate env vars that has no well-defined value.
+            if index > 0 && index + 1 < self.key_stack.len() {
+                let cycle = self.key_stack[index..]
+                    .iter()
+                    .chain(std::iter::once(&parsed_env.name))
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(format!(
+                    "The env var values form a reference cycle: {cycle}. An env var can only refer \
+                    to its own ambient value."
+                ));
+            }
             // If we're already calculating a Cmd env var value for `key`, we can only
             // pull references to that `key` needed to compute the value from the
             // ambient environment.
@@ -216,20 +240,12 @@ mod tests {
 
     #[test]
     fn multi_step_recurse() {
-        let env_parser = EnvParser::new(
-            &[
-                (
-                    EnvVar::Replace("PATH".to_string()),
-                    Some("foo:{scie.env.X}".to_string()),
-                ),
-                (
-                    EnvVar::Replace("X".to_string()),
-                    Some("{scie.env.PATH}:bar".to_string()),
-                ),
-            ]
-            .into_iter()
-            .collect::<IndexMap<_, _>>(),
-        );
+        let env = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(name, value)| (EnvVar::Replace(name.to_string()), Some(value.to_string())))
+                .collect::<IndexMap<_, _>>()
+        };
 
         let expected_path = env::var("PATH").unwrap();
         let expected = [
@@ -238,8 +254,54 @@ mod tests {
         ]
         .into_iter()
         .collect::<IndexMap<_, _>>();
+        assert_eq!(
+            expected,
+            EnvParser::new(&env(&[
+                ("PATH", "foo:{scie.env.X}"),
+                ("X", "{scie.env.PATH}:bar"),
+            ]))
+            .parse_env()
+            .unwrap()
+        );
+
+        // N.B.: References that do not lead back to an env var being calculated are resolved no
+        // matter the order the env vars are defined in.
+        let expected = [
+            ("PATH".to_string(), format!("foo:bar:baz:{expected_path}")),
+            ("X".to_string(), "bar".to_string()),
+            ("Y".to_string(), "bar:baz".to_string()),
+        ]
+        .into_iter()
+        .collect::<IndexMap<_, _>>();
+        assert_eq!(
+            expected,
+            EnvParser::new(&env(&[
+                ("PATH", "foo:{scie.env.Y}:{scie.env.PATH}"),
+                ("X", "bar"),
+                ("Y", "{scie.env.X}:baz"),
+            ]))
+            .parse_env()
+            .unwrap()
+        );
+        assert_eq!(
+            expected.into_iter().rev().collect::<IndexMap<_, _>>(),
+            EnvParser::new(&env(&[
+                ("Y", "{scie.env.X}:baz"),
+                ("X", "bar"),
+                ("PATH", "foo:{scie.env.Y}:{scie.env.PATH}"),
+            ]))
+            .parse_env()
+            .unwrap()
+        );
 
-        assert_eq!(expected, env_parser.parse_env().unwrap());
+        let error = EnvParser::new(&env(&[
+            ("A", "{scie.env.B}"),
+            ("B", "{scie.env.C}"),
+            ("C", "{scie.env.B}"),
+        ]))
+        .parse_env()
+        .unwrap_err();
+        assert!(error.contains("B -> C -> B"), "{error}");
     }
 
     #[test]
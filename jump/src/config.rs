@@ -1,13 +1,17 @@
 // Copyright 2022 Science project contributors.
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::borrow::Cow;
 use std::fmt::Formatter;
 use std::io::Write;
 
 use indexmap::IndexMap;
-use serde::de::{Error, Unexpected, Visitor};
+use serde::de::{Error, MapAccess, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
+use unicode_normalization::UnicodeNormalization as _;
+
+use crate::error::JumpError;
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub enum Compression {
@@ -110,12 +114,258 @@ impl<'de> Deserialize<'de> for FileType {
     }
 }
 
+/// The Unicode normalization form of file names.
+///
+/// The same logical name can be spelled with different code points; e.g.: macOS HFS+ file systems
+/// decompose accented characters (NFD) whereas Linux tools generally leave them composed (NFC).
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnicodeNormalization {
+    Nfc,
+    Nfd,
+    /// Leave names spelled as they are.
+    Preserve,
+}
+
+impl UnicodeNormalization {
+    pub(crate) fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self {
+            UnicodeNormalization::Nfc if !unicode_normalization::is_nfc(name) => {
+                Cow::Owned(name.nfc().collect())
+            }
+            UnicodeNormalization::Nfd if !unicode_normalization::is_nfd(name) => {
+                Cow::Owned(name.nfd().collect())
+            }
+            _ => Cow::Borrowed(name),
+        }
+    }
+}
+
 fn is_false(value: &bool) -> bool {
     !*value
 }
 
+fn is_not_true(value: &Option<bool>) -> bool {
+    value != &Some(true)
+}
+
+// N.B.: The order of the entries in these maps carries no meaning; so we serialize them with sorted
+// keys so that logically identical lift manifests serialize to identical bytes.
+fn serialize_sorted<V, S>(map: &IndexMap<String, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    V: Serialize,
+    S: Serializer,
+{
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _)| *key);
+    serializer.collect_map(entries)
+}
+
+/// Where a file that is not stored in the scie gets its bytes from.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FileSource {
+    /// The name of a binding command that writes the file's bytes to its stdout.
+    Binding(String),
+    /// The file's bytes are the `size` bytes starting at `offset` in the artifact at `url` or else
+    /// at the same offset in the 1st of its `mirrors` that can be fetched.
+    EntryRange {
+        url: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        mirrors: Vec<String>,
+        #[serde(default)]
+        offset: usize,
+    },
+}
+
+impl std::fmt::Display for FileSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileSource::Binding(binding) => write!(f, "{binding}"),
+            FileSource::EntryRange { url, offset: 0, .. } => write!(f, "{url}"),
+            FileSource::EntryRange { url, offset, .. } => write!(f, "{url} at offset {offset}"),
+        }
+    }
+}
+
+/// How files located by URL are fetched at runtime.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Fetch {
+    /// The number of times to retry a failed fetch.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// The delay before the 1st retry in milliseconds; doubled for each subsequent retry.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backoff_ms: Option<u64>,
+    /// The name of an environment variable holding a bearer token to authenticate fetches with.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearer_token_env: Option<String>,
+}
+
+/// How the permissions and metadata of extracted files are set.
+///
+/// By default, files extracted from archives keep the rwx permissions recorded in the archive but
+/// lose any setuid, setgid or sticky bits and any extended attributes.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Permissions {
+    /// Restrict all extracted files and directories to the current user; directories are made
+    /// `0700` and files lose all group and other permission bits.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub private: bool,
+    /// An octal umask, like `"022"`, whose group and other permission bits are cleared from all
+    /// extracted files and directories.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub umask: Option<String>,
+    /// Preserve the setuid, setgid and sticky bits recorded in archives instead of stripping them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub setuid: bool,
+    /// Restore the extended attributes recorded in tar archives, including any POSIX ACLs, instead
+    /// of dropping them. The macOS quarantine flag is still dropped unless `quarantine` is set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub xattrs: bool,
+    /// Restore the macOS quarantine flags recorded in tar archives instead of dropping them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub quarantine: bool,
+    /// Remove the macOS quarantine flag from extracted files that carry one anyway; e.g.: because
+    /// the scie was run by an application that quarantines the files its processes create.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub strip_quarantine: bool,
+    /// Ad-hoc code sign extracted Mach-O executables and libraries that lack a code signature,
+    /// since macOS refuses to run unsigned code on Apple Silicon.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub codesign: bool,
+    /// Restore the default SELinux security contexts of extracted files with `restorecon`, so
+    /// that confined domains may use them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub selinux: bool,
+}
+
+impl Permissions {
+    pub(crate) fn umask(&self) -> Result<u32, String> {
+        let Some(umask) = self.umask.as_deref() else {
+            return Ok(0);
+        };
+        match u32::from_str_radix(umask.trim_start_matches("0o"), 8) {
+            Ok(mask) if mask <= 0o077 => Ok(mask),
+            Ok(_) => Err(format!(
+                "The permissions umask {umask} would clear the owner's permission bits; only \
+                group and other permission bits can be cleared."
+            )),
+            Err(e) => Err(format!(
+                "The permissions umask {umask} is not an octal number: {e}"
+            )),
+        }
+    }
+}
+
+/// How the files a scie extracts to the `nce` cache are kept intact over time.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Integrity {
+    /// How often to re-hash the files extracted to the `nce` cache; e.g.: `"7d"`. Extracted files
+    /// that no longer match their hashes are re-extracted. By default, files are only re-hashed
+    /// when their size or modification time changes and extracted directories are not re-hashed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverify: Option<String>,
+    /// What to do about extracted files whose contents no longer match their hashes; by default
+    /// they are re-extracted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_mutation: Option<OnMutation>,
+}
+
+/// What to do about files extracted to the `nce` cache whose contents were changed since.
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnMutation {
+    /// Re-extract the files, noting which were mutated.
+    #[default]
+    ReExtract,
+    /// Fail the boot, listing the files that were mutated; so a scie only ever runs exactly the
+    /// files it was packed with.
+    Fail,
+}
+
+#[cfg(not(target_os = "wasi"))]
+impl Integrity {
+    pub(crate) fn reverify(&self) -> Result<Option<std::time::Duration>, String> {
+        self.reverify.as_deref().map(parse_interval).transpose()
+    }
+}
+
+#[cfg(not(target_os = "wasi"))]
+/// Parses an interval given in seconds or with a unit suffix; e.g.: `3600`, `90m`, `12h`, `7d` or
+/// `2w`.
+pub(crate) fn parse_interval(value: &str) -> Result<std::time::Duration, String> {
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "The interval {value:?} has an unknown unit {unit:?}; expected one of s, m, h, d \
+                or w."
+            ))
+        }
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(seconds))
+        .map(std::time::Duration::from_secs)
+        .ok_or_else(|| {
+            format!("The interval {value:?} is not a whole number with an optional unit.")
+        })
+}
+
+/// Where and how the boot-pack writes the zips it creates from directories.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Zips {
+    /// The directory to write zips to, relative to the lift manifest; by default each zip is
+    /// written next to the directory it holds.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    /// The file name of each zip, in which `{name}` is replaced by the name of the directory and
+    /// `{hash}` by the sha256 hash of the zip. By default, the zip is named after the directory
+    /// with its extension, if any, replaced by `.zip`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Replace existing files instead of failing.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub overwrite: bool,
+}
+
+/// A content-defined chunk of a file the boot-pack stored in pieces; see `boot-pack --chunk`.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    /// The offset of the chunk's bytes from the start of the scie.
+    pub offset: usize,
+    pub size: usize,
+    pub hash: String,
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
 pub struct File {
     pub name: String,
     #[serde(default)]
@@ -124,19 +374,48 @@ pub struct File {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<usize>,
+    /// The total size of the files an archive unpacks to; recorded by the boot-pack.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extracted_size: Option<usize>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// The chunks holding the file's bytes, in order, when it is stored in pieces instead of at
+    /// `offset`; recorded by the boot-pack.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<Chunk>,
     #[serde(default)]
     pub hash: Option<String>,
     #[serde(default, rename = "type")]
     pub file_type: Option<FileType>,
+    // N.B.: Not executable is the default; so we only serialize `true` to keep the lift manifest
+    // canonical.
     #[serde(default)]
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "is_not_true")]
     pub executable: Option<bool>,
     #[serde(default)]
     #[serde(skip_serializing_if = "is_false")]
     pub eager_extract: bool,
     #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub execute_in_place: bool,
+    #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub source: Option<String>,
+    pub source: Option<FileSource>,
+    /// Where this file's contents were originally obtained from.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// The version of the third party software this file contains.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The license of this file's contents; ideally as an SPDX license expression.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -198,8 +477,7 @@ impl<'de> Deserialize<'de> for EnvVar {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Cmd {
     pub exe: String,
     #[serde(default)]
@@ -210,28 +488,167 @@ pub struct Cmd {
     pub env: IndexMap<EnvVar, Option<String>>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub additional_files: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled_if: Option<EnabledIf>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_args: Option<UserArgs>,
+    /// The number of seconds the command may run for before it is killed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// How a binding command that fails is retried.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<Retry>,
+    /// Run the command with only the environment variables its "env" sets plus a small set of
+    /// essential ones, like `PATH` and `HOME`, inherited from the ambient environment.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub clean_env: bool,
+    /// Directories to add to the front of the `PATH` the command runs with, in order.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prepend_path: Vec<String>,
+    /// Run this command when the scie is used as the interpreter of a script; i.e.: from the
+    /// script's shebang line.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub shebang: bool,
+    /// The command launches a GUI application; so a Windows scie need not open a console window.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub gui: bool,
+    // N.B.: This preserves any extra metadata other tools may have stored in a command.
+    #[serde(flatten, serialize_with = "serialize_sorted")]
+    pub other: IndexMap<String, Value>,
 }
 
+impl Cmd {
+    /// Returns `false` if the command is gated behind a feature flag that is not turned on.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled_if
+            .as_ref()
+            .map(EnabledIf::holds)
+            .unwrap_or(true)
+    }
+}
+
+/// How a binding command that fails, say because the network it needs is flaky, is retried.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Retry {
+    /// The number of times to retry the binding command after it fails.
+    pub retries: u32,
+    /// The delay before the 1st retry in milliseconds; doubled for each subsequent retry.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backoff_ms: Option<u64>,
+}
+
+/// The condition under which a command that ships disabled is enabled.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+pub struct EnabledIf {
+    /// An environment variable that turns the command on when set to a value other than an empty
+    /// string, `0` or `false`.
+    pub env: String,
+}
+
+impl EnabledIf {
+    fn holds(&self) -> bool {
+        std::env::var_os(&self.env).map_or(false, |value| {
+            !(value.is_empty() || value == "0" || value.eq_ignore_ascii_case("false"))
+        })
+    }
+}
+
+/// A positional argument a command accepts from the user.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Positional {
+    /// The name the argument goes by in usage messages.
+    pub name: String,
+    /// The values the argument may take; any value is allowed if empty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+}
+
+/// A flag a command accepts from the user.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Flag {
+    /// Whether the flag takes a value; either as `--flag=value` or as the following argument.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub value: bool,
+    /// The values the flag may take; any value is allowed if empty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+}
+
+/// The arguments a command accepts from the user; checked before the command is run.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UserArgs {
+    /// The fewest positional arguments the command accepts.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    /// The most positional arguments the command accepts.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+    /// Describes the leading positional arguments.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub positional: Vec<Positional>,
+    /// The flags the command accepts. If there are none, flags are not checked and every
+    /// argument counts as a positional argument.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    #[serde(serialize_with = "serialize_sorted")]
+    pub flags: IndexMap<String, Flag>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Jump {
     pub size: usize,
     #[serde(default)]
     pub version: String,
+    /// The oldest scie-jump version that can boot the scie, as recorded by the boot-pack.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Boot {
     pub commands: IndexMap<String, Cmd>,
     #[serde(default)]
     #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    #[serde(serialize_with = "serialize_sorted")]
     pub bindings: IndexMap<String, Cmd>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+impl Boot {
+    /// Splits the `<name>.<key>` of a `{scie.bindings.<name>.<key>}` placeholder into the name of
+    /// the binding and the key of its output; preferring the longest binding name that matches.
+    pub(crate) fn binding_output<'a>(&self, placeholder: &'a str) -> Option<(&'a str, &'a str)> {
+        placeholder
+            .match_indices('.')
+            .rev()
+            .map(|(index, _)| (&placeholder[..index], &placeholder[index + 1..]))
+            .find(|(binding, key)| !key.is_empty() && self.bindings.contains_key(*binding))
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Lift {
     pub name: String,
     #[serde(default)]
@@ -240,15 +657,40 @@ pub struct Lift {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base: Option<String>,
+    /// Where files being fetched or extracted are staged; the system temporary directory by
+    /// default.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmp: Option<String>,
     pub files: Vec<File>,
     pub boot: Boot,
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub load_dotenv: Option<bool>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boot_timing: Option<bool>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch: Option<Fetch>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Permissions>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unicode_normalization: Option<UnicodeNormalization>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_junk: Option<bool>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zips: Option<Zips>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<Integrity>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Scie {
     pub lift: Lift,
     #[serde(default)]
@@ -292,25 +734,97 @@ impl Default for Fmt {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Other {
-    #[serde(flatten)]
+    #[serde(flatten, serialize_with = "serialize_sorted")]
     other: IndexMap<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// How to treat fields in a lift manifest that are not recognized.
+///
+/// Unrecognized fields are always allowed at the top level of the lift manifest alongside the
+/// "scie" field since that is where applications store their own metadata.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnknownFields {
+    Ignore,
+    Warn,
+    Deny,
+}
+
+impl UnknownFields {
+    /// Returns `UnknownFields::Deny` if `SCIE_STRICT=1` is set in the environment and the given
+    /// policy otherwise.
+    pub fn or_strict_from_env(self) -> Self {
+        if std::env::var_os("SCIE_STRICT").map_or(false, |value| value == "1") {
+            UnknownFields::Deny
+        } else {
+            self
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
 pub struct Config {
     pub scie: Scie,
     #[serde(flatten)]
     pub(crate) other: Option<Other>,
 }
 
+struct ConfigVisitor;
+
+impl<'de> Visitor<'de> for ConfigVisitor {
+    type Value = Config;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "a scie lift manifest")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut scie = None;
+        let mut other = IndexMap::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if "scie" == key {
+                if scie.is_some() {
+                    return Err(A::Error::duplicate_field("scie"));
+                }
+                scie = Some(map.next_value()?);
+            } else {
+                other.insert(key, map.next_value()?);
+            }
+        }
+        Ok(Config {
+            scie: scie.ok_or_else(|| A::Error::missing_field("scie"))?,
+            other: Some(Other { other }),
+        })
+    }
+}
+
+// N.B.: A derived impl would buffer the whole manifest to fill the flattened `other` field before
+// deserializing `scie` from that buffer. Visiting the top level map by hand deserializes `scie`
+// straight from the manifest bytes and only buffers the (rare) extra top level fields.
+//
+// This is not zero-copy parsing though: strings like command exes, args and env values and file
+// names and hashes are still copied out of the manifest bytes. Borrowing them as `Cow<'a, str>`
+// would give `Config` and the public `Lift` that holds its `Boot` a lifetime tied to those bytes,
+// which the packing and repacking APIs outlive.
+impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ConfigVisitor)
+    }
+}
+
 impl Config {
     pub const MAXIMUM_CONFIG_SIZE: usize = 0xFFFF;
     #[cfg(target_family = "windows")]
     const NEWLINE: &'static [u8] = b"\r\n";
 
-    #[cfg(target_family = "unix")]
+    #[cfg(not(target_family = "windows"))]
     const NEWLINE: &'static [u8] = b"\n";
 
     pub fn new<L: Into<Lift>>(jump: Jump, lift: L, other: Option<Other>) -> Self {
@@ -323,17 +837,64 @@ impl Config {
         }
     }
 
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
-        let config: Self = serde_json::from_slice(data)
-            .map_err(|e| format!("Failed to decode scie lift manifest: {e}"))?;
+    pub fn parse(data: &[u8], unknown_fields: UnknownFields) -> Result<Self, JumpError> {
+        // N.B.: The manifest is parsed in a single pass over `data`, noting any unknown fields along
+        // the way when they are to be reported.
+        let mut unknown = vec![];
+        let mut deserializer = serde_json::Deserializer::from_slice(data);
+        let config = if UnknownFields::Ignore == unknown_fields {
+            Self::deserialize(&mut deserializer)
+        } else {
+            serde_ignored::deserialize(&mut deserializer, |path| unknown.push(path.to_string()))
+        }
+        .and_then(|config| deserializer.end().map(|()| config))
+        .map_err(|e| JumpError::Config(format!("Failed to decode scie lift manifest: {e}")))?;
+        if UnknownFields::Ignore != unknown_fields {
+            config
+                .check_unknown_fields(unknown, unknown_fields)
+                .map_err(JumpError::Config)?;
+        }
         Ok(config)
     }
 
-    pub fn serialize<W: Write>(&self, mut stream: W, fmt: Fmt) -> Result<(), String> {
+    fn check_unknown_fields(
+        &self,
+        mut unknown: Vec<String>,
+        unknown_fields: UnknownFields,
+    ) -> Result<(), String> {
+        // Commands preserve unrecognized fields instead of ignoring them.
+        let boot = &self.scie.lift.boot;
+        for (kind, cmds) in [("commands", &boot.commands), ("bindings", &boot.bindings)] {
+            for (name, cmd) in cmds {
+                for field in cmd.other.keys() {
+                    unknown.push(format!("scie.lift.boot.{kind}.{name}.{field}"));
+                }
+            }
+        }
+        unknown.sort();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        let message = format!(
+            "The lift manifest contains unrecognized fields:\n{fields}",
+            fields = unknown
+                .iter()
+                .map(|field| format!("+ {field}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        if UnknownFields::Deny == unknown_fields {
+            return Err(message);
+        }
+        warn!("{message}");
+        Ok(())
+    }
+
+    pub fn serialize<W: Write>(&self, mut stream: W, fmt: Fmt) -> Result<(), JumpError> {
         let mut write_bytes = |bytes| {
             stream
                 .write_all(bytes)
-                .map_err(|e| format!("Failed to write scie lift manifest: {e}"))
+                .map_err(|e| JumpError::io("Failed to write scie lift manifest", e))
         };
 
         if fmt.leading_newline {
@@ -345,7 +906,7 @@ impl Config {
         } else {
             serde_json::to_vec(self)
         }
-        .map_err(|e| format!("Failed to serialize scie lift manifest: {e}"))?;
+        .map_err(|e| JumpError::Config(format!("Failed to serialize scie lift manifest: {e}")))?;
         write_bytes(body.as_slice())?;
 
         if fmt.trailing_newline {
@@ -359,7 +920,10 @@ impl Config {
 mod tests {
     use indexmap::IndexMap;
 
-    use super::{ArchiveType, Boot, Cmd, Compression, Config, EnvVar, File, Jump, Lift};
+    use super::{
+        parse_interval, ArchiveType, Boot, Cmd, Compression, Config, EnvVar, File, FileSource, Fmt,
+        Jump, Lift, UnknownFields,
+    };
     use crate::config::FileType;
 
     #[test]
@@ -370,6 +934,7 @@ mod tests {
                 Jump {
                     version: "0.1.0".to_string(),
                     size: 37,
+                    ..Default::default()
                 },
                 Lift {
                     base: None,
@@ -378,33 +943,54 @@ mod tests {
                             name: "pants-client".to_string(),
                             key: None,
                             size: Some(1137),
+                            extracted_size: None,
+                            offset: None,
+                            chunks: vec![],
                             hash: Some("abc".to_string()),
                             file_type: Some(FileType::Blob),
                             executable: Some(true),
                             eager_extract: true,
+                            execute_in_place: false,
                             source: None,
+                            source_url: None,
+                            version: None,
+                            license: None,
                         },
                         File {
                             name: "python".to_string(),
                             key: None,
                             size: Some(123),
+                            extracted_size: None,
+                            offset: None,
+                            chunks: vec![],
                             hash: Some("345".to_string()),
                             file_type: Some(FileType::Archive(ArchiveType::CompressedTar(
                                 Compression::Zstd
                             ))),
                             executable: None,
                             eager_extract: false,
+                            execute_in_place: false,
                             source: None,
+                            source_url: None,
+                            version: None,
+                            license: None,
                         },
                         File {
                             name: "foo.zip".to_string(),
                             key: None,
                             size: Some(42),
+                            extracted_size: None,
+                            offset: None,
+                            chunks: vec![],
                             hash: Some("def".to_string()),
                             file_type: Some(FileType::Archive(ArchiveType::Zip)),
                             executable: None,
                             eager_extract: false,
+                            execute_in_place: false,
                             source: None,
+                            source_url: None,
+                            version: None,
+                            license: None,
                         }
                     ],
                     boot: Boot {
@@ -412,7 +998,6 @@ mod tests {
                             "".to_string(),
                             Cmd {
                                 exe: "bob/exe".to_string(),
-                                args: Default::default(),
                                 env: [
                                     (
                                         EnvVar::Default("DEFAULT".to_string()),
@@ -427,7 +1012,7 @@ mod tests {
                                 ]
                                 .into_iter()
                                 .collect(),
-                                description: None
+                                ..Default::default()
                             }
                         )]
                         .into_iter()
@@ -436,7 +1021,15 @@ mod tests {
                     },
                     name: "test".to_string(),
                     description: None,
-                    load_dotenv: Some(false)
+                    load_dotenv: Some(false),
+                    boot_timing: None,
+                    fetch: None,
+                    permissions: None,
+                    unicode_normalization: None,
+                    keep_junk: None,
+                    zips: None,
+                    integrity: None,
+                    tmp: None,
                 },
                 None,
             ))
@@ -477,7 +1070,8 @@ mod tests {
                                         "exe":"{python}/bin/python",
                                         "args": [
                                             "{app}"
-                                        ]
+                                        ],
+                                        "cwd": "{app}"
                                     }
                                 }
                             }
@@ -489,4 +1083,671 @@ mod tests {
             .unwrap()
         )
     }
+
+    #[test]
+    fn test_file_source() {
+        let config = Config::parse(
+            br#"
+            {
+                "scie": {
+                    "lift": {
+                        "name": "example",
+                        "files": [
+                            {"name": "tool", "source": "fetch"},
+                            {"name": "lib", "source": {"url": "https://example.com/b", "offset": 42}},
+                            {"name": "data", "source": {"url": "https://example.com/data"}},
+                            {
+                                "name": "mirrored",
+                                "source": {
+                                    "url": "https://example.com/m",
+                                    "mirrors": ["https://mirror.example.com/m"]
+                                }
+                            }
+                        ],
+                        "boot": {"commands": {"": {"exe": "{tool}"}}}
+                    }
+                }
+            }
+            "#,
+            UnknownFields::Deny,
+        )
+        .unwrap();
+        let files = &config.scie.lift.files;
+        assert_eq!(
+            Some(FileSource::Binding("fetch".to_string())),
+            files[0].source
+        );
+        assert_eq!(
+            Some(FileSource::EntryRange {
+                url: "https://example.com/b".to_string(),
+                mirrors: vec![],
+                offset: 42
+            }),
+            files[1].source
+        );
+        assert_eq!(
+            serde_json::json!({"url": "https://example.com/b", "offset": 42}),
+            serde_json::to_value(&files[1].source).unwrap()
+        );
+        assert_eq!(
+            Some(FileSource::EntryRange {
+                url: "https://example.com/data".to_string(),
+                mirrors: vec![],
+                offset: 0
+            }),
+            files[2].source
+        );
+        assert_eq!(
+            Some(FileSource::EntryRange {
+                url: "https://example.com/m".to_string(),
+                mirrors: vec!["https://mirror.example.com/m".to_string()],
+                offset: 0
+            }),
+            files[3].source
+        );
+    }
+
+    #[test]
+    fn test_canonical_serialization() {
+        let serialize = |manifest: &str| {
+            let config = Config::parse(manifest.as_bytes(), UnknownFields::Ignore).unwrap();
+            let mut serialized = vec![];
+            config.serialize(&mut serialized, Fmt::new()).unwrap();
+            String::from_utf8(serialized).unwrap()
+        };
+        let canonical = serialize(
+            r#"
+            {
+                "scie": {
+                    "lift": {
+                        "name": "example",
+                        "files": [
+                            {"name": "tool", "size": 1, "hash": "abc", "type": "blob", "executable": false}
+                        ],
+                        "boot": {
+                            "commands": {
+                                "": {"exe": "{tool}", "x-b": 1, "x-a": {"z": 1, "y": 2}}
+                            },
+                            "bindings": {"b": {"exe": "b"}, "a": {"exe": "a"}}
+                        }
+                    },
+                    "jump": {"size": 1, "version": "0.1.0"}
+                },
+                "zz": 1,
+                "aa": {"k": 2, "j": 1}
+            }
+            "#,
+        );
+        assert_eq!(
+            canonical,
+            serialize(
+                r#"{"aa":{"j":1,"k":2},"zz":1,"scie":{"jump":{"version":"0.1.0","size":1},
+                "lift":{"boot":{"bindings":{"a":{"exe":"a"},"b":{"exe":"b"}},
+                "commands":{"":{"x-a":{"y":2,"z":1},"exe":"{tool}","x-b":1}}},
+                "files":[{"type":"blob","hash":"abc","size":1,"name":"tool"}],"name":"example"}}}"#
+            )
+        );
+        assert!(!canonical.contains("executable"));
+        assert!(canonical.find(r#""a":"#).unwrap() < canonical.find(r#""b":"#).unwrap());
+        assert!(canonical.find(r#""x-a":"#).unwrap() < canonical.find(r#""x-b":"#).unwrap());
+        assert!(canonical.find(r#""aa":"#).unwrap() < canonical.find(r#""zz":"#).unwrap());
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let manifest = br#"
+        {
+            "scie": {
+                "lift": {
+                    "name": "example",
+                    "files": [{"name": "app.zip", "allways_extract": true}],
+                    "boot": {"commands": {"": {"exe": "{app.zip}/app", "argz": []}}}
+                }
+            },
+            "custom": {"metadata": 42}
+        }
+        "#;
+        Config::parse(manifest, UnknownFields::Ignore).unwrap();
+        Config::parse(manifest, UnknownFields::Warn).unwrap();
+        assert_eq!(
+            "The lift manifest contains unrecognized fields:\n\
+            + scie.lift.boot.commands..argz\n\
+            + scie.lift.files.0.allways_extract",
+            Config::parse(manifest, UnknownFields::Deny)
+                .unwrap_err()
+                .to_string()
+        );
+
+        assert!(Config::parse(br#"{"custom": 42}"#, UnknownFields::Deny)
+            .unwrap_err()
+            .to_string()
+            .contains("missing field `scie`"));
+        assert!(Config::parse(
+            br#"{"scie": {"lift": {"name": "a", "files": [], "boot": {"commands": {}}}}, "scie": {}}"#,
+            UnknownFields::Warn
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("duplicate field `scie`"));
+    }
+
+    #[test]
+    fn test_round_trip_other() {
+        let manifest = r#"
+        {
+            "scie": {
+                "lift": {
+                    "name": "example",
+                    "files": [{"name": "app.zip", "hash": "abc", "type": "zip"}],
+                    "boot": {
+                        "commands": {
+                            "": {
+                                "exe": "{app.zip}/app",
+                                "pex": {"entry_point": "app:main"}
+                            }
+                        }
+                    }
+                },
+                "jump": {"size": 42, "version": "0.1.0"}
+            },
+            "custom": {"metadata": 42}
+        }
+        "#;
+        let config = Config::parse(manifest.as_bytes(), UnknownFields::Ignore).unwrap();
+        let mut serialized = vec![];
+        config
+            .serialize(&mut serialized, Default::default())
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(manifest).unwrap(),
+            serde_json::from_slice::<serde_json::Value>(&serialized).unwrap()
+        );
+    }
+
+    #[test]
+    fn binding_output() {
+        let boot: Boot = serde_json::from_str(
+            r#"{"commands": {}, "bindings": {"venv": {"exe": "a"}, "venv.b": {"exe": "b"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(Some(("venv", "path")), boot.binding_output("venv.path"));
+        assert_eq!(
+            Some(("venv", "python.version")),
+            boot.binding_output("venv.python.version")
+        );
+        assert_eq!(Some(("venv.b", "path")), boot.binding_output("venv.b.path"));
+        assert_eq!(Some(("venv", "b")), boot.binding_output("venv.b"));
+        assert_eq!(None, boot.binding_output("venv"));
+        assert_eq!(None, boot.binding_output("venv."));
+        assert_eq!(None, boot.binding_output("other.path"));
+    }
+
+    #[test]
+    fn intervals() {
+        let secs = std::time::Duration::from_secs;
+        assert_eq!(Ok(secs(0)), parse_interval("0"));
+        assert_eq!(Ok(secs(3600)), parse_interval("3600"));
+        assert_eq!(Ok(secs(45)), parse_interval("45s"));
+        assert_eq!(Ok(secs(90 * 60)), parse_interval("90m"));
+        assert_eq!(Ok(secs(12 * 60 * 60)), parse_interval("12h"));
+        assert_eq!(Ok(secs(7 * 24 * 60 * 60)), parse_interval("7d"));
+        assert_eq!(Ok(secs(14 * 24 * 60 * 60)), parse_interval("2w"));
+        assert!(parse_interval("7 days").is_err());
+        assert!(parse_interval("d").is_err());
+        assert!(parse_interval("-1d").is_err());
+        assert!(parse_interval("99999999999999999999w").is_err());
+    }
+
+    mod proptests {
+        use indexmap::IndexMap;
+        use proptest::collection::vec;
+        use proptest::option;
+        use proptest::prelude::*;
+        use proptest::sample::select;
+        use serde_json::Value;
+
+        use crate::config::{
+            ArchiveType, Boot, Chunk, Cmd, Compression, Config, EnabledIf, EnvVar, Fetch, File,
+            FileSource, FileType, Flag, Fmt, Integrity, Jump, Lift, OnMutation, Other, Permissions,
+            Positional, Retry, Scie, UnicodeNormalization, UnknownFields, UserArgs, Zips,
+        };
+        use crate::footer::scie_magic;
+        use crate::pack::lift_zip;
+
+        // N.B.: The generators only produce canonical values; i.e.: values that serialize to a
+        // lift manifest that deserializes back to the same value. For example, a file is never
+        // `"executable": false` since that is the default and so is not serialized.
+
+        fn name() -> impl Strategy<Value = String> {
+            "[a-z][a-z0-9_.-]{0,8}"
+        }
+
+        fn env_name() -> impl Strategy<Value = String> {
+            "[A-Z_][A-Z0-9_]{0,8}"
+        }
+
+        fn text() -> impl Strategy<Value = String> {
+            "\\PC{0,16}"
+        }
+
+        fn url() -> impl Strategy<Value = String> {
+            name().prop_map(|name| format!("https://example.org/{name}"))
+        }
+
+        // Text interspersed with placeholders as found in command exes, args, env values and the
+        // like.
+        fn placeholder_text() -> impl Strategy<Value = String> {
+            vec(
+                prop_oneof![
+                    "[a-zA-Z0-9 /=:._-]{1,8}",
+                    Just("{{".to_string()),
+                    Just("{scie}".to_string()),
+                    Just("{scie.base}".to_string()),
+                    Just("{scie.lift}".to_string()),
+                    Just("{scie.platform.arch}".to_string()),
+                    name().prop_map(|name| format!("{{{name}}}")),
+                    name().prop_map(|name| format!("{{scie.files.{name}}}")),
+                    name().prop_map(|name| format!("{{scie.files.{name}.hash}}")),
+                    (env_name(), name())
+                        .prop_map(|(env, default)| format!("{{scie.env.{env}={default}}}")),
+                    (name(), env_name())
+                        .prop_map(|(binding, env)| format!("{{scie.bindings.{binding}:{env}}}")),
+                ],
+                0..4,
+            )
+            .prop_map(|items| items.concat())
+        }
+
+        fn json_value() -> impl Strategy<Value = Value> {
+            prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::from),
+                any::<i64>().prop_map(Value::from),
+                text().prop_map(Value::from),
+                vec(text(), 0..3).prop_map(Value::from),
+            ]
+        }
+
+        fn other() -> impl Strategy<Value = IndexMap<String, Value>> {
+            vec(("x-[a-z]{1,6}", json_value()), 0..3)
+                .prop_map(|entries| entries.into_iter().collect())
+        }
+
+        fn file_type() -> impl Strategy<Value = FileType> {
+            select(vec![
+                FileType::Blob,
+                FileType::Directory,
+                FileType::Archive(ArchiveType::Zip),
+                FileType::Archive(ArchiveType::Tar),
+                FileType::Archive(ArchiveType::CompressedTar(Compression::Bzip2)),
+                FileType::Archive(ArchiveType::CompressedTar(Compression::Gzip)),
+                FileType::Archive(ArchiveType::CompressedTar(Compression::Xz)),
+                FileType::Archive(ArchiveType::CompressedTar(Compression::Zlib)),
+                FileType::Archive(ArchiveType::CompressedTar(Compression::Zstd)),
+            ])
+        }
+
+        fn file_source() -> impl Strategy<Value = FileSource> {
+            prop_oneof![
+                name().prop_map(FileSource::Binding),
+                (url(), vec(url(), 0..2), any::<u32>()).prop_map(|(url, mirrors, offset)| {
+                    FileSource::EntryRange {
+                        url,
+                        mirrors,
+                        offset: offset as usize,
+                    }
+                }),
+            ]
+        }
+
+        fn size() -> impl Strategy<Value = Option<usize>> {
+            option::of(any::<u32>().prop_map(|size| size as usize))
+        }
+
+        fn file() -> impl Strategy<Value = File> {
+            (
+                (name(), option::of(name()), size(), size(), size()),
+                (
+                    option::of("[0-9a-f]{64}"),
+                    option::of(file_type()),
+                    vec(
+                        (any::<usize>(), any::<usize>(), "[0-9a-f]{64}")
+                            .prop_map(|(offset, size, hash)| Chunk { offset, size, hash }),
+                        0..3,
+                    ),
+                ),
+                (any::<bool>(), any::<bool>(), any::<bool>()),
+                (
+                    option::of(file_source()),
+                    option::of(url()),
+                    option::of("[0-9][0-9.]{0,6}"),
+                    option::of(select(vec!["Apache-2.0", "MIT OR Apache-2.0"])),
+                ),
+            )
+                .prop_map(
+                    |(
+                        (name, key, size, extracted_size, offset),
+                        (hash, file_type, chunks),
+                        (executable, eager_extract, execute_in_place),
+                        (source, source_url, version, license),
+                    )| File {
+                        name,
+                        key,
+                        size,
+                        extracted_size,
+                        offset,
+                        chunks,
+                        hash,
+                        file_type,
+                        executable: executable.then_some(true),
+                        eager_extract,
+                        execute_in_place,
+                        source,
+                        source_url,
+                        version,
+                        license: license.map(str::to_string),
+                    },
+                )
+        }
+
+        fn user_args() -> impl Strategy<Value = UserArgs> {
+            (
+                option::of(0..4_usize),
+                option::of(0..8_usize),
+                vec(
+                    (name(), vec(name(), 0..3))
+                        .prop_map(|(name, choices)| Positional { name, choices }),
+                    0..3,
+                ),
+                vec(
+                    ("--[a-z]{1,8}", any::<bool>(), vec(name(), 0..3))
+                        .prop_map(|(flag, value, choices)| (flag, Flag { value, choices })),
+                    0..3,
+                ),
+            )
+                .prop_map(|(min, max, positional, flags)| UserArgs {
+                    min,
+                    max,
+                    positional,
+                    flags: flags.into_iter().collect(),
+                })
+        }
+
+        fn cmd() -> impl Strategy<Value = Cmd> {
+            let env_var = prop_oneof![
+                env_name().prop_map(EnvVar::Default),
+                env_name().prop_map(EnvVar::Replace),
+            ];
+            (
+                (
+                    placeholder_text(),
+                    vec(placeholder_text(), 0..4),
+                    vec((env_var, option::of(placeholder_text())), 0..3),
+                    option::of(placeholder_text()),
+                    vec(name(), 0..2),
+                ),
+                (
+                    option::of(text()),
+                    option::of(env_name().prop_map(|env| EnabledIf { env })),
+                    option::of(user_args()),
+                    option::of(any::<u64>()),
+                    option::of((any::<u32>(), option::of(any::<u64>())).prop_map(
+                        |(retries, backoff_ms)| Retry {
+                            retries,
+                            backoff_ms,
+                        },
+                    )),
+                ),
+                (
+                    any::<bool>(),
+                    vec(placeholder_text(), 0..2),
+                    any::<bool>(),
+                    any::<bool>(),
+                    other(),
+                ),
+            )
+                .prop_map(
+                    |(
+                        (exe, args, env, cwd, additional_files),
+                        (description, enabled_if, user_args, timeout, retry),
+                        (clean_env, prepend_path, shebang, gui, other),
+                    )| Cmd {
+                        exe,
+                        args,
+                        env: env.into_iter().collect(),
+                        cwd,
+                        additional_files,
+                        description,
+                        enabled_if,
+                        user_args,
+                        timeout,
+                        retry,
+                        clean_env,
+                        prepend_path,
+                        shebang,
+                        gui,
+                        other,
+                    },
+                )
+        }
+
+        fn boot() -> impl Strategy<Value = Boot> {
+            (
+                vec((prop_oneof![Just(String::new()), name()], cmd()), 1..4),
+                vec((name(), cmd()), 0..3),
+            )
+                .prop_map(|(commands, bindings)| Boot {
+                    commands: commands.into_iter().collect(),
+                    bindings: bindings.into_iter().collect(),
+                })
+        }
+
+        fn fetch() -> impl Strategy<Value = Fetch> {
+            (
+                option::of(any::<u32>()),
+                option::of(any::<u64>()),
+                option::of(env_name()),
+            )
+                .prop_map(|(retries, backoff_ms, bearer_token_env)| Fetch {
+                    retries,
+                    backoff_ms,
+                    bearer_token_env,
+                })
+        }
+
+        fn permissions() -> impl Strategy<Value = Permissions> {
+            (any::<bool>(), option::of("0[0-7]{2}"), [any::<bool>(); 6]).prop_map(
+                |(
+                    private,
+                    umask,
+                    [setuid, xattrs, quarantine, strip_quarantine, codesign, selinux],
+                )| Permissions {
+                    private,
+                    umask,
+                    setuid,
+                    xattrs,
+                    quarantine,
+                    strip_quarantine,
+                    codesign,
+                    selinux,
+                },
+            )
+        }
+
+        fn zips() -> impl Strategy<Value = Zips> {
+            (
+                option::of("[a-z][a-z0-9/]{0,8}"),
+                option::of(select(vec![
+                    "{name}.zip",
+                    "{name}-{hash}.zip",
+                    "{hash}.zip",
+                ])),
+                any::<bool>(),
+            )
+                .prop_map(|(dir, name, overwrite)| Zips {
+                    dir,
+                    name: name.map(str::to_string),
+                    overwrite,
+                })
+        }
+
+        fn integrity() -> impl Strategy<Value = Integrity> {
+            (
+                option::of("[1-9][0-9]{0,2}[smhdw]?"),
+                option::of(select(vec![OnMutation::ReExtract, OnMutation::Fail])),
+            )
+                .prop_map(|(reverify, on_mutation)| Integrity {
+                    reverify,
+                    on_mutation,
+                })
+        }
+
+        fn lift() -> impl Strategy<Value = Lift> {
+            (
+                (
+                    name(),
+                    option::of(text()),
+                    option::of(placeholder_text()),
+                    option::of(placeholder_text()),
+                    vec(file(), 0..4),
+                    boot(),
+                ),
+                (
+                    [
+                        option::of(any::<bool>()),
+                        option::of(any::<bool>()),
+                        option::of(any::<bool>()),
+                    ],
+                    option::of(fetch()),
+                    option::of(permissions()),
+                    option::of(select(vec![
+                        UnicodeNormalization::Nfc,
+                        UnicodeNormalization::Nfd,
+                        UnicodeNormalization::Preserve,
+                    ])),
+                    option::of(zips()),
+                    option::of(integrity()),
+                ),
+            )
+                .prop_map(
+                    |(
+                        (name, description, base, tmp, files, boot),
+                        (
+                            [load_dotenv, boot_timing, keep_junk],
+                            fetch,
+                            permissions,
+                            unicode_normalization,
+                            zips,
+                            integrity,
+                        ),
+                    )| Lift {
+                        name,
+                        description,
+                        base,
+                        tmp,
+                        files,
+                        boot,
+                        load_dotenv,
+                        boot_timing,
+                        fetch,
+                        permissions,
+                        unicode_normalization,
+                        keep_junk,
+                        zips,
+                        integrity,
+                    },
+                )
+        }
+
+        fn jump() -> impl Strategy<Value = Jump> {
+            (
+                any::<u32>(),
+                "[0-9]{1,2}\\.[0-9]{1,2}\\.[0-9]{1,2}",
+                option::of("[0-9]{1,2}\\.[0-9]{1,2}\\.0"),
+            )
+                .prop_map(|(size, version, min_version)| Jump {
+                    size: size as usize,
+                    version,
+                    min_version,
+                })
+        }
+
+        fn config() -> impl Strategy<Value = Config> {
+            (lift(), option::of(jump()), other()).prop_map(|(lift, jump, other)| Config {
+                scie: Scie { lift, jump },
+                other: Some(Other { other }),
+            })
+        }
+
+        fn serialize(config: &Config, pretty: bool) -> Vec<u8> {
+            let mut data = vec![];
+            config
+                .serialize(
+                    &mut data,
+                    Fmt::new()
+                        .pretty(pretty)
+                        .leading_newline(true)
+                        .trailing_newline(true),
+                )
+                .unwrap();
+            data
+        }
+
+        proptest! {
+            #[test]
+            fn round_trip(config in config(), pretty in any::<bool>()) {
+                let boot = &config.scie.lift.boot;
+                for cmd in boot.commands.values().chain(boot.bindings.values()) {
+                    for arg in &cmd.args {
+                        prop_assert!(crate::placeholders::parse(arg).is_ok(), "{arg}");
+                    }
+                }
+
+                let data = serialize(&config, pretty);
+                let parsed = Config::parse(&data, UnknownFields::Ignore).unwrap();
+                prop_assert_eq!(&config, &parsed);
+                prop_assert_eq!(data, serialize(&parsed, pretty));
+            }
+
+            #[test]
+            fn load_after_zip(
+                config in config(),
+                prefix in vec(any::<u8>(), 0..256),
+                entries in vec((name(), vec(any::<u8>(), 0..256)), 0..4),
+                lift_in_zip in any::<bool>(),
+            ) {
+                let mut data = prefix;
+                let mut zip = ::zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+                for (index, (name, contents)) in entries.iter().enumerate() {
+                    zip.start_file(format!("{index}/{name}"), Default::default()).unwrap();
+                    std::io::Write::write_all(&mut zip, contents).unwrap();
+                }
+                data.extend(zip.finish().unwrap().into_inner());
+
+                let offset = data.len();
+                let jump = Jump {
+                    size: 0,
+                    version: "0.0.0".to_string(),
+                    ..Default::default()
+                };
+                let lift_manifest = serialize(&config, false);
+                let magic = scie_magic(&jump, offset).unwrap();
+                if lift_in_zip {
+                    data.extend(lift_zip(&lift_manifest, magic).unwrap());
+                } else {
+                    data.extend(lift_manifest);
+                    data.extend(magic.into_bytes());
+                }
+
+                let loaded =
+                    crate::zip::lift_manifest(&data, Config::MAXIMUM_CONFIG_SIZE).unwrap();
+                prop_assert_eq!(offset, loaded.offset);
+                prop_assert_eq!(lift_in_zip, loaded.in_zip);
+                prop_assert_eq!(
+                    config,
+                    Config::parse(&loaded.data, UnknownFields::Ignore).unwrap()
+                );
+            }
+        }
+    }
 }
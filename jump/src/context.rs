@@ -5,21 +5,56 @@ use std::collections::{HashMap, HashSet};
 use std::env;
 use std::ffi::OsStr;
 use std::fmt::{Debug, Formatter};
+use std::io::Read;
 use std::path::{Component, Path, PathBuf};
 use std::process::Child;
+use std::time::Duration;
 
 use bstr::ByteSlice;
 use indexmap::IndexMap;
 use logging_timer::time;
+use serde_json::Value;
 
-use crate::atomic::{atomic_path, Target};
+use crate::atomic::{atomic_path, is_complete, Target};
 use crate::cmd_env::{parse_scie_env_placeholder, prepare_env, ParsedEnv};
-use crate::config::{Cmd, Fmt};
-use crate::installer::Installer;
+use crate::config::{Cmd, FileType, Fmt, UserArgs};
+use crate::error::JumpError;
+use crate::fetch::{self, Download, Fetcher};
+use crate::installer::{is_noexec, Installer};
 use crate::lift::{File, Lift};
 use crate::placeholders::{self, Item, Placeholder, ScieBindingEnv};
 use crate::process::{EnvVar, Process};
 use crate::{config, CurrentExe, EnvVars, Jump, Source};
+use crate::{host, in_place};
+
+/// Expands `$NAME` and `${NAME}` environment variable references (and `%NAME%` references on
+/// Windows) in the given path. References to unset environment variables are left as-is.
+fn expandvars(path: &Path) -> Result<PathBuf, String> {
+    let path_str = path.to_str().ok_or_else(|| {
+        format!(
+            "Failed to decode the path {} as a utf-8 string",
+            path.display()
+        )
+    })?;
+    let pattern = if cfg!(windows) {
+        r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}|\$([A-Za-z_][A-Za-z0-9_]*)|%([^%]+)%"
+    } else {
+        r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}|\$([A-Za-z_][A-Za-z0-9_]*)"
+    };
+    let env_var_ref = regex::Regex::new(pattern)
+        .map_err(|e| format!("Failed to compile environment variable pattern: {e}"))?;
+    let expanded = env_var_ref.replace_all(path_str, |captures: &regex::Captures| {
+        let name = captures
+            .iter()
+            .skip(1)
+            .flatten()
+            .next()
+            .map(|m| m.as_str())
+            .unwrap_or_default();
+        env::var(name).unwrap_or_else(|_| captures[0].to_string())
+    });
+    Ok(PathBuf::from(expanded.as_ref()))
+}
 
 fn expanduser(path: &Path) -> Result<PathBuf, String> {
     if !<[u8]>::from_path(path)
@@ -50,6 +85,97 @@ fn expanduser(path: &Path) -> Result<PathBuf, String> {
     Ok(components.into_iter().collect())
 }
 
+fn default_base() -> PathBuf {
+    if let Some(dir) = dirs::cache_dir() {
+        dir.join("nce")
+    } else {
+        PathBuf::from("~/.nce")
+    }
+}
+
+// A base that anyone can write to, like `/tmp/nce`, is shared by all the users of the machine;
+// so each user, root included, gets a subdirectory of it named after their uid that others cannot
+// plant files in.
+#[cfg(unix)]
+fn scope_to_user(base: PathBuf) -> Result<PathBuf, String> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::metadata(&base) {
+        Ok(metadata) if metadata.mode() & 0o002 != 0 => {}
+        _ => return Ok(base),
+    }
+    let uid = nix::unistd::geteuid().as_raw();
+    let user_base = base.join(uid.to_string());
+    if let Err(e) = std::fs::DirBuilder::new().mode(0o700).create(&user_base) {
+        if e.kind() != std::io::ErrorKind::AlreadyExists {
+            return Err(format!(
+                "Failed to create the per-user nce cache {user_base}: {e}",
+                user_base = user_base.display()
+            ));
+        }
+    }
+    let metadata = std::fs::symlink_metadata(&user_base).map_err(|e| {
+        format!(
+            "Failed to read the metadata of the per-user nce cache {user_base}: {e}",
+            user_base = user_base.display()
+        )
+    })?;
+    if !metadata.is_dir() || metadata.uid() != uid {
+        return Err(format!(
+            "Refusing to use the per-user nce cache {user_base} since it is not a directory owned \
+            by you (uid {uid}). Set SCIE_BASE to a directory only you can write to.",
+            user_base = user_base.display()
+        ));
+    }
+    Ok(user_base)
+}
+
+#[cfg(not(unix))]
+fn scope_to_user(base: PathBuf) -> Result<PathBuf, String> {
+    Ok(base)
+}
+
+pub(crate) fn is_writable(path: &Path) -> bool {
+    // N.B.: We probe by creating a file since permission bits do not tell the whole story (ACLs,
+    // read-only mounts, etc.). A base that does not yet exist is writable if we can create it.
+    let mut existing = path;
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => return false,
+        }
+    }
+    tempfile::Builder::new()
+        .prefix(".scie-probe")
+        .tempfile_in(existing)
+        .is_ok()
+}
+
+fn is_installed(files: &[FileEntry]) -> Result<bool, String> {
+    let mut entries = vec![];
+    for file_entry in files {
+        match file_entry {
+            FileEntry::Skip(_) => {}
+            FileEntry::Install((file, dst)) => entries.push((file, dst)),
+            FileEntry::LoadAndInstall((_, file, dst)) => entries.push((file, dst)),
+            FileEntry::FetchAndInstall((_, file, dst)) => entries.push((file, dst)),
+            FileEntry::ScieTote((_, tote_entries)) => {
+                entries.extend(tote_entries.iter().map(|(file, dst)| (file, dst)))
+            }
+        }
+    }
+    for (file, dst) in entries {
+        let target_type = match file.file_type {
+            FileType::Blob => Target::File,
+            _ => Target::Directory,
+        };
+        if !is_complete(dst, target_type, Some(file.hash.as_str()))? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
 fn path_to_str(path: &Path) -> Result<&str, String> {
     <[u8]>::from_path(path)
         .ok_or_else(|| format!("Failed to decode {} as a utf-8 path name", path.display()))?
@@ -65,17 +191,20 @@ struct LiftManifest {
 }
 
 impl LiftManifest {
-    fn install(&self) -> Result<(), String> {
-        atomic_path(&self.path, Target::File, |path| {
+    fn install(&self) -> Result<(), JumpError> {
+        atomic_path(&self.path, Target::File, None, |path| {
             config(self.jump.clone(), self.lift.clone()).serialize(
                 std::fs::OpenOptions::new()
                     .write(true)
                     .create_new(true)
                     .open(path)
                     .map_err(|e| {
-                        format!(
-                            "Failed top open lift manifest at {path} for writing: {e}",
-                            path = self.path.display()
+                        JumpError::io(
+                            format!(
+                                "Failed to open lift manifest at {path} for writing",
+                                path = self.path.display()
+                            ),
+                            e,
                         )
                     })?,
                 Fmt::new().trailing_newline(true).pretty(true),
@@ -91,7 +220,7 @@ pub(crate) struct LoadProcess {
 }
 
 impl LoadProcess {
-    pub(crate) fn spawn_stdout(&self, args: &[&str]) -> Result<Child, String> {
+    pub(crate) fn spawn_stdout(&self, args: &[&str]) -> Result<Child, JumpError> {
         if let Some(ref lift_manifest) = self.lift_manifest {
             lift_manifest.install()?;
         }
@@ -116,47 +245,29 @@ pub(crate) enum FileEntry {
     Skip(usize),
     Install((File, PathBuf)),
     LoadAndInstall((LoadProcess, File, PathBuf)),
+    FetchAndInstall((Download, File, PathBuf)),
     ScieTote((File, Vec<(File, PathBuf)>)),
 }
 
+// N.B.: This matches the default backoff for fetches.
+const DEFAULT_RETRY_BACKOFF_MS: u64 = 500;
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct Binding {
     target: PathBuf,
     process: Process,
+    retries: u32,
+    backoff: Duration,
 }
 
 impl Binding {
-    fn execute<F>(&self, install_required_files: F) -> Result<HashMap<String, String>, String>
+    fn execute<F>(&self, install_required_files: F) -> Result<IndexMap<String, String>, String>
     where
         F: FnOnce() -> Result<(), String>,
     {
-        if let Some(env) = atomic_path(self.target.as_path(), Target::File, |lock| {
-            trace!("Installing boot binding {binding:#?}", binding = &self);
-            install_required_files()?;
-
-            let result = self
-                .process
-                .execute(vec![("SCIE_BINDING_ENV".into(), lock.into())]);
-
-            match result {
-                Err(err) => Err(format!("Failed to launch boot binding: {err}")),
-                Ok(exit_status) if !exit_status.success() => {
-                    Err(format!("Boot binding command failed: {exit_status}"))
-                }
-                _ => std::fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(lock)
-                    .map_err(|e| {
-                        format!(
-                            "Failed to touch lock file {path}: {e}",
-                            path = lock.display()
-                        )
-                    }),
-            }?;
-            // We eagerly load the env file before we exit the lock such that malformed env files
-            // are detected and the lock is not poisoned.
-            Self::load_env_file(lock)
+        if let Some(env) = atomic_path(self.target.as_path(), Target::File, None, |lock| {
+            self.establish(lock, install_required_files)
+                .map_err(JumpError::Other)
         })? {
             Ok(env)
         } else {
@@ -164,18 +275,135 @@ impl Binding {
         }
     }
 
-    fn load_env(&self) -> Result<HashMap<String, String>, String> {
+    // Runs the binding with its lock held, leaving the env it produced in the lock file.
+    fn establish<F>(
+        &self,
+        lock: &Path,
+        install_required_files: F,
+    ) -> Result<IndexMap<String, String>, String>
+    where
+        F: FnOnce() -> Result<(), String>,
+    {
+        trace!("Installing boot binding {binding:#?}", binding = &self);
+        install_required_files()?;
+        let outputs_file = self.outputs_file();
+        Self::remove_outputs_file(&outputs_file)?;
+        self.run(lock, &outputs_file)?;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(lock)
+            .map_err(|e| {
+                format!(
+                    "Failed to touch lock file {path}: {e}",
+                    path = lock.display()
+                )
+            })?;
+        // We eagerly load the env and outputs files before we exit the lock such that malformed
+        // files are detected and the lock is not poisoned.
+        Self::load_outputs_file(&outputs_file)?;
+        Self::load_env_file(lock)
+    }
+
+    fn run(&self, env_file: &Path, outputs_file: &Path) -> Result<(), String> {
+        let mut attempt = 0;
+        loop {
+            let error = match self.process.execute(vec![
+                ("SCIE_BINDING_ENV".into(), env_file.into()),
+                ("SCIE_BINDING_OUTPUTS".into(), outputs_file.into()),
+            ]) {
+                Err(err) => format!("Failed to launch boot binding: {err}"),
+                Ok(exit_status) if !exit_status.success() => {
+                    format!("Boot binding command failed: {exit_status}")
+                }
+                Ok(_) => return Ok(()),
+            };
+            if attempt >= self.retries {
+                return Err(if self.retries > 0 {
+                    format!(
+                        "{error}\nGave up on the boot binding after {attempts} attempts.",
+                        attempts = attempt + 1
+                    )
+                } else {
+                    error
+                });
+            }
+            // Any env or outputs the failed attempt wrote are discarded so the next attempt starts
+            // afresh.
+            if env_file.exists() {
+                std::fs::write(env_file, "").map_err(|e| {
+                    format!(
+                        "Failed to reset binding env file {path}: {e}",
+                        path = env_file.display()
+                    )
+                })?;
+            }
+            Self::remove_outputs_file(outputs_file)?;
+            let delay = self.backoff.saturating_mul(2_u32.saturating_pow(attempt));
+            attempt += 1;
+            warn!(
+                "{error}\nRetrying in {delay:?} ({attempt} of {retries})...",
+                retries = self.retries
+            );
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn load_env(&self) -> Result<IndexMap<String, String>, String> {
         Self::load_env_file(self.target.as_path())
     }
 
-    fn load_env_file(env_file: &Path) -> Result<HashMap<String, String>, String> {
+    fn outputs_file(&self) -> PathBuf {
+        let mut outputs_file = self.target.clone().into_os_string();
+        outputs_file.push(".outputs.json");
+        outputs_file.into()
+    }
+
+    fn remove_outputs_file(outputs_file: &Path) -> Result<(), String> {
+        match std::fs::remove_file(outputs_file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!(
+                "Failed to remove binding outputs file {path}: {e}",
+                path = outputs_file.display()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn load_outputs(&self) -> Result<serde_json::Map<String, Value>, String> {
+        Self::load_outputs_file(&self.outputs_file())
+    }
+
+    fn load_outputs_file(outputs_file: &Path) -> Result<serde_json::Map<String, Value>, String> {
+        if !outputs_file.exists() {
+            return Ok(serde_json::Map::new());
+        }
+        let contents = std::fs::read_to_string(outputs_file).map_err(|e| {
+            format!(
+                "Failed to read binding outputs from {path}: {e}",
+                path = outputs_file.display()
+            )
+        })?;
+        match serde_json::from_str(&contents) {
+            Ok(Value::Object(outputs)) => Ok(outputs),
+            Ok(_) => Err(format!(
+                "The binding outputs in {path} must be a JSON object.",
+                path = outputs_file.display()
+            )),
+            Err(e) => Err(format!(
+                "Failed to parse binding outputs from {path}: {e}",
+                path = outputs_file.display()
+            )),
+        }
+    }
+
+    fn load_env_file(env_file: &Path) -> Result<IndexMap<String, String>, String> {
         let contents = std::fs::read_to_string(env_file).map_err(|e| {
             format!(
                 "Failed to read binding env from {env_file}: {e}",
                 env_file = env_file.display()
             )
         })?;
-        let mut env = HashMap::new();
+        let mut env = IndexMap::new();
         for line in contents.lines() {
             let trimmed = line.trim();
             if !trimmed.is_empty() {
@@ -192,9 +420,34 @@ impl Binding {
 }
 
 pub(crate) struct SelectedCmd {
+    pub(crate) name: String,
+    pub(crate) base: PathBuf,
     pub(crate) process: Process,
     pub(crate) files: Vec<FileEntry>,
     pub(crate) argv1_consumed: bool,
+    pub(crate) exe_in_place: Option<File>,
+    pub(crate) user_args: Option<UserArgs>,
+}
+
+/// The expansion of a text holding placeholders.
+#[derive(Clone, Debug, Default)]
+struct Expansion<'a> {
+    value: String,
+    lift_manifest_required: bool,
+    /// The files whose paths the expansion holds; so they can be installed.
+    files: Vec<&'a File>,
+    /// Whether the expansion depends on the env of the command being prepared.
+    env_dependent: bool,
+}
+
+impl<'a> Expansion<'a> {
+    // Folds in the nested expansion of part of a placeholder, returning its value.
+    fn absorb(&mut self, nested: Expansion<'a>) -> String {
+        self.lift_manifest_required |= nested.lift_manifest_required;
+        self.files.extend(nested.files);
+        self.env_dependent |= nested.env_dependent;
+        nested.value
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -205,11 +458,13 @@ pub(crate) struct Context<'a> {
     installer: &'a Installer<'a>,
     files_by_name: HashMap<&'a str, &'a File>,
     replacements: HashSet<&'a File>,
+    expansions: HashMap<String, Expansion<'a>>,
     lift_manifest: LiftManifest,
     lift_manifest_dependants: HashSet<Process>,
     lift_manifest_installed: bool,
     bound: HashMap<String, Binding>,
     installed: HashSet<File>,
+    script: Option<PathBuf>,
 }
 
 impl<'a> Context<'a> {
@@ -227,16 +482,27 @@ impl<'a> Context<'a> {
                 files_by_name.insert(key.as_str(), file);
             }
         }
-        let base = if let Ok(base) = env::var("SCIE_BASE") {
+        let base = if let Some(base) = &crate::scie_args().base {
+            PathBuf::from(base)
+        } else if let Ok(base) = env::var("SCIE_BASE") {
             PathBuf::from(base)
         } else if let Some(base) = &lift.base {
             PathBuf::from(base)
-        } else if let Some(dir) = dirs::cache_dir() {
-            dir.join("nce")
         } else {
-            PathBuf::from("~/.nce")
+            default_base()
         };
-        let base = expanduser(base.as_path())?;
+        Self::with_base(scie, jump, lift, installer, files_by_name, base)
+    }
+
+    fn with_base(
+        scie: &'a Path,
+        jump: &'a Jump,
+        lift: &'a Lift,
+        installer: &'a Installer,
+        files_by_name: HashMap<&'a str, &'a File>,
+        base: PathBuf,
+    ) -> Result<Self, String> {
+        let base = expanduser(&expandvars(&base)?)?;
         let mut context = Context {
             scie,
             lift,
@@ -244,6 +510,7 @@ impl<'a> Context<'a> {
             installer,
             files_by_name,
             replacements: HashSet::new(),
+            expansions: HashMap::new(),
             lift_manifest: LiftManifest {
                 path: PathBuf::new(), // N.B.: We replace this empty value below.
                 jump: jump.clone(),
@@ -253,6 +520,7 @@ impl<'a> Context<'a> {
             lift_manifest_installed: false,
             bound: HashMap::new(),
             installed: HashSet::new(),
+            script: None,
         };
 
         // Now patch up the base and the lift path (which is derived from it) with any placeholder
@@ -285,15 +553,31 @@ impl<'a> Context<'a> {
                 base = context.base.display()
             ));
         }
-        context.base = PathBuf::from(parsed_base.clone());
+        context.base = scope_to_user(PathBuf::from(parsed_base.clone()))?;
         context.lift_manifest.path = context.base.join(&lift.hash).join("lift.json");
+        // N.B.: Expansions of the base may hold paths relative to the base before it was resolved.
+        context.expansions.clear();
         Ok(context)
     }
 
-    fn prepare_process(&mut self, cmd: &'a Cmd) -> Result<Process, String> {
+    fn prepare_process(&mut self, name: &'a str, cmd: &'a Cmd) -> Result<Process, String> {
+        self.prepare_process_inheriting(cmd, &mut vec![name], false)
+    }
+
+    fn prepare_process_inheriting(
+        &mut self,
+        cmd: &'a Cmd,
+        inheriting: &mut Vec<&'a str>,
+        exe_in_place: bool,
+    ) -> Result<Process, String> {
         let mut env = prepare_env(cmd)?;
         let mut needs_lift_manifest = false;
-        let (exe, needs_manifest) = self.reify_string(&env, &cmd.exe)?;
+        // N.B.: An exe executed in place is never extracted; the caller fills in the exe.
+        let (exe, needs_manifest) = if exe_in_place {
+            (cmd.exe.clone(), false)
+        } else {
+            self.reify_string(&env, &cmd.exe)?
+        };
         needs_lift_manifest |= needs_manifest;
 
         let mut args = vec![];
@@ -324,11 +608,55 @@ impl<'a> Context<'a> {
             };
             vars.push(EnvVar::try_from((key, final_value))?);
         }
+        if cmd.clean_env {
+            vars.push(EnvVar::Clean);
+        }
+        if !cmd.prepend_path.is_empty() {
+            let mut path = vec![];
+            for entry in &cmd.prepend_path {
+                let (reified_entry, needs_manifest) = self.reify_string(&env, entry)?;
+                needs_lift_manifest |= needs_manifest;
+                path.push(PathBuf::from(reified_entry));
+            }
+            // N.B.: We prepend to the PATH the command's other env vars leave it with, if any.
+            let env_vars = EnvVars { vars: vars.clone() }.to_env_vars();
+            let base_path = match env_vars.iter().rev().find(|(name, _)| name == "PATH") {
+                Some((_, value)) => value.clone(),
+                None => env::var_os("PATH"),
+            };
+            if let Some(base_path) = base_path.filter(|base_path| !base_path.is_empty()) {
+                path.extend(env::split_paths(&base_path));
+            }
+            let path = env::join_paths(path).map_err(|e| {
+                format!(
+                    "Failed to prepend {prepend_path:?} to the PATH: {e}",
+                    prepend_path = cmd.prepend_path
+                )
+            })?;
+            vars.push(EnvVar::Replace(("PATH".into(), path)));
+        }
+
+        let cwd = if let Some(cwd) = &cmd.cwd {
+            let (reified_cwd, needs_manifest) = self.reify_string(&env, cwd)?;
+            needs_lift_manifest |= needs_manifest;
+            Some(reified_cwd.into())
+        } else {
+            None
+        };
+
+        for additional_file in &cmd.additional_files {
+            let (name, needs_manifest) = self.reify_string(&env, additional_file)?;
+            needs_lift_manifest |= needs_manifest;
+            self.add_additional_file(name.as_str(), inheriting)?;
+        }
 
         let process = Process {
             env: EnvVars { vars },
             exe: exe.into(),
             args,
+            cwd,
+            exe_fd: None,
+            timeout: cmd.timeout.map(Duration::from_secs),
         };
         if needs_lift_manifest {
             self.lift_manifest_dependants.insert(process.clone());
@@ -336,21 +664,93 @@ impl<'a> Context<'a> {
         Ok(process)
     }
 
-    fn prepare(&mut self, cmd: &'a Cmd) -> Result<(Process, Vec<FileEntry>), String> {
-        let process = self.prepare_process(cmd)?;
+    fn add_additional_file(
+        &mut self,
+        name: &str,
+        inheriting: &mut Vec<&'a str>,
+    ) -> Result<(), String> {
+        if let Some(file) = self.files_by_name.get(name) {
+            self.replacements.insert(*file);
+            return Ok(());
+        }
+        let lift = self.lift;
+        if let Some((cmd_name, cmd)) = lift
+            .boot
+            .commands
+            .get_key_value(name)
+            .or_else(|| lift.boot.bindings.get_key_value(name))
+        {
+            if inheriting.contains(&cmd_name.as_str()) {
+                return Err(format!(
+                    "The additional_files of command {cmd_name} transitively include its own \
+                    files: {cycle} -> {cmd_name}",
+                    cycle = inheriting.join(" -> ")
+                ));
+            }
+            inheriting.push(cmd_name.as_str());
+            self.prepare_process_inheriting(cmd, inheriting, false)?;
+            inheriting.pop();
+            return Ok(());
+        }
+        Err(format!(
+            "The additional file {name} is neither a file stored in this scie nor the name of a \
+            command whose files should be included."
+        ))
+    }
+
+    // Returns the file named by the command's exe if it should be executed in place from memory.
+    fn in_place_exe(&self, cmd: &Cmd) -> Result<Option<&'a File>, String> {
+        if !in_place::SUPPORTED {
+            return Ok(None);
+        }
+        let parsed = placeholders::parse(&cmd.exe)?;
+        if let [Item::Placeholder(Placeholder::FileName(name))] = parsed.items.as_slice() {
+            if let Some(file) = self.files_by_name.get(name) {
+                if file.execute_in_place && Source::Scie == file.source && file.size > 0 {
+                    return Ok(Some(*file));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn prepare(
+        &mut self,
+        name: &'a str,
+        cmd: &'a Cmd,
+        exe_in_place: bool,
+    ) -> Result<(Process, Vec<FileEntry>), String> {
+        // N.B.: A binding can be prepared while the command that depends on it is still being
+        // prepared. We collect the files each needs apart so that running the binding only
+        // extracts the files the binding itself needs.
+        let outer_replacements = std::mem::take(&mut self.replacements);
+        let result = self.prepare_files(name, cmd, exe_in_place);
+        self.replacements = outer_replacements;
+        result
+    }
+
+    fn prepare_files(
+        &mut self,
+        name: &'a str,
+        cmd: &'a Cmd,
+        exe_in_place: bool,
+    ) -> Result<(Process, Vec<FileEntry>), String> {
+        // N.B.: The command being prepared starts the chain of commands whose files are included;
+        // so a command that includes its own files, directly or not, is reported as a cycle.
+        let process = self.prepare_process_inheriting(cmd, &mut vec![name], exe_in_place)?;
 
         let mut load_entries = vec![];
         for file in &self.lift.files {
             if self.replacements.contains(&file) && !self.installed.contains(file) {
                 if let Source::LoadBinding(binding_name) = &file.source {
                     let path = self.get_path(file);
-                    let file_source_process = self.prepare_process(
-                        self.lift
-                            .boot
-                            .bindings
-                            .get(binding_name)
-                            .ok_or_else(|| format!("No boot binding named {binding_name}."))?,
-                    )?;
+                    let (binding_name, binding) = self
+                        .lift
+                        .boot
+                        .bindings
+                        .get_key_value(binding_name)
+                        .ok_or_else(|| format!("No boot binding named {binding_name}."))?;
+                    let file_source_process = self.prepare_process(binding_name, binding)?;
                     let lift_manifest = if !self.lift_manifest_installed
                         && self.lift_manifest_dependants.contains(&file_source_process)
                     {
@@ -366,6 +766,21 @@ impl<'a> Context<'a> {
                         file.clone(),
                         path,
                     )))
+                } else if let Source::EntryRange {
+                    url,
+                    mirrors,
+                    offset,
+                } = &file.source
+                {
+                    load_entries.push(FileEntry::FetchAndInstall((
+                        Download {
+                            urls: fetch::candidate_urls(url, mirrors)?,
+                            offset: *offset,
+                            fetcher: Fetcher::new(self.lift.fetch.as_ref())?,
+                        },
+                        file.clone(),
+                        self.get_path(file),
+                    )))
                 }
             }
         }
@@ -416,20 +831,67 @@ impl<'a> Context<'a> {
         name: &str,
         argv1_consumed: bool,
     ) -> Result<Option<SelectedCmd>, String> {
-        if let Some(cmd) = self.lift.boot.commands.get(name) {
-            let (process, files) = self.prepare(cmd)?;
+        if let Some((name, cmd)) = self
+            .lift
+            .boot
+            .commands
+            .get_key_value(name)
+            .filter(|(_, cmd)| cmd.is_enabled())
+        {
+            let exe_in_place = self.in_place_exe(cmd)?;
+            let (process, files) = self.prepare(name, cmd, exe_in_place.is_some())?;
             self.maybe_install_lift_manifest(&process)?;
             return Ok(Some(SelectedCmd {
+                name: name.to_string(),
+                base: self.base.clone(),
                 process,
                 files,
                 argv1_consumed,
+                exe_in_place: exe_in_place.cloned(),
+                user_args: cmd.user_args.clone(),
             }));
         }
         Ok(None)
     }
 
+    fn is_cmd_enabled(&self, name: &str) -> bool {
+        self.lift
+            .boot
+            .commands
+            .get(name)
+            .is_some_and(Cmd::is_enabled)
+    }
+
+    // Explains why a command that exists cannot be selected.
+    fn disabled_hint(&self, name: &str) -> String {
+        match self
+            .lift
+            .boot
+            .commands
+            .get(name)
+            .and_then(|cmd| cmd.enabled_if.as_ref())
+        {
+            Some(enabled_if) => format!(
+                " It is disabled unless the {env} environment variable is set.",
+                env = enabled_if.env
+            ),
+            None => String::new(),
+        }
+    }
+
     fn select_command(&mut self, scie_name: &str, exe: &CurrentExe) -> Result<SelectedCmd, String> {
         // Forced command.
+        if let Some(name) = &crate::scie_args().boot {
+            if let Some(selected_cmd) = self.select_cmd(name, false)? {
+                return Ok(selected_cmd);
+            } else {
+                return Err(format!(
+                    "`--scie-boot={name}` was given but \"{name}\" does not correspond to any \
+                        {scie_name} commands.{hint}",
+                    hint = self.disabled_hint(name)
+                ));
+            }
+        }
         if let Some(cmd) = env::var_os("SCIE_BOOT") {
             // Avoid subprocesses that re-execute this SCIE unintentionally getting in an infinite
             // loop.
@@ -442,11 +904,36 @@ impl<'a> Context<'a> {
             } else {
                 return Err(format!(
                     "`SCIE_BOOT={name}` was found in the environment but \"{name}\" does \
-                        not correspond to any {scie_name} commands."
+                        not correspond to any {scie_name} commands.{hint}",
+                    hint = self.disabled_hint(&name)
                 ));
             }
         }
 
+        // Shebang style where the scie is the interpreter of the script given as the 1st arg; unless
+        // the 1st arg names a command, in which case BusyBox style selection below wins even if a
+        // script of the same name happens to be in the current directory.
+        let argv1_names_cmd = crate::scie_args()
+            .args()
+            .nth(1)
+            .is_some_and(|argv1| self.is_cmd_enabled(&argv1));
+        if let Some(name) = self
+            .lift
+            .boot
+            .commands
+            .iter()
+            .find(|(_, cmd)| cmd.shebang && cmd.is_enabled())
+            .map(|(name, _)| name)
+            .filter(|_| !argv1_names_cmd)
+        {
+            if let Some(script) = shebang_script()? {
+                self.script = Some(script);
+                if let Some(selected_cmd) = self.select_cmd(name, true)? {
+                    return Ok(selected_cmd);
+                }
+            }
+        }
+
         // Default command.
         if let Some(selected_cmd) = self.select_cmd("", false)? {
             return Ok(selected_cmd);
@@ -460,7 +947,7 @@ impl<'a> Context<'a> {
         }
 
         // BusyBox style where 1st arg indicates command name.
-        if let Some(argv1) = env::args().nth(1) {
+        if let Some(argv1) = crate::scie_args().args().nth(1) {
             if let Some(selected_cmd) = self.select_cmd(&argv1, true)? {
                 return Ok(selected_cmd);
             }
@@ -497,17 +984,18 @@ impl<'a> Context<'a> {
         ))
     }
 
-    fn bind(&mut self, name: &str) -> Result<HashMap<String, String>, String> {
+    fn bind(&mut self, name: &str) -> Result<IndexMap<String, String>, String> {
         if let Some(binding) = self.bound.get(name) {
             binding.load_env()
         } else {
-            let (process, files) = self.prepare(
-                self.lift
-                    .boot
-                    .bindings
-                    .get(name)
-                    .ok_or_else(|| format!("No boot binding named {name}."))?,
-            )?;
+            let (name, cmd) = self
+                .lift
+                .boot
+                .bindings
+                .get_key_value(name)
+                .ok_or_else(|| format!("No boot binding named {name}."))?;
+            let (process, files) = self.prepare(name, cmd, false)?;
+            check_executable(&self.base, &process.exe)?;
             let process_hash = process.fingerprint()?;
             let boot_binding = Binding {
                 target: self
@@ -516,10 +1004,17 @@ impl<'a> Context<'a> {
                     .join("locks")
                     .join(format!("{name}-{process_hash}")),
                 process,
+                retries: cmd.retry.as_ref().map_or(0, |retry| retry.retries),
+                backoff: Duration::from_millis(
+                    cmd.retry
+                        .as_ref()
+                        .and_then(|retry| retry.backoff_ms)
+                        .unwrap_or(DEFAULT_RETRY_BACKOFF_MS),
+                ),
             };
             let binding_env = boot_binding.execute(|| {
                 self.maybe_install_lift_manifest(&boot_binding.process)?;
-                self.installer.install(files.as_slice())
+                Ok(self.installer.install(files.as_slice())?)
             })?;
             self.bound.insert(name.to_string(), boot_binding);
             for file_entry in files {
@@ -531,6 +1026,9 @@ impl<'a> Context<'a> {
                     FileEntry::LoadAndInstall((_, file, _)) => {
                         self.installed.insert(file);
                     }
+                    FileEntry::FetchAndInstall((_, file, _)) => {
+                        self.installed.insert(file);
+                    }
                     FileEntry::ScieTote((_, tote_entries)) => {
                         for (file, _) in tote_entries {
                             self.installed.insert(file);
@@ -542,63 +1040,122 @@ impl<'a> Context<'a> {
         }
     }
 
+    fn binding_output(&mut self, name: &str, key: &str) -> Result<String, String> {
+        self.bind(name)?;
+        let outputs = self
+            .bound
+            .get(name)
+            .ok_or_else(|| format!("No boot binding named {name}."))?
+            .load_outputs()?;
+        let mut keys = key.split('.');
+        let mut value = keys.next().and_then(|first| outputs.get(first));
+        for component in keys {
+            value = value.and_then(|value| value.get(component));
+        }
+        match value {
+            Some(Value::String(value)) => Ok(value.to_owned()),
+            Some(Value::Number(value)) => Ok(value.to_string()),
+            Some(Value::Bool(value)) => Ok(value.to_string()),
+            Some(_) => Err(format!(
+                "The output {key} of boot binding {name} is not a string, number or boolean."
+            )),
+            None => Err(format!(
+                "The boot binding {name} has no output named {key}."
+            )),
+        }
+    }
+
     fn reify_string(
         &mut self,
         env: &IndexMap<String, String>,
         value: &str,
     ) -> Result<(String, bool), String> {
-        let mut reified = String::with_capacity(value.len());
-        let mut lift_manifest_required = false;
+        let expansion = self.expand(env, value)?;
+        Ok((expansion.value, expansion.lift_manifest_required))
+    }
 
+    // Commands and bindings tend to refer to the same few files and placeholders over and over;
+    // so each distinct text is only scanned and expanded once and later expansions of it are a
+    // lookup in the table of expansions. Only expansions that depend on the env of the command
+    // being prepared are computed afresh each time.
+    fn expand(
+        &mut self,
+        env: &IndexMap<String, String>,
+        value: &str,
+    ) -> Result<Expansion<'a>, String> {
+        if let Some(expansion) = self.expansions.get(value) {
+            self.replacements.extend(expansion.files.iter().copied());
+            return Ok(expansion.clone());
+        }
+        let expansion = self.expand_placeholders(env, value)?;
+        if !expansion.env_dependent {
+            self.expansions.insert(value.to_string(), expansion.clone());
+        }
+        Ok(expansion)
+    }
+
+    fn expand_file(
+        &mut self,
+        env: &IndexMap<String, String>,
+        name: &str,
+        expansion: &mut Expansion<'a>,
+    ) -> Result<&'a File, String> {
+        let parsed_name = expansion.absorb(self.expand(env, name)?);
+        self.files_by_name
+            .get(parsed_name.as_str())
+            .copied()
+            .ok_or_else(|| format!("No file named {parsed_name} is stored in this scie."))
+    }
+
+    fn expand_placeholders(
+        &mut self,
+        env: &IndexMap<String, String>,
+        value: &str,
+    ) -> Result<Expansion<'a>, String> {
+        let mut expansion = Expansion {
+            value: String::with_capacity(value.len()),
+            ..Expansion::default()
+        };
         let parsed = placeholders::parse(value)?;
         for item in &parsed.items {
             match item {
-                Item::LeftBrace => reified.push('{'),
-                Item::Text(text) => reified.push_str(text),
+                Item::LeftBrace => expansion.value.push('{'),
+                Item::Text(text) => expansion.value.push_str(text),
                 Item::Placeholder(Placeholder::FileHash(name)) => {
-                    let (parsed_name, needs_manifest) = self.reify_string(env, name)?;
-                    lift_manifest_required |= needs_manifest;
-                    let file = self
-                        .files_by_name
-                        .get(parsed_name.as_str())
-                        .ok_or_else(|| {
-                            format!("No file named {parsed_name} is stored in this scie.")
-                        })?;
-                    reified.push_str(&file.hash);
+                    let file = self.expand_file(env, name, &mut expansion)?;
+                    expansion.value.push_str(&file.hash);
+                }
+                Item::Placeholder(Placeholder::FileSize(name)) => {
+                    let file = self.expand_file(env, name, &mut expansion)?;
+                    expansion.value.push_str(&file.size.to_string());
                 }
                 Item::Placeholder(Placeholder::FileName(name)) => {
-                    let (parsed_name, needs_manifest) = self.reify_string(env, name)?;
-                    lift_manifest_required |= needs_manifest;
-                    let file = self
-                        .files_by_name
-                        .get(parsed_name.as_str())
-                        .ok_or_else(|| {
-                            format!("No file named {parsed_name} is stored in this scie.")
-                        })?;
+                    let file = self.expand_file(env, name, &mut expansion)?;
                     let path = self.get_path(file);
-                    reified.push_str(path_to_str(&path)?);
+                    expansion.value.push_str(path_to_str(&path)?);
                     self.replacements.insert(file);
+                    expansion.files.push(file);
                 }
                 Item::Placeholder(Placeholder::Env(env_var)) => {
+                    expansion.env_dependent = true;
                     let (parsed_env, needs_manifest) = self.parse_env(env, env_var)?;
-                    lift_manifest_required |= needs_manifest;
+                    expansion.lift_manifest_required |= needs_manifest;
                     let value = if let Some(val) = env
                         .get(&parsed_env.name)
                         .map(String::to_owned)
                         .or_else(|| env::var(&parsed_env.name).ok())
                     {
                         let (parsed_value, needs_manifest) = self.reify_string(env, &val)?;
-                        lift_manifest_required |= needs_manifest;
+                        expansion.lift_manifest_required |= needs_manifest;
                         parsed_value
                     } else {
                         parsed_env.default.unwrap_or_default()
                     };
-                    reified.push_str(&value)
+                    expansion.value.push_str(&value)
                 }
                 Item::Placeholder(Placeholder::UserCacheDir(fallback)) => {
-                    let (parsed_fallback, needs_manifest) = self.reify_string(env, fallback)?;
-                    lift_manifest_required |= needs_manifest;
-                    reified.push_str(if let Some(user_cache_dir) = dirs::cache_dir() {
+                    let parsed_fallback = expansion.absorb(self.expand(env, fallback)?);
+                    expansion.value.push_str(if let Some(user_cache_dir) = dirs::cache_dir() {
                         user_cache_dir.into_os_string().into_string().map_err(|e| {
                             format!("Could not interpret the user cache directory as a utf-8 string: {e:?}")
                         })?
@@ -606,36 +1163,55 @@ impl<'a> Context<'a> {
                         parsed_fallback
                     }.as_str())
                 }
-                Item::Placeholder(Placeholder::Scie) => reified.push_str(path_to_str(self.scie)?),
+                Item::Placeholder(Placeholder::Scie) => {
+                    expansion.value.push_str(path_to_str(self.scie)?)
+                }
                 Item::Placeholder(Placeholder::ScieBase) => {
-                    reified.push_str(path_to_str(&self.base)?)
+                    expansion.value.push_str(path_to_str(&self.base)?)
                 }
                 Item::Placeholder(Placeholder::ScieBindings) => {
-                    reified.push_str(path_to_str(self.get_bindings_dir().as_path())?);
+                    expansion
+                        .value
+                        .push_str(path_to_str(self.get_bindings_dir().as_path())?);
                 }
                 Item::Placeholder(Placeholder::ScieBindingCmd(name)) => {
-                    self.bind(name)?;
-                    reified.push_str(path_to_str(self.get_bindings_dir().as_path())?);
+                    match self.lift.boot.binding_output(name) {
+                        Some((binding, key)) if !self.lift.boot.bindings.contains_key(*name) => {
+                            expansion
+                                .value
+                                .push_str(&self.binding_output(binding, key)?)
+                        }
+                        _ => {
+                            self.bind(name)?;
+                            expansion
+                                .value
+                                .push_str(path_to_str(self.get_bindings_dir().as_path())?);
+                        }
+                    }
                 }
                 Item::Placeholder(Placeholder::ScieBindingEnv(ScieBindingEnv {
                     binding,
                     env: env_var,
                 })) => {
+                    expansion.env_dependent = true;
                     let binding_env = self.bind(binding)?;
                     let (parsed_env, needs_manifest) = self.parse_env(env, env_var)?;
-                    lift_manifest_required |= needs_manifest;
+                    expansion.lift_manifest_required |= needs_manifest;
                     let value = binding_env
                         .get(&parsed_env.name)
                         .map(String::to_owned)
                         .or(parsed_env.default)
                         .unwrap_or_default();
-                    reified.push_str(&value)
+                    expansion.value.push_str(&value)
                 }
+                Item::Placeholder(Placeholder::ScieLibc) => expansion.value.push_str(host::libc()),
                 Item::Placeholder(Placeholder::ScieLift) => {
-                    lift_manifest_required = true;
-                    reified.push_str(path_to_str(&self.lift_manifest.path)?);
+                    expansion.lift_manifest_required = true;
+                    expansion
+                        .value
+                        .push_str(path_to_str(&self.lift_manifest.path)?);
                 }
-                Item::Placeholder(Placeholder::SciePlatform) => reified.push_str(
+                Item::Placeholder(Placeholder::SciePlatform) => expansion.value.push_str(
                     format!(
                         "{os}-{arch}",
                         os = env::consts::OS,
@@ -644,15 +1220,91 @@ impl<'a> Context<'a> {
                     .as_str(),
                 ),
                 Item::Placeholder(Placeholder::SciePlatformArch) => {
-                    reified.push_str(env::consts::ARCH)
+                    expansion.value.push_str(env::consts::ARCH)
+                }
+                Item::Placeholder(Placeholder::SciePlatformOs) => {
+                    expansion.value.push_str(env::consts::OS)
+                }
+                Item::Placeholder(Placeholder::ScieScript) => {
+                    let script = self.script.as_deref().ok_or_else(|| {
+                        "The {scie.script} placeholder is only available when the scie is run as \
+                        the interpreter of a script."
+                            .to_string()
+                    })?;
+                    expansion.value.push_str(path_to_str(script)?)
                 }
-                Item::Placeholder(Placeholder::SciePlatformOs) => reified.push_str(env::consts::OS),
             }
         }
-        Ok((reified, lift_manifest_required))
+        Ok(expansion)
     }
 }
 
+/// Returns the `nce` cache base the scie uses, before any fallback for a read-only base.
+pub(crate) fn cache_base(
+    scie: &Path,
+    jump: &Jump,
+    lift: &Lift,
+    installer: &Installer,
+) -> Result<PathBuf, String> {
+    Ok(Context::new(scie, jump, lift, installer)?.base)
+}
+
+/// Returns the directory to stage files in given by `SCIE_TMP` or else the lift manifest's "tmp",
+/// if either is set.
+pub(crate) fn tmp_dir(lift_tmp: Option<&str>) -> Result<Option<PathBuf>, String> {
+    let tmp = match env::var_os("SCIE_TMP") {
+        Some(tmp) => PathBuf::from(tmp),
+        None => match lift_tmp {
+            Some(tmp) => PathBuf::from(tmp),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(expanduser(&expandvars(&tmp)?)?))
+}
+
+/// Fails with an actionable error if `exe` would be executed from an `nce` cache on a file system
+/// mounted `noexec`, instead of letting the exec fail with a bare permission error.
+pub(crate) fn check_executable(base: &Path, exe: &OsStr) -> Result<(), String> {
+    let exe = Path::new(exe);
+    if exe.starts_with(base) && is_noexec(base) {
+        return Err(format!(
+            "The nce cache {base} is on a file system mounted noexec; so {exe} cannot be executed \
+            from it. Set SCIE_BASE to a directory on a file system that allows executing programs.",
+            base = base.display(),
+            exe = exe.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the per-user `nce` cache base used when the configured base is read-only.
+pub(crate) fn fallback_base() -> Result<PathBuf, String> {
+    expanduser(&expandvars(&default_base())?)
+}
+
+// Returns the absolute path of the 1st argument if it is a script starting with a shebang line.
+fn shebang_script() -> Result<Option<PathBuf>, String> {
+    let Some(argv1) = crate::scie_args().args_os().nth(1) else {
+        return Ok(None);
+    };
+    let mut magic = [0_u8; 2];
+    let is_script = std::fs::File::open(argv1)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| &magic == b"#!")
+        .unwrap_or(false);
+    if !is_script {
+        return Ok(None);
+    }
+    let script = Path::new(argv1);
+    if script.is_absolute() {
+        return Ok(Some(script.to_path_buf()));
+    }
+    let cwd = env::current_dir()
+        .map_err(|e| format!("Failed to determine the current directory: {e}"))?;
+    // N.B.: Collecting the components drops any `.` components; e.g.: in `./script.sh`.
+    Ok(Some(cwd.join(script).components().collect()))
+}
+
 pub(crate) fn select_command(
     current_exe: &CurrentExe,
     jump: &Jump,
@@ -660,26 +1312,79 @@ pub(crate) fn select_command(
     installer: &Installer,
 ) -> Result<SelectedCmd, String> {
     let mut context = Context::new(&current_exe.exe, jump, lift, installer)?;
+    let result = context.select_command(lift.name.as_str(), current_exe);
+    // N.B.: Files we cannot trust count as missing here; so an untrusted read-only base is
+    // swapped for our own below and otherwise installing them reports why they are not trusted.
+    if let Ok(ref selected_cmd) = result {
+        if is_installed(&selected_cmd.files).unwrap_or(false) {
+            return result;
+        }
+    }
+
+    // The base may be a shared root populated ahead of time by an administrator and read-only to
+    // us. If it does not have everything we need, we fall back to our own per-user root.
+    if is_writable(&context.base) {
+        return result;
+    }
+    let fallback_base = fallback_base()?;
+    if fallback_base == context.base {
+        return result;
+    }
+    info!(
+        "The nce cache at {base} is read-only and does not contain all the files needed by \
+        {scie}; using {fallback_base} instead.",
+        base = context.base.display(),
+        scie = current_exe.exe.display(),
+        fallback_base = fallback_base.display()
+    );
+    let mut context = Context::with_base(
+        &current_exe.exe,
+        jump,
+        lift,
+        installer,
+        context.files_by_name,
+        fallback_base,
+    )?;
     context.select_command(lift.name.as_str(), current_exe)
 }
 
 #[cfg(test)]
 mod tests {
     use std::env;
+    use std::ffi::OsString;
     use std::path::{Path, PathBuf};
+    use std::time::Duration;
 
     use indexmap::IndexMap;
 
-    use super::Context;
+    use super::{Binding, Context, FileEntry};
     use crate::config::{ArchiveType, Boot, Cmd, Compression, FileType};
     use crate::installer::Installer;
-    use crate::{config, process, File, Jump, Lift, Process, Source};
+    use crate::{config, fingerprint, process, File, Jump, Lift, Process, Source};
+
+    #[test]
+    fn expandvars() {
+        env::set_var("__SCIE_EXPANDVARS_ROOT__", "/opt");
+        assert_eq!(
+            PathBuf::from("/opt/nce/opt/$__SCIE_EXPANDVARS_DNE__/${__SCIE_EXPANDVARS_DNE__}"),
+            super::expandvars(Path::new(
+                "$__SCIE_EXPANDVARS_ROOT__/nce${__SCIE_EXPANDVARS_ROOT__}/\
+                $__SCIE_EXPANDVARS_DNE__/${__SCIE_EXPANDVARS_DNE__}"
+            ))
+            .unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("{scie.env.FOO}/$"),
+            super::expandvars(Path::new("{scie.env.FOO}/$")).unwrap()
+        );
+    }
 
     #[test]
     fn env() {
         let jump = Jump {
             size: 42,
             version: "0.1.0".to_string(),
+            ..Default::default()
         };
         let lift = Lift {
             name: "test".to_string(),
@@ -692,6 +1397,14 @@ mod tests {
                     .unwrap(),
             ),
             load_dotenv: true,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
             size: 137,
             hash: "abc".to_string(),
             boot: Boot {
@@ -702,15 +1415,22 @@ mod tests {
                 name: "file".to_string(),
                 key: None,
                 size: 37,
+                extracted_size: None,
+                offset: None,
+                chunks: vec![],
                 hash: "def".to_string(),
                 file_type: FileType::Blob,
                 executable: None,
                 eager_extract: false,
+                execute_in_place: false,
                 source: Source::Scie,
+                source_url: None,
+                version: None,
+                license: None,
             }],
             other: None,
         };
-        let installer = Installer::new(&[]);
+        let installer = Installer::new(&[], 0);
         let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
 
         assert!(env::var_os("__DNE__").is_none());
@@ -718,16 +1438,14 @@ mod tests {
         let mut env = IndexMap::new();
         assert_eq!(
             ("".to_string(), false),
-            context
-                .reify_string(&mut env, "{scie.env.__DNE__}")
-                .unwrap()
+            context.reify_string(&env, "{scie.env.__DNE__}").unwrap()
         );
 
         env.clear();
         assert_eq!(
             ("default".to_string(), false),
             context
-                .reify_string(&mut env, "{scie.env.__DNE__=default}")
+                .reify_string(&env, "{scie.env.__DNE__=default}")
                 .unwrap()
         );
 
@@ -736,7 +1454,7 @@ mod tests {
         assert_eq!(
             ("foo".to_string(), false),
             context
-                .reify_string(&mut env, "{scie.env.__DNE__=default}")
+                .reify_string(&env, "{scie.env.__DNE__=default}")
                 .unwrap()
         );
 
@@ -744,7 +1462,7 @@ mod tests {
         assert_eq!(
             ("scie_path".to_string(), false),
             context
-                .reify_string(&mut env, "{scie.env.__DNE__={scie}}")
+                .reify_string(&env, "{scie.env.__DNE__={scie}}")
                 .unwrap()
         );
 
@@ -761,7 +1479,7 @@ mod tests {
                     .unwrap(),
                 false
             ),
-            context.reify_string(&mut env, "{scie.base}").unwrap()
+            context.reify_string(&env, "{scie.base}").unwrap()
         );
         assert_eq!(
             (
@@ -774,7 +1492,7 @@ mod tests {
                 true
             ),
             context
-                .reify_string(&mut env, "{scie.env.__DNE__={scie.lift}}")
+                .reify_string(&env, "{scie.env.__DNE__={scie.lift}}")
                 .unwrap()
         );
 
@@ -800,7 +1518,7 @@ mod tests {
                 false
             ),
             context
-                .reify_string(&mut env, "{scie.env.__DNE__={file}}")
+                .reify_string(&env, "{scie.env.__DNE__={file}}")
                 .unwrap()
         );
 
@@ -808,7 +1526,7 @@ mod tests {
         assert_eq!(
             ("42".to_string(), false),
             context
-                .reify_string(&mut env, "{scie.env.__DNE__={scie.env.__DNE2__=42}}")
+                .reify_string(&env, "{scie.env.__DNE__={scie.env.__DNE2__=42}}")
                 .unwrap()
         );
 
@@ -817,9 +1535,31 @@ mod tests {
         assert_eq!(
             ("bar".to_string(), false),
             context
-                .reify_string(&mut env, "{scie.env.__DNE__={scie.env.__DNE2__=42}}")
+                .reify_string(&env, "{scie.env.__DNE__={scie.env.__DNE2__=42}}")
                 .unwrap()
         );
+        assert!(!context
+            .expansions
+            .contains_key("{scie.env.__DNE__={scie.env.__DNE2__=42}}"));
+
+        // Expansions that do not depend on the env are memoized along with the files they need.
+        let file_path = expected_scie_base
+            .join("def")
+            .join("file")
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            (file_path.clone(), false),
+            context.reify_string(&env, "{file}").unwrap()
+        );
+        assert!(context.expansions.contains_key("{file}"));
+        context.replacements.clear();
+        assert_eq!(
+            (file_path, false),
+            context.reify_string(&env, "{file}").unwrap()
+        );
+        assert!(context.replacements.contains(&lift.files[0]));
     }
 
     #[test]
@@ -827,12 +1567,21 @@ mod tests {
         let jump = Jump {
             size: 42,
             version: "0.1.0".to_string(),
+            ..Default::default()
         };
         let lift = Lift {
             name: "test".to_string(),
             description: None,
             base: Some("/tmp/nce".to_string()),
             load_dotenv: true,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
             size: 137,
             hash: "abc".to_string(),
             boot: Boot {
@@ -854,8 +1603,7 @@ mod tests {
                         exe: "{scie.files.dist-{scie.env.SELECT=v1}}/\
                             {scie.env.SUB_SELECT-{scie.env.SELECT=v2}}"
                             .to_string(),
-                        args: vec![],
-                        description: None,
+                        ..Default::default()
                     },
                 )]
                 .into_iter()
@@ -867,26 +1615,40 @@ mod tests {
                     name: "dist-v1".to_string(),
                     key: None,
                     size: 37,
+                    extracted_size: None,
+                    offset: None,
+                    chunks: vec![],
                     hash: "def".to_string(),
                     file_type: FileType::Archive(ArchiveType::CompressedTar(Compression::Zstd)),
                     executable: None,
                     eager_extract: false,
+                    execute_in_place: false,
                     source: Source::Scie,
+                    source_url: None,
+                    version: None,
+                    license: None,
                 },
                 File {
                     name: "dist-v2".to_string(),
                     key: None,
                     size: 42,
+                    extracted_size: None,
+                    offset: None,
+                    chunks: vec![],
                     hash: "ghi".to_string(),
                     file_type: FileType::Archive(ArchiveType::Zip),
                     executable: None,
                     eager_extract: false,
+                    execute_in_place: false,
                     source: Source::Scie,
+                    source_url: None,
+                    version: None,
+                    license: None,
                 },
             ],
             other: None,
         };
-        let installer = Installer::new(&[]);
+        let installer = Installer::new(&[], 0);
         let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
 
         let cmd = lift.boot.commands.get("").unwrap();
@@ -897,7 +1659,7 @@ mod tests {
             ],
         };
 
-        let process = context.prepare_process(&cmd).unwrap();
+        let process = context.prepare_process("", cmd).unwrap();
         assert_eq!(
             Process {
                 env: expected_env.clone(),
@@ -906,12 +1668,15 @@ mod tests {
                     .join("dist-v1/v2/binary")
                     .into(),
                 args: vec![],
+                cwd: None,
+                exe_fd: None,
+                timeout: None,
             },
             process
         );
 
         env::set_var("SELECT", "v1");
-        let process = context.prepare_process(&cmd).unwrap();
+        let process = context.prepare_process("", cmd).unwrap();
         assert_eq!(
             Process {
                 env: expected_env.clone(),
@@ -920,13 +1685,16 @@ mod tests {
                     .join("dist-v1/v1/exe")
                     .into(),
                 args: vec![],
+                cwd: None,
+                exe_fd: None,
+                timeout: None,
             },
             process
         );
         env::remove_var("SELECT");
 
         env::set_var("SELECT", "v2");
-        let process = context.prepare_process(&cmd).unwrap();
+        let process = context.prepare_process("", cmd).unwrap();
         assert_eq!(
             Process {
                 env: expected_env,
@@ -935,6 +1703,9 @@ mod tests {
                     .join("dist-v2/v2/binary")
                     .into(),
                 args: vec![],
+                cwd: None,
+                exe_fd: None,
+                timeout: None,
             },
             process
         );
@@ -946,12 +1717,21 @@ mod tests {
         let jump = Jump {
             size: 42,
             version: "0.1.0".to_string(),
+            ..Default::default()
         };
         let lift = Lift {
             name: "test".to_string(),
             description: None,
             base: Some("/tmp/nce".to_string()),
             load_dotenv: true,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
             size: 137,
             hash: "abc".to_string(),
             boot: Boot {
@@ -983,8 +1763,7 @@ mod tests {
                         .into_iter()
                         .collect::<IndexMap<_, _>>(),
                         exe: "{scie.env.A}".to_string(),
-                        args: vec![],
-                        description: None,
+                        ..Default::default()
                     },
                 )]
                 .into_iter()
@@ -994,12 +1773,12 @@ mod tests {
             files: vec![],
             other: None,
         };
-        let installer = Installer::new(&[]);
+        let installer = Installer::new(&[], 0);
         let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
 
         let cmd = lift.boot.commands.get("").unwrap();
 
-        let process = context.prepare_process(&cmd).unwrap();
+        let process = context.prepare_process("", cmd).unwrap();
         let reified_path = format!("c:e:{}", env::var("PATH").unwrap());
         assert_eq!(
             Process {
@@ -1014,12 +1793,15 @@ mod tests {
                 },
                 exe: "c".into(),
                 args: vec![],
+                cwd: None,
+                exe_fd: None,
+                timeout: None,
             },
             process
         );
 
         env::set_var("D", "d");
-        let process = context.prepare_process(&cmd).unwrap();
+        let process = context.prepare_process("", cmd).unwrap();
         let reified_path = format!("d:e:{}", env::var("PATH").unwrap());
         assert_eq!(
             Process {
@@ -1034,9 +1816,559 @@ mod tests {
                 },
                 exe: "d".into(),
                 args: vec![],
+                cwd: None,
+                exe_fd: None,
+                timeout: None,
             },
             process
         );
         env::remove_var("D");
     }
+
+    #[test]
+    fn prepare_process_cwd() {
+        let jump = Jump {
+            size: 42,
+            version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        let lift = Lift {
+            name: "test".to_string(),
+            description: None,
+            base: Some("/tmp/nce".to_string()),
+            load_dotenv: false,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
+            size: 137,
+            hash: "abc".to_string(),
+            boot: Boot {
+                commands: vec![(
+                    "".to_owned(),
+                    Cmd {
+                        exe: "{app}/bin/app".to_string(),
+                        args: vec![
+                            "--cache-key={scie.files.app.hash}".to_string(),
+                            "--cache-size={scie.files.app.size}".to_string(),
+                        ],
+                        cwd: Some("{app}/{scie.env.APP_SUBDIR=lib}".to_string()),
+                        timeout: Some(30),
+                        prepend_path: vec!["{app}/bin".to_string()],
+                        ..Default::default()
+                    },
+                )]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+                bindings: Default::default(),
+            },
+            files: vec![File {
+                name: "app".to_string(),
+                key: None,
+                size: 37,
+                extracted_size: None,
+                offset: None,
+                chunks: vec![],
+                hash: "def".to_string(),
+                file_type: FileType::Archive(ArchiveType::Zip),
+                executable: None,
+                eager_extract: false,
+                execute_in_place: false,
+                source: Source::Scie,
+                source_url: None,
+                version: None,
+                license: None,
+            }],
+            other: None,
+        };
+        let installer = Installer::new(&[], 0);
+        let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
+
+        let cmd = lift.boot.commands.get("").unwrap();
+        let process = context.prepare_process("", cmd).unwrap();
+        let app_dir = PathBuf::from("/tmp/nce").join("def").join("app");
+        let mut path = vec![app_dir.join("bin")];
+        path.extend(env::split_paths(&env::var_os("PATH").unwrap()));
+        assert_eq!(
+            Process {
+                env: process::EnvVars {
+                    vars: vec![process::EnvVar::Replace((
+                        "PATH".into(),
+                        env::join_paths(path).unwrap()
+                    ))]
+                },
+                exe: app_dir.join("bin/app").into(),
+                args: vec!["--cache-key=def".into(), "--cache-size=37".into()],
+                cwd: Some(app_dir.join("lib").into()),
+                exe_fd: None,
+                timeout: Some(Duration::from_secs(30)),
+            },
+            process
+        );
+    }
+
+    #[test]
+    fn prepare_process_script() {
+        let jump = Jump {
+            size: 42,
+            version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        let lift = Lift {
+            name: "test".to_string(),
+            description: None,
+            base: Some("/tmp/nce".to_string()),
+            load_dotenv: false,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
+            size: 137,
+            hash: "abc".to_string(),
+            boot: Boot {
+                commands: vec![(
+                    "".to_owned(),
+                    Cmd {
+                        exe: "/usr/bin/python3".to_string(),
+                        args: vec!["-I".to_string(), "{scie.script}".to_string()],
+                        shebang: true,
+                        ..Default::default()
+                    },
+                )]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+                bindings: Default::default(),
+            },
+            files: vec![],
+            other: None,
+        };
+        let installer = Installer::new(&[], 0);
+        let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
+
+        let cmd = lift.boot.commands.get("").unwrap();
+        assert_eq!(
+            "The {scie.script} placeholder is only available when the scie is run as the \
+            interpreter of a script.",
+            context.prepare_process("", cmd).unwrap_err()
+        );
+
+        context.script = Some(PathBuf::from("/home/jane/bin/tool.py"));
+        assert_eq!(
+            vec![
+                OsString::from("-I"),
+                OsString::from("/home/jane/bin/tool.py")
+            ],
+            context.prepare_process("", cmd).unwrap().args
+        );
+    }
+
+    #[test]
+    fn prepare_process_additional_files() {
+        let jump = Jump {
+            size: 42,
+            version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        let cmd = |exe: &str, additional_files: &[&str]| Cmd {
+            exe: exe.to_string(),
+            additional_files: additional_files.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        };
+        let file = |name: &str, hash: &str| File {
+            name: name.to_string(),
+            key: None,
+            size: 37,
+            extracted_size: None,
+            offset: None,
+            chunks: vec![],
+            hash: hash.to_string(),
+            file_type: FileType::Blob,
+            executable: None,
+            eager_extract: false,
+            execute_in_place: false,
+            source: Source::Scie,
+            source_url: None,
+            version: None,
+            license: None,
+        };
+        let lift = Lift {
+            name: "test".to_string(),
+            description: None,
+            base: Some("/tmp/nce".to_string()),
+            load_dotenv: false,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
+            size: 137,
+            hash: "abc".to_string(),
+            boot: Boot {
+                commands: vec![
+                    ("run-tool".to_owned(), cmd("{tool}", &["lib"])),
+                    ("other".to_owned(), cmd("{other}", &["run-tool"])),
+                    ("loop1".to_owned(), cmd("{other}", &["loop2"])),
+                    ("loop2".to_owned(), cmd("{other}", &["loop1"])),
+                    ("self".to_owned(), cmd("{other}", &["self"])),
+                    ("cycle1".to_owned(), cmd("{other}", &["cycle2"])),
+                    ("cycle2".to_owned(), cmd("{other}", &["cycle3"])),
+                    ("cycle3".to_owned(), cmd("{other}", &["cycle1"])),
+                    ("bad".to_owned(), cmd("{other}", &["dne"])),
+                ]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+                bindings: Default::default(),
+            },
+            files: vec![
+                file("tool", "def"),
+                file("lib", "ghi"),
+                file("other", "jkl"),
+                file("unused", "mno"),
+            ],
+            other: None,
+        };
+        let installer = Installer::new(&[], 0);
+        let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
+
+        context
+            .prepare_process("other", lift.boot.commands.get("other").unwrap())
+            .unwrap();
+        let mut replacements = context
+            .replacements
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect::<Vec<_>>();
+        replacements.sort();
+        assert_eq!(vec!["lib", "other", "tool"], replacements);
+
+        assert_eq!(
+            "The additional_files of command loop1 transitively include its own files: \
+            loop1 -> loop2 -> loop1",
+            context
+                .prepare_process("loop1", lift.boot.commands.get("loop1").unwrap())
+                .unwrap_err()
+        );
+        assert_eq!(
+            "The additional_files of command self transitively include its own files: \
+            self -> self",
+            context
+                .prepare_process("self", lift.boot.commands.get("self").unwrap())
+                .unwrap_err()
+        );
+        assert_eq!(
+            "The additional_files of command cycle1 transitively include its own files: \
+            cycle1 -> cycle2 -> cycle3 -> cycle1",
+            context
+                .prepare("cycle1", lift.boot.commands.get("cycle1").unwrap(), false)
+                .unwrap_err()
+        );
+        assert!(context
+            .prepare_process("bad", lift.boot.commands.get("bad").unwrap())
+            .unwrap_err()
+            .contains("The additional file dne is neither"));
+
+        // N.B.: Only the files the prepared command needs are extracted, regardless of the files
+        // needed by any command that was prepared before it.
+        let (_, files) = context
+            .prepare(
+                "run-tool",
+                lift.boot.commands.get("run-tool").unwrap(),
+                false,
+            )
+            .unwrap();
+        let installs = files
+            .iter()
+            .filter_map(|entry| match entry {
+                FileEntry::Install((file, _)) => Some(file.name.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["tool", "lib"], installs);
+    }
+
+    #[test]
+    fn prepare_entry_range() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bundle = tmp.path().join("bundle");
+        std::fs::write(&bundle, "header|library bytes|trailer").unwrap();
+
+        let jump = Jump {
+            size: 0,
+            version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        let file = |name: &str, contents: &[u8], offset: Option<usize>, source: Source| File {
+            name: name.to_string(),
+            key: None,
+            size: contents.len(),
+            extracted_size: None,
+            offset,
+            chunks: vec![],
+            hash: fingerprint::digest(contents),
+            file_type: FileType::Blob,
+            executable: None,
+            eager_extract: false,
+            execute_in_place: false,
+            source,
+            source_url: None,
+            version: None,
+            license: None,
+        };
+        let tool = b"tool bytes";
+        let lift = Lift {
+            name: "test".to_string(),
+            description: None,
+            base: Some(tmp.path().join("nce").to_str().unwrap().to_string()),
+            load_dotenv: false,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
+            size: 137,
+            hash: "abc".to_string(),
+            boot: Boot {
+                commands: vec![(
+                    "".to_owned(),
+                    Cmd {
+                        exe: "{tool}".to_string(),
+                        additional_files: vec!["lib".to_string()],
+                        ..Default::default()
+                    },
+                )]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+                bindings: Default::default(),
+            },
+            files: vec![
+                file("tool", tool, Some(0), Source::Scie),
+                file(
+                    "lib",
+                    b"library bytes",
+                    None,
+                    Source::EntryRange {
+                        url: format!("file://{path}", path = bundle.display()),
+                        mirrors: vec![],
+                        offset: 7,
+                    },
+                ),
+            ],
+            other: None,
+        };
+        let installer = Installer::new(tool, 0);
+        let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
+        let (_, files) = context
+            .prepare("", lift.boot.commands.get("").unwrap(), false)
+            .unwrap();
+        installer.install(&files).unwrap();
+        assert_eq!(
+            b"tool bytes".as_slice(),
+            std::fs::read(context.get_path(&lift.files[0])).unwrap()
+        );
+        assert_eq!(
+            b"library bytes".as_slice(),
+            std::fs::read(context.get_path(&lift.files[1])).unwrap()
+        );
+    }
+
+    #[test]
+    fn verify_before_install() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = |name: &str, contents: &[u8], offset: usize| File {
+            name: name.to_string(),
+            key: None,
+            size: contents.len(),
+            extracted_size: None,
+            offset: Some(offset),
+            chunks: vec![],
+            hash: fingerprint::digest(contents),
+            file_type: FileType::Blob,
+            executable: None,
+            eager_extract: false,
+            execute_in_place: false,
+            source: Source::Scie,
+            source_url: None,
+            version: None,
+            license: None,
+        };
+        let installer = Installer::new(b"firstsecond", 0);
+        let entries = |second: &[u8]| {
+            vec![
+                FileEntry::Install((file("first", b"first", 0), tmp.path().join("first"))),
+                FileEntry::Install((file("second", second, 5), tmp.path().join("second"))),
+            ]
+        };
+
+        // All files are verified before any are installed.
+        let err = installer
+            .install(&entries(b"secund"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unexpected hash"), "{err}");
+        assert!(!tmp.path().join("first").exists());
+
+        installer.install(&entries(b"second")).unwrap();
+        assert_eq!(
+            b"first".as_slice(),
+            std::fs::read(tmp.path().join("first")).unwrap()
+        );
+        assert_eq!(
+            b"second".as_slice(),
+            std::fs::read(tmp.path().join("second")).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn binding_retry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let attempts = tmp.path().join("attempts");
+        let binding = |name: &str, retries: u32| Binding {
+            target: tmp.path().join("locks").join(name),
+            process: Process {
+                env: process::EnvVars { vars: vec![] },
+                exe: "/bin/sh".into(),
+                args: vec![
+                    "-c".into(),
+                    "echo x >> \"$0\"; count=$(wc -l < \"$0\"); \
+                    echo \"ATTEMPT=$count\" >> \"$SCIE_BINDING_ENV\"; \
+                    [ \"$count\" -ge 3 ]"
+                        .into(),
+                    attempts.clone().into(),
+                ],
+                cwd: None,
+                exe_fd: None,
+                timeout: None,
+            },
+            retries,
+            backoff: Duration::ZERO,
+        };
+
+        let error = binding("flaky", 1).execute(|| Ok(())).unwrap_err();
+        assert!(
+            error.ends_with("Gave up on the boot binding after 2 attempts."),
+            "{error}"
+        );
+
+        std::fs::remove_file(&attempts).unwrap();
+        let flaky = binding("flaky", 2);
+        let env = flaky.execute(|| Ok(())).unwrap();
+        assert_eq!(
+            vec![("ATTEMPT".to_string(), "3".to_string())],
+            env.into_iter().collect::<Vec<_>>()
+        );
+
+        // A binding that succeeded is not run again.
+        assert_eq!(
+            "3",
+            flaky.execute(|| Ok(())).unwrap().get("ATTEMPT").unwrap()
+        );
+        assert_eq!(
+            3,
+            std::fs::read_to_string(&attempts).unwrap().lines().count()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn binding_outputs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let jump = Jump {
+            size: 42,
+            version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        let cmd = |exe: &str, args: &[&str]| Cmd {
+            exe: exe.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        };
+        let lift = Lift {
+            name: "test".to_string(),
+            description: None,
+            base: Some(tmp.path().to_str().unwrap().to_string()),
+            load_dotenv: false,
+            boot_timing: false,
+            fetch: None,
+            permissions: None,
+            integrity: None,
+            unicode_normalization: None,
+            keep_junk: false,
+            zips: None,
+            tmp: None,
+            size: 137,
+            hash: "abc".to_string(),
+            boot: Boot {
+                commands: vec![
+                    (
+                        "".to_owned(),
+                        cmd(
+                            "{scie.bindings.venv.setup.path}/bin/python",
+                            &[
+                                "{scie.bindings.venv.setup.python.version}",
+                                "{scie.bindings.venv.setup.python.free_threaded}",
+                                "{scie.bindings.venv.setup}",
+                            ],
+                        ),
+                    ),
+                    (
+                        "missing".to_owned(),
+                        cmd("{scie.bindings.venv.setup.python.abi}", &[]),
+                    ),
+                ]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+                bindings: vec![(
+                    "venv.setup".to_owned(),
+                    cmd(
+                        "/bin/sh",
+                        &[
+                            "-c",
+                            r#"echo '{{"path": "/opt/venv", "python": {{"version": 3.12, "free_threaded": false}}' > "$SCIE_BINDING_OUTPUTS""#,
+                        ],
+                    ),
+                )]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+            },
+            files: vec![],
+            other: None,
+        };
+        let installer = Installer::new(&[], 0);
+        let mut context = Context::new(Path::new("scie_path"), &jump, &lift, &installer).unwrap();
+
+        let process = context
+            .prepare_process("", lift.boot.commands.get("").unwrap())
+            .unwrap();
+        assert_eq!(OsString::from("/opt/venv/bin/python"), process.exe);
+        assert_eq!(
+            vec![
+                OsString::from("3.12"),
+                OsString::from("false"),
+                tmp.path().join("abc").join("bindings").into_os_string()
+            ],
+            process.args
+        );
+
+        assert_eq!(
+            "The boot binding venv.setup has no output named python.abi.",
+            context
+                .prepare_process("missing", lift.boot.commands.get("missing").unwrap())
+                .unwrap_err()
+        );
+    }
 }
@@ -0,0 +1,518 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Binary patches that turn one release of a scie into the next.
+//!
+//! A patch describes the new scie as a sequence of ranges copied from the old scie and of bytes
+//! that are new. Both scies are cut into content-defined segments; along the chunks recorded in
+//! their lift manifests when they were packed with `boot-pack --chunk` and with FastCDC elsewhere.
+//! Segments of the new scie found in the old scie are copied from it and all other bytes are
+//! stored in the patch, deflated.
+//!
+//! The patch format is a `scie-patch` line followed by a single line JSON header describing the
+//! old and new scies and the operations that turn one into the other; followed in turn by the
+//! deflated bytes the insert operations consume, in order.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use logging_timer::time;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::pack::finalize_executable;
+use crate::{chunk, embed, fingerprint, JumpError};
+
+const MAGIC: &[u8] = b"scie-patch\n";
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Scie {
+    size: usize,
+    hash: String,
+}
+
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Op {
+    /// Copies `size` bytes from `offset` in the old scie.
+    Copy { offset: usize, size: usize },
+    /// Inserts the next `size` bytes stored in the patch.
+    Insert { size: usize },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Header {
+    old: Scie,
+    new: Scie,
+    ops: Vec<Op>,
+}
+
+/// Describes a patch written by [`delta`].
+#[derive(Debug, Serialize)]
+pub struct Delta {
+    pub patch: PathBuf,
+    /// The size of the patch file.
+    pub size: u64,
+    /// The size of the new scie.
+    pub new_size: usize,
+    /// How many bytes of the new scie are copied from the old scie.
+    pub copied: usize,
+    /// How many bytes of the new scie are stored in the patch.
+    pub inserted: usize,
+}
+
+fn read(path: &Path) -> Result<impl std::ops::Deref<Target = [u8]>, JumpError> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| JumpError::io(format!("Failed to open {path}", path = path.display()), e))?;
+    crate::map(&file)
+        .map_err(|e| JumpError::io(format!("Failed to read {path}", path = path.display()), e))
+}
+
+// Cuts the scie along the chunks its lift manifest records, if any, and cuts the rest with FastCDC.
+fn segments(path: &Path, data: &[u8]) -> Result<Vec<Range<usize>>, JumpError> {
+    let (_, lift) = crate::lift::load_scie(path, embed::scie_data(data)).map_err(|e| {
+        e.context(|message| {
+            format!(
+                "The file at {path} is not a scie: {message}",
+                path = path.display()
+            )
+        })
+    })?;
+    let mut chunks = if embed::payload_end(data).is_some() {
+        vec![]
+    } else {
+        lift.files
+            .iter()
+            .flat_map(|file| &file.chunks)
+            .map(|chunk| chunk.offset..chunk.offset + chunk.size)
+            .filter(|range| range.end <= data.len())
+            .collect::<Vec<_>>()
+    };
+    chunks.sort_by_key(|range| (range.start, range.end));
+    chunks.dedup();
+
+    let mut segments = vec![];
+    let mut start = 0;
+    for range in chunks
+        .into_iter()
+        .chain(std::iter::once(data.len()..data.len()))
+    {
+        if range.start < start {
+            continue;
+        }
+        segments.extend(
+            chunk::cut(&data[start..range.start])
+                .into_iter()
+                .map(|cut| start + cut.start..start + cut.end),
+        );
+        if !range.is_empty() {
+            segments.push(range.clone());
+        }
+        start = range.end;
+    }
+    Ok(segments)
+}
+
+/// Writes a patch to `patch` that turns the scie at `old` into the scie at `new`.
+#[time("debug", "delta::{}")]
+pub fn delta(old: &Path, new: &Path, patch: &Path) -> Result<Delta, JumpError> {
+    let old_data = read(old)?;
+    let new_data = read(new)?;
+
+    let mut old_segments = HashMap::new();
+    for range in segments(old, &old_data)? {
+        old_segments
+            .entry(fingerprint::digest(&old_data[range.clone()]))
+            .or_insert(range.start);
+    }
+    let mut ops: Vec<Op> = vec![];
+    let (mut copied, mut inserted) = (0, 0);
+    for range in segments(new, &new_data)? {
+        let size = range.len();
+        match old_segments.get(&fingerprint::digest(&new_data[range])) {
+            Some(&offset) => {
+                copied += size;
+                match ops.last_mut() {
+                    Some(Op::Copy {
+                        offset: last_offset,
+                        size: last_size,
+                    }) if *last_offset + *last_size == offset => *last_size += size,
+                    _ => ops.push(Op::Copy { offset, size }),
+                }
+            }
+            None => {
+                inserted += size;
+                match ops.last_mut() {
+                    Some(Op::Insert { size: last_size }) => *last_size += size,
+                    _ => ops.push(Op::Insert { size }),
+                }
+            }
+        }
+    }
+    let header = Header {
+        old: Scie {
+            size: old_data.len(),
+            hash: fingerprint::digest(&old_data),
+        },
+        new: Scie {
+            size: new_data.len(),
+            hash: fingerprint::digest(&new_data),
+        },
+        ops,
+    };
+
+    let write_error = |e: std::io::Error| {
+        JumpError::io(
+            format!("Failed to write the patch {patch}", patch = patch.display()),
+            e,
+        )
+    };
+    let mut patch_file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(patch)
+        .map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to open {patch} for writing the patch",
+                    patch = patch.display()
+                ),
+                e,
+            )
+        })?;
+    patch_file.write_all(MAGIC).map_err(write_error)?;
+    serde_json::to_writer(&mut patch_file, &header)
+        .map_err(|e| JumpError::Other(format!("Failed to serialize the patch header: {e}")))?;
+    patch_file.write_all(b"\n").map_err(write_error)?;
+    let mut encoder = DeflateEncoder::new(patch_file, Compression::best());
+    let mut offset = 0;
+    for op in &header.ops {
+        let size = match op {
+            Op::Copy { size, .. } => *size,
+            Op::Insert { size } => {
+                encoder
+                    .write_all(&new_data[offset..offset + size])
+                    .map_err(write_error)?;
+                *size
+            }
+        };
+        offset += size;
+    }
+    let patch_file = encoder.finish().map_err(write_error)?;
+    let size = patch_file
+        .metadata()
+        .map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to determine the size of the patch {patch}",
+                    patch = patch.display()
+                ),
+                e,
+            )
+        })?
+        .len();
+    Ok(Delta {
+        patch: patch.to_path_buf(),
+        size,
+        new_size: new_data.len(),
+        copied,
+        inserted,
+    })
+}
+
+/// Applies a patch written by [`delta`] to the scie it was made from.
+///
+/// The scie is checked to be the one the patch was made from before the patch is applied and the
+/// patched scie is checked to be the one the patch was made for.
+pub struct Patch<'a> {
+    scie: &'a Path,
+    patch: &'a Path,
+}
+
+impl<'a> Patch<'a> {
+    pub fn new(scie: &'a Path, patch: &'a Path) -> Self {
+        Self { scie, patch }
+    }
+
+    /// Patches the scie over itself.
+    ///
+    /// The patched scie is written to a temporary file beside the scie first and then moved into
+    /// place; so the scie is never left partially patched.
+    pub fn write_in_place(self) -> Result<PathBuf, JumpError> {
+        let scie = self.scie;
+        let parent = scie
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let file_name = scie.file_name().ok_or_else(|| {
+            JumpError::Config(format!(
+                "The scie path {scie} has no file name.",
+                scie = scie.display()
+            ))
+        })?;
+        let work_dir = tempfile::tempdir_in(parent).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to create a temporary directory in {parent} for patching {scie}",
+                    parent = parent.display(),
+                    scie = scie.display()
+                ),
+                e,
+            )
+        })?;
+        let dest = work_dir.path().join(file_name);
+        let dest = self.write(&dest)?;
+        std::fs::rename(&dest, scie).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to move the patched scie {dest} into place at {scie}",
+                    dest = dest.display(),
+                    scie = scie.display()
+                ),
+                e,
+            )
+        })?;
+        Ok(scie.to_path_buf())
+    }
+
+    /// Writes the patched scie to `dest`, which must not exist yet, and returns the path of the
+    /// executable written.
+    #[time("debug", "Patch::{}")]
+    pub fn write(self, dest: &Path) -> Result<PathBuf, JumpError> {
+        let old_data = read(self.scie)?;
+        let read_error = |e: std::io::Error| {
+            JumpError::io(
+                format!(
+                    "Failed to read the patch {patch}",
+                    patch = self.patch.display()
+                ),
+                e,
+            )
+        };
+        let patch_error = |e: &dyn std::fmt::Display| {
+            JumpError::Config(format!(
+                "Failed to read the patch {patch}: {e}",
+                patch = self.patch.display()
+            ))
+        };
+        let mut patch = BufReader::new(std::fs::File::open(self.patch).map_err(read_error)?);
+        let mut magic = vec![];
+        patch
+            .by_ref()
+            .take(MAGIC.len() as u64)
+            .read_to_end(&mut magic)
+            .map_err(read_error)?;
+        if MAGIC != magic {
+            return Err(JumpError::Config(format!(
+                "The file at {patch} is not a scie patch.",
+                patch = self.patch.display()
+            )));
+        }
+        let mut header = vec![];
+        patch.read_until(b'\n', &mut header).map_err(read_error)?;
+        let header: Header = serde_json::from_slice(&header).map_err(|e| patch_error(&e))?;
+        if header.old.size != old_data.len() || header.old.hash != fingerprint::digest(&old_data) {
+            return Err(JumpError::Config(format!(
+                "The patch {patch} does not apply to {scie}; it was made from a scie of size {size} \
+                with hash {hash}.",
+                patch = self.patch.display(),
+                scie = self.scie.display(),
+                size = header.old.size,
+                hash = header.old.hash
+            )));
+        }
+
+        let mut binary = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dest)
+            .map_err(|e| {
+                JumpError::io(
+                    format!(
+                        "Failed to open {dest} for writing the patched scie",
+                        dest = dest.display()
+                    ),
+                    e,
+                )
+            })?;
+        let write_error = |e: std::io::Error| {
+            JumpError::io(
+                format!(
+                    "Failed to write the patched scie {dest}",
+                    dest = dest.display()
+                ),
+                e,
+            )
+        };
+        let mut inserts = DeflateDecoder::new(patch);
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        for op in header.ops {
+            match op {
+                Op::Copy {
+                    offset,
+                    size: copy_size,
+                } => {
+                    let bytes = offset
+                        .checked_add(copy_size)
+                        .and_then(|end| old_data.get(offset..end))
+                        .ok_or_else(|| {
+                            JumpError::Config(format!(
+                                "The patch {patch} copies {copy_size} bytes at offset {offset} \
+                                which lie outside {scie}.",
+                                patch = self.patch.display(),
+                                scie = self.scie.display()
+                            ))
+                        })?;
+                    hasher.update(bytes);
+                    binary.write_all(bytes).map_err(write_error)?;
+                    size += copy_size;
+                }
+                Op::Insert { size: insert_size } => {
+                    let mut bytes = vec![];
+                    inserts
+                        .by_ref()
+                        .take(insert_size as u64)
+                        .read_to_end(&mut bytes)
+                        .map_err(read_error)?;
+                    if bytes.len() != insert_size {
+                        return Err(patch_error(&"it is truncated"));
+                    }
+                    hasher.update(&bytes);
+                    binary.write_all(&bytes).map_err(write_error)?;
+                    size += insert_size;
+                }
+            }
+        }
+        drop(binary);
+        let hash = format!("{digest:x}", digest = hasher.finalize());
+        if header.new.size != size || header.new.hash != hash {
+            let _ = std::fs::remove_file(dest);
+            return Err(JumpError::Integrity(format!(
+                "Patching {scie} with {patch} produced a scie of size {size} with hash {hash} but \
+                expected a scie of size {expected_size} with hash {expected_hash}.",
+                scie = self.scie.display(),
+                patch = self.patch.display(),
+                expected_size = header.new.size,
+                expected_hash = header.new.hash
+            )));
+        }
+        finalize_executable(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::{delta, Patch};
+    use crate::chunk::noise;
+    use crate::config::UnknownFields;
+    use crate::pack::{load_manifest, load_scie_jump, pack, PackOptions};
+    use crate::{JumpError, PackCache, EOF_MAGIC};
+
+    fn pack_release(dir: &Path, scie_jump: &Path, archive: &[u8], chunk: bool) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("archive.bin"), archive).unwrap();
+        std::fs::write(dir.join("tool.sh"), "echo tool").unwrap();
+        std::fs::write(
+            dir.join("lift.json"),
+            r#"{"scie": {"lift": {"name": "tool", "files": [{"name": "archive.bin"},
+            {"name": "tool.sh"}], "boot": {"commands": {"": {"exe": "{tool.sh}"}}}}}}"#,
+        )
+        .unwrap();
+        let jump = load_scie_jump(scie_jump).unwrap();
+        let (lift, manifest) =
+            load_manifest(dir, &jump, UnknownFields::Deny, &mut PackCache::default()).unwrap();
+        pack(
+            lift,
+            &manifest,
+            &jump,
+            scie_jump,
+            dir,
+            &PackOptions {
+                lift_in_zip: true,
+                chunk,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .binary
+    }
+
+    #[test]
+    fn round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let mut scie_jump = b"#!/bin/sh\n".to_vec();
+        let size = scie_jump.len() as u32 + 8;
+        scie_jump.write_u32::<LittleEndian>(size).unwrap();
+        scie_jump.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+        let scie_jump_path = dir.join("scie-jump");
+        std::fs::write(&scie_jump_path, scie_jump).unwrap();
+
+        let archive = noise(12 * 1024 * 1024, 1);
+        let mut edited = archive.clone();
+        edited[6 * 1024 * 1024..6 * 1024 * 1024 + 5].copy_from_slice(b"patch");
+
+        for chunk in [true, false] {
+            let work = dir.join(format!("chunk-{chunk}"));
+            let old = pack_release(&work.join("old"), &scie_jump_path, &archive, chunk);
+            let new = pack_release(&work.join("new"), &scie_jump_path, &edited, chunk);
+
+            let patch = work.join("patch");
+            let delta = delta(&old, &new, &patch).unwrap();
+            let new_size = std::fs::metadata(&new).unwrap().len();
+            assert_eq!(new_size as usize, delta.new_size);
+            assert_eq!(delta.new_size, delta.copied + delta.inserted);
+            assert!(delta.inserted < 5 * 1024 * 1024, "{delta:?}");
+            assert!(delta.size < new_size / 2, "{delta:?}");
+
+            let patched = Patch::new(&old, &patch)
+                .write(&work.join("patched"))
+                .unwrap();
+            assert_eq!(
+                std::fs::read(&new).unwrap(),
+                std::fs::read(patched).unwrap()
+            );
+
+            // A patch only applies to the scie it was made from.
+            let err = Patch::new(&new, &patch)
+                .write(&work.join("mispatched"))
+                .unwrap_err();
+            assert!(matches!(err, JumpError::Config(_)), "{err:?}");
+            assert!(err.to_string().contains("does not apply"), "{err}");
+            assert!(!work.join("mispatched").exists());
+
+            Patch::new(&old, &patch).write_in_place().unwrap();
+            assert_eq!(std::fs::read(&new).unwrap(), std::fs::read(&old).unwrap());
+        }
+    }
+
+    #[test]
+    fn not_a_patch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let scie = tmp.path().join("scie");
+        let patch = tmp.path().join("patch");
+        std::fs::write(&scie, "scie").unwrap();
+        std::fs::write(&patch, "not a patch").unwrap();
+        let err = Patch::new(&scie, &patch)
+            .write(&tmp.path().join("dest"))
+            .unwrap_err();
+        assert!(matches!(err, JumpError::Config(_)), "{err:?}");
+        assert_eq!(
+            format!(
+                "The file at {patch} is not a scie patch.",
+                patch = patch.display()
+            ),
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::config::{Cmd, Config, File, Jump};
+
+/// A difference in one named item between two scies.
+///
+/// The item was added if there is no `before`, removed if there is no `after` and changed
+/// otherwise.
+#[derive(Debug, Serialize)]
+pub struct Change<T> {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<T>,
+}
+
+impl<T> Change<T> {
+    pub fn kind(&self) -> &'static str {
+        match (&self.before, &self.after) {
+            (None, _) => "added",
+            (_, None) => "removed",
+            _ => "changed",
+        }
+    }
+}
+
+/// The differences between the lift manifests of two scies.
+#[derive(Debug, Default, Serialize)]
+pub struct ScieDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jump: Option<Change<Jump>>,
+    pub files: Vec<Change<File>>,
+    pub commands: Vec<Change<Cmd>>,
+    pub bindings: Vec<Change<Cmd>>,
+}
+
+impl ScieDiff {
+    pub fn is_empty(&self) -> bool {
+        self.jump.is_none()
+            && self.files.is_empty()
+            && self.commands.is_empty()
+            && self.bindings.is_empty()
+    }
+}
+
+fn diff_named<T, F>(
+    before: IndexMap<String, T>,
+    mut after: IndexMap<String, T>,
+    same: F,
+) -> Vec<Change<T>>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let mut changes = vec![];
+    for (name, before) in before {
+        match after.shift_remove(&name) {
+            None => changes.push(Change {
+                name,
+                before: Some(before),
+                after: None,
+            }),
+            Some(after) if !same(&before, &after) => changes.push(Change {
+                name,
+                before: Some(before),
+                after: Some(after),
+            }),
+            Some(_) => {}
+        }
+    }
+    changes.extend(after.into_iter().map(|(name, after)| Change {
+        name,
+        before: None,
+        after: Some(after),
+    }));
+    changes
+}
+
+fn same_file(before: &File, after: &File) -> bool {
+    // N.B.: A file's offset changes whenever a file packed ahead of it changes size; so it is not
+    // a change to the file itself.
+    File {
+        offset: None,
+        ..before.clone()
+    } == File {
+        offset: None,
+        ..after.clone()
+    }
+}
+
+fn files(config: Config) -> IndexMap<String, File> {
+    config
+        .scie
+        .lift
+        .files
+        .into_iter()
+        .map(|file| (file.name.clone(), file))
+        .collect()
+}
+
+/// Compares the lift manifests of two scies, reporting the files, commands and bindings added,
+/// removed or changed going from `before` to `after`.
+pub fn diff(before: Config, after: Config) -> ScieDiff {
+    let jump = match (&before.scie.jump, &after.scie.jump) {
+        (Some(before), Some(after)) if before == after => None,
+        (None, None) => None,
+        (before_jump, after_jump) => Some(Change {
+            name: "scie-jump".to_string(),
+            before: before_jump.clone(),
+            after: after_jump.clone(),
+        }),
+    };
+    let commands = diff_named(
+        before.scie.lift.boot.commands.clone(),
+        after.scie.lift.boot.commands.clone(),
+        |before, after| before == after,
+    );
+    let bindings = diff_named(
+        before.scie.lift.boot.bindings.clone(),
+        after.scie.lift.boot.bindings.clone(),
+        |before, after| before == after,
+    );
+    let files = diff_named(files(before), files(after), same_file);
+    ScieDiff {
+        jump,
+        files,
+        commands,
+        bindings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff;
+    use crate::config::{Config, UnknownFields};
+
+    fn config(files: &str, commands: &str) -> Config {
+        Config::parse(
+            format!(
+                r#"{{"scie":{{"lift":{{"name":"example","files":[{files}],
+                "boot":{{"commands":{{{commands}}}}}}},"jump":{{"size":42,"version":"0.1.0"}}}}}}"#
+            )
+            .as_bytes(),
+            UnknownFields::Deny,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn same() {
+        let before = config(
+            r#"{"name":"a","hash":"abc","size":1,"offset":42}"#,
+            r#""":{"exe":"{a}"}"#,
+        );
+        let after = config(
+            r#"{"name":"a","hash":"abc","size":1,"offset":1000}"#,
+            r#""":{"exe":"{a}"}"#,
+        );
+        assert!(diff(before, after).is_empty());
+    }
+
+    #[test]
+    fn changes() {
+        let before = config(
+            r#"{"name":"a","hash":"abc"},{"name":"b","hash":"def"}"#,
+            r#""":{"exe":"{a}"},"old":{"exe":"{b}"}"#,
+        );
+        let after = config(
+            r#"{"name":"a","hash":"xyz"},{"name":"c","hash":"ghi"}"#,
+            r#""":{"exe":"{a}","args":["-v"]},"new":{"exe":"{c}"}"#,
+        );
+        let scie_diff = diff(before, after);
+        assert!(scie_diff.jump.is_none());
+        assert_eq!(
+            vec![("a", "changed"), ("b", "removed"), ("c", "added")],
+            scie_diff
+                .files
+                .iter()
+                .map(|change| (change.name.as_str(), change.kind()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![("", "changed"), ("old", "removed"), ("new", "added")],
+            scie_diff
+                .commands
+                .iter()
+                .map(|change| (change.name.as_str(), change.kind()))
+                .collect::<Vec<_>>()
+        );
+        assert!(scie_diff.bindings.is_empty());
+    }
+}
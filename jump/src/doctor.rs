@@ -0,0 +1,461 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::atomic::{status, Status, Target};
+use crate::config::{FileType, OnMutation};
+use crate::context::{cache_base, fallback_base, is_writable};
+use crate::host;
+use crate::installer::{available_space, extracted_size, human_size, is_noexec, Installer};
+use crate::lift::{File, Lift};
+use crate::placeholders::{self, Item, Placeholder};
+use crate::{CurrentExe, Jump, Source};
+
+/// The outcome of a single health check.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Health {
+    Ok,
+    Warning,
+    Problem,
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Health::Ok => "ok",
+            Health::Warning => "warning",
+            Health::Problem => "problem",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub health: Health,
+    pub detail: String,
+}
+
+/// The results of checking whether a scie can boot on this machine; see `SCIE=doctor`.
+#[derive(Debug)]
+pub struct Diagnosis {
+    pub scie: PathBuf,
+    pub checks: Vec<Check>,
+}
+
+impl Diagnosis {
+    fn check(&mut self, name: &'static str, health: Health, detail: String) {
+        self.checks.push(Check {
+            name,
+            health,
+            detail,
+        })
+    }
+
+    /// Returns `true` if none of the checks found a problem that would stop the scie booting.
+    pub fn healthy(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.health != Health::Problem)
+    }
+}
+
+fn file_path(base: &Path, file: &File) -> PathBuf {
+    base.join(&file.hash).join(&file.name)
+}
+
+fn target_type(file: &File) -> Target {
+    match file.file_type {
+        FileType::Blob => Target::File,
+        _ => Target::Directory,
+    }
+}
+
+// Identifies the platform an executable was built for from its leading bytes, if it is a native
+// executable we recognize.
+fn executable_platform(header: &[u8]) -> Option<(&'static str, Option<&'static str>)> {
+    match header {
+        [0x7f, b'E', b'L', b'F', _, data, ..] if header.len() >= 20 => {
+            let machine = if *data == 2 {
+                u16::from_be_bytes([header[18], header[19]])
+            } else {
+                u16::from_le_bytes([header[18], header[19]])
+            };
+            let arch = match machine {
+                0x03 => Some("x86"),
+                0x28 => Some("arm"),
+                0x3e => Some("x86_64"),
+                0xb7 => Some("aarch64"),
+                0xf3 => Some("riscv64"),
+                _ => None,
+            };
+            Some(("linux", arch))
+        }
+        [0xcf, 0xfa, 0xed, 0xfe, cpu_type @ ..] if header.len() >= 8 => {
+            let arch =
+                match u32::from_le_bytes([cpu_type[0], cpu_type[1], cpu_type[2], cpu_type[3]]) {
+                    0x0100_0007 => Some("x86_64"),
+                    0x0100_000c => Some("aarch64"),
+                    _ => None,
+                };
+            Some(("macos", arch))
+        }
+        [0xce, 0xfa, 0xed, 0xfe, ..] | [0xca, 0xfe, 0xba, 0xbe, ..] => Some(("macos", None)),
+        [b'M', b'Z', ..] => Some(("windows", None)),
+        _ => None,
+    }
+}
+
+fn platform_matches(os: &str) -> bool {
+    // N.B.: ELF is used by unixes other than Linux too; so we only rule it out where we know a
+    // different format is used.
+    match os {
+        "linux" => !cfg!(any(windows, target_os = "macos")),
+        _ => os == env::consts::OS,
+    }
+}
+
+fn check_platform(diagnosis: &mut Diagnosis, lift: &Lift, installer: &Installer) {
+    let mut mismatches = vec![];
+    let mut checked = 0;
+    for (name, cmd) in lift
+        .boot
+        .commands
+        .iter()
+        .filter(|(_, cmd)| cmd.is_enabled())
+    {
+        let Ok(parsed) = placeholders::parse(&cmd.exe) else {
+            continue;
+        };
+        let Some(Item::Placeholder(Placeholder::FileName(file_name))) = parsed.items.first() else {
+            continue;
+        };
+        let Some(file) = lift
+            .files
+            .iter()
+            .find(|file| file.name == *file_name || file.key.as_deref() == Some(file_name))
+        else {
+            continue;
+        };
+        if file.file_type != FileType::Blob || file.source != Source::Scie || file.size == 0 {
+            continue;
+        }
+        // N.B.: The ELF program headers, which name the libc an executable needs, follow the
+        // file header closely.
+        let Some(header) = installer.leading_bytes(file, 4096) else {
+            continue;
+        };
+        let Some((os, arch)) = executable_platform(header) else {
+            continue;
+        };
+        checked += 1;
+        let cmd_name = if name.is_empty() { "<default>" } else { name };
+        let arch_matches = arch.map(|arch| arch == env::consts::ARCH).unwrap_or(true);
+        if !platform_matches(os) || !arch_matches {
+            mismatches.push(format!(
+                "{cmd_name} runs {file} which is built for {os}{arch} but this machine is \
+                {this_os}-{this_arch}.",
+                file = file.name,
+                arch = arch.map(|arch| format!("-{arch}")).unwrap_or_default(),
+                this_os = env::consts::OS,
+                this_arch = env::consts::ARCH,
+            ));
+        } else if cfg!(target_os = "linux") {
+            let this_libc = host::libc();
+            if let Some(libc) = host::elf_libc(header).filter(|libc| *libc != this_libc) {
+                mismatches.push(format!(
+                    "{cmd_name} runs {file} which is linked against {libc} libc but this machine \
+                    {has}; consider selecting a build with the {{scie.libc}} placeholder.",
+                    file = file.name,
+                    has = if this_libc == "none" {
+                        "has no libc".to_string()
+                    } else {
+                        format!("uses {this_libc} libc")
+                    }
+                ));
+            }
+        }
+    }
+    if !mismatches.is_empty() {
+        diagnosis.check("platform", Health::Problem, mismatches.join("\n"));
+    } else if checked > 0 {
+        diagnosis.check(
+            "platform",
+            Health::Ok,
+            format!(
+                "The native executables of {checked} command(s) match this {os}-{arch} machine.",
+                os = env::consts::OS,
+                arch = env::consts::ARCH
+            ),
+        );
+    }
+}
+
+fn find_on_path(exe: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&path).any(|dir| {
+        let candidate = dir.join(exe);
+        candidate.is_file()
+            || (!env::consts::EXE_EXTENSION.is_empty()
+                && candidate
+                    .with_extension(env::consts::EXE_EXTENSION)
+                    .is_file())
+    })
+}
+
+fn check_interpreters(diagnosis: &mut Diagnosis, lift: &Lift) {
+    let mut missing = vec![];
+    for (name, cmd) in lift
+        .boot
+        .commands
+        .iter()
+        .filter(|(_, cmd)| cmd.is_enabled())
+    {
+        // N.B.: We can only check exes that do not depend on the scie's own files or environment.
+        let Ok(parsed) = placeholders::parse(&cmd.exe) else {
+            continue;
+        };
+        if parsed
+            .items
+            .iter()
+            .any(|item| matches!(item, Item::Placeholder(_)))
+        {
+            continue;
+        }
+        let exe = Path::new(&cmd.exe);
+        let found = if exe.is_absolute() {
+            exe.is_file()
+        } else if exe.components().count() == 1 {
+            find_on_path(&cmd.exe)
+        } else {
+            // A relative path depends on the working directory the scie is launched from.
+            continue;
+        };
+        if !found {
+            missing.push(format!(
+                "{cmd_name} runs {exe} which was not found{where_}.",
+                cmd_name = if name.is_empty() { "<default>" } else { name },
+                exe = cmd.exe,
+                where_ = if exe.is_absolute() {
+                    ""
+                } else {
+                    " on the PATH"
+                }
+            ));
+        }
+    }
+    if missing.is_empty() {
+        diagnosis.check(
+            "interpreters",
+            Health::Ok,
+            "All the commands' system executables are present.".to_string(),
+        );
+    } else {
+        diagnosis.check("interpreters", Health::Problem, missing.join("\n"));
+    }
+}
+
+fn check_cache(diagnosis: &mut Diagnosis, base: &Path, lift: &Lift) -> u64 {
+    let mut complete = 0;
+    let mut pending_size = 0_u64;
+    let mut partial = vec![];
+    let mut broken = vec![];
+    // N.B.: Checking an extracted file can mean hashing it; so the files are checked concurrently.
+    let checks = lift
+        .files
+        .par_iter()
+        .map(|file| {
+            let path = file_path(base, file);
+            let check = status(&path, target_type(file), Some(file.hash.as_str()));
+            (file, path, check)
+        })
+        .collect::<Vec<_>>();
+    let on_mutation = lift
+        .integrity
+        .as_ref()
+        .and_then(|integrity| integrity.on_mutation)
+        .unwrap_or_default();
+    for (file, path, check) in checks {
+        match check {
+            Ok(Status::Complete) => {
+                complete += 1;
+                continue;
+            }
+            Ok(Status::Mutated) if OnMutation::Fail == on_mutation => broken.push(format!(
+                "{path} was mutated since it was extracted; booting will fail until it is removed.",
+                path = path.display()
+            )),
+            Ok(Status::Mutated) => partial.push(format!(
+                "{path} was mutated since it was extracted; it will be extracted afresh.",
+                path = path.display()
+            )),
+            Ok(Status::Incomplete) if path.symlink_metadata().is_ok() => partial.push(format!(
+                "{path} was not completely extracted; it will be extracted afresh.",
+                path = path.display()
+            )),
+            Ok(Status::Incomplete) => {}
+            Err(err) => broken.push(err.to_string()),
+        }
+        pending_size += extracted_size(file);
+    }
+    if !broken.is_empty() {
+        broken.extend(partial);
+        diagnosis.check("cache", Health::Problem, broken.join("\n"));
+    } else if !partial.is_empty() {
+        diagnosis.check("cache", Health::Warning, partial.join("\n"));
+    } else {
+        diagnosis.check(
+            "cache",
+            Health::Ok,
+            format!(
+                "{complete} of {count} files are extracted.",
+                count = lift.files.len()
+            ),
+        );
+    }
+    pending_size
+}
+
+fn check_base(diagnosis: &mut Diagnosis, base: &Path) -> Result<PathBuf, String> {
+    if is_writable(base) {
+        diagnosis.check(
+            "base",
+            Health::Ok,
+            format!("The nce cache {base} is writable.", base = base.display()),
+        );
+        return Ok(base.to_path_buf());
+    }
+    let fallback = fallback_base()?;
+    if fallback != base && is_writable(&fallback) {
+        diagnosis.check(
+            "base",
+            Health::Warning,
+            format!(
+                "The nce cache {base} is not writable; files it is missing will be extracted to \
+                {fallback} instead.",
+                base = base.display(),
+                fallback = fallback.display()
+            ),
+        );
+        return Ok(fallback);
+    }
+    diagnosis.check(
+        "base",
+        Health::Problem,
+        format!(
+            "The nce cache {base} is not writable. Set SCIE_BASE to a writable directory.",
+            base = base.display()
+        ),
+    );
+    Ok(base.to_path_buf())
+}
+
+fn check_jump_version(diagnosis: &mut Diagnosis, jump: &Jump) {
+    if let Err(message) = crate::jump::check_version(jump) {
+        diagnosis.check("scie-jump version", Health::Problem, message);
+    }
+}
+
+fn check_noexec(diagnosis: &mut Diagnosis, base: &Path) {
+    if is_noexec(base) {
+        diagnosis.check(
+            "noexec",
+            Health::Problem,
+            format!(
+                "The nce cache {base} is on a file system mounted noexec; so programs extracted \
+                there cannot be executed. Set SCIE_BASE to a directory on a file system that \
+                allows executing programs.",
+                base = base.display()
+            ),
+        );
+    }
+}
+
+fn check_disk_space(diagnosis: &mut Diagnosis, base: &Path, pending_size: u64) {
+    if pending_size == 0 {
+        return;
+    }
+    match available_space(base) {
+        Some(available) if available < pending_size => diagnosis.check(
+            "disk space",
+            Health::Problem,
+            format!(
+                "Extracting the remaining files needs {need} but only {have} is available for \
+                {base}.",
+                need = human_size(pending_size),
+                have = human_size(available),
+                base = base.display()
+            ),
+        ),
+        Some(available) => diagnosis.check(
+            "disk space",
+            Health::Ok,
+            format!(
+                "Extracting the remaining files needs {need} and {have} is available.",
+                need = human_size(pending_size),
+                have = human_size(available)
+            ),
+        ),
+        None => diagnosis.check(
+            "disk space",
+            Health::Warning,
+            format!(
+                "Could not determine the space available for {base}.",
+                base = base.display()
+            ),
+        ),
+    }
+}
+
+pub(crate) fn diagnose(
+    current_exe: &CurrentExe,
+    jump: &Jump,
+    lift: &Lift,
+    installer: &Installer,
+) -> Result<Diagnosis, String> {
+    let mut diagnosis = Diagnosis {
+        scie: current_exe.exe.clone(),
+        checks: vec![],
+    };
+    check_jump_version(&mut diagnosis, jump);
+    let base = cache_base(&current_exe.exe, jump, lift, installer)?;
+    let base = check_base(&mut diagnosis, &base)?;
+    check_noexec(&mut diagnosis, &base);
+    let pending_size = check_cache(&mut diagnosis, &base, lift);
+    check_disk_space(&mut diagnosis, &base, pending_size);
+    check_platform(&mut diagnosis, lift, installer);
+    check_interpreters(&mut diagnosis, lift);
+    Ok(diagnosis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::executable_platform;
+
+    #[test]
+    fn platforms() {
+        let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1];
+        elf.resize(18, 0);
+        elf.extend([0x3e, 0x00]);
+        assert_eq!(Some(("linux", Some("x86_64"))), executable_platform(&elf));
+        elf[18] = 0xb7;
+        assert_eq!(Some(("linux", Some("aarch64"))), executable_platform(&elf));
+
+        let macho = [0xcf, 0xfa, 0xed, 0xfe, 0x0c, 0x00, 0x00, 0x01];
+        assert_eq!(
+            Some(("macos", Some("aarch64"))),
+            executable_platform(&macho)
+        );
+
+        assert_eq!(Some(("windows", None)), executable_platform(b"MZ\x90\x00"));
+        assert_eq!(None, executable_platform(b"#!/bin/sh\n"));
+        assert_eq!(None, executable_platform(b"\x7fELF"));
+    }
+}
@@ -0,0 +1,961 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::io::{Seek, SeekFrom, Write};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+// Scies normally append their files and lift manifest to the scie-jump executable. Code signing
+// tools only sign the parts of an executable its headers describe though; so instead we can grow
+// the scie-jump executable by a PE section or Mach-O segment that holds the files and lift
+// manifest. The offsets recorded in the lift manifest remain file offsets and the payload is read
+// from the scie file just like an appended one.
+//
+// See https://learn.microsoft.com/en-us/windows/win32/debug/pe-format for PE format facts and
+// https://github.com/apple-oss-distributions/xnu/blob/main/EXTERNAL_HEADERS/mach-o/loader.h for
+// Mach-O format facts leveraged here.
+
+const PE_SECTION_NAME: &[u8; 8] = b".scie\0\0\0";
+const PE_SECTION_HEADER_SIZE: usize = 40;
+const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+const IMAGE_DIRECTORY_ENTRY_SECURITY: usize = 4;
+const IMAGE_SUBSYSTEM_WINDOWS_GUI: u16 = 2;
+
+const MACHO_SEGMENT_NAME: &[u8; 16] = b"__SCIE\0\0\0\0\0\0\0\0\0\0";
+const MACHO_LINKEDIT_SEGMENT_NAME: &[u8; 16] = b"__LINKEDIT\0\0\0\0\0\0";
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const MACHO_HEADER_SIZE: usize = 32;
+const MACHO_SEGMENT_COMMAND_SIZE: usize = 72;
+const MACHO_SECTION_SIZE: usize = 80;
+// N.B.: Apple Silicon uses 16KiB pages; which are also 4KiB aligned as x86_64 requires.
+const MACHO_PAGE_SIZE: usize = 0x4000;
+const VM_PROT_READ: u32 = 0x1;
+
+const LC_SYMTAB: u32 = 0x2;
+const LC_DYSYMTAB: u32 = 0xb;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_CODE_SIGNATURE: u32 = 0x1d;
+const LC_SEGMENT_SPLIT_INFO: u32 = 0x1e;
+const LC_DYLD_INFO: u32 = 0x22;
+const LC_DYLD_INFO_ONLY: u32 = 0x8000_0022;
+const LC_FUNCTION_STARTS: u32 = 0x26;
+const LC_DATA_IN_CODE: u32 = 0x29;
+const LC_DYLIB_CODE_SIGN_DRS: u32 = 0x2b;
+const LC_LINKER_OPTIMIZATION_HINT: u32 = 0x2e;
+const LC_DYLD_EXPORTS_TRIE: u32 = 0x8000_0033;
+const LC_DYLD_CHAINED_FIXUPS: u32 = 0x8000_0034;
+
+fn align(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) / alignment * alignment
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(LittleEndian::read_u16)
+        .ok_or_else(|| format!("Expected a 16 bit value at offset {offset}."))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(LittleEndian::read_u32)
+        .ok_or_else(|| format!("Expected a 32 bit value at offset {offset}."))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Result<u64, String> {
+    data.get(offset..offset + 8)
+        .map(LittleEndian::read_u64)
+        .ok_or_else(|| format!("Expected a 64 bit value at offset {offset}."))
+}
+
+fn to_u32(value: usize, what: &str) -> Result<u32, String> {
+    u32::try_from(value).map_err(|e| format!("The {what} of {value} does not fit in 32 bits: {e}"))
+}
+
+/// The layout of a scie-jump executable that has room for the files and lift manifest of a scie
+/// in a section or segment of its own.
+pub(crate) enum Embedding {
+    Pe(Pe),
+    MachO(MachO),
+}
+
+impl Embedding {
+    /// Plans embedding a scie payload in the given scie-jump executable.
+    pub(crate) fn new(scie_jump: Vec<u8>) -> Result<Self, String> {
+        if scie_jump.starts_with(b"MZ") {
+            Pe::new(scie_jump)
+                .map(Embedding::Pe)
+                .map_err(|e| format!("Cannot embed a scie in the scie-jump PE executable: {e}"))
+        } else if [Ok(MH_MAGIC_64), Ok(FAT_MAGIC.swap_bytes())].contains(&u32_at(&scie_jump, 0)) {
+            MachO::new(scie_jump)
+                .map(Embedding::MachO)
+                .map_err(|e| format!("Cannot embed a scie in the scie-jump Mach-O executable: {e}"))
+        } else {
+            Err(
+                "Scies can only be embedded in scie-jump PE (Windows) or Mach-O (macOS) \
+                executables."
+                    .to_string(),
+            )
+        }
+    }
+
+    /// The offset at which the files of the scie start; i.e.: the size of the scie tip.
+    pub(crate) fn payload_offset(&self) -> usize {
+        match self {
+            Embedding::Pe(pe) => pe.payload_offset,
+            Embedding::MachO(macho) => macho.linkedit.fileoff,
+        }
+    }
+
+    /// The bytes of the scie-jump that precede the payload.
+    ///
+    /// The headers describing the payload are only filled in by `finish` once its size is known.
+    pub(crate) fn tip(&self) -> &[u8] {
+        match self {
+            Embedding::Pe(pe) => &pe.data[..pe.payload_offset],
+            Embedding::MachO(macho) => &macho.data[..macho.linkedit.fileoff],
+        }
+    }
+
+    /// Completes the embedding of the `payload_size` bytes written to the binary after the tip;
+    /// returning the final size of the binary.
+    pub(crate) fn finish(
+        &self,
+        binary: &mut std::fs::File,
+        payload_size: usize,
+    ) -> Result<usize, String> {
+        let (headers, tail) = match self {
+            Embedding::Pe(pe) => pe.finish(payload_size)?,
+            Embedding::MachO(macho) => macho.finish(payload_size)?,
+        };
+        binary
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| binary.write_all(&headers))
+            .map_err(|e| format!("Failed to update the executable headers of the scie: {e}"))?;
+        binary
+            .seek(SeekFrom::End(0))
+            .and_then(|_| binary.write_all(&tail))
+            .map_err(|e| format!("Failed to complete the executable tail of the scie: {e}"))?;
+        Ok(self.payload_offset() + payload_size + tail.len())
+    }
+}
+
+/// Returns the offset of the end of the embedded scie payload in the given executable if it has
+/// one.
+pub(crate) fn payload_end(data: &[u8]) -> Option<usize> {
+    let end = if data.starts_with(b"MZ") {
+        Pe::payload_end(data)
+    } else if Ok(MH_MAGIC_64) == u32_at(data, 0) {
+        MachO::payload_end(data)
+    } else {
+        return None;
+    };
+    end.ok().flatten().filter(|end| *end <= data.len())
+}
+
+/// Returns the portion of the data holding the scie: all of it unless the scie payload is
+/// embedded in a section or segment, in which case everything up to the end of that payload.
+pub(crate) fn scie_data(data: &[u8]) -> &[u8] {
+    &data[..payload_end(data).unwrap_or(data.len())]
+}
+
+/// Returns `true` if the data is a 64 bit Mach-O executable or library, or a fat (universal) one
+/// holding them, that lacks a code signature.
+#[cfg(unix)]
+pub(crate) fn is_unsigned_macho(data: &[u8]) -> bool {
+    use byteorder::BigEndian;
+
+    let unsigned = |data: &[u8]| {
+        u32_at(data, 0) == Ok(MH_MAGIC_64)
+            && MachO::load_commands(data)
+                .map(|commands| commands.iter().all(|(cmd, _)| LC_CODE_SIGNATURE != *cmd))
+                .unwrap_or(false)
+    };
+    if u32_at(data, 0) != Ok(FAT_MAGIC.swap_bytes()) {
+        return unsigned(data);
+    }
+    // N.B.: Fat headers are big endian and Java class files share their magic number; but the major
+    // version of a class file, which sits where the count of architectures would, is at least 45.
+    let Some(count) = data
+        .get(4..8)
+        .map(BigEndian::read_u32)
+        .filter(|count| *count < 45)
+    else {
+        return false;
+    };
+    (0..count as usize).any(|index| {
+        let fat_arch = 8 + index * 20;
+        data.get(fat_arch + 8..fat_arch + 16)
+            .and_then(|location| {
+                let offset = BigEndian::read_u32(&location[..4]) as usize;
+                let size = BigEndian::read_u32(&location[4..]) as usize;
+                data.get(offset..offset + size)
+            })
+            .map(unsigned)
+            .unwrap_or(false)
+    })
+}
+
+/// Switches a scie-jump PE executable from the console subsystem to the Windows GUI subsystem so
+/// that Windows does not open a console window when the scie is launched; returning `false` if the
+/// executable is not a PE executable and was left alone.
+pub(crate) fn use_windows_gui_subsystem(data: &mut [u8]) -> Result<bool, String> {
+    if !data.starts_with(b"MZ") {
+        return Ok(false);
+    }
+    let (coff_header, _, _) = Pe::sections(data).map_err(|e| {
+        format!("Cannot switch the scie-jump PE executable to the GUI subsystem: {e}")
+    })?;
+    // N.B.: The checksum and subsystem fields are at the same offsets in both PE32 and PE32+
+    // optional headers. The checksum is cleared like `Pe::finish` does since it no longer matches.
+    let optional_header = coff_header + 20;
+    let (check_sum, subsystem) = (optional_header + 64, optional_header + 68);
+    u16_at(data, subsystem)?;
+    LittleEndian::write_u32(&mut data[check_sum..check_sum + 4], 0);
+    LittleEndian::write_u16(
+        &mut data[subsystem..subsystem + 2],
+        IMAGE_SUBSYSTEM_WINDOWS_GUI,
+    );
+    Ok(true)
+}
+
+pub(crate) struct Pe {
+    data: Vec<u8>,
+    coff_header: usize,
+    optional_header: usize,
+    data_directories: Option<usize>,
+    section_header: usize,
+    size_of_headers: usize,
+    section_alignment: usize,
+    file_alignment: usize,
+    virtual_address: usize,
+    payload_offset: usize,
+}
+
+struct PeSection {
+    name: [u8; 8],
+    virtual_size: usize,
+    virtual_address: usize,
+    size_of_raw_data: usize,
+    pointer_to_raw_data: usize,
+}
+
+impl Pe {
+    fn sections(data: &[u8]) -> Result<(usize, usize, Vec<PeSection>), String> {
+        let pe_header = u32_at(data, 0x3c)? as usize;
+        if data.get(pe_header..pe_header + 4) != Some(b"PE\0\0") {
+            return Err(format!("No PE signature found at offset {pe_header}."));
+        }
+        let coff_header = pe_header + 4;
+        let number_of_sections = u16_at(data, coff_header + 2)? as usize;
+        let size_of_optional_header = u16_at(data, coff_header + 16)? as usize;
+        let optional_header = coff_header + 20;
+        let section_table = optional_header + size_of_optional_header;
+        let sections = (0..number_of_sections)
+            .map(|index| {
+                let header = section_table + index * PE_SECTION_HEADER_SIZE;
+                let name = data
+                    .get(header..header + 8)
+                    .ok_or_else(|| format!("Section header {index} is truncated."))?;
+                Ok(PeSection {
+                    name: name.try_into().map_err(|e| format!("{e}"))?,
+                    virtual_size: u32_at(data, header + 8)? as usize,
+                    virtual_address: u32_at(data, header + 12)? as usize,
+                    size_of_raw_data: u32_at(data, header + 16)? as usize,
+                    pointer_to_raw_data: u32_at(data, header + 20)? as usize,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok((coff_header, section_table, sections))
+    }
+
+    fn payload_end(data: &[u8]) -> Result<Option<usize>, String> {
+        let (_, _, sections) = Self::sections(data)?;
+        Ok(sections
+            .iter()
+            .find(|section| &section.name == PE_SECTION_NAME)
+            .map(|section| {
+                section.pointer_to_raw_data + section.virtual_size.min(section.size_of_raw_data)
+            }))
+    }
+
+    fn new(data: Vec<u8>) -> Result<Self, String> {
+        let (coff_header, section_table, sections) = Self::sections(&data)?;
+        if sections
+            .iter()
+            .any(|section| &section.name == PE_SECTION_NAME)
+        {
+            return Err("It already has an embedded scie.".to_string());
+        }
+        let optional_header = coff_header + 20;
+        let data_directories = match u16_at(&data, optional_header)? {
+            0x10b => (optional_header + 92, optional_header + 96),
+            0x20b => (optional_header + 108, optional_header + 112),
+            magic => return Err(format!("Unknown optional header magic {magic:#x}.")),
+        };
+        let data_directories = (u32_at(&data, data_directories.0)? as usize
+            > IMAGE_DIRECTORY_ENTRY_SECURITY)
+            .then_some(data_directories.1);
+        let section_alignment = u32_at(&data, optional_header + 32)? as usize;
+        let file_alignment = u32_at(&data, optional_header + 36)? as usize;
+        let size_of_headers = u32_at(&data, optional_header + 60)? as usize;
+        if section_alignment == 0 || file_alignment == 0 {
+            return Err("Its section and file alignments must be non-zero.".to_string());
+        }
+
+        let section_header = section_table + sections.len() * PE_SECTION_HEADER_SIZE;
+        let first_raw_data = sections
+            .iter()
+            .filter(|section| section.size_of_raw_data > 0)
+            .map(|section| section.pointer_to_raw_data)
+            .min()
+            .unwrap_or(size_of_headers);
+        let section_header_end = section_header + PE_SECTION_HEADER_SIZE;
+        if section_header_end > size_of_headers.min(first_raw_data)
+            || data
+                .get(section_header..section_header_end)
+                .map(|header| header.iter().any(|byte| *byte != 0))
+                .unwrap_or(true)
+        {
+            return Err(format!(
+                "There is no room for another section header after its {count} section headers.",
+                count = sections.len()
+            ));
+        }
+
+        let raw_data_end = sections
+            .iter()
+            .map(|section| section.pointer_to_raw_data + section.size_of_raw_data)
+            .max()
+            .unwrap_or(size_of_headers);
+        if raw_data_end > data.len() {
+            return Err(format!(
+                "Its sections extend to offset {raw_data_end} but it is only {size} bytes.",
+                size = data.len()
+            ));
+        }
+        let virtual_end = sections
+            .iter()
+            .map(|section| {
+                section.virtual_address + section.virtual_size.max(section.size_of_raw_data)
+            })
+            .max()
+            .unwrap_or(size_of_headers);
+        let payload_offset = align(raw_data_end, file_alignment);
+        if payload_offset > data.len() {
+            return Err(format!(
+                "Its last section ends at offset {raw_data_end} which is not aligned to its file \
+                alignment of {file_alignment} bytes."
+            ));
+        }
+        Ok(Self {
+            data,
+            coff_header,
+            optional_header,
+            data_directories,
+            section_header,
+            size_of_headers,
+            section_alignment,
+            file_alignment,
+            virtual_address: align(virtual_end, section_alignment),
+            payload_offset,
+        })
+    }
+
+    fn finish(&self, payload_size: usize) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let mut headers = self.data[..self.size_of_headers].to_vec();
+
+        let size_of_raw_data = align(payload_size, self.file_alignment);
+        let section_header = &mut headers[self.section_header..];
+        section_header[..8].copy_from_slice(PE_SECTION_NAME);
+        LittleEndian::write_u32(
+            &mut section_header[8..],
+            to_u32(payload_size, "scie payload size")?,
+        );
+        LittleEndian::write_u32(
+            &mut section_header[12..],
+            to_u32(self.virtual_address, "scie section address")?,
+        );
+        LittleEndian::write_u32(
+            &mut section_header[16..],
+            to_u32(size_of_raw_data, "scie section size")?,
+        );
+        LittleEndian::write_u32(
+            &mut section_header[20..],
+            to_u32(self.payload_offset, "scie payload offset")?,
+        );
+        LittleEndian::write_u32(
+            &mut section_header[36..],
+            IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ,
+        );
+
+        let number_of_sections = u16_at(&headers, self.coff_header + 2)? + 1;
+        LittleEndian::write_u16(&mut headers[self.coff_header + 2..], number_of_sections);
+        // N.B.: The COFF symbol table, if any, follows the sections in the file; so it is dropped
+        // along with everything else following them.
+        LittleEndian::write_u32(&mut headers[self.coff_header + 8..], 0);
+        LittleEndian::write_u32(&mut headers[self.coff_header + 12..], 0);
+
+        let size_of_image = align(self.virtual_address + payload_size, self.section_alignment);
+        LittleEndian::write_u32(
+            &mut headers[self.optional_header + 56..],
+            to_u32(size_of_image, "image size")?,
+        );
+        // The checksum and any signature of the scie-jump no longer apply; signing the scie
+        // re-computes both.
+        LittleEndian::write_u32(&mut headers[self.optional_header + 64..], 0);
+        if let Some(data_directories) = self.data_directories {
+            let security = data_directories + IMAGE_DIRECTORY_ENTRY_SECURITY * 8;
+            headers[security..security + 8].fill(0);
+        }
+        Ok((headers, vec![0; size_of_raw_data - payload_size]))
+    }
+}
+
+pub(crate) struct MachO {
+    data: Vec<u8>,
+    load_commands: Vec<(u32, std::ops::Range<usize>)>,
+    linkedit_command: usize,
+    header_size: usize,
+    linkedit: MachOSegment,
+}
+
+struct MachOSegment {
+    vmaddr: usize,
+    fileoff: usize,
+    filesize: usize,
+}
+
+impl MachO {
+    fn load_commands(data: &[u8]) -> Result<Vec<(u32, std::ops::Range<usize>)>, String> {
+        let ncmds = u32_at(data, 16)? as usize;
+        let mut offset = MACHO_HEADER_SIZE;
+        let mut load_commands = Vec::with_capacity(ncmds);
+        for index in 0..ncmds {
+            let cmd = u32_at(data, offset)?;
+            let cmdsize = u32_at(data, offset + 4)? as usize;
+            if cmdsize < 8 || offset + cmdsize > data.len() {
+                return Err(format!(
+                    "Load command {index} has an invalid size of {cmdsize} bytes."
+                ));
+            }
+            load_commands.push((cmd, offset..offset + cmdsize));
+            offset += cmdsize;
+        }
+        Ok(load_commands)
+    }
+
+    fn segment_name(data: &[u8], command: &std::ops::Range<usize>) -> Option<[u8; 16]> {
+        data.get(command.start + 8..command.start + 24)
+            .and_then(|name| name.try_into().ok())
+    }
+
+    fn payload_end(data: &[u8]) -> Result<Option<usize>, String> {
+        for (cmd, command) in Self::load_commands(data)? {
+            if LC_SEGMENT_64 == cmd
+                && Some(*MACHO_SEGMENT_NAME) == Self::segment_name(data, &command)
+            {
+                let fileoff = u64_at(data, command.start + 40)? as usize;
+                let filesize = u64_at(data, command.start + 48)? as usize;
+                return Ok(Some(fileoff + filesize));
+            }
+        }
+        Ok(None)
+    }
+
+    fn new(data: Vec<u8>) -> Result<Self, String> {
+        if Ok(MH_MAGIC_64) != u32_at(&data, 0) {
+            return Err(
+                "Universal binaries are not supported; extract the scie-jump for a single \
+                architecture with `lipo -thin` first."
+                    .to_string(),
+            );
+        }
+        let load_commands = Self::load_commands(&data)?;
+        let header_size = MACHO_HEADER_SIZE + u32_at(&data, 20)? as usize;
+
+        let mut first_content = data.len();
+        let mut segments_end = 0;
+        let mut linkedit = None;
+        for (index, (cmd, command)) in load_commands.iter().enumerate() {
+            match *cmd {
+                LC_SEGMENT_64 => {
+                    let name = Self::segment_name(&data, command);
+                    if Some(*MACHO_SEGMENT_NAME) == name {
+                        return Err("It already has an embedded scie.".to_string());
+                    }
+                    let segment = MachOSegment {
+                        vmaddr: u64_at(&data, command.start + 24)? as usize,
+                        fileoff: u64_at(&data, command.start + 40)? as usize,
+                        filesize: u64_at(&data, command.start + 48)? as usize,
+                    };
+                    let nsects = u32_at(&data, command.start + 64)? as usize;
+                    for section in 0..nsects {
+                        let section = command.start
+                            + MACHO_SEGMENT_COMMAND_SIZE
+                            + section * MACHO_SECTION_SIZE;
+                        let offset = u32_at(&data, section + 48)? as usize;
+                        if offset > 0 {
+                            first_content = first_content.min(offset);
+                        }
+                    }
+                    if segment.fileoff > 0 && segment.filesize > 0 {
+                        first_content = first_content.min(segment.fileoff);
+                    }
+                    segments_end = segments_end.max(segment.fileoff + segment.filesize);
+                    if Some(*MACHO_LINKEDIT_SEGMENT_NAME) == name {
+                        linkedit = Some((index, segment));
+                    }
+                }
+                LC_DYLD_CHAINED_FIXUPS => {
+                    return Err(
+                        "Executables that use chained fixups are not supported; link the \
+                        scie-jump with `-no_fixup_chains`."
+                            .to_string(),
+                    );
+                }
+                _ => {}
+            }
+        }
+        let (linkedit_command, linkedit) =
+            linkedit.ok_or_else(|| "It has no __LINKEDIT segment.".to_string())?;
+        if linkedit.fileoff + linkedit.filesize != segments_end
+            || linkedit.fileoff % 0x1000 != 0
+            || segments_end > data.len()
+        {
+            return Err(
+                "Its __LINKEDIT segment must be the last, page aligned, segment in the file."
+                    .to_string(),
+            );
+        }
+        if header_size + MACHO_SEGMENT_COMMAND_SIZE > first_content {
+            return Err(format!(
+                "There is no room for another load command; its load commands end at offset \
+                {header_size} and its content starts at offset {first_content}. Link the \
+                scie-jump with `-headerpad` to reserve room."
+            ));
+        }
+        Ok(Self {
+            data,
+            load_commands,
+            linkedit_command,
+            header_size,
+            linkedit,
+        })
+    }
+
+    fn finish(&self, payload_size: usize) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let shift = align(payload_size, MACHO_PAGE_SIZE);
+        let shifted = |value: u64| -> u64 {
+            if value >= self.linkedit.fileoff as u64 {
+                value + shift as u64
+            } else {
+                value
+            }
+        };
+        let shift_u32 = |command: &mut [u8], field: usize| -> Result<(), String> {
+            let value = shifted(u32_at(command, field)? as u64);
+            LittleEndian::write_u32(
+                &mut command[field..],
+                to_u32(value as usize, "__LINKEDIT offset")?,
+            );
+            Ok(())
+        };
+
+        let mut commands = Vec::with_capacity(self.header_size + MACHO_SEGMENT_COMMAND_SIZE);
+        for (index, (cmd, range)) in self.load_commands.iter().enumerate() {
+            let mut command = self.data[range.clone()].to_vec();
+            if index == self.linkedit_command {
+                let mut segment = vec![0; MACHO_SEGMENT_COMMAND_SIZE];
+                LittleEndian::write_u32(&mut segment[0..], LC_SEGMENT_64);
+                LittleEndian::write_u32(&mut segment[4..], MACHO_SEGMENT_COMMAND_SIZE as u32);
+                segment[8..24].copy_from_slice(MACHO_SEGMENT_NAME);
+                LittleEndian::write_u64(&mut segment[24..], self.linkedit.vmaddr as u64);
+                LittleEndian::write_u64(&mut segment[32..], shift as u64);
+                LittleEndian::write_u64(&mut segment[40..], self.linkedit.fileoff as u64);
+                LittleEndian::write_u64(&mut segment[48..], payload_size as u64);
+                LittleEndian::write_u32(&mut segment[56..], VM_PROT_READ);
+                LittleEndian::write_u32(&mut segment[60..], VM_PROT_READ);
+                commands.extend(segment);
+
+                LittleEndian::write_u64(&mut command[24..], (self.linkedit.vmaddr + shift) as u64);
+                let fileoff = shifted(u64_at(&command, 40)?);
+                LittleEndian::write_u64(&mut command[40..], fileoff);
+            }
+            match *cmd {
+                LC_SYMTAB => {
+                    for field in [8, 16] {
+                        shift_u32(&mut command, field)?;
+                    }
+                }
+                LC_DYSYMTAB => {
+                    for field in [32, 40, 48, 56, 64, 72] {
+                        shift_u32(&mut command, field)?;
+                    }
+                }
+                LC_DYLD_INFO | LC_DYLD_INFO_ONLY => {
+                    for field in [8, 16, 24, 32, 40] {
+                        shift_u32(&mut command, field)?;
+                    }
+                }
+                LC_CODE_SIGNATURE
+                | LC_SEGMENT_SPLIT_INFO
+                | LC_FUNCTION_STARTS
+                | LC_DATA_IN_CODE
+                | LC_DYLIB_CODE_SIGN_DRS
+                | LC_LINKER_OPTIMIZATION_HINT
+                | LC_DYLD_EXPORTS_TRIE => shift_u32(&mut command, 8)?,
+                _ => {}
+            }
+            commands.extend(command);
+        }
+
+        let mut headers = self.data[..self.header_size + MACHO_SEGMENT_COMMAND_SIZE].to_vec();
+        headers[MACHO_HEADER_SIZE..].copy_from_slice(&commands);
+        let ncmds = u32_at(&headers, 16)? + 1;
+        LittleEndian::write_u32(&mut headers[16..], ncmds);
+        let sizeofcmds = u32_at(&headers, 20)? + MACHO_SEGMENT_COMMAND_SIZE as u32;
+        LittleEndian::write_u32(&mut headers[20..], sizeofcmds);
+
+        // N.B.: The __LINKEDIT segment must remain the last segment in the file for codesign; so
+        // it follows the scie payload. Any existing code signature it holds no longer applies and
+        // must be replaced by signing the scie.
+        let mut tail = vec![0; shift - payload_size];
+        tail.extend_from_slice(
+            &self.data[self.linkedit.fileoff..self.linkedit.fileoff + self.linkedit.filesize],
+        );
+        Ok((headers, tail))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+    use super::payload_end;
+    use crate::config::UnknownFields;
+    use crate::{
+        fingerprint, load_manifest, load_scie_jump, pack, PackCache, PackOptions, Packed, Repack,
+        ScieFooter, EOF_MAGIC,
+    };
+
+    fn pe() -> Vec<u8> {
+        let mut data = vec![0; 0x400];
+        data[..2].copy_from_slice(b"MZ");
+        LittleEndian::write_u32(&mut data[0x3c..], 0x40);
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let coff_header = 0x44;
+        LittleEndian::write_u16(&mut data[coff_header..], 0x8664);
+        LittleEndian::write_u16(&mut data[coff_header + 2..], 1);
+        LittleEndian::write_u16(&mut data[coff_header + 16..], 240);
+        LittleEndian::write_u16(&mut data[coff_header + 18..], 0x22);
+        let optional_header = coff_header + 20;
+        LittleEndian::write_u16(&mut data[optional_header..], 0x20b);
+        LittleEndian::write_u32(&mut data[optional_header + 16..], 0x1000);
+        LittleEndian::write_u32(&mut data[optional_header + 32..], 0x1000);
+        LittleEndian::write_u32(&mut data[optional_header + 36..], 0x200);
+        LittleEndian::write_u32(&mut data[optional_header + 56..], 0x2000);
+        LittleEndian::write_u32(&mut data[optional_header + 60..], 0x200);
+        LittleEndian::write_u32(&mut data[optional_header + 64..], 0xdead);
+        LittleEndian::write_u16(&mut data[optional_header + 68..], 3);
+        LittleEndian::write_u32(&mut data[optional_header + 108..], 16);
+        let section = optional_header + 240;
+        data[section..section + 8].copy_from_slice(b".text\0\0\0");
+        LittleEndian::write_u32(&mut data[section + 8..], 0x10);
+        LittleEndian::write_u32(&mut data[section + 12..], 0x1000);
+        LittleEndian::write_u32(&mut data[section + 16..], 0x200);
+        LittleEndian::write_u32(&mut data[section + 20..], 0x200);
+        LittleEndian::write_u32(&mut data[section + 36..], 0x6000_0020);
+        data[0x200..0x210].fill(0xc3);
+        data
+    }
+
+    fn macho() -> Vec<u8> {
+        let mut data = vec![0; 0x4100];
+        LittleEndian::write_u32(&mut data[0..], 0xfeed_facf);
+        LittleEndian::write_u32(&mut data[4..], 0x0100_000c);
+        LittleEndian::write_u32(&mut data[12..], 0x2);
+        LittleEndian::write_u32(&mut data[16..], 4);
+        LittleEndian::write_u32(&mut data[20..], 152 + 72 + 24 + 16);
+
+        let text = 32;
+        LittleEndian::write_u32(&mut data[text..], 0x19);
+        LittleEndian::write_u32(&mut data[text + 4..], 152);
+        data[text + 8..text + 14].copy_from_slice(b"__TEXT");
+        LittleEndian::write_u64(&mut data[text + 24..], 0x1_0000_0000);
+        LittleEndian::write_u64(&mut data[text + 32..], 0x4000);
+        LittleEndian::write_u64(&mut data[text + 48..], 0x4000);
+        LittleEndian::write_u32(&mut data[text + 56..], 5);
+        LittleEndian::write_u32(&mut data[text + 60..], 5);
+        LittleEndian::write_u32(&mut data[text + 64..], 1);
+        let section = text + 72;
+        data[section..section + 6].copy_from_slice(b"__text");
+        data[section + 16..section + 22].copy_from_slice(b"__TEXT");
+        LittleEndian::write_u64(&mut data[section + 32..], 0x1_0000_3000);
+        LittleEndian::write_u64(&mut data[section + 40..], 0x10);
+        LittleEndian::write_u32(&mut data[section + 48..], 0x3000);
+
+        let linkedit = text + 152;
+        LittleEndian::write_u32(&mut data[linkedit..], 0x19);
+        LittleEndian::write_u32(&mut data[linkedit + 4..], 72);
+        data[linkedit + 8..linkedit + 18].copy_from_slice(b"__LINKEDIT");
+        LittleEndian::write_u64(&mut data[linkedit + 24..], 0x1_0000_4000);
+        LittleEndian::write_u64(&mut data[linkedit + 32..], 0x4000);
+        LittleEndian::write_u64(&mut data[linkedit + 40..], 0x4000);
+        LittleEndian::write_u64(&mut data[linkedit + 48..], 0x100);
+        LittleEndian::write_u32(&mut data[linkedit + 56..], 1);
+        LittleEndian::write_u32(&mut data[linkedit + 60..], 1);
+
+        let symtab = linkedit + 72;
+        LittleEndian::write_u32(&mut data[symtab..], 0x2);
+        LittleEndian::write_u32(&mut data[symtab + 4..], 24);
+        LittleEndian::write_u32(&mut data[symtab + 8..], 0x4000);
+        LittleEndian::write_u32(&mut data[symtab + 16..], 0x4040);
+        LittleEndian::write_u32(&mut data[symtab + 20..], 0x40);
+
+        let code_signature = symtab + 24;
+        LittleEndian::write_u32(&mut data[code_signature..], 0x1d);
+        LittleEndian::write_u32(&mut data[code_signature + 4..], 16);
+        LittleEndian::write_u32(&mut data[code_signature + 8..], 0x4080);
+        LittleEndian::write_u32(&mut data[code_signature + 12..], 0x80);
+
+        data[0x3000..0x3010].fill(0xc3);
+        for (index, byte) in data[0x4000..].iter_mut().enumerate() {
+            *byte = (index % 251) as u8;
+        }
+        data
+    }
+
+    fn embed(dir: &Path, mut scie_jump: Vec<u8>) -> (Packed, Vec<u8>) {
+        let size = scie_jump.len() as u32 + 8;
+        scie_jump.write_u32::<LittleEndian>(size).unwrap();
+        scie_jump.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+        let scie_jump_path = dir.join("scie-jump");
+        std::fs::write(&scie_jump_path, scie_jump).unwrap();
+
+        std::fs::write(dir.join("data.txt"), "data").unwrap();
+        std::fs::write(dir.join("tool.sh"), "echo tool").unwrap();
+        std::fs::write(
+            dir.join("lift.json"),
+            r#"{"scie":{"lift":{"name":"example","files":[{"name":"data.txt"},{"name":"tool.sh"}],
+            "boot":{"commands":{"":{"exe":"{tool.sh}"}}}}}}"#,
+        )
+        .unwrap();
+        let jump = load_scie_jump(&scie_jump_path).unwrap();
+        let (lift, manifest) =
+            load_manifest(dir, &jump, UnknownFields::Deny, &mut PackCache::default()).unwrap();
+        let packed = pack(
+            lift,
+            &manifest,
+            &jump,
+            &scie_jump_path,
+            dir,
+            &PackOptions {
+                lift_in_zip: true,
+                embed: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let data = std::fs::read(&packed.binary).unwrap();
+        assert_eq!(packed.size as usize, data.len());
+        let config = crate::inspect(&packed.binary).unwrap();
+        assert_eq!(Some(packed.jump.clone()), config.scie.jump);
+        let files = config
+            .scie
+            .lift
+            .files
+            .into_iter()
+            .map(|file| {
+                let offset = file.offset.unwrap();
+                let bytes = &data[offset..offset + file.size.unwrap()];
+                assert_eq!(file.hash.unwrap(), fingerprint::digest(bytes));
+                file.name
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["data.txt", "tool.sh"], files);
+
+        let footer = ScieFooter::parse(&data).unwrap().unwrap();
+        assert_eq!(packed.jump.size, footer.jump.size);
+        assert_eq!(packed.jump.version, footer.jump.version);
+        assert_eq!(packed.lift_manifest_offset, footer.lift_manifest_offset);
+        assert_eq!(
+            Some(packed.lift_manifest_offset + packed.lift_manifest_size),
+            payload_end(&data)
+        );
+        assert!(Repack::new(&packed.binary)
+            .write(&dir.join("repacked"))
+            .is_err());
+        (packed, data)
+    }
+
+    #[test]
+    fn pe_section() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (packed, data) = embed(tmp.path(), pe());
+        assert_eq!(0x400, packed.jump.size);
+        let payload_size = packed.lift_manifest_offset + packed.lift_manifest_size - 0x400;
+        assert_eq!(0x400 + (payload_size + 0x1ff) / 0x200 * 0x200, data.len());
+        assert_eq!(pe()[0x200..], data[0x200..0x400]);
+
+        let pe = goblin::pe::PE::parse(&data).unwrap();
+        assert_eq!(
+            vec![".text", ".scie"],
+            pe.sections
+                .iter()
+                .map(|section| section.name().unwrap())
+                .collect::<Vec<_>>()
+        );
+        let section = &pe.sections[1];
+        assert_eq!(0x2000, section.virtual_address);
+        assert_eq!(payload_size as u32, section.virtual_size);
+        assert_eq!(0x400, section.pointer_to_raw_data);
+        let optional_header = pe.header.optional_header.unwrap();
+        assert_eq!(
+            0x2000 + (payload_size as u32 + 0xfff) / 0x1000 * 0x1000,
+            optional_header.windows_fields.size_of_image
+        );
+        assert_eq!(0, optional_header.windows_fields.check_sum);
+    }
+
+    #[test]
+    fn gui_subsystem() {
+        let subsystem = |embed: bool, gui: bool| {
+            let tmp = tempfile::tempdir().unwrap();
+            let dir = tmp.path();
+            let mut scie_jump = pe();
+            let size = scie_jump.len() as u32 + 8;
+            scie_jump.write_u32::<LittleEndian>(size).unwrap();
+            scie_jump.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+            let scie_jump_path = dir.join("scie-jump");
+            std::fs::write(&scie_jump_path, scie_jump).unwrap();
+            std::fs::write(dir.join("app.exe"), "app").unwrap();
+            std::fs::write(
+                dir.join("lift.json"),
+                format!(
+                    r#"{{"scie":{{"lift":{{"name":"app","files":[{{"name":"app.exe"}}],
+                    "boot":{{"commands":{{
+                        "":{{"exe":"{{app.exe}}","gui":true}},
+                        "cli":{{"exe":"{{app.exe}}","gui":{gui}}}
+                    }}}}}}}}}}"#
+                ),
+            )
+            .unwrap();
+            let jump = load_scie_jump(&scie_jump_path).unwrap();
+            let (lift, manifest) =
+                load_manifest(dir, &jump, UnknownFields::Deny, &mut PackCache::default()).unwrap();
+            let packed = pack(
+                lift,
+                &manifest,
+                &jump,
+                &scie_jump_path,
+                dir,
+                &PackOptions {
+                    lift_in_zip: true,
+                    embed,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let data = std::fs::read(packed.binary).unwrap();
+            let pe = goblin::pe::PE::parse(&data).unwrap();
+            let windows_fields = pe.header.optional_header.unwrap().windows_fields;
+            (windows_fields.subsystem, windows_fields.check_sum)
+        };
+        assert_eq!((3, 0xdead), subsystem(false, false));
+        assert_eq!((2, 0), subsystem(false, true));
+        assert_eq!((3, 0), subsystem(true, false));
+        assert_eq!((2, 0), subsystem(true, true));
+    }
+
+    #[test]
+    fn unsigned_macho() {
+        use super::is_unsigned_macho;
+
+        let signed = macho();
+        assert!(!is_unsigned_macho(&signed));
+        let mut unsigned = macho();
+        // N.B.: Turn the code signature load command into a function starts one.
+        LittleEndian::write_u32(&mut unsigned[32 + 152 + 72 + 24..], 0x26);
+        assert!(is_unsigned_macho(&unsigned));
+
+        let fat = |slices: &[&[u8]]| {
+            let mut data = vec![0; 0x1000];
+            data[..4].copy_from_slice(&0xcafe_babe_u32.to_be_bytes());
+            data[4..8].copy_from_slice(&(slices.len() as u32).to_be_bytes());
+            for (index, slice) in slices.iter().enumerate() {
+                let fat_arch = 8 + index * 20;
+                let offset = data.len() as u32;
+                data[fat_arch + 8..fat_arch + 12].copy_from_slice(&offset.to_be_bytes());
+                data[fat_arch + 12..fat_arch + 16]
+                    .copy_from_slice(&(slice.len() as u32).to_be_bytes());
+                data.extend_from_slice(slice);
+            }
+            data
+        };
+        assert!(!is_unsigned_macho(&fat(&[&signed])));
+        assert!(is_unsigned_macho(&fat(&[&signed, &unsigned])));
+
+        let mut class_file = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 61];
+        class_file.resize(0x100, 0);
+        assert!(!is_unsigned_macho(&class_file));
+        assert!(!is_unsigned_macho(&pe()));
+    }
+
+    #[test]
+    fn macho_segment() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (packed, data) = embed(tmp.path(), macho());
+        assert_eq!(0x4000, packed.jump.size);
+        let payload_size = packed.lift_manifest_offset + packed.lift_manifest_size - 0x4000;
+        let shift = (payload_size + 0x3fff) / 0x4000 * 0x4000;
+        assert_eq!(macho()[0x4000..], data[0x4000 + shift..]);
+
+        let macho = goblin::mach::MachO::parse(&data, 0).unwrap();
+        let segments = macho
+            .segments
+            .iter()
+            .map(|segment| {
+                (
+                    segment.name().unwrap(),
+                    segment.vmaddr,
+                    segment.vmsize,
+                    segment.fileoff,
+                    segment.filesize,
+                )
+            })
+            .collect::<Vec<_>>();
+        let shift = shift as u64;
+        assert_eq!(
+            vec![
+                ("__TEXT", 0x1_0000_0000, 0x4000, 0, 0x4000),
+                ("__SCIE", 0x1_0000_4000, shift, 0x4000, payload_size as u64),
+                (
+                    "__LINKEDIT",
+                    0x1_0000_4000 + shift,
+                    0x4000,
+                    0x4000 + shift,
+                    0x100
+                ),
+            ],
+            segments
+        );
+        let shift = shift as u32;
+        for load_command in &macho.load_commands {
+            match load_command.command {
+                goblin::mach::load_command::CommandVariant::Symtab(symtab) => {
+                    assert_eq!(0x4000 + shift, symtab.symoff);
+                    assert_eq!(0x4040 + shift, symtab.stroff);
+                }
+                goblin::mach::load_command::CommandVariant::CodeSignature(code_signature) => {
+                    assert_eq!(0x4080 + shift, code_signature.dataoff);
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use thiserror::Error;
+
+/// The ways in which loading, installing or launching a scie can fail.
+#[derive(Debug, Error)]
+pub enum JumpError {
+    /// A lift manifest or other scie metadata is malformed or inconsistent.
+    #[error("{0}")]
+    Config(String),
+    /// An I/O operation failed.
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Some content did not match its recorded fingerprint.
+    #[error("{0}")]
+    Integrity(String),
+    /// Extracting a file into the `nce` cache failed; the underlying error says why.
+    #[error(transparent)]
+    Extraction(Box<JumpError>),
+    /// A child process could not be spawned or awaited.
+    #[error("{context}: {source}")]
+    Process {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Any other failure.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl JumpError {
+    pub(crate) fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn process(context: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Process {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Marks an error encountered while installing files as an extraction failure.
+    ///
+    /// Problems with the scie itself, like malformed metadata or corrupted content, are left as-is.
+    #[cfg(not(target_os = "wasi"))]
+    pub(crate) fn extraction(self) -> Self {
+        match self {
+            Self::Config(_) | Self::Integrity(_) | Self::Extraction(_) => self,
+            error => Self::Extraction(Box::new(error)),
+        }
+    }
+
+    /// Adds context to the error message while preserving the kind of error and its source.
+    pub(crate) fn context(self, context: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Self::Config(message) => Self::Config(context(message)),
+            Self::Io {
+                context: message,
+                source,
+            } => Self::Io {
+                context: context(message),
+                source,
+            },
+            Self::Integrity(message) => Self::Integrity(context(message)),
+            Self::Extraction(error) => Self::Extraction(Box::new(error.context(context))),
+            Self::Process {
+                context: message,
+                source,
+            } => Self::Process {
+                context: context(message),
+                source,
+            },
+            Self::Other(message) => Self::Other(context(message)),
+        }
+    }
+}
+
+// N.B.: Internal helpers and the scie-jump binary still report errors as plain messages. A
+// `JumpError` can flow into those through `?`, but a plain message must be given a kind explicitly
+// wherever it becomes a `JumpError`.
+impl From<JumpError> for String {
+    fn from(error: JumpError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::JumpError;
+
+    #[test]
+    fn context() {
+        let error = JumpError::io(
+            "Failed to open file",
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"),
+        )
+        .context(|message| format!("Failed to install: {message}"));
+        assert_eq!(
+            "Failed to install: Failed to open file: no such file",
+            error.to_string()
+        );
+        assert!(matches!(error, JumpError::Io { .. }));
+        assert_eq!("no such file", error.source().unwrap().to_string());
+
+        let error =
+            JumpError::Integrity("bad hash".to_string()).context(|message| format!("{message}!"));
+        assert!(matches!(error, JumpError::Integrity(ref message) if message == "bad hash!"));
+    }
+
+    #[test]
+    fn extraction() {
+        let error = JumpError::Integrity("bad hash".to_string()).extraction();
+        assert!(matches!(error, JumpError::Integrity(_)));
+
+        let error = JumpError::Other("disk full".to_string())
+            .extraction()
+            .context(|message| format!("Failed to install: {message}"));
+        assert!(matches!(error, JumpError::Extraction(_)));
+        assert_eq!("Failed to install: disk full", error.to_string());
+    }
+}
@@ -0,0 +1,1722 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fs::OpenOptions;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Condvar, Mutex};
+
+use tar::EntryType;
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::archive::windows_path_problem;
+use crate::config::UnicodeNormalization;
+use crate::zip::ZipStream;
+
+// N.B.: Extraction of archives with many small files is dominated by file system latency and
+// not by decompression; so a handful of writers is enough to hide that latency without swamping
+// the file system.
+const MAX_WORKERS: usize = 8;
+
+// Tar entries up to this size are buffered in memory and handed off to writer threads; larger
+// entries are streamed straight to disk by the thread decoding the archive.
+const MAX_BUFFERED_SIZE: u64 = 1024 * 1024;
+
+/// Bytes to extract along with the file and offset they were mapped from, if known.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Source<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) file: Option<(&'a std::fs::File, u64)>,
+}
+
+impl<'a> Source<'a> {
+    fn slice(&self, start: u64, len: u64) -> Option<Source<'a>> {
+        let end = start.checked_add(len)?;
+        let bytes = self
+            .bytes
+            .get(usize::try_from(start).ok()?..usize::try_from(end).ok()?)?;
+        Some(Source {
+            bytes,
+            file: self.file.map(|(file, offset)| (file, offset + start)),
+        })
+    }
+}
+
+/// Writes the source bytes to `out`.
+///
+/// On Linux, when the file backing the source is known, the bytes are copied between files in the
+/// kernel.
+pub(crate) fn write(source: Source, mut out: &std::fs::File) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    if let Some((file, offset)) = source.file {
+        let copied = copy_in_kernel(file, offset, out, source.bytes.len());
+        return out.write_all(&source.bytes[copied..]);
+    }
+    out.write_all(source.bytes)
+}
+
+// N.B.: Copies as many bytes as the kernel allows and returns the count copied; any remainder is
+// left for the caller to write from the mapped bytes. The `copy_file_range` syscall can fail
+// across file systems on older kernels and `sendfile` covers most of those cases.
+#[cfg(target_os = "linux")]
+fn copy_in_kernel(file: &std::fs::File, offset: u64, out: &std::fs::File, len: usize) -> usize {
+    use std::os::fd::AsFd;
+
+    use nix::fcntl::copy_file_range;
+    use nix::sys::sendfile::sendfile64;
+
+    let Ok(mut off_in) = i64::try_from(offset) else {
+        return 0;
+    };
+    let mut copied = 0;
+    while copied < len {
+        // N.B.: The nix `copy_file_range` wrapper passes its input argument to the raw syscall
+        // as-is; so it must be a `BorrowedFd` and not a reference to a `File`.
+        match copy_file_range(file.as_fd(), Some(&mut off_in), out, None, len - copied) {
+            Ok(0) => return copied,
+            Ok(count) => copied += count,
+            Err(e) if copied == 0 => {
+                debug!("Falling back from copy_file_range to sendfile: {e}");
+                break;
+            }
+            Err(_) => return copied,
+        }
+    }
+    while copied < len {
+        match sendfile64(out, file, Some(&mut off_in), len - copied) {
+            Ok(0) | Err(_) => return copied,
+            Ok(count) => copied += count,
+        }
+    }
+    copied
+}
+
+fn workers() -> usize {
+    std::thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(MAX_WORKERS)
+}
+
+// N.B.: Panic payloads are almost always the formatted message as a `&str` or `String`.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<unknown>")
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn get_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn get_mode(_metadata: &std::fs::Metadata) -> u32 {
+    0
+}
+
+const QUARANTINE_XATTR: &str = "com.apple.quarantine";
+
+// N.B.: Tar archives record extended attributes as PAX extended header records.
+const PAX_XATTR_PREFIX: &str = "SCHILY.xattr.";
+
+type Xattrs = Vec<(String, Vec<u8>)>;
+
+// Returns the extended attributes recorded for a tar entry that the policy keeps.
+fn entry_xattrs<R: Read>(
+    entry: &mut tar::Entry<R>,
+    policy: &ExtractPolicy,
+) -> Result<Xattrs, String> {
+    let mut xattrs = vec![];
+    if !policy.xattrs && !policy.quarantine {
+        return Ok(xattrs);
+    }
+    let Some(extensions) = entry
+        .pax_extensions()
+        .map_err(|e| format!("Failed to read PAX extensions: {e}"))?
+    else {
+        return Ok(xattrs);
+    };
+    for extension in extensions {
+        let extension = extension.map_err(|e| format!("Invalid PAX extension: {e}"))?;
+        let Some(name) = extension
+            .key()
+            .ok()
+            .and_then(|key| key.strip_prefix(PAX_XATTR_PREFIX))
+        else {
+            continue;
+        };
+        if policy.keep_xattr(name) {
+            xattrs.push((name.to_string(), extension.value_bytes().to_vec()));
+        }
+    }
+    Ok(xattrs)
+}
+
+// A file stored in one of the PAX sparse formats written by `tar --sparse --format=pax`.
+//
+// N.B.: The `tar` crate only understands the older GNU sparse headers, which it unpacks sparsely
+// itself; so we handle these formats ourselves. See:
+// https://www.gnu.org/software/tar/manual/html_node/Sparse-Formats.html
+pub(crate) struct PaxSparse {
+    name: Option<PathBuf>,
+    size: u64,
+    // The offset and length of each region of data; `None` for format 1.0 where this map leads
+    // the entry data instead.
+    map: Option<Vec<(u64, u64)>>,
+}
+
+fn parse_sparse_number(value: &str) -> Result<u64, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid sparse file number {value:?}: {e}"))
+}
+
+pub(crate) fn pax_sparse<R: Read>(entry: &mut tar::Entry<R>) -> Result<Option<PaxSparse>, String> {
+    let Some(extensions) = entry
+        .pax_extensions()
+        .map_err(|e| format!("Failed to read PAX extensions: {e}"))?
+    else {
+        return Ok(None);
+    };
+    let mut major = None;
+    let mut name = None;
+    let mut size = None;
+    let mut real_size = None;
+    let mut map = None;
+    let mut offsets = vec![];
+    let mut lengths = vec![];
+    for extension in extensions {
+        let extension = extension.map_err(|e| format!("Invalid PAX extension: {e}"))?;
+        let (Ok(key), Ok(value)) = (extension.key(), extension.value()) else {
+            continue;
+        };
+        match key {
+            "GNU.sparse.major" => major = Some(value.to_string()),
+            "GNU.sparse.name" => name = Some(PathBuf::from(value)),
+            "GNU.sparse.size" => size = Some(parse_sparse_number(value)?),
+            "GNU.sparse.realsize" => real_size = Some(parse_sparse_number(value)?),
+            "GNU.sparse.map" => map = Some(value.to_string()),
+            "GNU.sparse.offset" => offsets.push(parse_sparse_number(value)?),
+            "GNU.sparse.numbytes" => lengths.push(parse_sparse_number(value)?),
+            _ => {}
+        }
+    }
+    let (size, map) = if major.as_deref() == Some("1") {
+        (real_size, None)
+    } else if let Some(map) = map {
+        let numbers = map
+            .split(',')
+            .map(parse_sparse_number)
+            .collect::<Result<Vec<_>, _>>()?;
+        if numbers.len() % 2 != 0 {
+            return Err(format!(
+                "The sparse file map {map:?} has an odd number of entries."
+            ));
+        }
+        (
+            size,
+            Some(numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect()),
+        )
+    } else if !offsets.is_empty() {
+        if offsets.len() != lengths.len() {
+            return Err("The sparse file offsets and lengths do not pair up.".to_string());
+        }
+        (size, Some(offsets.into_iter().zip(lengths).collect()))
+    } else {
+        return Ok(None);
+    };
+    let size = size.ok_or_else(|| "The sparse file does not record its size.".to_string())?;
+    Ok(Some(PaxSparse { name, size, map }))
+}
+
+// Reads the sparse map that leads the data of a format 1.0 sparse file along with the padding
+// that rounds it up to a whole tar block.
+fn read_sparse_map<R: Read>(data: &mut R) -> Result<Vec<(u64, u64)>, String> {
+    let mut consumed = 0_u64;
+    let mut next_number = || {
+        let mut digits = String::new();
+        loop {
+            let mut byte = [0];
+            data.read_exact(&mut byte)
+                .map_err(|e| format!("Failed to read the sparse file map: {e}"))?;
+            consumed += 1;
+            match byte[0] {
+                b'\n' => break,
+                digit @ b'0'..=b'9' if digits.len() < 20 => digits.push(char::from(digit)),
+                _ => return Err("The sparse file map is invalid.".to_string()),
+            }
+        }
+        parse_sparse_number(&digits)
+    };
+    let count = next_number()?;
+    let mut map = vec![];
+    for _ in 0..count {
+        map.push((next_number()?, next_number()?));
+    }
+    let padding = (512 - consumed % 512) % 512;
+    std::io::copy(&mut data.take(padding), &mut std::io::sink())
+        .map_err(|e| format!("Failed to read the sparse file map: {e}"))?;
+    Ok(map)
+}
+
+// Writes the regions of data of a sparse file, leaving holes between them.
+fn write_sparse<R: Read>(
+    data: &mut R,
+    sparse: PaxSparse,
+    file_path: &Path,
+    dst: &Path,
+) -> Result<Option<(PathBuf, std::fs::File)>, String> {
+    let map = match sparse.map {
+        Some(map) => map,
+        None => read_sparse_map(data)?,
+    };
+    let Some((path, mut out)) = create_file(file_path, dst)? else {
+        return Ok(None);
+    };
+    for (offset, length) in map {
+        if offset
+            .checked_add(length)
+            .map(|end| end > sparse.size)
+            .unwrap_or(true)
+        {
+            return Err(format!(
+                "The sparse file {path} has {length} bytes of data at offset {offset} which lie \
+                beyond its size of {size} bytes.",
+                path = file_path.display(),
+                size = sparse.size
+            ));
+        }
+        out.seek(SeekFrom::Start(offset))
+            .and_then(|_| std::io::copy(&mut data.take(length), &mut out))
+            .and_then(|copied| {
+                if copied == length {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "the archive ended part way through its data",
+                    ))
+                }
+            })
+            .map_err(|e| format!("Failed to write {path}: {e}", path = path.display()))?;
+    }
+    out.set_len(sparse.size)
+        .map_err(|e| format!("Failed to write {path}: {e}", path = path.display()))?;
+    Ok(Some((path, out)))
+}
+
+#[cfg(unix)]
+fn set_xattrs(path: &Path, xattrs: &Xattrs) -> Result<(), String> {
+    for (name, value) in xattrs {
+        xattr::set(path, name, value).map_err(|e| {
+            format!(
+                "Failed to set the extended attribute {name} of {path}: {e}",
+                path = path.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_xattrs(_path: &Path, _xattrs: &Xattrs) -> Result<(), String> {
+    Ok(())
+}
+
+// Removes the quarantine flag from `path` and everything beneath it and ad-hoc code signs any
+// unsigned Mach-O files amongst them, as asked; so that macOS will run them.
+#[cfg(unix)]
+fn prepare_for_macos(path: &Path, strip_quarantine: bool, codesign: bool) -> Result<(), String> {
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry.map_err(|e| {
+            format!(
+                "Failed to walk {path} to prepare it for macOS: {e}",
+                path = path.display()
+            )
+        })?;
+        if entry.file_type().is_symlink() {
+            continue;
+        }
+        let path = entry.path();
+        if strip_quarantine {
+            let quarantined = xattr::get(path, QUARANTINE_XATTR)
+                .map(|value| value.is_some())
+                .unwrap_or(false);
+            if quarantined {
+                xattr::remove(path, QUARANTINE_XATTR).map_err(|e| {
+                    format!(
+                        "Failed to remove the quarantine flag from {path}: {e}",
+                        path = path.display()
+                    )
+                })?;
+            }
+        }
+        if codesign && entry.file_type().is_file() && is_unsigned_macho(path)? {
+            let output = std::process::Command::new("/usr/bin/codesign")
+                .args(["--sign", "-"])
+                .arg(path)
+                .output()
+                .map_err(|e| {
+                    format!(
+                        "Failed to run codesign to sign {path}: {e}",
+                        path = path.display()
+                    )
+                })?;
+            if !output.status.success() {
+                return Err(format!(
+                    "Failed to ad-hoc code sign {path}: {stderr}",
+                    path = path.display(),
+                    stderr = String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_unsigned_macho(path: &Path) -> Result<bool, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {path}: {e}", path = path.display()))?;
+    let metadata = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat {path}: {e}", path = path.display()))?;
+    if metadata.len() < 4 {
+        return Ok(false);
+    }
+    // SAFETY: The file was just extracted by us and is not modified while mapped.
+    let data = unsafe { memmap2::Mmap::map(&file) }
+        .map_err(|e| format!("Failed to map {path}: {e}", path = path.display()))?;
+    Ok(crate::embed::is_unsigned_macho(&data))
+}
+
+#[cfg(not(unix))]
+fn prepare_for_macos(_path: &Path, _strip_quarantine: bool, _codesign: bool) -> Result<(), String> {
+    Ok(())
+}
+
+// N.B.: Extraction happens in a work path next to the final path that is then renamed into place;
+// so the contexts restored for the work path are those of its siblings.
+fn restore_selinux_contexts(path: &Path) -> Result<(), String> {
+    match std::process::Command::new("restorecon")
+        .arg("-R")
+        .arg(path)
+        .output()
+    {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!(
+            "Failed to restore the SELinux contexts of {path}: {stderr}",
+            path = path.display(),
+            stderr = String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!(
+                "No restorecon found; leaving the SELinux contexts of {path} as-is.",
+                path = path.display()
+            );
+            Ok(())
+        }
+        Err(e) => Err(format!(
+            "Failed to run restorecon on {path}: {e}",
+            path = path.display()
+        )),
+    }
+}
+
+/// Adjusts the permissions and metadata extracted files end up with.
+///
+/// By default, the rwx permission bits recorded in archives are respected but setuid, setgid and
+/// sticky bits as well as extended attributes are dropped.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ExtractPolicy {
+    /// Permission bits to clear from every file and directory.
+    pub(crate) umask: u32,
+    /// Restricts directories to `0700` and clears the group and other bits of files.
+    pub(crate) private: bool,
+    /// Preserves the setuid, setgid and sticky bits recorded in archives.
+    pub(crate) setuid: bool,
+    /// Restores the extended attributes, including any POSIX ACLs, recorded in tar archives;
+    /// except for macOS quarantine flags.
+    pub(crate) xattrs: bool,
+    /// Restores the macOS quarantine flags recorded in tar archives.
+    pub(crate) quarantine: bool,
+    /// Removes the macOS quarantine flag from extracted files on macOS.
+    pub(crate) strip_quarantine: bool,
+    /// Ad-hoc code signs extracted Mach-O files that are not signed on macOS.
+    pub(crate) codesign: bool,
+    /// Restores the default SELinux contexts of extracted files on Linux.
+    pub(crate) selinux: bool,
+    /// Renames extracted files, and retargets symlinks, to use this Unicode normalization form.
+    pub(crate) unicode_normalization: Option<UnicodeNormalization>,
+}
+
+impl ExtractPolicy {
+    fn archive_mode(&self, mode: u32) -> u32 {
+        mode & if self.setuid { 0o7777 } else { 0o777 }
+    }
+
+    fn keep_xattr(&self, name: &str) -> bool {
+        if name == QUARANTINE_XATTR {
+            self.quarantine
+        } else {
+            self.xattrs
+        }
+    }
+
+    fn mode(&self, mode: u32, is_dir: bool) -> u32 {
+        if self.private && is_dir {
+            0o700
+        } else if self.private {
+            mode & !0o077
+        } else {
+            mode & !self.umask
+        }
+    }
+
+    /// Restricts a directory created to hold extracted files to the current user when the policy
+    /// is private; so the names of the files it holds are private too.
+    pub(crate) fn protect_dir(&self, dir: &Path) -> Result<(), String> {
+        if !self.private {
+            return Ok(());
+        }
+        set_mode(dir, 0o700).map_err(|e| {
+            format!(
+                "Failed to set permissions of {dir}: {e}",
+                dir = dir.display()
+            )
+        })
+    }
+
+    /// Applies the policy to `path` and, if it is a directory, everything beneath it.
+    ///
+    /// Symlinks are left alone since their own modes are not used and changing the mode of their
+    /// targets could reach outside `path`.
+    pub(crate) fn apply(&self, path: &Path) -> Result<(), String> {
+        match self.unicode_normalization {
+            Some(UnicodeNormalization::Preserve) | None => {}
+            Some(form) => normalize_names(path, form)?,
+        }
+        if cfg!(target_os = "macos") && (self.strip_quarantine || self.codesign) {
+            prepare_for_macos(path, self.strip_quarantine, self.codesign)?;
+        }
+        if cfg!(target_os = "linux") && self.selinux {
+            restore_selinux_contexts(path)?;
+        }
+        if (self.umask == 0 && !self.private) || !cfg!(unix) {
+            return Ok(());
+        }
+        // N.B.: Contents are visited before the directory containing them; so a directory made
+        // unreadable by the policy is not visited afterwards.
+        for entry in walkdir::WalkDir::new(path).contents_first(true) {
+            let entry = entry.map_err(|e| {
+                format!(
+                    "Failed to walk {path} to apply permissions: {e}",
+                    path = path.display()
+                )
+            })?;
+            let file_type = entry.file_type();
+            if file_type.is_symlink() {
+                continue;
+            }
+            let metadata = entry.metadata().map_err(|e| {
+                format!(
+                    "Failed to read the permissions of {path}: {e}",
+                    path = entry.path().display()
+                )
+            })?;
+            let mode = get_mode(&metadata);
+            let new_mode = self.mode(mode, file_type.is_dir());
+            if new_mode != mode {
+                set_mode(entry.path(), new_mode).map_err(|e| {
+                    format!(
+                        "Failed to set permissions of {path}: {e}",
+                        path = entry.path().display()
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Runs `op` on an entry in `dir`, temporarily making `dir` writable if it was extracted read-only.
+fn in_writable_dir<T>(
+    dir: &Path,
+    mut op: impl FnMut() -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    match op() {
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            let mode = get_mode(&dir.metadata()?);
+            if mode & 0o200 != 0 {
+                return Err(e);
+            }
+            set_mode(dir, mode | 0o200)?;
+            let result = op();
+            set_mode(dir, mode)?;
+            result
+        }
+        result => result,
+    }
+}
+
+#[cfg(unix)]
+fn normalize_symlink(link: &Path, form: UnicodeNormalization) -> Result<(), String> {
+    let target = std::fs::read_link(link).map_err(|e| {
+        format!(
+            "Failed to read the target of the symlink {link}: {e}",
+            link = link.display()
+        )
+    })?;
+    let Some(target) = target.to_str() else {
+        return Ok(());
+    };
+    let normalized = form.normalize(target);
+    if normalized == target {
+        return Ok(());
+    }
+    let dir = link.parent().unwrap_or(link);
+    in_writable_dir(dir, || {
+        std::fs::remove_file(link)?;
+        std::os::unix::fs::symlink(normalized.as_ref(), link)
+    })
+    .map_err(|e| {
+        format!(
+            "Failed to retarget the symlink {link} -> {target} to {normalized}: {e}",
+            link = link.display()
+        )
+    })
+}
+
+#[cfg(not(unix))]
+fn normalize_symlink(_link: &Path, _form: UnicodeNormalization) -> Result<(), String> {
+    Ok(())
+}
+
+// Renames the entries under `dir` whose names are not in the given normalization form. Names that
+// are not valid UTF-8 are left alone.
+fn normalize_names(dir: &Path, form: UnicodeNormalization) -> Result<(), String> {
+    // N.B.: Contents are visited before the directory containing them; so entries are renamed
+    // while the path to them is still spelled as it was extracted.
+    let entries = walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            format!(
+                "Failed to walk {dir} to normalize file names: {e}",
+                dir = dir.display()
+            )
+        })?;
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type().is_symlink() {
+            normalize_symlink(path, form)?;
+        }
+        let Some(name) = entry.file_name().to_str() else {
+            continue;
+        };
+        let normalized = form.normalize(name);
+        if normalized == name {
+            continue;
+        }
+        let parent = path.parent().unwrap_or(dir);
+        let normalized_path = parent.join(normalized.as_ref());
+        if normalized_path.symlink_metadata().is_ok() {
+            return Err(format!(
+                "Cannot normalize the name of {path} since {normalized_path} already exists.",
+                path = path.display(),
+                normalized_path = normalized_path.display()
+            ));
+        }
+        in_writable_dir(parent, || std::fs::rename(path, &normalized_path)).map_err(|e| {
+            format!(
+                "Failed to rename {path} to {normalized_path}: {e}",
+                path = path.display(),
+                normalized_path = normalized_path.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Extracts the zip archive contained in `source` to `dst`, spreading its entries over a pool of
+/// threads.
+///
+/// Entries with absolute paths or `..` components fail the extraction.
+///
+/// N.B.: The archive as a whole has already been verified against its expected hash; so the
+/// contents of stored entries are written out directly without re-checking their CRCs.
+pub(crate) fn zip(source: Source, dst: &Path, policy: &ExtractPolicy) -> Result<(), String> {
+    let stream = ZipStream::new(Cursor::new(source.bytes)).map_err(|e| format!("{e}"))?;
+    let skew = stream.skew();
+    let archive = ZipArchive::new(stream).map_err(|e| format!("{e}"))?;
+    let count = archive.len();
+    let workers = workers().min(count);
+
+    let next = AtomicUsize::new(0);
+    let directories = Mutex::new(vec![]);
+    std::thread::scope(|scope| {
+        let handles = (0..workers.max(1))
+            .map(|_| {
+                let mut archive = archive.clone();
+                let next = &next;
+                let directories = &directories;
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= count {
+                        return Ok::<_, String>(());
+                    }
+                    if let Some(directory) =
+                        extract_zip_entry(&mut archive, source, skew, index, dst, policy)?
+                    {
+                        directories
+                            .lock()
+                            .map_err(|e| format!("Failed to record a directory: {e}"))?
+                            .push(directory);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().try_for_each(|handle| {
+            handle.join().map_err(|e| {
+                format!(
+                    "A zip extraction thread panicked: {message}",
+                    message = panic_message(e.as_ref())
+                )
+            })?
+        })
+    })?;
+
+    // N.B.: Directory permissions are applied last so that a read-only directory does not prevent
+    // the extraction of its contents.
+    let directories = directories
+        .into_inner()
+        .map_err(|e| format!("Failed to collect directories: {e}"))?;
+    for (path, mode) in directories {
+        set_mode(&path, mode).map_err(|e| {
+            format!(
+                "Failed to set permissions of {path}: {e}",
+                path = path.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn extract_zip_entry(
+    archive: &mut ZipArchive<ZipStream<Cursor<&[u8]>>>,
+    source: Source,
+    skew: u64,
+    index: usize,
+    dst: &Path,
+    policy: &ExtractPolicy,
+) -> Result<Option<(PathBuf, u32)>, String> {
+    let mut entry = archive
+        .by_index(index)
+        .map_err(|e| format!("Failed to read entry {index}: {e}"))?;
+    if entry.name().contains('\0') {
+        return Err(format!("Invalid file path: {name}", name = entry.name()));
+    }
+    let path = dst.join(relative_entry_path(Path::new(entry.name()))?);
+    if entry.is_dir() {
+        std::fs::create_dir_all(&path).map_err(|e| {
+            format!(
+                "Failed to create directory {path}: {e}",
+                path = path.display()
+            )
+        })?;
+        return Ok(entry
+            .unix_mode()
+            .map(|mode| (path, policy.archive_mode(mode))));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            format!(
+                "Failed to create directory {parent}: {e}",
+                parent = parent.display()
+            )
+        })?;
+    }
+    let mut file = std::fs::File::create(&path)
+        .map_err(|e| format!("Failed to create {path}: {e}", path = path.display()))?;
+    let stored = if entry.compression() == CompressionMethod::Stored {
+        entry
+            .data_start()
+            .checked_sub(skew)
+            .and_then(|data_start| source.slice(data_start, entry.size()))
+    } else {
+        None
+    };
+    match stored {
+        Some(stored) => write(stored, &file),
+        None => std::io::copy(&mut entry, &mut file).map(|_| ()),
+    }
+    .map_err(|e| format!("Failed to extract {path}: {e}", path = path.display()))?;
+    if let Some(mode) = entry.unix_mode() {
+        set_mode(&path, policy.archive_mode(mode)).map_err(|e| {
+            format!(
+                "Failed to set permissions of {path}: {e}",
+                path = path.display()
+            )
+        })?;
+    }
+    Ok(None)
+}
+
+struct BufferedFile {
+    path: PathBuf,
+    mode: u32,
+    mtime: u64,
+    xattrs: Xattrs,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Pending {
+    count: Mutex<usize>,
+    done: Condvar,
+    error: Mutex<Option<String>>,
+}
+
+impl Pending {
+    fn add(&self) {
+        *self.count.lock().unwrap() += 1;
+    }
+
+    fn finish(&self, result: Result<(), String>) {
+        if let Err(err) = result {
+            self.error.lock().unwrap().get_or_insert(err);
+        }
+        let mut count = self.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    fn wait(&self) -> Result<(), String> {
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            count = self.done.wait(count).unwrap();
+        }
+        match self.error.lock().unwrap().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+// Returns the path of an archive entry relative to the directory it is extracted to.
+//
+// N.B.: A crafted archive can use absolute paths or `..` components to write outside of the
+// directory it is extracted to; so these are rejected outright instead of being stripped or skipped.
+fn relative_entry_path(path: &Path) -> Result<PathBuf, String> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => continue,
+            Component::Prefix(_) | Component::RootDir => {
+                return Err(format!(
+                    "Refusing to extract {path} since it is an absolute path.",
+                    path = path.display()
+                ))
+            }
+            Component::ParentDir => {
+                return Err(format!(
+                    "Refusing to extract {path} since it contains a `..` component.",
+                    path = path.display()
+                ))
+            }
+        }
+    }
+    // N.B.: Windows would open a device for a reserved name like `aux` and silently strip trailing
+    // dots; so entries that cannot be faithfully extracted there are rejected.
+    if cfg!(windows) {
+        if let Some(problem) = windows_path_problem(&relative) {
+            return Err(format!(
+                "Cannot extract {path} since {problem}.",
+                path = path.display()
+            ));
+        }
+    }
+    Ok(relative)
+}
+
+// Checks that the symlink at `link`, relative to `dst`, points inside of `dst`.
+//
+// N.B.: The target is resolved from the real path of the link's parent directory and `..`
+// components may only lead the target. Since every symlink extracted before this one was checked the
+// same way, any symlinks the target descends through also point inside of `dst`.
+fn check_symlink(link: &Path, target: &Path, canonical_dst: &Path) -> Result<(), String> {
+    let escapes = || {
+        format!(
+            "Refusing to extract the symlink {link} -> {target} since it points outside of the \
+            directory being extracted to.",
+            link = link.display(),
+            target = target.display()
+        )
+    };
+    let parent = canonical_dst.join(link);
+    let parent = parent.parent().unwrap_or(canonical_dst);
+    std::fs::create_dir_all(parent).map_err(|e| {
+        format!(
+            "Failed to create directory {parent}: {e}",
+            parent = parent.display()
+        )
+    })?;
+    let mut resolved = parent.canonicalize().map_err(|e| {
+        format!(
+            "Failed to canonicalize {parent}: {e}",
+            parent = parent.display()
+        )
+    })?;
+    if !resolved.starts_with(canonical_dst) {
+        return Err(escapes());
+    }
+    let mut descending = false;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => descending = true,
+            Component::CurDir => continue,
+            Component::ParentDir if !descending => {
+                if resolved == canonical_dst {
+                    return Err(escapes());
+                }
+                resolved.pop();
+            }
+            Component::ParentDir => {
+                return Err(format!(
+                    "Refusing to extract the symlink {link} -> {target} since its target has a \
+                    `..` component after a named one.",
+                    link = link.display(),
+                    target = target.display()
+                ))
+            }
+            Component::Prefix(_) | Component::RootDir => return Err(escapes()),
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the tar stream to `dst`.
+///
+/// The stream is decoded on the calling thread while small regular files are written out by a
+/// pool of threads. Any other kind of entry waits for in-flight writes to complete before being
+/// unpacked in order; so links always see their targets.
+///
+/// Entries with absolute paths or `..` components, symlinks that point outside of `dst` and hard
+/// links to files outside of `dst` all fail the extraction.
+pub(crate) fn tar<R: Read>(stream: R, dst: &Path, policy: &ExtractPolicy) -> Result<(), String> {
+    let mut archive = tar::Archive::new(stream);
+    let workers = workers();
+    let buffer = workers > 1;
+
+    std::fs::create_dir_all(dst)
+        .map_err(|e| format!("Failed to create {dst}: {e}", dst = dst.display()))?;
+    let canonical_dst = dst
+        .canonicalize()
+        .map_err(|e| format!("Failed to canonicalize {dst}: {e}", dst = dst.display()))?;
+
+    let pending = Pending::default();
+    let (sender, receiver) = sync_channel::<BufferedFile>(workers * 2);
+    let receiver = Mutex::new(receiver);
+    std::thread::scope(|scope| {
+        for _ in 0..if buffer { workers } else { 0 } {
+            let receiver = &receiver;
+            let pending = &pending;
+            let canonical_dst = canonical_dst.as_path();
+            scope.spawn(move || {
+                while let Some(file) = next(receiver) {
+                    pending.finish(write_buffered_file(file, canonical_dst));
+                }
+            });
+        }
+
+        let result = (|| {
+            let mut directories = vec![];
+            let entries = archive
+                .entries()
+                .map_err(|e| format!("Failed to read entries: {e}"))?;
+            for entry in entries {
+                let mut entry = entry.map_err(|e| format!("Failed to read entry: {e}"))?;
+                let entry_type = entry.header().entry_type();
+                let regular = matches!(entry_type, EntryType::Regular | EntryType::Continuous);
+                let sparse = if regular {
+                    pax_sparse(&mut entry)?
+                } else {
+                    None
+                };
+                let path = match sparse.as_ref().and_then(|sparse| sparse.name.as_deref()) {
+                    Some(name) => relative_entry_path(name)?,
+                    None => relative_entry_path(
+                        &entry
+                            .path()
+                            .map_err(|e| format!("Invalid entry path: {e}"))?,
+                    )?,
+                };
+                if matches!(entry_type, EntryType::Symlink | EntryType::Link) {
+                    let target = entry
+                        .link_name()
+                        .map_err(|e| {
+                            format!("Invalid link target for {path}: {e}", path = path.display())
+                        })?
+                        .ok_or_else(|| {
+                            format!("The link {path} has no target.", path = path.display())
+                        })?;
+                    if entry_type == EntryType::Symlink {
+                        check_symlink(&path, &target, &canonical_dst)?;
+                    } else {
+                        relative_entry_path(&target)?;
+                    }
+                }
+                entry.set_preserve_permissions(policy.setuid);
+                let xattrs = entry_xattrs(&mut entry, policy)?;
+                if entry_type == EntryType::Directory {
+                    // N.B.: This mirrors `tar::Archive::unpack` which defers directories so that
+                    // their permissions do not interfere with the extraction of their contents.
+                    directories.push((entry, path, xattrs));
+                    continue;
+                }
+                if regular && (sparse.is_some() || (buffer && entry.size() <= MAX_BUFFERED_SIZE)) {
+                    let mode = entry.header().mode().map_err(|e| {
+                        format!("Invalid mode for {path}: {e}", path = path.display())
+                    })?;
+                    let mtime = entry.header().mtime().map_err(|e| {
+                        format!("Invalid mtime for {path}: {e}", path = path.display())
+                    })?;
+                    if let Some(sparse) = sparse {
+                        pending.wait()?;
+                        if let Some((path, out)) =
+                            write_sparse(&mut entry, sparse, &path, &canonical_dst)?
+                        {
+                            finish_file(&out, &path, policy.archive_mode(mode), mtime, &xattrs)?;
+                        }
+                        continue;
+                    }
+                    let mut data = Vec::with_capacity(entry.size() as usize);
+                    entry.read_to_end(&mut data).map_err(|e| {
+                        format!("Failed to read {path}: {e}", path = path.display())
+                    })?;
+                    pending.add();
+                    sender
+                        .send(BufferedFile {
+                            path,
+                            mode: policy.archive_mode(mode),
+                            mtime,
+                            xattrs,
+                            data,
+                        })
+                        .map_err(|e| format!("Failed to hand off a file for writing: {e}"))?;
+                } else {
+                    pending.wait()?;
+                    entry
+                        .unpack_in(dst)
+                        .map_err(|e| format!("Failed to unpack entry: {e}"))?;
+                    // N.B.: Setting extended attributes on a symlink would set them on its target.
+                    if entry_type != EntryType::Symlink {
+                        set_xattrs(&dst.join(&path), &xattrs)?;
+                    }
+                }
+            }
+            pending.wait()?;
+            for (mut directory, path, xattrs) in directories {
+                directory
+                    .unpack_in(dst)
+                    .map_err(|e| format!("Failed to unpack directory: {e}"))?;
+                set_xattrs(&dst.join(path), &xattrs)?;
+            }
+            Ok(())
+        })();
+        // N.B.: Dropping the sender lets the writer threads exit once the channel is drained.
+        drop(sender);
+        result
+    })
+}
+
+fn next(receiver: &Mutex<Receiver<BufferedFile>>) -> Option<BufferedFile> {
+    receiver.lock().ok()?.recv().ok()
+}
+
+fn write_buffered_file(file: BufferedFile, dst: &Path) -> Result<(), String> {
+    let Some((path, mut out)) = create_file(&file.path, dst)? else {
+        return Ok(());
+    };
+    out.write_all(&file.data)
+        .map_err(|e| format!("Failed to write {path}: {e}", path = path.display()))?;
+    finish_file(&out, &path, file.mode, file.mtime, &file.xattrs)
+}
+
+// Creates the file at the relative `file_path` under `dst` for writing; returning `None` if the
+// path names `dst` itself.
+fn create_file(file_path: &Path, dst: &Path) -> Result<Option<(PathBuf, std::fs::File)>, String> {
+    // N.B.: The file path was already checked to be relative and free of `..` components but a
+    // parent directory could still be a symlink; so we check where the file really lands below.
+    let path = dst.join(file_path);
+    if path == dst {
+        return Ok(None);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            format!(
+                "Failed to create directory {parent}: {e}",
+                parent = parent.display()
+            )
+        })?;
+        let canonical_parent = parent.canonicalize().map_err(|e| {
+            format!(
+                "Failed to canonicalize {parent}: {e}",
+                parent = parent.display()
+            )
+        })?;
+        if !canonical_parent.starts_with(dst) {
+            return Err(format!(
+                "Refusing to write {path} outside of {dst} via {canonical_parent}",
+                path = file_path.display(),
+                dst = dst.display(),
+                canonical_parent = canonical_parent.display()
+            ));
+        }
+    }
+
+    let out = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .or_else(|_| {
+            std::fs::remove_file(&path)?;
+            OpenOptions::new().write(true).create_new(true).open(&path)
+        })
+        .map_err(|e| format!("Failed to create {path}: {e}", path = path.display()))?;
+    Ok(Some((path, out)))
+}
+
+fn finish_file(
+    out: &std::fs::File,
+    path: &Path,
+    mode: u32,
+    mtime: u64,
+    xattrs: &Xattrs,
+) -> Result<(), String> {
+    // N.B.: These match the `tar::Archive` defaults of preserving mtimes but not ownership. The
+    // mode was already masked by the extraction policy.
+    let mtime = filetime::FileTime::from_unix_time(mtime.max(1) as i64, 0);
+    filetime::set_file_handle_times(out, Some(mtime), Some(mtime)).map_err(|e| {
+        format!(
+            "Failed to set modification time of {path}: {e}",
+            path = path.display()
+        )
+    })?;
+    set_mode(path, mode).map_err(|e| {
+        format!(
+            "Failed to set permissions of {path}: {e}",
+            path = path.display()
+        )
+    })?;
+    set_xattrs(path, xattrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+    use zip::write::FileOptions;
+    use zip::CompressionMethod;
+
+    use super::{panic_message, ExtractPolicy, Source};
+    use crate::config::UnicodeNormalization;
+
+    fn assert_tree(dst: &Path) {
+        for index in 0..100 {
+            assert_eq!(
+                format!("file {index}"),
+                std::fs::read_to_string(dst.join(format!("dir{}/file{index}", index % 7))).unwrap()
+            );
+        }
+        assert!(dst.join("empty").is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dst.join("dir0/file0"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(0o755, mode & 0o777);
+        }
+    }
+
+    fn create_zip(compression: CompressionMethod) -> Vec<u8> {
+        let mut buffer = std::io::Cursor::new(vec![]);
+        let mut archive = zip::ZipWriter::new(&mut buffer);
+        archive
+            .add_directory("empty/", FileOptions::default())
+            .unwrap();
+        for index in 0..100 {
+            archive
+                .start_file(
+                    format!("dir{}/file{index}", index % 7),
+                    FileOptions::default()
+                        .compression_method(compression)
+                        .unix_permissions(0o755),
+                )
+                .unwrap();
+            write!(archive, "file {index}").unwrap();
+        }
+        archive.finish().unwrap();
+        drop(archive);
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn zip() {
+        let bytes = create_zip(CompressionMethod::Deflated);
+        let dst = tempdir().unwrap();
+        super::zip(
+            Source {
+                bytes: &bytes,
+                file: None,
+            },
+            dst.path(),
+            &ExtractPolicy::default(),
+        )
+        .unwrap();
+        assert_tree(dst.path());
+    }
+
+    #[test]
+    fn zip_stored_from_file() {
+        let prefix = b"#!/not/a/zip\n";
+        let bytes = create_zip(CompressionMethod::Stored);
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(prefix).unwrap();
+        file.write_all(&bytes).unwrap();
+
+        let dst = tempdir().unwrap();
+        super::zip(
+            Source {
+                bytes: &bytes,
+                file: Some((&file, prefix.len() as u64)),
+            },
+            dst.path(),
+            &ExtractPolicy::default(),
+        )
+        .unwrap();
+        assert_tree(dst.path());
+    }
+
+    #[test]
+    fn zip_skewed() {
+        // Zip offsets recorded relative to the start of a file with a prefix, as `zip -A` does.
+        for compression in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let prefix = b"#!/not/a/zip\n";
+            let mut buffer = std::io::Cursor::new(prefix.to_vec());
+            buffer.set_position(prefix.len() as u64);
+            let mut archive = zip::ZipWriter::new(buffer);
+            archive
+                .add_directory("empty/", FileOptions::default())
+                .unwrap();
+            for index in 0..100 {
+                archive
+                    .start_file(
+                        format!("dir{}/file{index}", index % 7),
+                        FileOptions::default()
+                            .compression_method(compression)
+                            .unix_permissions(0o755),
+                    )
+                    .unwrap();
+                write!(archive, "file {index}").unwrap();
+            }
+            let bytes = archive.finish().unwrap().into_inner();
+
+            let dst = tempdir().unwrap();
+            super::zip(
+                Source {
+                    bytes: &bytes[prefix.len()..],
+                    file: None,
+                },
+                dst.path(),
+                &ExtractPolicy::default(),
+            )
+            .unwrap();
+            assert_tree(dst.path());
+        }
+    }
+
+    #[test]
+    fn tar() {
+        let mut archive = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o555);
+        header.set_size(0);
+        archive
+            .append_data(&mut header, "empty", std::io::empty())
+            .unwrap();
+        for index in 0..100 {
+            let data = format!("file {index}");
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o755);
+            header.set_mtime(1_000_000);
+            header.set_size(data.len() as u64);
+            archive
+                .append_data(
+                    &mut header,
+                    format!("dir{}/file{index}", index % 7),
+                    data.as_bytes(),
+                )
+                .unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        archive
+            .append_link(&mut header, "link", "dir0/file0")
+            .unwrap();
+        let buffer = archive.into_inner().unwrap();
+
+        let dst = tempdir().unwrap();
+        let root = dst.path().join("root");
+        super::tar(buffer.as_slice(), &root, &ExtractPolicy::default()).unwrap();
+        assert_tree(&root);
+        assert_eq!(
+            "file 0",
+            std::fs::read_to_string(root.join("link")).unwrap()
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let inode = |path: &str| std::fs::metadata(root.join(path)).unwrap().ino();
+            assert_eq!(inode("dir0/file0"), inode("link"));
+        }
+        assert_eq!(
+            filetime::FileTime::from_unix_time(1_000_000, 0),
+            filetime::FileTime::from_last_modification_time(
+                &std::fs::metadata(root.join("dir3/file10")).unwrap()
+            )
+        );
+    }
+
+    // N.B.: The `tar` crate refuses to build entries like these; so we write their names directly.
+    fn crafted_tar(entries: &[(tar::EntryType, &[u8], &[u8])]) -> Vec<u8> {
+        let mut archive = tar::Builder::new(vec![]);
+        for (entry_type, name, link_name) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*entry_type);
+            header.set_mode(0o644);
+            header.set_size(0);
+            let gnu = header.as_gnu_mut().unwrap();
+            gnu.name[..name.len()].copy_from_slice(name);
+            gnu.linkname[..link_name.len()].copy_from_slice(link_name);
+            header.set_cksum();
+            archive.append(&header, std::io::empty()).unwrap();
+        }
+        archive.into_inner().unwrap()
+    }
+
+    #[test]
+    fn tar_malicious() {
+        use tar::EntryType::{Link, Regular, Symlink};
+
+        for (entries, error) in [
+            (
+                vec![(Regular, b"/abs".as_slice(), b"".as_slice())],
+                "Refusing to extract /abs since it is an absolute path.",
+            ),
+            (
+                vec![(Regular, b"a/../../escape", b"")],
+                "Refusing to extract a/../../escape since it contains a `..` component.",
+            ),
+            (
+                vec![(Symlink, b"link", b"/etc")],
+                "Refusing to extract the symlink link -> /etc since it points outside",
+            ),
+            (
+                vec![(Symlink, b"a/link", b"../..")],
+                "Refusing to extract the symlink a/link -> ../.. since it points outside",
+            ),
+            (
+                vec![
+                    (Symlink, b"here", b"."),
+                    (Symlink, b"link", b"here/../escape"),
+                ],
+                "Refusing to extract the symlink link -> here/../escape since its target has a \
+                `..` component after a named one.",
+            ),
+            (
+                vec![
+                    (Symlink, b"a/b/up", b"../.."),
+                    (Symlink, b"a/b/up/link", b"../x"),
+                ],
+                "Refusing to extract the symlink a/b/up/link -> ../x since it points outside",
+            ),
+            (
+                vec![(Link, b"hard", b"../escape")],
+                "Refusing to extract ../escape since it contains a `..` component.",
+            ),
+        ] {
+            let dst = tempdir().unwrap();
+            let root = dst.path().join("root");
+            let err = super::tar(
+                crafted_tar(&entries).as_slice(),
+                &root,
+                &ExtractPolicy::default(),
+            )
+            .unwrap_err();
+            assert!(err.starts_with(error), "{err}");
+            assert_eq!(
+                vec![root.clone()],
+                std::fs::read_dir(dst.path())
+                    .unwrap()
+                    .map(|entry| entry.unwrap().path())
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tar_internal_symlinks() {
+        use tar::EntryType::{Directory, Symlink};
+
+        let dst = tempdir().unwrap();
+        super::tar(
+            crafted_tar(&[
+                (Directory, b"lib", b""),
+                (Symlink, b"bin/lib", b"../lib"),
+                (Symlink, b"bin/self", b"."),
+                (Symlink, b"bin/python", b"self/lib/python3"),
+            ])
+            .as_slice(),
+            dst.path(),
+            &ExtractPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            Path::new("self/lib/python3"),
+            std::fs::read_link(dst.path().join("bin/python")).unwrap()
+        );
+    }
+
+    #[test]
+    fn zip_malicious() {
+        for (name, error) in [
+            (
+                "../escape",
+                "Refusing to extract ../escape since it contains a `..` component.",
+            ),
+            (
+                "a/../../escape",
+                "Refusing to extract a/../../escape since it contains a `..` component.",
+            ),
+            (
+                "/abs",
+                "Refusing to extract /abs since it is an absolute path.",
+            ),
+        ] {
+            let mut buffer = std::io::Cursor::new(vec![]);
+            let mut archive = zip::ZipWriter::new(&mut buffer);
+            archive.start_file(name, FileOptions::default()).unwrap();
+            archive.write_all(b"escaped").unwrap();
+            archive.finish().unwrap();
+            drop(archive);
+            let bytes = buffer.into_inner();
+
+            let dst = tempdir().unwrap();
+            let root = dst.path().join("root");
+            let err = super::zip(
+                Source {
+                    bytes: &bytes,
+                    file: None,
+                },
+                &root,
+                &ExtractPolicy::default(),
+            )
+            .unwrap_err();
+            assert!(err.contains(error), "{err}");
+            assert!(!dst.path().join("escape").exists());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_policy() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let extract = || {
+            let dst = tempdir().unwrap();
+            let bytes = create_zip(CompressionMethod::Deflated);
+            super::zip(
+                Source {
+                    bytes: &bytes,
+                    file: None,
+                },
+                dst.path(),
+                &ExtractPolicy::default(),
+            )
+            .unwrap();
+            std::fs::set_permissions(
+                dst.path().join("empty"),
+                std::fs::Permissions::from_mode(0o777),
+            )
+            .unwrap();
+            dst
+        };
+
+        let dst = extract();
+        ExtractPolicy::default().apply(dst.path()).unwrap();
+        assert_eq!(0o755, mode(&dst.path().join("dir0/file0")));
+        assert_eq!(0o777, mode(&dst.path().join("empty")));
+
+        let dst = extract();
+        ExtractPolicy {
+            umask: 0o022,
+            ..Default::default()
+        }
+        .apply(dst.path())
+        .unwrap();
+        assert_eq!(0o755, mode(&dst.path().join("dir0/file0")));
+        assert_eq!(0o755, mode(&dst.path().join("empty")));
+
+        let dst = extract();
+        ExtractPolicy {
+            private: true,
+            ..Default::default()
+        }
+        .apply(dst.path())
+        .unwrap();
+        assert_eq!(0o700, mode(&dst.path().join("dir0/file0")));
+        assert_eq!(0o700, mode(&dst.path().join("empty")));
+        assert_eq!(0o700, mode(dst.path()));
+    }
+
+    fn pax_tar(records: &[(&str, &[u8])], name: &str, mode: u32, data: &[u8]) -> Vec<u8> {
+        let records = records.iter().fold(vec![], |mut records, (key, value)| {
+            let record = [format!(" {key}=").as_bytes(), value, b"\n".as_slice()].concat();
+            // N.B.: The length prefix counts its own digits.
+            let mut len = record.len();
+            while len != record.len() + len.to_string().len() {
+                len = record.len() + len.to_string().len();
+            }
+            records.extend(len.to_string().as_bytes());
+            records.extend(record);
+            records
+        });
+        let mut archive = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::XHeader);
+        header.set_size(records.len() as u64);
+        header.set_path("PaxHeader").unwrap();
+        header.set_cksum();
+        archive.append(&header, records.as_slice()).unwrap();
+        let mut header = tar::Header::new_ustar();
+        header.set_mode(mode);
+        header.set_size(data.len() as u64);
+        archive.append_data(&mut header, name, data).unwrap();
+        archive.into_inner().unwrap()
+    }
+
+    #[test]
+    fn entry_xattrs() {
+        let archive = pax_tar(
+            &[
+                ("SCHILY.xattr.user.scie", b"1"),
+                ("SCHILY.xattr.com.apple.quarantine", b"0081;"),
+            ],
+            "tool",
+            0o644,
+            b"tool",
+        );
+        let names = |policy: ExtractPolicy| {
+            let mut archive = tar::Archive::new(archive.as_slice());
+            let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+            super::entry_xattrs(&mut entry, &policy)
+                .unwrap()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert!(names(ExtractPolicy::default()).is_empty());
+        assert_eq!(
+            vec!["user.scie".to_string()],
+            names(ExtractPolicy {
+                xattrs: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            vec!["com.apple.quarantine".to_string()],
+            names(ExtractPolicy {
+                quarantine: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tar_metadata_policy() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let archive = pax_tar(&[("SCHILY.xattr.user.scie", b"1")], "tool", 0o4755, b"tool");
+        let extract = |policy: ExtractPolicy| {
+            let dst = tempdir().unwrap();
+            super::tar(archive.as_slice(), dst.path(), &policy).unwrap();
+            let tool = dst.path().join("tool");
+            (
+                std::fs::metadata(&tool).unwrap().permissions().mode() & 0o7777,
+                xattr::get(&tool, "user.scie").unwrap(),
+            )
+        };
+        assert_eq!((0o755, None), extract(ExtractPolicy::default()));
+        assert_eq!(
+            (0o4755, Some(b"1".to_vec())),
+            extract(ExtractPolicy {
+                setuid: true,
+                xattrs: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unicode_normalization() {
+        let dst = tempdir().unwrap();
+        let dir = dst.path().join("cafe\u{301}");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("cr\u{e8}pe"), "sucre").unwrap();
+        std::os::unix::fs::symlink("cafe\u{301}/cr\u{e8}pe", dst.path().join("link")).unwrap();
+
+        ExtractPolicy {
+            unicode_normalization: Some(UnicodeNormalization::Nfd),
+            ..Default::default()
+        }
+        .apply(dst.path())
+        .unwrap();
+        assert_eq!(
+            "sucre",
+            std::fs::read_to_string(dst.path().join("cafe\u{301}/cre\u{300}pe")).unwrap()
+        );
+        assert_eq!(
+            Path::new("cafe\u{301}/cre\u{300}pe"),
+            std::fs::read_link(dst.path().join("link")).unwrap()
+        );
+        assert_eq!(
+            "sucre",
+            std::fs::read_to_string(dst.path().join("link")).unwrap()
+        );
+
+        std::fs::create_dir(dst.path().join("caf\u{e9}")).unwrap();
+        let err = ExtractPolicy {
+            unicode_normalization: Some(UnicodeNormalization::Nfc),
+            ..Default::default()
+        }
+        .apply(dst.path())
+        .unwrap_err();
+        assert!(err.contains("already exists"), "{err}");
+    }
+
+    #[test]
+    fn tar_pax_sparse() {
+        let size = 1024 * 1024 + 3;
+        let size_record = size.to_string();
+        let mut map_data = b"2\n0\n3\n1048576\n3\n".to_vec();
+        map_data.resize(512, 0);
+        map_data.extend(b"abcxyz");
+        for (version, records, data) in [
+            (
+                "0.0",
+                vec![
+                    ("GNU.sparse.size", size_record.as_bytes()),
+                    ("GNU.sparse.offset", b"0".as_slice()),
+                    ("GNU.sparse.numbytes", b"3".as_slice()),
+                    ("GNU.sparse.offset", b"1048576".as_slice()),
+                    ("GNU.sparse.numbytes", b"3".as_slice()),
+                ],
+                b"abcxyz".as_slice(),
+            ),
+            (
+                "0.1",
+                vec![
+                    ("GNU.sparse.size", size_record.as_bytes()),
+                    ("GNU.sparse.name", b"sparse".as_slice()),
+                    ("GNU.sparse.map", b"0,3,1048576,3".as_slice()),
+                ],
+                b"abcxyz".as_slice(),
+            ),
+            (
+                "1.0",
+                vec![
+                    ("GNU.sparse.major", b"1".as_slice()),
+                    ("GNU.sparse.minor", b"0".as_slice()),
+                    ("GNU.sparse.name", b"sparse".as_slice()),
+                    ("GNU.sparse.realsize", size_record.as_bytes()),
+                ],
+                map_data.as_slice(),
+            ),
+        ] {
+            let name = if version == "0.0" {
+                "sparse"
+            } else {
+                "GNUSparseFile.0/sparse"
+            };
+            let archive = pax_tar(&records, name, 0o644, data);
+            let dst = tempdir().unwrap();
+            super::tar(archive.as_slice(), dst.path(), &ExtractPolicy::default()).unwrap();
+            assert!(
+                !dst.path().join("GNUSparseFile.0").exists(),
+                "version {version}"
+            );
+            let sparse = dst.path().join("sparse");
+            let content = std::fs::read(&sparse).unwrap();
+            assert_eq!(size, content.len(), "version {version}");
+            assert_eq!(b"abc", &content[..3], "version {version}");
+            assert_eq!(b"xyz", &content[1024 * 1024..], "version {version}");
+            assert!(
+                content[3..1024 * 1024].iter().all(|byte| *byte == 0),
+                "version {version}"
+            );
+            #[cfg(target_os = "linux")]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let blocks = std::fs::metadata(&sparse).unwrap().blocks();
+                assert!(blocks * 512 < size as u64, "version {version}: {blocks}");
+            }
+        }
+    }
+
+    #[test]
+    fn panic_messages() {
+        let message = |panic: fn()| {
+            let payload = std::thread::spawn(panic).join().unwrap_err();
+            panic_message(payload.as_ref()).to_string()
+        };
+        assert_eq!("static", message(|| panic!("static")));
+        assert_eq!("formatted 42", message(|| panic!("formatted {}", 42)));
+        assert_eq!("<unknown>", message(|| std::panic::panic_any(42_u8)));
+    }
+}
@@ -1,39 +1,53 @@
 // Copyright 2022 Science project contributors.
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::io::Read;
+use std::io::{ErrorKind, Read};
 use std::path::Path;
 
 use logging_timer::time;
 use sha2::{Digest, Sha256};
 
+use crate::error::JumpError;
+
+// N.B.: Reading in large blocks cuts the number of reads needed to hash large files many fold.
+const BUFFER_SIZE: usize = 1024 * 1024;
+
 #[time("debug", "fingerprint::{}")]
 pub fn digest(data: &[u8]) -> String {
     format!("{digest:x}", digest = Sha256::digest(data))
 }
 
-pub fn digest_file(path: &Path) -> Result<(usize, String), String> {
+pub fn digest_file(path: &Path) -> Result<(usize, String), JumpError> {
     let file = std::fs::File::open(path).map_err(|e| {
-        format!(
-            "Failed to open {path} for digesting: {e}",
-            path = path.display()
+        JumpError::io(
+            format!("Failed to open {path} for digesting", path = path.display()),
+            e,
         )
     })?;
     digest_reader(file)
 }
 
 #[time("debug", "fingerprint::{}")]
-pub fn digest_reader<R: Read>(mut reader: R) -> Result<(usize, String), String> {
+pub fn digest_reader<R: Read>(mut reader: R) -> Result<(usize, String), JumpError> {
     let mut hasher = Sha256::new();
-    let copied_size = std::io::copy(&mut reader, &mut hasher)
-        .map_err(|e| format!("Failed to digest stream: {e}"))?;
-    let file_size = usize::try_from(copied_size).map_err(|e| {
-        format!(
-            "Read {copied_size} bytes from stream which was more than can fit in a usize which \
-            is {usize_bits} bits on this platform: {e}",
-            usize_bits = usize::BITS
-        )
-    })?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut file_size = 0_usize;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(JumpError::io("Failed to digest stream", e)),
+        };
+        hasher.update(&buffer[..read]);
+        file_size = file_size.checked_add(read).ok_or_else(|| {
+            JumpError::Other(format!(
+                "Read more bytes from stream than can fit in a usize which is {usize_bits} bits \
+                on this platform.",
+                usize_bits = usize::BITS
+            ))
+        })?;
+    }
     let hash = format!("{digest:x}", digest = hasher.finalize());
     Ok((file_size, hash))
 }
@@ -0,0 +1,420 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::config::{Config, Jump, UnknownFields};
+use crate::error::JumpError;
+use crate::jump::EOF_MAGIC;
+use crate::zip::{lift_manifest, maximum_trailer_size};
+
+/// The size in bytes of the scie-jump trailer: a little-endian u32 scie-jump size followed by the
+/// little-endian u32 `EOF_MAGIC`.
+pub const JUMP_TRAILER_SIZE: usize = 8;
+
+/// The size in bytes of the line identifying a scie that the boot-pack writes at its very end.
+pub const SCIE_MAGIC_SIZE: usize = 64;
+
+const SCIE_MAGIC_PREFIX: &[u8] = b"scie ";
+const SCIE_MAGIC_VERSION_WIDTH: usize = 24;
+
+/// Renders the line identifying a scie: `scie <version> <jump size> <lift manifest offset>\n`
+/// where the scie-jump version is padded with spaces to 24 characters and the sizes are 16 digit
+/// hex numbers. Being fixed size and at the very end of the scie data, it lets tools identify a
+/// scie and the scie-jump that built it by reading just its last few bytes.
+pub(crate) fn scie_magic(jump: &Jump, lift_manifest_offset: usize) -> Result<String, String> {
+    if jump.version.len() > SCIE_MAGIC_VERSION_WIDTH || jump.version.contains(char::is_whitespace) {
+        return Err(format!(
+            "The scie-jump version {version:?} cannot be recorded at the end of the scie; it must \
+            be at most {SCIE_MAGIC_VERSION_WIDTH} characters with no whitespace.",
+            version = jump.version
+        ));
+    }
+    let magic = format!(
+        "scie {version:<SCIE_MAGIC_VERSION_WIDTH$} {size:016x} {lift_manifest_offset:016x}\n",
+        version = jump.version,
+        size = jump.size
+    );
+    debug_assert_eq!(SCIE_MAGIC_SIZE, magic.len());
+    Ok(magic)
+}
+
+fn parse_scie_magic(data: &[u8]) -> Option<(Jump, usize)> {
+    let magic = data.get(data.len().checked_sub(SCIE_MAGIC_SIZE)?..)?;
+    let fields = magic.strip_prefix(SCIE_MAGIC_PREFIX)?.strip_suffix(b"\n")?;
+    let fields = std::str::from_utf8(fields).ok()?;
+    let version = fields
+        .get(..SCIE_MAGIC_VERSION_WIDTH)?
+        .trim_end_matches(' ');
+    let (size, lift_manifest_offset) = fields
+        .get(SCIE_MAGIC_VERSION_WIDTH..)?
+        .strip_prefix(' ')?
+        .split_once(' ')?;
+    if version.is_empty()
+        || version.contains(char::is_whitespace)
+        || [size, lift_manifest_offset]
+            .iter()
+            .any(|hex| hex.len() != 16 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+    {
+        return None;
+    }
+    Some((
+        Jump {
+            size: usize::from_str_radix(size, 16).ok()?,
+            version: version.to_string(),
+            min_version: None,
+        },
+        usize::from_str_radix(lift_manifest_offset, 16).ok()?,
+    ))
+}
+
+/// The tail of a scie: the scie-jump information and the location of the lift manifest.
+///
+/// A scie is laid out as `[scie-jump][file1]...[fileN][lift manifest]` where the last file is a
+/// zip and the lift manifest is a JSON document with a top-level "scie" key. Alternatively, the
+/// lift manifest can be stored as the `__lift__.json` entry of a zip that follows the files. This
+/// type describes just enough of that layout for third party tools to detect and index scies
+/// without having to understand the full lift manifest format.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScieFooter {
+    /// The scie-jump recorded in the lift manifest. When read from the line identifying the scie,
+    /// its "min_version" is not known.
+    pub jump: Jump,
+    /// If the scie-jump tip of the scie ends with the `EOF_MAGIC` trailer.
+    pub jump_magic: bool,
+    /// The offset of the lift manifest, or the zip holding it, from the start of the scie.
+    pub lift_manifest_offset: usize,
+    /// The size of the lift manifest (including any leading and trailing newlines) or of the zip
+    /// holding it, plus the size of the line identifying the scie and of any code signature
+    /// appended to the scie after it.
+    pub lift_manifest_size: usize,
+}
+
+impl ScieFooter {
+    /// Attempts to parse a scie footer from the full contents of a file.
+    ///
+    /// Scies built by the boot-pack end with a fixed size line identifying them which is all that
+    /// needs to be read. For other scies, like those assembled by hand or signed after packing,
+    /// the lift manifest is found and parsed.
+    ///
+    /// If the scie's files and lift manifest are embedded in a section or segment of the scie-jump
+    /// executable, the footer is parsed from the end of that section or segment.
+    ///
+    /// Returns `Ok(None)` if the data is not a scie and an error if the data looks like a scie but
+    /// its footer is malformed.
+    pub fn parse(data: &[u8]) -> Result<Option<Self>, JumpError> {
+        let data = crate::embed::scie_data(data);
+        if let Some((jump, lift_manifest_offset)) = parse_scie_magic(data) {
+            if jump.size > lift_manifest_offset
+                || lift_manifest_offset > data.len() - SCIE_MAGIC_SIZE
+            {
+                return Err(JumpError::Config(format!(
+                    "The scie ends with a scie-jump size of {size} and a lift manifest offset of \
+                    {lift_manifest_offset} which do not fit in its {len} bytes.",
+                    size = jump.size,
+                    len = data.len()
+                )));
+            }
+            let jump_magic = jump_magic(&data[..jump.size]);
+            return Ok(Some(ScieFooter {
+                jump,
+                jump_magic,
+                lift_manifest_offset,
+                lift_manifest_size: data.len() - lift_manifest_offset,
+            }));
+        }
+        let maximum_trailer_size = maximum_trailer_size().map_err(JumpError::Config)?;
+        let lift_manifest = match lift_manifest(data, maximum_trailer_size) {
+            Ok(lift_manifest) => lift_manifest,
+            Err(_) => return Ok(None),
+        };
+        let lift_manifest_offset = lift_manifest.offset;
+        let config = match Config::parse(&lift_manifest.data, UnknownFields::Ignore) {
+            Ok(config) => config,
+            Err(_) => return Ok(None),
+        };
+        let jump = config.scie.jump.ok_or_else(|| {
+            JumpError::Config(
+                "The lift manifest at the tail of the data has no scie-jump information."
+                    .to_string(),
+            )
+        })?;
+        if jump.size > lift_manifest_offset {
+            return Err(JumpError::Config(format!(
+                "The lift manifest records a scie-jump size of {size} but the lift manifest \
+                starts at byte {lift_manifest_offset}.",
+                size = jump.size
+            )));
+        }
+        let jump_magic = jump_magic(&data[..jump.size]);
+        Ok(Some(ScieFooter {
+            jump,
+            jump_magic,
+            lift_manifest_offset,
+            lift_manifest_size: data.len() - lift_manifest_offset,
+        }))
+    }
+
+    /// The range of bytes holding the files packed in the scie.
+    pub fn payload(&self) -> std::ops::Range<usize> {
+        self.jump.size..self.lift_manifest_offset
+    }
+}
+
+/// Determines if the file at the given path is a scie and, if so, which scie-jump built it.
+///
+/// For scies built by the boot-pack, only the last few bytes of the file are read. Other scies
+/// are identified as `ScieFooter::parse` does.
+pub fn is_scie(path: &Path) -> Result<Option<ScieFooter>, JumpError> {
+    let mut file = std::fs::File::open(path).map_err(|e| {
+        JumpError::io(
+            format!("Failed to open {path} for reading", path = path.display()),
+            e,
+        )
+    })?;
+    let len = file
+        .metadata()
+        .map_err(|e| JumpError::io(format!("Failed to stat {path}", path = path.display()), e))?
+        .len() as usize;
+    if len >= SCIE_MAGIC_SIZE {
+        let mut tail = [0; SCIE_MAGIC_SIZE];
+        file.seek(SeekFrom::End(-(SCIE_MAGIC_SIZE as i64)))
+            .and_then(|_| file.read_exact(&mut tail))
+            .map_err(|e| {
+                JumpError::io(
+                    format!("Failed to read the tail of {path}", path = path.display()),
+                    e,
+                )
+            })?;
+        if let Some((jump, lift_manifest_offset)) = parse_scie_magic(&tail) {
+            if jump.size <= lift_manifest_offset && lift_manifest_offset <= len - SCIE_MAGIC_SIZE {
+                let mut jump_trailer = [0; JUMP_TRAILER_SIZE];
+                let jump_magic = jump.size >= JUMP_TRAILER_SIZE
+                    && file
+                        .seek(SeekFrom::Start((jump.size - JUMP_TRAILER_SIZE) as u64))
+                        .and_then(|_| file.read_exact(&mut jump_trailer))
+                        .is_ok()
+                    && jump_trailer_matches(&jump_trailer, jump.size);
+                return Ok(Some(ScieFooter {
+                    jump,
+                    jump_magic,
+                    lift_manifest_offset,
+                    lift_manifest_size: len - lift_manifest_offset,
+                }));
+            }
+        }
+    }
+    let data = crate::map(&file)
+        .map_err(|e| JumpError::io(format!("Failed to read {path}", path = path.display()), e))?;
+    ScieFooter::parse(&data)
+}
+
+fn jump_magic(jump: &[u8]) -> bool {
+    jump.len() >= JUMP_TRAILER_SIZE
+        && jump_trailer_matches(&jump[jump.len() - JUMP_TRAILER_SIZE..], jump.len())
+}
+
+fn jump_trailer_matches(trailer: &[u8], jump_size: usize) -> bool {
+    let mut trailer = Cursor::new(trailer);
+    matches!(
+        (
+            trailer.read_u32::<LittleEndian>(),
+            trailer.read_u32::<LittleEndian>()
+        ),
+        (Ok(size), Ok(EOF_MAGIC)) if size as usize == jump_size
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use zip::ZipWriter;
+
+    use super::{is_scie, parse_scie_magic, scie_magic, ScieFooter, SCIE_MAGIC_SIZE};
+    use crate::config::Jump;
+    use crate::error::JumpError;
+    use crate::jump::EOF_MAGIC;
+
+    fn scie(jump_magic: bool) -> (Vec<u8>, usize, usize) {
+        let mut data = b"#!jump".to_vec();
+        if jump_magic {
+            data.write_u32::<LittleEndian>(14).unwrap();
+            data.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+        }
+        let jump_size = data.len();
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("file", Default::default()).unwrap();
+        zip.write_all(b"contents").unwrap();
+        data.extend(zip.finish().unwrap().into_inner());
+
+        let lift_manifest_offset = data.len();
+        data.extend(
+            format!(
+                "\n{{\"scie\":{{\"lift\":{{\"name\":\"test\",\"files\":[],\"boot\":\
+                {{\"commands\":{{}}}}}},\"jump\":{{\"size\":{jump_size},\"version\":\"1.0.0\"}}}}}}\n"
+            )
+            .as_bytes(),
+        );
+        (data, jump_size, lift_manifest_offset)
+    }
+
+    #[test]
+    fn parse() {
+        let (data, jump_size, lift_manifest_offset) = scie(true);
+        assert_eq!(
+            Some(ScieFooter {
+                jump: Jump {
+                    size: jump_size,
+                    version: "1.0.0".to_string(),
+                    ..Default::default()
+                },
+                jump_magic: true,
+                lift_manifest_offset,
+                lift_manifest_size: data.len() - lift_manifest_offset,
+            }),
+            ScieFooter::parse(&data).unwrap()
+        );
+
+        let (data, _, _) = scie(false);
+        assert!(!ScieFooter::parse(&data).unwrap().unwrap().jump_magic);
+    }
+
+    #[test]
+    fn parse_signed() {
+        let (mut data, _, lift_manifest_offset) = scie(true);
+        let footer = ScieFooter::parse(&data).unwrap().unwrap();
+
+        // N.B.: Code signatures are appended after the lift manifest and may contain anything,
+        // including stray end of central directory record signatures.
+        data.extend([0; 16]);
+        data.extend(b"PK\x05\x06");
+        data.extend([0xff; 32]);
+        let signed = ScieFooter::parse(&data).unwrap().unwrap();
+        assert_eq!(footer.jump, signed.jump);
+        assert_eq!(lift_manifest_offset, signed.lift_manifest_offset);
+        assert_eq!(data.len() - lift_manifest_offset, signed.lift_manifest_size);
+    }
+
+    #[test]
+    fn parse_not_a_scie() {
+        assert_eq!(None, ScieFooter::parse(b"").unwrap());
+        assert_eq!(None, ScieFooter::parse(b"PK\x05\x06").unwrap());
+
+        let (data, _, lift_manifest_offset) = scie(true);
+        assert_eq!(
+            None,
+            ScieFooter::parse(&data[..lift_manifest_offset]).unwrap()
+        );
+
+        // An end of central directory record whose comment runs past the end of the data.
+        let mut eocd = b"PK\x05\x06".to_vec();
+        eocd.extend([0; 16]);
+        eocd.write_u16::<LittleEndian>(u16::MAX).unwrap();
+        eocd.extend(b"{}");
+        assert_eq!(None, ScieFooter::parse(&eocd).unwrap());
+    }
+
+    #[test]
+    fn magic() {
+        let jump = Jump {
+            size: 1557952,
+            version: "0.14.0".to_string(),
+            ..Default::default()
+        };
+        let magic = scie_magic(&jump, 0x12345678).unwrap();
+        assert_eq!(
+            "scie 0.14.0                   000000000017c5c0 0000000012345678\n",
+            magic
+        );
+        assert_eq!(SCIE_MAGIC_SIZE, magic.len());
+        assert_eq!(
+            Some((jump.clone(), 0x12345678)),
+            parse_scie_magic(magic.as_bytes())
+        );
+
+        let mut data = b"{}".to_vec();
+        data.extend(magic.as_bytes());
+        assert_eq!(Some((jump, 0x12345678)), parse_scie_magic(&data));
+        assert_eq!(None, parse_scie_magic(&data[..data.len() - 1]));
+        assert_eq!(None, parse_scie_magic(&magic.as_bytes()[1..]));
+        assert_eq!(
+            None,
+            parse_scie_magic(magic.replace("0017", "0x17").as_bytes())
+        );
+
+        assert!(scie_magic(
+            &Jump {
+                size: 1,
+                version: "1.0.0-a.very.long.pre.release".to_string(),
+                ..Default::default()
+            },
+            1
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_magic() {
+        let (mut data, jump_size, lift_manifest_offset) = scie(true);
+        let expected = ScieFooter::parse(&data).unwrap().unwrap();
+
+        // N.B.: The lift manifest is not parsed when the scie ends with the line identifying it;
+        // so an unparseable one makes no difference.
+        data.truncate(lift_manifest_offset);
+        data.extend(b"\n{\"not\": \"a lift manifest\"}\n");
+        let jump = Jump {
+            size: jump_size,
+            version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+        data.extend(scie_magic(&jump, lift_manifest_offset).unwrap().as_bytes());
+        let footer = ScieFooter::parse(&data).unwrap().unwrap();
+        assert_eq!(expected.jump, footer.jump);
+        assert!(footer.jump_magic);
+        assert_eq!(lift_manifest_offset, footer.lift_manifest_offset);
+        assert_eq!(data.len() - lift_manifest_offset, footer.lift_manifest_size);
+
+        let mut bad = data[..lift_manifest_offset].to_vec();
+        bad.extend(scie_magic(&jump, data.len()).unwrap().as_bytes());
+        assert!(ScieFooter::parse(&bad).is_err());
+    }
+
+    #[test]
+    fn is_scie_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (mut data, jump_size, lift_manifest_offset) = scie(true);
+
+        let legacy = tmp.path().join("legacy");
+        std::fs::write(&legacy, &data).unwrap();
+        let expected = ScieFooter::parse(&data).unwrap();
+        assert!(expected.is_some());
+        assert_eq!(expected, is_scie(&legacy).unwrap());
+
+        let jump = Jump {
+            size: jump_size,
+            version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+        data.extend(scie_magic(&jump, lift_manifest_offset).unwrap().as_bytes());
+        let scie = tmp.path().join("scie");
+        std::fs::write(&scie, &data).unwrap();
+        let footer = is_scie(&scie).unwrap().unwrap();
+        assert_eq!(ScieFooter::parse(&data).unwrap().unwrap(), footer);
+        assert_eq!(jump, footer.jump);
+        assert!(footer.jump_magic);
+
+        let not_a_scie = tmp.path().join("not-a-scie");
+        std::fs::write(&not_a_scie, [b'x'; 128]).unwrap();
+        assert_eq!(None, is_scie(&not_a_scie).unwrap());
+        assert!(matches!(
+            is_scie(&tmp.path().join("missing")),
+            Err(JumpError::Io { .. })
+        ));
+    }
+}
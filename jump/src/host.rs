@@ -0,0 +1,210 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Facts about the machine a scie boots on that the scie-jump cannot know from how it was built.
+//!
+//! The Linux scie-jump is a static musl binary; so it runs the same on glibc and musl distros and
+//! on images with no libc at all. The native executables a scie launches are usually dynamically
+//! linked against one libc or the other though; so scies can use `{scie.libc}` to pick the right
+//! build for the machine at hand.
+
+use std::io::Read;
+use std::sync::OnceLock;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+const PT_INTERP: u64 = 3;
+
+/// Returns the program interpreter (dynamic loader) an ELF executable requests, if any.
+pub(crate) fn elf_interpreter(data: &[u8]) -> Option<&[u8]> {
+    let (is_64, little_endian) = match data.get(..6)? {
+        [0x7f, b'E', b'L', b'F', class, encoding] => (*class == 2, *encoding == 1),
+        _ => return None,
+    };
+    let uint = |offset: u64, size: usize| -> Option<u64> {
+        let start = usize::try_from(offset).ok()?;
+        let bytes = data.get(start..start.checked_add(size)?)?;
+        Some(if little_endian {
+            LittleEndian::read_uint(bytes, size)
+        } else {
+            BigEndian::read_uint(bytes, size)
+        })
+    };
+    let (phoff, phentsize, phnum) = if is_64 {
+        (uint(0x20, 8)?, uint(0x36, 2)?, uint(0x38, 2)?)
+    } else {
+        (uint(0x1c, 4)?, uint(0x2a, 2)?, uint(0x2c, 2)?)
+    };
+    for index in 0..phnum {
+        let header = phoff.checked_add(index.checked_mul(phentsize)?)?;
+        if uint(header, 4)? != PT_INTERP {
+            continue;
+        }
+        let (offset, size) = if is_64 {
+            (uint(header + 0x08, 8)?, uint(header + 0x20, 8)?)
+        } else {
+            (uint(header + 0x04, 4)?, uint(header + 0x10, 4)?)
+        };
+        let start = usize::try_from(offset).ok()?;
+        let end = start.checked_add(usize::try_from(size).ok()?)?;
+        let interpreter = data.get(start..end)?;
+        return Some(interpreter.strip_suffix(b"\0").unwrap_or(interpreter));
+    }
+    None
+}
+
+/// Returns the libc a dynamic loader belongs to: `gnu` or `musl`.
+fn loader_libc(loader: &[u8]) -> Option<&'static str> {
+    let name = loader.rsplit(|byte| *byte == b'/').next()?;
+    if name.starts_with(b"ld-musl-") {
+        Some("musl")
+    } else if name.starts_with(b"ld-linux") || name.starts_with(b"ld64.so") {
+        Some("gnu")
+    } else {
+        None
+    }
+}
+
+/// Returns the libc an ELF executable is dynamically linked against, if any.
+pub(crate) fn elf_libc(data: &[u8]) -> Option<&'static str> {
+    elf_interpreter(data).and_then(loader_libc)
+}
+
+fn detect_libc() -> &'static str {
+    // The libc of the system shell is the best witness to the libc of the system; but a static
+    // busybox or a distroless image can leave us without one; so we fall back to looking for the
+    // dynamic loaders themselves.
+    let mut header = Vec::with_capacity(64 * 1024);
+    if std::fs::File::open("/bin/sh")
+        .and_then(|sh| sh.take(64 * 1024).read_to_end(&mut header))
+        .is_ok()
+    {
+        if let Some(libc) = elf_libc(&header) {
+            return libc;
+        }
+    }
+    let mut found = None;
+    for dir in ["/lib", "/lib64", "/usr/lib", "/usr/lib64"] {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            match loader_libc(entry.file_name().to_string_lossy().as_bytes()) {
+                // N.B.: The musl loader is installed alongside glibc by packages like Debian's
+                // musl-tools; so glibc wins when both are present.
+                Some("gnu") => return "gnu",
+                Some(libc) => found = Some(libc),
+                None => {}
+            }
+        }
+    }
+    found.unwrap_or("none")
+}
+
+/// Returns the libc of this machine: `gnu`, `musl` or `none` on Linux and the empty string
+/// elsewhere.
+pub(crate) fn libc() -> &'static str {
+    static LIBC: OnceLock<&'static str> = OnceLock::new();
+    LIBC.get_or_init(|| {
+        if cfg!(target_os = "linux") {
+            detect_libc()
+        } else {
+            ""
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{elf_interpreter, elf_libc, libc, loader_libc};
+
+    fn elf(is_64: bool, little_endian: bool, interpreter: Option<&[u8]>) -> Vec<u8> {
+        let put = |data: &mut Vec<u8>, offset: usize, value: u64, size: usize| {
+            let bytes = if little_endian {
+                value.to_le_bytes()[..size].to_vec()
+            } else {
+                value.to_be_bytes()[8 - size..].to_vec()
+            };
+            data[offset..offset + size].copy_from_slice(&bytes);
+        };
+        let mut data = vec![0; 512];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = if is_64 { 2 } else { 1 };
+        data[5] = if little_endian { 1 } else { 2 };
+        let (phoff, phentsize) = if is_64 { (64, 56) } else { (52, 32) };
+        let headers = [
+            (1, 0, 0),
+            (3, 400, interpreter.map(<[u8]>::len).unwrap_or(0)),
+        ];
+        let headers = if interpreter.is_some() {
+            &headers[..]
+        } else {
+            &headers[..1]
+        };
+        if is_64 {
+            put(&mut data, 0x20, phoff, 8);
+            put(&mut data, 0x36, phentsize, 2);
+            put(&mut data, 0x38, headers.len() as u64, 2);
+        } else {
+            put(&mut data, 0x1c, phoff, 4);
+            put(&mut data, 0x2a, phentsize, 2);
+            put(&mut data, 0x2c, headers.len() as u64, 2);
+        }
+        for (index, (kind, offset, size)) in headers.iter().enumerate() {
+            let header = (phoff + index as u64 * phentsize) as usize;
+            put(&mut data, header, *kind, 4);
+            if is_64 {
+                put(&mut data, header + 0x08, *offset, 8);
+                put(&mut data, header + 0x20, *size as u64, 8);
+            } else {
+                put(&mut data, header + 0x04, *offset, 4);
+                put(&mut data, header + 0x10, *size as u64, 4);
+            }
+        }
+        if let Some(interpreter) = interpreter {
+            data[400..400 + interpreter.len()].copy_from_slice(interpreter);
+        }
+        data
+    }
+
+    #[test]
+    fn interpreter() {
+        for is_64 in [true, false] {
+            for little_endian in [true, false] {
+                assert_eq!(
+                    Some(b"/lib/ld-musl-x86_64.so.1".as_slice()),
+                    elf_interpreter(&elf(
+                        is_64,
+                        little_endian,
+                        Some(b"/lib/ld-musl-x86_64.so.1\0")
+                    ))
+                );
+                assert_eq!(None, elf_interpreter(&elf(is_64, little_endian, None)));
+            }
+        }
+        let truncated = elf(true, true, Some(b"/lib64/ld-linux-x86-64.so.2\0"));
+        assert_eq!(None, elf_interpreter(&truncated[..300]));
+        assert_eq!(None, elf_interpreter(b"#!/bin/sh\n"));
+        assert_eq!(None, elf_interpreter(b"\x7fELF"));
+    }
+
+    #[test]
+    fn libcs() {
+        assert_eq!(Some("musl"), loader_libc(b"/lib/ld-musl-aarch64.so.1"));
+        assert_eq!(Some("gnu"), loader_libc(b"/lib64/ld-linux-x86-64.so.2"));
+        assert_eq!(Some("gnu"), loader_libc(b"/lib/ld-linux-aarch64.so.1"));
+        assert_eq!(Some("gnu"), loader_libc(b"/lib64/ld64.so.2"));
+        assert_eq!(None, loader_libc(b"/system/bin/linker64"));
+        assert_eq!(
+            Some("gnu"),
+            elf_libc(&elf(true, true, Some(b"/lib64/ld-linux-x86-64.so.2\0")))
+        );
+        assert_eq!(None, elf_libc(&elf(true, true, None)));
+
+        if cfg!(target_os = "linux") {
+            assert!(["gnu", "musl", "none"].contains(&libc()), "{}", libc());
+        } else {
+            assert_eq!("", libc());
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::lift::File;
+
+/// Returns `true` if blobs marked `execute_in_place` can be run from memory on this platform.
+///
+/// On other platforms these blobs are extracted to the `nce` cache like any other file.
+pub(crate) const SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// Loads the given executable bytes into an anonymous in-memory file and returns its file
+/// descriptor.
+///
+/// The file descriptor is close-on-exec; so it does not leak into the process it is used to
+/// `fexecve`.
+#[cfg(target_os = "linux")]
+pub(crate) fn load(file: &File, bytes: &[u8]) -> Result<i32, String> {
+    use std::ffi::CString;
+    use std::io::Write;
+    use std::os::fd::IntoRawFd;
+
+    use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+
+    let name = CString::new(file.name.as_str()).map_err(|e| {
+        format!(
+            "Invalid name for an in-memory file {name}: {e}",
+            name = file.name
+        )
+    })?;
+    let fd = memfd_create(&name, MemFdCreateFlag::MFD_CLOEXEC).map_err(|e| {
+        format!(
+            "Failed to create an in-memory file to execute {name} from: {e}",
+            name = file.name
+        )
+    })?;
+    let mut memfd = std::fs::File::from(fd);
+    memfd.write_all(bytes).map_err(|e| {
+        format!(
+            "Failed to load {name} into an in-memory file: {e}",
+            name = file.name
+        )
+    })?;
+    Ok(memfd.into_raw_fd())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn load(file: &File, _bytes: &[u8]) -> Result<i32, String> {
+    Err(format!(
+        "Cannot execute {name} in place: this is only supported on Linux.",
+        name = file.name
+    ))
+}
@@ -1,35 +1,123 @@
 // Copyright 2022 Science project contributors.
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::fs::{OpenOptions, Permissions};
-use std::io::{Cursor, Read, Seek};
-use std::path::Path;
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use logging_timer::time;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
 use tempfile::TempDir;
 
-use crate::atomic::{atomic_path, Target};
-use crate::config::{ArchiveType, Compression, FileType};
+use crate::atomic::{atomic_path, invalidate, is_complete, status, update_marker, Status, Target};
+use crate::config::{
+    ArchiveType, Compression, FileType, Integrity, OnMutation, Permissions, UnicodeNormalization,
+};
 use crate::context::FileEntry;
-use crate::fingerprint;
+use crate::error::JumpError;
+use crate::extract::{ExtractPolicy, Source};
+use crate::lift::File;
+use crate::reverify::Check;
+use crate::{chunk, extract, fingerprint, jump, reverify};
 
-fn check_hash<R: Read + Seek>(
+// N.B.: A scie-jump built without support for a given decompressor can be pointed at a source of
+// fully featured scie-jumps by setting this env var at build time. The URL may contain `{version}`
+// and `{platform}` placeholders which are filled in with the running scie-jump's version and the
+// current platform; e.g.:
+// https://github.com/a-scie/jump/releases/download/v{version}/scie-jump-{platform}
+const UPDATE_URL: Option<&str> = option_env!("SCIE_JUMP_UPDATE_URL");
+
+fn compression_feature(compression: Compression) -> Option<&'static str> {
+    match compression {
+        Compression::Bzip2 if cfg!(not(feature = "bzip2")) => Some("bzip2"),
+        Compression::Xz if cfg!(not(feature = "xz")) => Some("xz"),
+        Compression::Zstd if cfg!(not(feature = "zstd")) => Some("zstd"),
+        _ => None,
+    }
+}
+
+fn check_decompressor(archive: ArchiveType, dst: &Path) -> Result<(), String> {
+    let feature = match archive {
+        ArchiveType::CompressedTar(compression) => compression_feature(compression),
+        _ => None,
+    };
+    if let Some(codec) = feature {
+        let platform = format!(
+            "{os}-{arch}",
+            os = std::env::consts::OS,
+            arch = std::env::consts::ARCH
+        );
+        let remedy = if let Some(url) = UPDATE_URL {
+            let url = url
+                .replace("{version}", jump::VERSION)
+                .replace("{platform}", platform.as_str());
+            format!(
+                "You can fetch a scie-jump with {codec} support with:\n\
+                \n\
+                    curl -fL -o scie-jump {url}\n\
+                \n\
+                And then re-pack this scie using it via `--scie-jump scie-jump`."
+            )
+        } else {
+            format!(
+                "You'll need to re-pack this scie using a scie-jump that includes {codec} support."
+            )
+        };
+        return Err(format!(
+            "Cannot unpack {dst}: the {archive_ext} archive requires {codec} decompression but \
+            this scie-jump {version} for {platform} was built without {codec} support.\n\
+            {remedy}",
+            dst = dst.display(),
+            archive_ext = archive.as_ext(),
+            version = jump::VERSION,
+        ));
+    }
+    Ok(())
+}
+
+enum Bytes<'a> {
+    Borrowed(Source<'a>),
+    /// Bytes whose hash was already checked by [`Installer::verify`].
+    Verified(Source<'a>),
+    Mapped((Mmap, std::fs::File)),
+}
+
+impl<'a> Bytes<'a> {
+    fn map(file: std::fs::File) -> std::io::Result<Self> {
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self::Mapped((mmap, file)))
+    }
+
+    fn source(&self) -> Source<'_> {
+        match self {
+            Self::Borrowed(source) | Self::Verified(source) => *source,
+            Self::Mapped((mmap, file)) => Source {
+                bytes: mmap,
+                file: Some((file, 0)),
+            },
+        }
+    }
+}
+
+fn check_hash<'a>(
     file_type: &str,
-    mut bytes: R,
+    bytes: &'a [u8],
     expected_hash: &str,
     dst: &Path,
-) -> Result<R, String> {
-    let (size, actual_hash) = fingerprint::digest_reader(&mut bytes)?;
+) -> Result<&'a [u8], JumpError> {
+    let size = bytes.len();
+    let actual_hash = fingerprint::digest(bytes);
     if expected_hash != actual_hash.as_str() {
-        Err(format!(
+        Err(JumpError::Integrity(format!(
             "The {file_type} destination {dst} of size {size} had unexpected hash: {actual_hash}",
             dst = dst.display(),
-        ))
+        )))
     } else {
-        // TODO(John Sirois): Hash in-line with extraction.
-        bytes
-            .rewind()
-            .map_err(|e| format!("Failed to re-wind {file_type} after hashing: {e}"))?;
         debug!(
             "The {file_type} destination {dst} of size {size} had expected hash",
             dst = dst.display()
@@ -39,143 +127,684 @@ fn check_hash<R: Read + Seek>(
 }
 
 #[time("debug", "installer::{}")]
-fn unpack_tar<R: Read>(archive_type: ArchiveType, tar_stream: R, dst: &Path) -> Result<(), String> {
-    let mut tar = tar::Archive::new(tar_stream);
-    tar.unpack(dst)
+fn unpack_tar<R: Read>(
+    archive_type: ArchiveType,
+    extract_policy: &ExtractPolicy,
+    tar_stream: R,
+    dst: &Path,
+) -> Result<(), String> {
+    extract::tar(tar_stream, dst, extract_policy)
         .map_err(|e| format!("Failed to unpack {archive_type:?}: {e}"))
 }
 
 #[time("debug", "installer::{}")]
-fn unpack_archive<R: Read + Seek, T, F>(
+fn unpack_archive<'a, T, F>(
     archive: ArchiveType,
+    extract_policy: ExtractPolicy,
     bytes_source: F,
     expected_hash: &str,
     dst: &Path,
-) -> Result<Option<T>, String>
+) -> Result<Option<T>, JumpError>
 where
-    F: FnOnce() -> Result<(R, T), String>,
+    F: FnOnce() -> Result<(Bytes<'a>, T), String>,
 {
-    atomic_path(dst, Target::Directory, |work_dir| {
-        let (bytes, result) = bytes_source()?;
-        let hashed_bytes = check_hash(archive.as_ext(), bytes, expected_hash, dst)?;
+    check_decompressor(archive, dst).map_err(JumpError::Other)?;
+    atomic_path(dst, Target::Directory, Some(expected_hash), |work_dir| {
+        let (bytes, result) = bytes_source().map_err(JumpError::Other)?;
+        let source = bytes.source();
+        let hashed_bytes = Cursor::new(if let Bytes::Verified(_) = bytes {
+            source.bytes
+        } else {
+            check_hash(archive.as_ext(), source.bytes, expected_hash, dst)?
+        });
         match archive {
-            ArchiveType::Zip => {
-                let mut zip = zip::ZipArchive::new(hashed_bytes)
-                    .map_err(|e| format!("Failed to open {archive:?}: {e}"))?;
-                zip.extract(work_dir)
-                    .map_err(|e| format!("Failed to extract {archive:?}: {e}"))
-            }
-            ArchiveType::Tar => unpack_tar(archive, hashed_bytes, work_dir),
+            ArchiveType::Zip => extract::zip(source, work_dir, &extract_policy)
+                .map_err(|e| format!("Failed to extract {archive:?}: {e}")),
+            ArchiveType::Tar => unpack_tar(archive, &extract_policy, hashed_bytes, work_dir),
+            #[cfg(feature = "bzip2")]
             ArchiveType::CompressedTar(Compression::Bzip2) => {
                 let bzip2_decoder = bzip2::read::BzDecoder::new(hashed_bytes);
-                unpack_tar(archive, bzip2_decoder, work_dir)
+                unpack_tar(archive, &extract_policy, bzip2_decoder, work_dir)
             }
             ArchiveType::CompressedTar(Compression::Gzip) => {
                 let gz_decoder = flate2::read::GzDecoder::new(hashed_bytes);
-                unpack_tar(archive, gz_decoder, work_dir)
+                unpack_tar(archive, &extract_policy, gz_decoder, work_dir)
             }
+            #[cfg(feature = "xz")]
             ArchiveType::CompressedTar(Compression::Xz) => {
                 let xz_decoder = xz2::read::XzDecoder::new(hashed_bytes);
-                unpack_tar(archive, xz_decoder, work_dir)
+                unpack_tar(archive, &extract_policy, xz_decoder, work_dir)
             }
             ArchiveType::CompressedTar(Compression::Zlib) => {
                 let zlib_decoder = flate2::read::ZlibDecoder::new(hashed_bytes);
-                unpack_tar(archive, zlib_decoder, work_dir)
+                unpack_tar(archive, &extract_policy, zlib_decoder, work_dir)
             }
+            #[cfg(feature = "zstd")]
             ArchiveType::CompressedTar(Compression::Zstd) => {
                 let zstd_decoder = zstd::stream::Decoder::new(hashed_bytes).map_err(|e| {
-                    format!(
-                        "Failed to create a zstd decoder for unpacking to {dst}: {e}",
-                        dst = dst.display()
+                    JumpError::io(
+                        format!(
+                            "Failed to create a zstd decoder for unpacking to {dst}",
+                            dst = dst.display()
+                        ),
+                        e,
                     )
                 })?;
-                unpack_tar(archive, zstd_decoder, work_dir)
+                unpack_tar(archive, &extract_policy, zstd_decoder, work_dir)
             }
-        }?;
-        Ok::<T, String>(result)
+            #[allow(unreachable_patterns)]
+            _ => Err(format!(
+                "No decompressor available for {archive:?}; this should have been caught by \
+                check_decompressor."
+            )),
+        }
+        .map_err(JumpError::Other)?;
+        extract_policy.apply(work_dir).map_err(JumpError::Other)?;
+        protect_parent(extract_policy, dst).map_err(JumpError::Other)?;
+        Ok::<T, JumpError>(result)
     })
 }
 
+// N.B.: This is only done when a file is actually extracted; so a read-only shared cache that
+// already holds the file is never modified.
+fn protect_parent(extract_policy: ExtractPolicy, dst: &Path) -> Result<(), String> {
+    match dst.parent() {
+        Some(parent) => extract_policy.protect_dir(parent),
+        None => Ok(()),
+    }
+}
+
 #[cfg(not(target_family = "unix"))]
-fn executable_permissions() -> Option<Permissions> {
+fn executable_permissions() -> Option<std::fs::Permissions> {
     None
 }
 
 #[cfg(target_family = "unix")]
-fn executable_permissions() -> Option<Permissions> {
+fn executable_permissions() -> Option<std::fs::Permissions> {
     use std::os::unix::fs::PermissionsExt;
-    Some(Permissions::from_mode(0o755))
+    Some(std::fs::Permissions::from_mode(0o755))
 }
 
 #[time("debug", "installer::{}")]
-fn unpack_blob<R: Read + Seek, T, F>(
+fn unpack_blob<'a, T, F>(
     executable: bool,
+    extract_policy: ExtractPolicy,
     bytes_source: F,
     expected_hash: &str,
     dst: &Path,
-) -> Result<Option<T>, String>
+) -> Result<Option<T>, JumpError>
 where
-    F: FnOnce() -> Result<(R, T), String>,
+    F: FnOnce() -> Result<(Bytes<'a>, T), String>,
 {
-    atomic_path(dst, Target::File, |blob_dst| {
-        let (bytes, result) = bytes_source()?;
-        let mut hashed_bytes = check_hash("blob", bytes, expected_hash, dst)?;
-        let mut blob_out = OpenOptions::new()
+    atomic_path(dst, Target::File, Some(expected_hash), |blob_dst| {
+        let (bytes, result) = bytes_source().map_err(JumpError::Other)?;
+        let source = bytes.source();
+        if !matches!(bytes, Bytes::Verified(_)) {
+            check_hash("blob", source.bytes, expected_hash, dst)?;
+        }
+        let blob_out = OpenOptions::new()
             .write(true)
             .create_new(true)
             .open(blob_dst)
             .map_err(|e| {
-                format!(
-                    "Failed to open blob destination {blob_dst} for writing: {e}",
-                    blob_dst = blob_dst.display()
+                JumpError::io(
+                    format!(
+                        "Failed to open blob destination {blob_dst} for writing",
+                        blob_dst = blob_dst.display()
+                    ),
+                    e,
                 )
             })?;
         if executable {
             if let Some(permissions) = executable_permissions() {
                 blob_out.set_permissions(permissions).map_err(|e| {
-                    format!(
-                        "Failed to set executable premissions on {dst}: {e}",
-                        dst = dst.display()
+                    JumpError::io(
+                        format!(
+                            "Failed to set executable premissions on {dst}",
+                            dst = dst.display()
+                        ),
+                        e,
                     )
                 })?;
             }
         }
-        std::io::copy(&mut hashed_bytes, &mut blob_out)
-            .map(|_| ())
-            .map_err(|e| format!("Failed to unpack blob to {dst}: {e}", dst = dst.display()))?;
-        Ok::<T, String>(result)
+        extract::write(source, &blob_out).map_err(|e| {
+            JumpError::io(
+                format!("Failed to unpack blob to {dst}", dst = dst.display()),
+                e,
+            )
+        })?;
+        extract_policy.apply(blob_dst).map_err(JumpError::Other)?;
+        protect_parent(extract_policy, dst).map_err(JumpError::Other)?;
+        Ok::<T, JumpError>(result)
     })
 }
 
-fn unpack<R: Read + Seek, T, F>(
+fn unpack<'a, T, F>(
     file_type: FileType,
     executable: bool,
+    extract_policy: ExtractPolicy,
     bytes: F,
     expected_hash: &str,
     dst: &Path,
-) -> Result<Option<T>, String>
+) -> Result<Option<T>, JumpError>
 where
-    F: FnOnce() -> Result<(R, T), String>,
+    F: FnOnce() -> Result<(Bytes<'a>, T), String>,
 {
     match file_type {
-        FileType::Archive(archive_type) => unpack_archive(archive_type, bytes, expected_hash, dst),
-        FileType::Blob => unpack_blob(executable, bytes, expected_hash, dst),
-        FileType::Directory => unpack_archive(ArchiveType::Zip, bytes, expected_hash, dst),
+        FileType::Archive(archive_type) => {
+            unpack_archive(archive_type, extract_policy, bytes, expected_hash, dst)
+        }
+        FileType::Blob => unpack_blob(executable, extract_policy, bytes, expected_hash, dst),
+        FileType::Directory => {
+            unpack_archive(ArchiveType::Zip, extract_policy, bytes, expected_hash, dst)
+        }
+    }
+}
+
+// Fails fast, before any files are installed, if any files still need to be fetched over the
+// network but SCIE_OFFLINE=1 forbids it.
+fn check_offline(files: &[FileEntry]) -> Result<(), JumpError> {
+    let mut missing = vec![];
+    for file_entry in files {
+        if let FileEntry::FetchAndInstall((download, file, dst)) = file_entry {
+            let target_type = match file.file_type {
+                FileType::Blob => Target::File,
+                _ => Target::Directory,
+            };
+            if download.is_forbidden() && !is_complete(dst, target_type, Some(&file.hash))? {
+                missing.push(format!(
+                    "+ {name} of size {size} with sha256 hash {hash} from {urls}",
+                    name = file.name,
+                    size = file.size,
+                    hash = file.hash,
+                    urls = download.urls.join(" or ")
+                ));
+            }
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(JumpError::Other(format!(
+        "SCIE_OFFLINE=1 is set but the following files must be fetched over the network:\n\
+        {missing}\n\
+        Run the scie once with network access to install them or point SCIE_MIRROR at \
+        file:// copies.",
+        missing = missing.join("\n")
+    )))
+}
+
+/// Returns the space available to us on the file system that holds the given path or, if the path
+/// does not exist yet, its nearest existing ancestor.
+pub(crate) fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    fs2::available_space(existing).ok()
+}
+
+/// Returns `true` if the file system that holds the given path or, if the path does not exist yet,
+/// its nearest existing ancestor is mounted `noexec`; in which case nothing extracted there can be
+/// executed.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_noexec(path: &Path) -> bool {
+    use nix::sys::statvfs::{statvfs, FsFlags};
+
+    path.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .and_then(|existing| statvfs(existing).ok())
+        .map(|stats| stats.flags().contains(FsFlags::ST_NOEXEC))
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn is_noexec(_path: &Path) -> bool {
+    false
+}
+
+// Compares the devices of the given paths or, for those that do not exist yet, of their nearest
+// existing ancestors.
+#[cfg(unix)]
+fn same_file_system(path: &Path, other: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| {
+        path.ancestors()
+            .find_map(|ancestor| std::fs::metadata(ancestor).ok())
+            .map(|metadata| metadata.dev())
+    };
+    match (device(path), device(other)) {
+        (Some(device), Some(other_device)) => device == other_device,
+        _ => true,
     }
 }
 
+#[cfg(not(unix))]
+fn same_file_system(_path: &Path, _other: &Path) -> bool {
+    true
+}
+
+/// Returns the disk space a file takes up once extracted.
+///
+/// For archives packed by older boot-packs that did not record their extracted size, this is just
+/// the archive size; so it is a lower bound.
+pub(crate) fn extracted_size(file: &File) -> u64 {
+    file.extracted_size.unwrap_or(file.size) as u64
+}
+
+// Returns the files to install along with their destinations in the `nce` cache.
+fn destinations(files: &[FileEntry]) -> Vec<(&File, &PathBuf)> {
+    let mut entries = vec![];
+    for file_entry in files {
+        match file_entry {
+            FileEntry::Skip(_) => {}
+            FileEntry::Install((file, dst)) => entries.push((file, dst)),
+            FileEntry::LoadAndInstall((_, file, dst)) => entries.push((file, dst)),
+            FileEntry::FetchAndInstall((_, file, dst)) => entries.push((file, dst)),
+            FileEntry::ScieTote((_, tote_entries)) => {
+                entries.extend(tote_entries.iter().map(|(file, dst)| (file, dst)))
+            }
+        }
+    }
+    entries
+}
+
+fn target_type(file: &File) -> Target {
+    match file.file_type {
+        FileType::Blob => Target::File,
+        _ => Target::Directory,
+    }
+}
+
+// Failing up front with a clear message beats running out of space part way through extraction.
+fn check_disk_space(files: &[FileEntry]) -> Result<(), JumpError> {
+    let mut needed = 0;
+    let mut base = None;
+    for (file, dst) in destinations(files) {
+        if !is_complete(dst, target_type(file), Some(&file.hash))? {
+            needed += extracted_size(file);
+            base = base.or_else(|| dst.parent().and_then(Path::parent));
+        }
+    }
+    let Some(base) = base else {
+        return Ok(());
+    };
+    match available_space(base) {
+        Some(available) if available < needed => Err(JumpError::Other(format!(
+            "Extracting this scie's files to {base} needs {need} of disk space but only {have} is \
+            available. Free up some space or point SCIE_BASE at a file system with more room.",
+            base = base.display(),
+            need = human_size(needed),
+            have = human_size(available)
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Renders a byte count for humans; e.g.: `1.5 GiB`.
+pub(crate) fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{size:.1} {unit}")
+}
+
+/// A file the installer extracted to the `nce` cache, as opposed to finding it there already.
+#[derive(Debug, Serialize)]
+pub(crate) struct Extraction {
+    pub(crate) name: String,
+    pub(crate) hash: String,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub(crate) duration: Duration,
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
+
 #[derive(Debug)]
 pub(crate) struct Installer<'a> {
     payload: &'a [u8],
+    payload_offset: usize,
+    scie: Option<&'a std::fs::File>,
+    extract_policy: ExtractPolicy,
+    tmp: Option<PathBuf>,
+    reverify: Option<Duration>,
+    on_mutation: OnMutation,
+    extractions: Mutex<Vec<Extraction>>,
 }
 
 impl<'a> Installer<'a> {
-    pub(crate) fn new(payload: &'a [u8]) -> Self {
-        Self { payload }
+    pub(crate) fn new(payload: &'a [u8], payload_offset: usize) -> Self {
+        Self {
+            payload,
+            payload_offset,
+            scie: None,
+            extract_policy: ExtractPolicy::default(),
+            tmp: None,
+            reverify: None,
+            on_mutation: OnMutation::default(),
+            extractions: Mutex::default(),
+        }
+    }
+
+    /// Returns the files extracted so far, in the order they were extracted.
+    pub(crate) fn extractions(&self) -> Vec<Extraction> {
+        std::mem::take(&mut *self.extractions.lock().unwrap())
+    }
+
+    // Notes the extraction of `file` to `dst` if `unpacked` shows it was extracted instead of found
+    // in the `nce` cache. When extracted files are to be re-verified, the hashes of the files in
+    // extracted directories are listed so they can be.
+    fn record<T>(
+        &self,
+        file: &File,
+        dst: &Path,
+        started: Instant,
+        unpacked: Option<T>,
+    ) -> Option<T> {
+        if unpacked.is_some() {
+            self.extractions.lock().unwrap().push(Extraction {
+                name: file.name.clone(),
+                hash: file.hash.clone(),
+                duration: started.elapsed(),
+            });
+            if self.reverify.is_some() && Target::Directory == target_type(file) {
+                if let Err(message) = reverify::record(dst) {
+                    warn!("{message}");
+                }
+            }
+        }
+        unpacked
+    }
+
+    /// Applies the lift manifest's integrity policy, if any, to the files already extracted to the
+    /// `nce` cache.
+    pub(crate) fn with_integrity(mut self, integrity: Option<&Integrity>) -> Result<Self, String> {
+        self.reverify = reverify::interval(integrity)?;
+        self.on_mutation = integrity
+            .and_then(|integrity| integrity.on_mutation)
+            .unwrap_or_default();
+        Ok(self)
+    }
+
+    // Checks the files already extracted to the `nce` cache for mutations, re-hashing them in full
+    // if they are due re-verification. Mutated files either fail the install or are invalidated;
+    // so they are re-extracted by the install that follows.
+    #[time("debug", "Installer::{}")]
+    fn check_integrity(&self, files: &[FileEntry]) -> Result<(), JumpError> {
+        let mut mutations = vec![];
+        for (file, dst) in destinations(files) {
+            let target_type = target_type(file);
+            let check = match status(dst, target_type, Some(&file.hash))? {
+                Status::Incomplete => continue,
+                Status::Mutated => Check::Mutated(vec![]),
+                Status::Complete => match self.reverify {
+                    Some(interval) if reverify::is_due(dst, interval) => {
+                        reverify::check(dst, target_type, &file.hash)
+                    }
+                    _ => continue,
+                },
+            };
+            match check {
+                Check::Intact => {
+                    debug!("Re-verified {dst}.", dst = dst.display());
+                    update_marker(dst, target_type, &file.hash);
+                    continue;
+                }
+                Check::Mutated(paths) => {
+                    let paths = paths
+                        .iter()
+                        .map(|path| format!("\n  - {path}"))
+                        .collect::<String>();
+                    if OnMutation::Fail == self.on_mutation {
+                        mutations.push(format!(
+                            "+ {file} at {dst}{paths}",
+                            file = file.name,
+                            dst = dst.display()
+                        ));
+                        continue;
+                    }
+                    info!(
+                        "The {file} extracted to {dst} was mutated; re-extracting it.{paths}",
+                        file = file.name,
+                        dst = dst.display()
+                    )
+                }
+                Check::Unlisted => debug!(
+                    "The {file} extracted to {dst} has no listing of its files to re-verify; \
+                    re-extracting it.",
+                    file = file.name,
+                    dst = dst.display()
+                ),
+            }
+            if let Err(message) = invalidate(dst) {
+                warn!(
+                    "Failed to re-verify the {file} extracted to {dst}: {message}",
+                    file = file.name,
+                    dst = dst.display()
+                );
+            }
+        }
+        if mutations.is_empty() {
+            return Ok(());
+        }
+        Err(JumpError::Integrity(format!(
+            "The following files were mutated since they were extracted to the nce cache:\n\
+            {mutations}\n\
+            The lift's integrity policy is to fail instead of re-extracting them. Remove them from \
+            the cache to have them re-extracted.",
+            mutations = mutations.join("\n")
+        )))
+    }
+
+    /// Stages files being fetched or extracted in `tmp` instead of the system temporary directory.
+    pub(crate) fn with_tmp(mut self, tmp: Option<PathBuf>) -> Self {
+        self.tmp = tmp;
+        self
+    }
+
+    // Returns the directory to stage files bound for `dst` in. If the configured temporary
+    // directory is on a different file system than `dst`, the directory `dst` is to be created in
+    // is used instead; so staging never spills onto another file system, like a small `/tmp`
+    // tmpfs, and the disk space checked for the `nce` cache covers it.
+    fn staging_dir(&self, dst: &Path) -> Result<PathBuf, String> {
+        let tmp = self.tmp.clone().unwrap_or_else(std::env::temp_dir);
+        let staging_dir = match dst.parent() {
+            Some(parent) if !same_file_system(&tmp, parent) => parent,
+            _ => {
+                if self.tmp.is_some() {
+                    std::fs::create_dir_all(&tmp).map_err(|e| {
+                        format!(
+                            "Failed to create the temporary directory {tmp}: {e}",
+                            tmp = tmp.display()
+                        )
+                    })?;
+                }
+                return Ok(tmp);
+            }
+        };
+        let message = format!(
+            "The temporary directory {tmp} is on a different file system than {staging_dir}; \
+            staging files there instead.",
+            tmp = tmp.display(),
+            staging_dir = staging_dir.display()
+        );
+        if self.tmp.is_some() {
+            warn!("{message}");
+        } else {
+            debug!("{message}");
+        }
+        std::fs::create_dir_all(staging_dir).map_err(|e| {
+            format!(
+                "Failed to create {staging_dir} to stage files in: {e}",
+                staging_dir = staging_dir.display()
+            )
+        })?;
+        Ok(staging_dir.to_path_buf())
+    }
+
+    /// Records the scie file the payload was mapped from; so file contents can be copied from it
+    /// directly where the platform supports doing so.
+    pub(crate) fn with_scie(mut self, scie: &'a std::fs::File) -> Self {
+        self.scie = Some(scie);
+        self
+    }
+
+    /// Applies the lift manifest's permissions policy, if any, to all the files installed.
+    pub(crate) fn with_permissions(
+        mut self,
+        permissions: Option<&Permissions>,
+    ) -> Result<Self, String> {
+        if let Some(permissions) = permissions {
+            self.extract_policy = ExtractPolicy {
+                umask: permissions.umask()?,
+                private: permissions.private,
+                setuid: permissions.setuid,
+                xattrs: permissions.xattrs,
+                quarantine: permissions.quarantine,
+                strip_quarantine: permissions.strip_quarantine,
+                codesign: permissions.codesign,
+                selinux: permissions.selinux,
+                ..self.extract_policy
+            };
+        }
+        Ok(self)
+    }
+
+    /// Renames installed files to use the lift manifest's Unicode normalization form, if any.
+    pub(crate) fn with_unicode_normalization(
+        mut self,
+        unicode_normalization: Option<UnicodeNormalization>,
+    ) -> Self {
+        self.extract_policy.unicode_normalization = unicode_normalization;
+        self
+    }
+
+    // Files packed by older boot-packs have no recorded offset; so we fall back to the location
+    // calculated from the cumulative sizes of the files that precede them.
+    fn source(&self, file: &File, location: usize) -> Result<Source<'a>, JumpError> {
+        let start = match file.offset {
+            Some(offset) => offset.checked_sub(self.payload_offset).ok_or_else(|| {
+                JumpError::Config(format!(
+                    "The file {name} has offset {offset} which lies inside the scie-jump of \
+                    size {payload_offset}.",
+                    name = file.name,
+                    payload_offset = self.payload_offset
+                ))
+            })?,
+            None => location,
+        };
+        let bytes = start
+            .checked_add(file.size)
+            .and_then(|end| self.payload.get(start..end))
+            .ok_or_else(|| {
+                JumpError::Config(format!(
+                    "The file {name} of size {size} at offset {offset} lies outside the scie \
+                    payload.",
+                    name = file.name,
+                    size = file.size,
+                    offset = start + self.payload_offset
+                ))
+            })?;
+        Ok(Source {
+            bytes,
+            file: self
+                .scie
+                .map(|scie| (scie, (start + self.payload_offset) as u64)),
+        })
+    }
+
+    fn chunks(&self, file: &File) -> Result<Vec<&'a [u8]>, JumpError> {
+        chunk::slices(self.payload, self.payload_offset, &file.name, &file.chunks)
+            .map_err(JumpError::Config)
+    }
+
+    /// Returns the verified bytes of a file to be executed in place from memory.
+    pub(crate) fn in_place_bytes(&self, file: &File) -> Result<&'a [u8], JumpError> {
+        if !file.chunks.is_empty() {
+            return Err(JumpError::Config(format!(
+                "Cannot execute {name} in place since it is stored in chunks.",
+                name = file.name
+            )));
+        }
+        if file.offset.is_none() {
+            return Err(JumpError::Config(format!(
+                "Cannot execute {name} in place since its offset in the scie is not recorded. \
+                Re-pack the scie with a newer scie-jump.",
+                name = file.name
+            )));
+        }
+        let bytes = self.source(file, 0)?.bytes;
+        let actual_hash = fingerprint::digest(bytes);
+        if file.hash != actual_hash {
+            return Err(JumpError::Integrity(format!(
+                "The in-place executable {name} of size {size} had unexpected hash: {actual_hash}",
+                name = file.name,
+                size = file.size
+            )));
+        }
+        Ok(bytes)
+    }
+
+    /// Returns up to `len` leading bytes of a file stored directly in the scie whose offset is
+    /// recorded.
+    pub(crate) fn leading_bytes(&self, file: &File, len: usize) -> Option<&'a [u8]> {
+        if let Some(first) = file.chunks.first() {
+            let bytes = *self.chunks(file).ok()?.first()?;
+            return Some(&bytes[..len.min(first.size)]);
+        }
+        file.offset?;
+        let bytes = self.source(file, 0).ok()?.bytes;
+        Some(&bytes[..len.min(bytes.len())])
+    }
+
+    // Hashes the files stored whole in the scie that are yet to be extracted concurrently; so
+    // scies with many files verify quickly and a corrupt file fails the boot before any files are
+    // extracted. Returns the names of the files verified.
+    #[time("debug", "Installer::{}")]
+    pub(crate) fn verify<'f>(&self, files: &'f [FileEntry]) -> Result<HashSet<&'f str>, JumpError> {
+        let mut pending = vec![];
+        for file_entry in files {
+            if let FileEntry::Install((file, dst)) = file_entry {
+                if file.size == 0 || file.offset.is_none() || !file.chunks.is_empty() {
+                    continue;
+                }
+                let (archive_type, target_type) = match file.file_type {
+                    FileType::Archive(archive_type) => (Some(archive_type), Target::Directory),
+                    FileType::Blob => (None, Target::File),
+                    FileType::Directory => (Some(ArchiveType::Zip), Target::Directory),
+                };
+                if !is_complete(dst, target_type, Some(&file.hash))? {
+                    pending.push((file, archive_type, self.source(file, 0)?.bytes, dst));
+                }
+            }
+        }
+        pending
+            .into_par_iter()
+            .map(|(file, archive_type, bytes, dst)| {
+                let file_type = archive_type.as_ref().map_or("blob", ArchiveType::as_ext);
+                check_hash(file_type, bytes, &file.hash, dst)?;
+                Ok(file.name.as_str())
+            })
+            .collect()
     }
 
     #[time("debug", "Installer::{}")]
-    pub(crate) fn install(&self, files: &[FileEntry]) -> Result<(), String> {
+    pub(crate) fn install(&self, files: &[FileEntry]) -> Result<(), JumpError> {
+        self.check_integrity(files)?;
+        check_offline(files)?;
+        check_disk_space(files)?;
+        let verified = self.verify(files)?;
         let mut scie_tote = vec![];
         let mut location = 0;
         for file_entry in files {
@@ -184,15 +813,56 @@ impl<'a> Installer<'a> {
                 FileEntry::Install((file, dst)) => {
                     if file.size == 0 {
                         scie_tote.push((file, file.file_type, dst.clone()));
+                    } else if !file.chunks.is_empty() {
+                        let chunks = self.chunks(file)?;
+                        let chunks_source = || {
+                            let mut buffer = tempfile::tempfile_in(self.staging_dir(dst)?)
+                                .map_err(|e| {
+                                    format!(
+                                        "Failed to establish a temporary file buffer for \
+                                        reassembling the chunks of {file:?}: {e}"
+                                    )
+                                })?;
+                            for bytes in chunks {
+                                buffer.write_all(bytes).map_err(|e| {
+                                    format!("Failed to reassemble the chunks of {file:?}: {e}")
+                                })?;
+                            }
+                            let bytes = Bytes::map(buffer).map_err(|e| {
+                                format!(
+                                    "Failed to map temp file for reading the reassembled \
+                                    {file:?}: {e}"
+                                )
+                            })?;
+                            Ok((bytes, ()))
+                        };
+                        let started = Instant::now();
+                        let unpacked = unpack(
+                            file.file_type,
+                            file.executable.unwrap_or(false),
+                            self.extract_policy,
+                            chunks_source,
+                            file.hash.as_str(),
+                            dst,
+                        )?;
+                        self.record(file, dst, started, unpacked);
                     } else {
-                        let bytes = &self.payload[location..(location + file.size)];
-                        unpack(
+                        let bytes = self.source(file, location)?;
+                        let bytes = if verified.contains(file.name.as_str()) {
+                            Bytes::Verified(bytes)
+                        } else {
+                            Bytes::Borrowed(bytes)
+                        };
+                        let started = Instant::now();
+                        let unpacked = unpack(
                             file.file_type,
                             file.executable.unwrap_or(false),
-                            || Ok((Cursor::new(bytes), ())),
+                            self.extract_policy,
+                            || Ok((bytes, ())),
                             file.hash.as_str(),
                             dst,
                         )?;
+                        self.record(file, dst, started, unpacked);
                     }
                     file.size
                 }
@@ -203,12 +873,13 @@ impl<'a> Installer<'a> {
                             file = file.name,
                             exe = binding.exe()
                         );
-                        let mut buffer = tempfile::tempfile().map_err(|e| {
-                            format!(
-                                "Failed to establish a temporary file buffer for loading {file:?} via \
-                                {binding:?}: {e}"
-                            )
-                        })?;
+                        let mut buffer =
+                            tempfile::tempfile_in(self.staging_dir(dst)?).map_err(|e| {
+                                format!(
+                                    "Failed to establish a temporary file buffer for loading \
+                                    {file:?} via {binding:?}: {e}"
+                                )
+                            })?;
                         let mut child =
                             binding.spawn_stdout(vec![file.name.as_str()].as_slice())?;
                         let mut stdout = child.stdout.take().ok_or_else(|| {
@@ -218,50 +889,102 @@ impl<'a> Installer<'a> {
                         })?;
                         std::io::copy(&mut stdout, &mut buffer)
                             .map_err(|e| format!("Failed to load {file:?} via {binding:?}: {e}"))?;
-                        buffer.rewind().map_err(|e| {
+                        let bytes = Bytes::map(buffer).map_err(|e| {
                             format!(
-                                "Failed to re-wind temp file for reading {file:?} loaded by \
+                                "Failed to map temp file for reading {file:?} loaded by \
                                 {binding:?}: {e}"
                             )
                         })?;
-                        Ok((buffer, child))
+                        Ok((bytes, child))
                     };
-                    if let Some(mut child) = unpack(
+                    let started = Instant::now();
+                    let unpacked = unpack(
                         file.file_type,
                         file.executable.unwrap_or(false),
+                        self.extract_policy,
                         buffer_source,
                         file.hash.as_str(),
                         dst,
-                    )? {
+                    )?;
+                    if let Some(mut child) = self.record(file, dst, started, unpacked) {
                         let exit_status = child.wait().map_err(|e| {
-                            format!(
-                                "Failed to await termination of {binding:?} when loading {file:?}: {e}"
+                            JumpError::process(
+                                format!(
+                                    "Failed to await termination of {binding:?} when loading \
+                                    {file:?}"
+                                ),
+                                e,
                             )
                         })?;
                         if !exit_status.success() {
-                            return Err(format!("Failed to load file {file:?}: {exit_status:?}"));
+                            return Err(JumpError::Other(format!(
+                                "Failed to load file {file:?}: {exit_status:?}"
+                            )));
                         }
                     }
                     0
                 }
+                FileEntry::FetchAndInstall((download, file, dst)) => {
+                    let buffer_source = || {
+                        info!(
+                            "Fetching {file} from {urls}...",
+                            file = file.name,
+                            urls = download.urls.join(" or ")
+                        );
+                        let mut buffer =
+                            tempfile::tempfile_in(self.staging_dir(dst)?).map_err(|e| {
+                                format!(
+                                    "Failed to establish a temporary file buffer for fetching \
+                                    {file:?}: {e}"
+                                )
+                            })?;
+                        download.fetcher.fetch_any(
+                            &download.urls,
+                            download.offset,
+                            file.size,
+                            &file.hash,
+                            &mut buffer,
+                        )?;
+                        let bytes = Bytes::map(buffer).map_err(|e| {
+                            format!("Failed to map temp file for reading fetched {file:?}: {e}")
+                        })?;
+                        Ok((bytes, ()))
+                    };
+                    let started = Instant::now();
+                    let unpacked = unpack(
+                        file.file_type,
+                        file.executable.unwrap_or(false),
+                        self.extract_policy,
+                        buffer_source,
+                        file.hash.as_str(),
+                        dst,
+                    )?;
+                    self.record(file, dst, started, unpacked);
+                    0
+                }
                 FileEntry::ScieTote((tote_file, entries)) => {
                     let mut scie_tote: Option<TempDir> = None;
                     let mut scie_tote_src = || {
                         if let Some(tempdir) = scie_tote.as_ref() {
                             return Ok::<_, String>(tempdir.path().join(&tote_file.name));
                         }
-                        let scie_tote_tmpdir = TempDir::new().map_err(|e| {
+                        let staging_dir = match entries.first() {
+                            Some((_, dst)) => self.staging_dir(dst)?,
+                            None => std::env::temp_dir(),
+                        };
+                        let scie_tote_tmpdir = TempDir::new_in(staging_dir).map_err(|e| {
                             format!(
                                 "Failed to create a temporary directory to extract the scie-tote \
                                 to: {e}"
                             )
                         })?;
                         let path = scie_tote_tmpdir.path().join(&tote_file.name);
-                        let bytes = &self.payload[location..(location + tote_file.size)];
+                        let bytes = self.source(tote_file, location)?;
                         unpack(
                             tote_file.file_type,
                             tote_file.executable.unwrap_or(false),
-                            || Ok((Cursor::new(bytes), ())),
+                            self.extract_policy,
+                            || Ok((Bytes::Borrowed(bytes), ())),
                             tote_file.hash.as_str(),
                             &path,
                         )?;
@@ -273,21 +996,27 @@ impl<'a> Installer<'a> {
                         let file_src = || {
                             let scie_tote_path = scie_tote_src()?;
                             let src_path = scie_tote_path.join(&file.name);
-                            let file = std::fs::File::open(&src_path).map_err(|e| {
-                                format!(
-                                    "Failed to open {file:?} at {src} from the unpacked scie-tote: {e}",
-                                    src = src_path.display()
-                                )
-                            })?;
-                            Ok((file, ()))
+                            let bytes = std::fs::File::open(&src_path)
+                                .and_then(Bytes::map)
+                                .map_err(|e| {
+                                    format!(
+                                        "Failed to open {file:?} at {src} from the unpacked \
+                                        scie-tote: {e}",
+                                        src = src_path.display()
+                                    )
+                                })?;
+                            Ok((bytes, ()))
                         };
-                        unpack(
+                        let started = Instant::now();
+                        let unpacked = unpack(
                             file.file_type,
                             file.executable.unwrap_or(false),
+                            self.extract_policy,
                             file_src,
                             file.hash.as_str(),
                             dst,
                         )?;
+                        self.record(file, dst, started, unpacked);
                     }
                     tote_file.size
                 }
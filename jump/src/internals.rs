@@ -0,0 +1,90 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Entry points into the crate internals exercised by the benchmarks under `benches/` and the fuzz
+//! targets under `../fuzz/`. These are not a stable API.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::Jump;
+use crate::error::JumpError;
+#[cfg(not(target_os = "wasi"))]
+use crate::extract::Source;
+
+pub fn end_of_zip(data: &[u8], maximum_trailer_size: usize) -> Result<usize, JumpError> {
+    crate::zip::end_of_zip(data, maximum_trailer_size).map_err(JumpError::Config)
+}
+
+pub fn load_jump(data: &[u8], path: &Path) -> Result<Option<Jump>, JumpError> {
+    crate::jump::load(data, path)
+}
+
+pub fn parse_placeholders(text: &str) -> Result<usize, JumpError> {
+    crate::placeholders::parse(text)
+        .map(|parsed| parsed.items.len())
+        .map_err(JumpError::Config)
+}
+
+pub fn create_zip(dir: &Path, name: &str) -> Result<PathBuf, JumpError> {
+    crate::archive::create(
+        dir,
+        name,
+        crate::config::UnicodeNormalization::Nfc,
+        false,
+        &Default::default(),
+    )
+    .map(|(zip, _)| zip)
+    .map_err(JumpError::Other)
+}
+
+#[cfg(not(target_os = "wasi"))]
+pub fn extract_zip(bytes: &[u8], dst: &Path) -> Result<(), JumpError> {
+    crate::extract::zip(Source { bytes, file: None }, dst, &Default::default())
+        .map_err(|e| JumpError::Other(e).extraction())
+}
+
+#[cfg(not(target_os = "wasi"))]
+pub fn extract_tar(bytes: &[u8], dst: &Path) -> Result<(), JumpError> {
+    crate::extract::tar(bytes, dst, &Default::default())
+        .map_err(|e| JumpError::Other(e).extraction())
+}
+
+/// Verifies blobs of the given sizes and hashes stored back to back in `payload` as a scie does
+/// before extracting them to `dst`.
+#[cfg(not(target_os = "wasi"))]
+pub fn verify_blobs(
+    payload: &[u8],
+    blobs: &[(usize, String)],
+    dst: &Path,
+) -> Result<(), JumpError> {
+    let mut offset = 0;
+    let files = blobs
+        .iter()
+        .enumerate()
+        .map(|(index, (size, hash))| {
+            let name = format!("blob{index}");
+            let file = crate::lift::File {
+                name: name.clone(),
+                key: None,
+                size: *size,
+                extracted_size: None,
+                offset: Some(offset),
+                chunks: vec![],
+                hash: hash.clone(),
+                file_type: crate::config::FileType::Blob,
+                executable: None,
+                eager_extract: false,
+                execute_in_place: false,
+                source: crate::lift::Source::Scie,
+                source_url: None,
+                version: None,
+                license: None,
+            };
+            offset += size;
+            crate::context::FileEntry::Install((file, dst.join(name)))
+        })
+        .collect::<Vec<_>>();
+    crate::installer::Installer::new(payload, 0)
+        .verify(&files)
+        .map(|_| ())
+}
@@ -6,41 +6,137 @@ use std::path::Path;
 
 use byteorder::{LittleEndian, ReadBytesExt};
 
+/// The magic number that terminates a scie-jump binary, stored as a little-endian u32.
 pub const EOF_MAGIC: u32 = 0x534a7219;
-pub(crate) const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The version of this scie-jump.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 use crate::config::Jump;
+use crate::footer::JUMP_TRAILER_SIZE;
+use crate::JumpError;
 
-pub fn load(data: &[u8], path: &Path) -> Result<Option<Jump>, String> {
-    let mut magic = Cursor::new(&data[data.len() - 8..]);
-    magic.seek(SeekFrom::End(-4)).map_err(|e| format!("{e}"))?;
+pub fn load(data: &[u8], path: &Path) -> Result<Option<Jump>, JumpError> {
+    if data.len() < JUMP_TRAILER_SIZE {
+        return Ok(None);
+    }
+    let mut magic = Cursor::new(&data[data.len() - JUMP_TRAILER_SIZE..]);
+    magic
+        .seek(SeekFrom::End(-4))
+        .map_err(|e| JumpError::Config(format!("{e}")))?;
     if let Ok(EOF_MAGIC) = magic.read_u32::<LittleEndian>() {
         magic.seek(SeekFrom::End(-8)).map_err(|e| {
-            format!(
+            JumpError::Config(format!(
                 "Failed to read scie-jump size from {path}: {e}",
                 path = path.display()
-            )
+            ))
         })?;
         let size = magic.read_u32::<LittleEndian>().map_err(|e| {
-            format!(
+            JumpError::Config(format!(
                 "The scie-jump size of {path} is malformed: {e}",
                 path = path.display()
-            )
+            ))
+        })?;
+        let actual_size = u32::try_from(data.len()).map_err(|e| {
+            JumpError::Config(format!(
+                "Expected the scie-jump launcher size to fit in 32 bits: {e}"
+            ))
         })?;
-        let actual_size = u32::try_from(data.len())
-            .map_err(|e| format!("Expected the scie-jump launcher size to fit in 32 bits: {e}"))?;
         if actual_size != size {
-            return Err(format!(
+            return Err(JumpError::Config(format!(
                 "The scie-jump launcher at {path} has size {actual_size} but the expected \
                 size is {expected_size}.",
                 path = path.display(),
                 expected_size = size
-            ));
+            )));
         }
         return Ok(Some(Jump {
             version: VERSION.to_string(),
             size: size as usize,
+            min_version: None,
         }));
     }
     Ok(None)
 }
+
+fn parse_version(version: &str) -> Option<[u64; 3]> {
+    let release = version
+        .split_once(['-', '+'])
+        .map(|(release, _)| release)
+        .unwrap_or(version);
+    let mut parts = release.split('.').map(|part| part.parse::<u64>().ok());
+    let version = [parts.next()??, parts.next()??, parts.next()??];
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(version)
+}
+
+/// The oldest scie-jump version that can boot the lift manifests this scie-jump writes.
+///
+/// New lift manifest features only ever arrive in minor releases; so any patch release of this
+/// scie-jump's minor release will do.
+pub(crate) fn min_version() -> String {
+    match parse_version(VERSION) {
+        Some([major, minor, _]) => format!("{major}.{minor}.0"),
+        None => VERSION.to_string(),
+    }
+}
+
+#[cfg(not(target_os = "wasi"))]
+/// Checks that this scie-jump is new enough to boot a scie with the given scie-jump information.
+pub(crate) fn check_version(jump: &Jump) -> Result<(), String> {
+    let Some(min_version) = jump.min_version.as_deref() else {
+        return Ok(());
+    };
+    let required = parse_version(min_version).ok_or_else(|| {
+        format!("The lift manifest requires an unrecognized scie-jump version {min_version:?}.")
+    })?;
+    match parse_version(VERSION) {
+        Some(current) if current >= required => Ok(()),
+        _ => Err(format!(
+            "This scie needs scie-jump {min_version} or newer but it has scie-jump {VERSION}. \
+            Re-pack it with a newer scie-jump via `SCIE=repack <scie> --scie-jump <scie-jump>`."
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_version, min_version, parse_version, VERSION};
+    use crate::config::Jump;
+
+    #[test]
+    fn versions() {
+        assert_eq!(Some([0, 13, 1]), parse_version("0.13.1"));
+        assert_eq!(Some([1, 2, 3]), parse_version("1.2.3-rc.1+build.5"));
+        assert_eq!(None, parse_version("1.2"));
+        assert_eq!(None, parse_version("1.2.3.4"));
+        assert_eq!(None, parse_version("one.2.3"));
+
+        let [major, minor, _] = parse_version(VERSION).unwrap();
+        assert_eq!(format!("{major}.{minor}.0"), min_version());
+        // Scies may use lift manifest features that first arrived in 0.14.0.
+        assert!(parse_version(&min_version()).unwrap() >= [0, 14, 0]);
+    }
+
+    #[test]
+    fn check() {
+        let jump = |min_version: Option<&str>| Jump {
+            size: 42,
+            version: "0.1.0".to_string(),
+            min_version: min_version.map(str::to_string),
+        };
+        check_version(&jump(None)).unwrap();
+        check_version(&jump(Some("0.1.0"))).unwrap();
+        check_version(&jump(Some(VERSION))).unwrap();
+        check_version(&jump(Some(&min_version()))).unwrap();
+        assert_eq!(
+            format!(
+                "This scie needs scie-jump 999.0.0 or newer but it has scie-jump {VERSION}. \
+                Re-pack it with a newer scie-jump via `SCIE=repack <scie> --scie-jump <scie-jump>`."
+            ),
+            check_version(&jump(Some("999.0.0"))).unwrap_err()
+        );
+        assert!(check_version(&jump(Some("latest"))).is_err());
+    }
+}
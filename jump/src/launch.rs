@@ -0,0 +1,116 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::ffi::OsString;
+use std::time::Duration;
+
+use crate::process::Process;
+
+/// The fully resolved command a scie boots into.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Launch {
+    pub exe: OsString,
+    /// The arguments from the lift manifest followed by those the scie was run with.
+    pub args: Vec<OsString>,
+    /// The env vars to set, or to remove when `None`, in the order they should be applied.
+    pub env: Vec<(OsString, Option<OsString>)>,
+    pub cwd: Option<OsString>,
+    /// A file descriptor holding the executable when it is run in place from memory; in which case
+    /// `exe` is only descriptive.
+    pub exe_fd: Option<i32>,
+    /// How long the command may run for before it should be killed.
+    pub timeout: Option<Duration>,
+}
+
+impl Launch {
+    /// Resolves the given process against the current environment, appending the given arguments
+    /// to those from the lift manifest.
+    pub fn new(process: Process, extra_args: impl IntoIterator<Item = OsString>) -> Self {
+        let env = process.env.to_env_vars();
+        let mut args = process.args;
+        args.extend(extra_args);
+        Self {
+            exe: process.exe,
+            args,
+            env,
+            cwd: process.cwd,
+            exe_fd: process.exe_fd,
+            timeout: process.timeout,
+        }
+    }
+}
+
+/// Hands a scie off to the command it boots into.
+///
+/// The scie-jump execs the command, or runs it as a child when it has a timeout. Tests and
+/// embedders can substitute a launcher that inspects the launch instead of running anything.
+pub trait Launcher {
+    type Output;
+
+    fn launch(&mut self, launch: Launch) -> Self::Output;
+}
+
+/// A launcher that records the launches it is asked to perform without running anything.
+#[derive(Debug, Default)]
+pub struct MockLauncher {
+    pub launches: Vec<Launch>,
+}
+
+impl Launcher for MockLauncher {
+    type Output = ();
+
+    fn launch(&mut self, launch: Launch) {
+        self.launches.push(launch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::time::Duration;
+
+    use super::{Launch, Launcher, MockLauncher};
+    use crate::{EnvVar, EnvVars, Process};
+
+    #[test]
+    fn launch() {
+        let process = Process {
+            env: EnvVars {
+                vars: vec![
+                    EnvVar::Remove("__SCIE_LAUNCH_REMOVED__".into()),
+                    EnvVar::Default(("__SCIE_LAUNCH_DEFAULT__".into(), "default".into())),
+                    EnvVar::Replace(("__SCIE_LAUNCH_REPLACED__".into(), "value".into())),
+                ],
+            },
+            exe: "/usr/bin/tool".into(),
+            args: vec!["--from-lift".into()],
+            cwd: Some("/work".into()),
+            exe_fd: None,
+            timeout: Some(Duration::from_secs(5)),
+        };
+
+        let mut launcher = MockLauncher::default();
+        launcher.launch(Launch::new(process, ["--from-user".into(), "file".into()]));
+        assert_eq!(
+            vec![Launch {
+                exe: "/usr/bin/tool".into(),
+                args: vec!["--from-lift".into(), "--from-user".into(), "file".into()],
+                env: vec![
+                    ("__SCIE_LAUNCH_REMOVED__".into(), None),
+                    (
+                        "__SCIE_LAUNCH_DEFAULT__".into(),
+                        Some(OsString::from("default"))
+                    ),
+                    (
+                        "__SCIE_LAUNCH_REPLACED__".into(),
+                        Some(OsString::from("value"))
+                    ),
+                ],
+                cwd: Some("/work".into()),
+                exe_fd: None,
+                timeout: Some(Duration::from_secs(5)),
+            }],
+            launcher.launches
+        );
+    }
+}
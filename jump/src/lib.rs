@@ -8,36 +8,107 @@ extern crate log;
 extern crate structure;
 
 mod archive;
+mod args;
+#[cfg(not(target_os = "wasi"))]
 mod atomic;
+#[cfg(not(target_os = "wasi"))]
+mod audit;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod chunk;
+#[cfg(not(target_os = "wasi"))]
 mod cmd_env;
 mod comparable_regex;
 pub mod config;
+#[cfg(not(target_os = "wasi"))]
 mod context;
+mod delta;
+mod diff;
+#[cfg(not(target_os = "wasi"))]
+mod doctor;
+mod embed;
+mod error;
+#[cfg(not(target_os = "wasi"))]
+mod extract;
+#[cfg(not(target_os = "wasi"))]
+mod fetch;
 pub mod fingerprint;
+mod footer;
+#[cfg(not(target_os = "wasi"))]
+mod host;
+#[cfg(not(target_os = "wasi"))]
+mod in_place;
+#[cfg(not(target_os = "wasi"))]
 mod installer;
+#[cfg(feature = "internals")]
+#[doc(hidden)]
+pub mod internals;
 mod jump;
+mod launch;
 mod lift;
+mod manual;
+mod pack;
 mod placeholders;
 mod process;
+mod repack;
+#[cfg(not(target_os = "wasi"))]
+mod reverify;
+mod sbom;
+#[cfg(not(target_os = "wasi"))]
+mod selection;
+#[cfg(not(target_os = "wasi"))]
+mod sigstore;
+mod user_args;
+mod validate;
 mod zip;
 
+#[cfg(not(target_os = "wasi"))]
 use std::env;
+#[cfg(not(target_os = "wasi"))]
 use std::env::current_exe;
+use std::path::Path;
+#[cfg(not(target_os = "wasi"))]
 use std::path::PathBuf;
+#[cfg(not(target_os = "wasi"))]
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+#[cfg(not(target_os = "wasi"))]
 use log::Level;
+#[cfg(not(target_os = "wasi"))]
 use logging_timer::{time, timer};
 
 pub use crate::archive::create_options;
+pub use crate::args::{scie_args, ScieArgs};
 use crate::config::Config;
 pub use crate::config::Jump;
+pub use crate::delta::{delta, Delta, Patch};
+pub use crate::diff::{diff, Change, ScieDiff};
+#[cfg(not(target_os = "wasi"))]
+pub use crate::doctor::{Check, Diagnosis, Health};
+pub use crate::error::JumpError;
+#[cfg(not(target_os = "wasi"))]
+pub use crate::fetch::{register_file_provider, FileProvider};
+#[cfg(not(target_os = "wasi"))]
 use crate::installer::Installer;
-// Exposed for the package crate post-processing of the scie-jump binary.
-pub use crate::jump::EOF_MAGIC;
-pub use crate::lift::{load_lift, File, Lift, ScieBoot, Source};
+// Exposed for the package crate post-processing of the scie-jump binary as well as for third party
+// tools that need to identify scies.
+pub use crate::footer::{is_scie, ScieFooter, JUMP_TRAILER_SIZE, SCIE_MAGIC_SIZE};
+pub use crate::jump::{EOF_MAGIC, VERSION};
+pub use crate::launch::{Launch, Launcher, MockLauncher};
+pub use crate::lift::{load_lift, load_lift_data, File, Lift, PackCache, ScieBoot, Source};
+pub use crate::manual::{manual, ManualFormat};
+pub use crate::pack::{
+    load_manifest, load_scie_jump, pack, pack_dry_run, read_manifest, PackOptions, Packed,
+    ReadManifest, SizeLimits, PAGE_ALIGNMENT,
+};
 pub use crate::process::{execute, EnvVar, EnvVars, Process};
-pub use crate::zip::check_is_zip;
+pub use crate::repack::Repack;
+pub use crate::sbom::{sbom, Sbom};
+#[cfg(not(target_os = "wasi"))]
+pub use crate::selection::{select, Selection};
+pub use crate::zip::{check_is_zip, ZipStream};
 
+#[cfg(not(target_os = "wasi"))]
 pub struct SelectBoot {
     pub scie: CurrentExe,
     pub boots: Vec<ScieBoot>,
@@ -45,13 +116,28 @@ pub struct SelectBoot {
     pub error_message: String,
 }
 
+#[cfg(not(target_os = "wasi"))]
 const HELP: &str = "\
 For SCIE=<boot_command> you can select from the following:
 
 boot-pack
     (-sj|--jump|--scie-jump [PATH])
     (-1|--single-lift-line|--no-single-lift-line)
+    (--strict)
+    (--json)
+    (-o|--dest-dir [DIR])
+    (--align)
+    (--lift-in-zip)
+    (--embed)
+    (--chunk)
+    (--sha256)
+    (--provenance)
+    (--dry-run)
+    (--warn-file-size|--max-file-size [SIZE])
+    (--warn-scie-size|--max-scie-size [SIZE])
+    (--allow-large)
     [lift manifest]*
+boot-pack --is-scie [file]*
 
     Pack the given lift manifests into scie executables. If no manifests
     are given, looks for `lift.json` in the current directory. By
@@ -59,46 +145,165 @@ boot-pack
     alternate scie-jump binary can be specified using --path. By default
     the lift manifest is appended to the tail of the scie as a single
     line JSON document, but can be made a multi-line pretty-printed JSON
-    document by passing --no-single-lift-line.
+    document by passing --no-single-lift-line. Unrecognized lift
+    manifest fields are warned about by default but can be made an error
+    by passing --strict or setting SCIE_STRICT=1. Pass --json to print a
+    JSON report describing each scie produced including the offsets and
+    fingerprints of its files and its lift manifest. Scies are written
+    to the current directory unless an alternate directory is specified
+    with --dest-dir. When several lift manifests are packed together,
+    files they share are only archived and fingerprinted once. Pass
+    --align to start each file stored directly in the scie on a 4KiB
+    boundary. Pass --lift-in-zip to store the lift manifest as the
+    `__lift__.json` entry of a zip at the end of the scie instead of as
+    a trailer. Pass --embed to store the files and lift manifest in a
+    section (Windows) or segment (macOS) added to the scie-jump
+    executable so the scie can be code signed. Pass --chunk to store
+    large files as content-defined chunks on 4KiB boundaries so that
+    successive releases of a scie dedupe and diff well. Pass --sha256 to
+    write a `<scie>.sha256` checksum file and --provenance to write a
+    `<scie>.provenance.json` file describing the scie next to each scie
+    produced. Pass --dry-run to report the files, sizes, hashes and lift
+    manifest trailer of each scie without writing it. Pass
+    --warn-file-size or --max-file-size to warn or fail when a file
+    stored in a scie is larger than SIZE and --warn-scie-size or
+    --max-scie-size to do the same for the whole scie; where SIZE is in
+    bytes or has a K, M, G or T (1024 based) suffix. Pass --allow-large
+    to only warn when a maximum is exceeded. Pass --is-scie to instead
+    report whether each file given is a scie and which scie-jump version
+    built it; exits with 1 if any is not.
+
+delta (-o|--output [PATH]) (--json) [scie]
+
+    Write a patch to the given output path that turns this scie into the
+    given scie; e.g.: the next release of this scie. Stretches of the
+    given scie found in this scie, like files or chunks of files that
+    did not change, are copied from this scie and everything else is
+    stored in the patch. Scies packed with --chunk patch best. Pass
+    --json to print a JSON report describing the patch.
+
+diff (--json)? [scie]
+
+    Compare this scie's lift manifest with that of the given scie and
+    report the files, commands and bindings added, removed or changed.
+    Pass --json to print the report as JSON. Exits with 0 if there are
+    no differences and 1 otherwise.
+
+doctor
+
+    Check whether this scie can boot on this machine and print a
+    diagnosis. Checks that the nce cache is writable, that there is
+    enough disk space to extract the files not yet extracted, that
+    the scie's native executables match this platform, that system
+    executables its commands run are present and that previously
+    extracted files are intact. Exits with 1 if a problem is found.
 
 help: Display this help message.
 
 inspect: Pretty-print this scie's lift manifest to stdout.
 
-install (-s|--symlink) [dest dir]*
+install (-s|--symlink|--shims) [dest dir]*
 
     Install all the commands in this scie to each dest dir given. If no
-    dest dirs are given, installs them in the current directory.
+    dest dirs are given, installs them in the current directory. Pass
+    --shims to install a single copy of this scie and a relative symlink
+    (or a `.cmd` shim on Windows) to it for each command instead of a
+    copy of the scie per command.
 
 list: List the names of the commands contained in this scie.
 
+patch (-o|--output [PATH]|--in-place) [patch]
+
+    Apply a patch written by the delta command to this scie and write the
+    patched scie to the given output path. Pass --in-place to patch this
+    scie over itself instead of writing a copy. The patch must have been
+    made from this scie and the patched scie is checked to be the scie
+    the patch was made for.
+
+repack (-o|--output [PATH]|--in-place) (-sj|--scie-jump [PATH]) (--json) (--no-single-lift-line) [name=path]*
+
+    Write a copy of this scie to the given output path with the named
+    files replaced by the files at the given paths. Names that do not
+    match a file in this scie add a new file. All other files are copied
+    as-is. Files stored in a scie-tote cannot be replaced and directories
+    must be replaced with a zip of their contents. Pass --scie-jump to
+    replace the scie-jump launcher at the tip of the scie and --in-place
+    to re-pack this scie over itself instead of writing a copy. Pass
+    --json to print a JSON report describing the new scie like the
+    boot-pack --json report.
+
+sbom: Print a CycloneDX SBOM describing the files in this scie to stdout.
+
 split [directory]?
 
     Split this scie into its component files in the given directory or
     else the current directory if no argument is given.
 ";
 
+#[cfg(not(target_os = "wasi"))]
 pub enum BootAction {
+    Delta(PathBuf),
+    Diff((Jump, Lift)),
+    Doctor(Diagnosis),
     Execute((Process, bool)),
     Help((String, i32)),
     Inspect((Jump, Lift)),
     Install((PathBuf, Vec<ScieBoot>)),
     List(Vec<ScieBoot>),
     Pack((Jump, PathBuf)),
+    Patch(PathBuf),
+    Repack(PathBuf),
+    Sbom((Jump, Lift)),
     Select(SelectBoot),
     Split((Jump, Lift, PathBuf)),
 }
 
+#[cfg(not(target_os = "wasi"))]
+fn map(file: &std::fs::File) -> std::io::Result<memmap2::Mmap> {
+    unsafe { memmap2::Mmap::map(file) }
+}
+
+// N.B.: There is no mmap on wasm32-wasi; so we read the file into memory instead.
+#[cfg(target_os = "wasi")]
+fn map(mut file: &std::fs::File) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut data = vec![];
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
 pub fn config(jump: Jump, mut lift: Lift) -> Config {
     let other = lift.other.take();
     Config::new(jump, lift, other)
 }
 
+/// Loads the lift manifest of the scie at the given path.
+pub fn inspect(scie: &Path) -> Result<Config, JumpError> {
+    let file = std::fs::File::open(scie).map_err(|e| {
+        JumpError::io(
+            format!("Failed to open {scie} for reading", scie = scie.display()),
+            e,
+        )
+    })?;
+    let data = map(&file)
+        .map_err(|e| JumpError::io(format!("Failed to read {scie}", scie = scie.display()), e))?;
+    if jump::load(&data, scie)?.is_some() {
+        return Err(JumpError::Config(format!(
+            "The file at {scie} is a scie-jump binary and not a scie.",
+            scie = scie.display()
+        )));
+    }
+    let (jump, lift) = lift::load_scie(scie, embed::scie_data(&data))?;
+    Ok(config(jump, lift))
+}
+
+#[cfg(not(target_os = "wasi"))]
 pub struct CurrentExe {
     exe: PathBuf,
     invoked_as: PathBuf,
 }
 
+#[cfg(not(target_os = "wasi"))]
 impl CurrentExe {
     pub fn name(&self) -> Option<&str> {
         #[cfg(windows)]
@@ -118,9 +323,10 @@ impl CurrentExe {
     }
 }
 
-fn find_current_exe() -> Result<CurrentExe, String> {
-    let exe =
-        current_exe().map_err(|e| format!("Failed to find path of the current executable: {e}"))?;
+#[cfg(not(target_os = "wasi"))]
+fn find_current_exe() -> Result<CurrentExe, JumpError> {
+    let exe = current_exe()
+        .map_err(|e| JumpError::io("Failed to find path of the current executable", e))?;
     let invoked_as = if let Some(arg) = env::args_os().next() {
         PathBuf::from(arg)
     } else {
@@ -129,25 +335,53 @@ fn find_current_exe() -> Result<CurrentExe, String> {
     Ok(CurrentExe { exe, invoked_as })
 }
 
+// Exports when the scie started booting and how long it took, so the command it launches can tell
+// the time spent in the scie-jump apart from its own start up time.
+#[cfg(not(target_os = "wasi"))]
+fn export_boot_timing(started: (Instant, SystemTime), extraction_time: Duration) {
+    let (boot_started, boot_start) = started;
+    if let Ok(since_epoch) = boot_start.duration_since(UNIX_EPOCH) {
+        env::set_var("SCIE_BOOT_START_MS", since_epoch.as_millis().to_string());
+    }
+    env::set_var(
+        "SCIE_BOOT_TIME_MS",
+        boot_started.elapsed().as_millis().to_string(),
+    );
+    env::set_var(
+        "SCIE_EXTRACT_TIME_MS",
+        extraction_time.as_millis().to_string(),
+    );
+}
+
+#[cfg(not(target_os = "wasi"))]
 #[time("debug", "jump::{}")]
-pub fn prepare_boot() -> Result<BootAction, String> {
+pub fn prepare_boot() -> Result<BootAction, JumpError> {
+    let started = (Instant::now(), SystemTime::now());
     let current_exe = find_current_exe()?;
     let file = std::fs::File::open(&current_exe.exe).map_err(|e| {
-        format!(
-            "Failed to open current exe at {exe} for reading: {e}",
-            exe = current_exe.exe.display(),
+        JumpError::io(
+            format!(
+                "Failed to open current exe at {exe} for reading",
+                exe = current_exe.exe.display(),
+            ),
+            e,
         )
     })?;
     let data = unsafe {
-        memmap2::Mmap::map(&file)
-            .map_err(|e| format!("Failed to mmap {exe}: {e}", exe = current_exe.exe.display()))?
+        memmap2::Mmap::map(&file).map_err(|e| {
+            JumpError::io(
+                format!("Failed to mmap {exe}", exe = current_exe.exe.display()),
+                e,
+            )
+        })?
     };
 
     if let Some(jump) = jump::load(&data, &current_exe.exe)? {
         return Ok(BootAction::Pack((jump, current_exe.exe)));
     }
 
-    let (jump, lift) = lift::load_scie(&current_exe.exe, &data)?;
+    let scie_data = embed::scie_data(&data);
+    let (jump, lift) = lift::load_scie(&current_exe.exe, scie_data)?;
     trace!(
         "Loaded lift manifest from {current_exe}:\n{lift:#?}",
         current_exe = current_exe.exe.display()
@@ -156,6 +390,17 @@ pub fn prepare_boot() -> Result<BootAction, String> {
     if let Some(value) = env::var_os("SCIE") {
         if "boot-pack" == value {
             return Ok(BootAction::Pack((jump, current_exe.exe)));
+        } else if "delta" == value {
+            return Ok(BootAction::Delta(current_exe.exe));
+        } else if "diff" == value {
+            return Ok(BootAction::Diff((jump, lift)));
+        } else if "doctor" == value {
+            let payload = &scie_data[jump.size..scie_data.len() - lift.size];
+            let installer = Installer::new(payload, jump.size);
+            return Ok(BootAction::Doctor(
+                doctor::diagnose(&current_exe, &jump, &lift, &installer)
+                    .map_err(JumpError::Other)?,
+            ));
         } else if "help" == value {
             return Ok(BootAction::Help((format!("{HELP}\n"), 0)));
         } else if "inspect" == value {
@@ -164,7 +409,19 @@ pub fn prepare_boot() -> Result<BootAction, String> {
             return Ok(BootAction::Install((current_exe.exe, lift.boots())));
         } else if "list" == value {
             return Ok(BootAction::List(lift.boots()));
+        } else if "patch" == value {
+            return Ok(BootAction::Patch(current_exe.exe));
+        } else if "repack" == value {
+            return Ok(BootAction::Repack(current_exe.exe));
+        } else if "sbom" == value {
+            return Ok(BootAction::Sbom((jump, lift)));
         } else if "split" == value {
+            if embed::payload_end(&data).is_some() {
+                return Err(JumpError::Config(
+                    "Scies embedded in an executable section or segment cannot be split."
+                        .to_string(),
+                ));
+            }
             return Ok(BootAction::Split((jump, lift, current_exe.exe)));
         } else if !PathBuf::from(&value).exists() {
             let help_message = format!(
@@ -178,21 +435,85 @@ pub fn prepare_boot() -> Result<BootAction, String> {
         }
     }
 
+    jump::check_version(&jump).map_err(JumpError::Config)?;
+
+    if let Some(format) = scie_args().help {
+        return Ok(BootAction::Help((manual(&config(jump, lift), format), 0)));
+    }
+
+    if let Some(policy) = sigstore::Policy::from_env(&current_exe.exe)? {
+        sigstore::verify(&policy, &data)?;
+    }
+
     if lift.load_dotenv {
         let _timer = timer!(Level::Debug; "jump::load_dotenv");
         if let Ok(dotenv_file) = dotenvy::dotenv() {
             debug!("Loaded env file from {path}", path = dotenv_file.display());
         }
     }
-    let payload = &data[jump.size..data.len() - lift.size];
-    let installer = Installer::new(payload);
+    let payload = &scie_data[jump.size..scie_data.len() - lift.size];
+    let installer = Installer::new(payload, jump.size)
+        .with_scie(&file)
+        .with_permissions(lift.permissions.as_ref())
+        .map_err(JumpError::Config)?
+        .with_unicode_normalization(lift.unicode_normalization)
+        .with_integrity(lift.integrity.as_ref())
+        .map_err(JumpError::Config)?
+        .with_tmp(context::tmp_dir(lift.tmp.as_deref()).map_err(JumpError::Config)?);
     match context::select_command(&current_exe, &jump, &lift, &installer) {
         Ok(selected_command) => {
-            installer.install(&selected_command.files)?;
-            let process = selected_command.process;
+            if let Some(user_args) = &selected_command.user_args {
+                let argv_skip = if selected_command.argv1_consumed {
+                    2
+                } else {
+                    1
+                };
+                let args = scie_args().args().skip(argv_skip).collect::<Vec<_>>();
+                let name = if selected_command.name.is_empty() {
+                    &lift.name
+                } else {
+                    &selected_command.name
+                };
+                if let Err(message) = user_args::check(name, user_args, &args) {
+                    return Ok(BootAction::Help((message, user_args::USAGE_ERROR)));
+                }
+            }
+            if selected_command.exe_in_place.is_none() {
+                context::check_executable(&selected_command.base, &selected_command.process.exe)
+                    .map_err(JumpError::Other)?;
+            }
+            let extraction_started = Instant::now();
+            installer
+                .install(&selected_command.files)
+                .map_err(JumpError::extraction)?;
+            let extraction_time = extraction_started.elapsed();
+            let mut process = selected_command.process;
+            if let Some(file) = selected_command.exe_in_place {
+                let fd = in_place::load(&file, installer.in_place_bytes(&file)?)
+                    .map_err(|e| JumpError::Other(e).extraction())?;
+                process.exe = format!("/proc/self/fd/{fd}").into();
+                process.exe_fd = Some(fd);
+            }
             trace!("Prepared {process:#?}");
+            if audit::enabled() {
+                audit::record(
+                    &selected_command.base,
+                    &audit::Boot {
+                        timestamp_ms: audit::timestamp_ms(started.1),
+                        scie: &current_exe.exe,
+                        lift: &lift.name,
+                        hash: &lift.hash,
+                        command: &selected_command.name,
+                        duration_ms: started.0.elapsed().as_millis(),
+                        extractions: &installer.extractions(),
+                    },
+                );
+            }
             env::set_var("SCIE", current_exe.exe.as_os_str());
             env::set_var("SCIE_ARGV0", current_exe.invoked_as.as_os_str());
+            if lift.boot_timing {
+                export_boot_timing(started, extraction_time);
+            }
             Ok(BootAction::Execute((
                 process,
                 selected_command.argv1_consumed,
@@ -1,18 +1,30 @@
 // Copyright 2022 Science project contributors.
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use bstr::ByteSlice;
+use log::warn;
 use logging_timer::time;
 
-use crate::config::{ArchiveType, Boot, Config, FileType, Jump, Other};
+use crate::config::{
+    ArchiveType, Boot, Chunk, Config, Fetch, FileSource, FileType, Integrity, Jump, Other,
+    Permissions, UnicodeNormalization, UnknownFields, Zips,
+};
+use crate::error::JumpError;
+use crate::validate::validate;
 use crate::{archive, fingerprint};
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub enum Source {
     Scie,
     LoadBinding(String),
+    EntryRange {
+        url: String,
+        mirrors: Vec<String>,
+        offset: usize,
+    },
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
@@ -20,11 +32,22 @@ pub struct File {
     pub name: String,
     pub key: Option<String>,
     pub size: usize,
+    /// The total size of the files this file unpacks to, if recorded by the boot-pack; blobs
+    /// stored directly in the scie just take up their size.
+    pub extracted_size: Option<usize>,
+    /// The offset of this file's bytes from the start of the scie, if recorded by the boot-pack.
+    pub offset: Option<usize>,
+    /// The chunks holding this file's bytes when the boot-pack stored it in pieces.
+    pub chunks: Vec<Chunk>,
     pub hash: String,
     pub file_type: FileType,
     pub executable: Option<bool>,
     pub eager_extract: bool,
+    pub execute_in_place: bool,
     pub source: Source,
+    pub source_url: Option<String>,
+    pub version: Option<String>,
+    pub license: Option<String>,
 }
 
 impl From<File> for crate::config::File {
@@ -36,14 +59,30 @@ impl From<File> for crate::config::File {
                 0 => None,
                 size => Some(size),
             },
+            extracted_size: value.extracted_size,
+            offset: value.offset,
+            chunks: value.chunks,
             hash: Some(value.hash),
             file_type: Some(value.file_type),
             executable: value.executable,
             eager_extract: value.eager_extract,
+            execute_in_place: value.execute_in_place,
             source: match value.source {
                 Source::Scie => None,
-                Source::LoadBinding(binding_name) => Some(binding_name),
+                Source::LoadBinding(binding_name) => Some(FileSource::Binding(binding_name)),
+                Source::EntryRange {
+                    url,
+                    mirrors,
+                    offset,
+                } => Some(FileSource::EntryRange {
+                    url,
+                    mirrors,
+                    offset,
+                }),
             },
+            source_url: value.source_url,
+            version: value.version,
+            license: value.license,
         }
     }
 }
@@ -54,6 +93,14 @@ pub struct Lift {
     pub description: Option<String>,
     pub base: Option<String>,
     pub(crate) load_dotenv: bool,
+    pub(crate) boot_timing: bool,
+    pub(crate) fetch: Option<Fetch>,
+    pub(crate) permissions: Option<Permissions>,
+    pub(crate) integrity: Option<Integrity>,
+    pub(crate) unicode_normalization: Option<UnicodeNormalization>,
+    pub(crate) keep_junk: bool,
+    pub(crate) zips: Option<Zips>,
+    pub(crate) tmp: Option<String>,
     pub size: usize,
     pub hash: String,
     pub boot: Boot,
@@ -68,10 +115,12 @@ pub struct ScieBoot {
 }
 
 impl Lift {
+    #[cfg(not(target_os = "wasi"))]
     pub(crate) fn boots(&self) -> Vec<ScieBoot> {
         self.boot
             .commands
             .iter()
+            .filter(|(_, cmd)| cmd.is_enabled())
             .map(|(name, cmd)| {
                 let default = name.is_empty();
                 let name = if default {
@@ -97,6 +146,14 @@ impl From<Lift> for crate::config::Lift {
             description: value.description,
             base: value.base,
             load_dotenv: if value.load_dotenv { Some(true) } else { None },
+            boot_timing: if value.boot_timing { Some(true) } else { None },
+            fetch: value.fetch,
+            permissions: value.permissions,
+            integrity: value.integrity,
+            unicode_normalization: value.unicode_normalization,
+            keep_junk: if value.keep_junk { Some(true) } else { None },
+            zips: value.zips,
+            tmp: value.tmp,
             boot: value.boot,
             files: value
                 .files
@@ -107,7 +164,28 @@ impl From<Lift> for crate::config::Lift {
     }
 }
 
-fn determine_file_type(path: &Path) -> Result<FileType, String> {
+fn file_type_from_name(name: &str) -> Result<FileType, String> {
+    let ext = match name.rsplitn(3, '.').collect::<Vec<_>>()[..] {
+        [_, "tar", stem] => name.trim_start_matches(stem).trim_start_matches('.'),
+        [ext, ..] => ext,
+        _ => {
+            return Err(format!(
+                "This archive has no type declared and it could not be guessed from its name: \
+                {name}",
+            ))
+        }
+    };
+    Ok(match ArchiveType::from_ext(ext) {
+        Some(archive_type) => FileType::Archive(archive_type),
+        None => FileType::Blob,
+    })
+}
+
+/// Determines the type of a file from its contents, falling back to its name.
+///
+/// Zips are only treated as archives when named like one since many formats, like jars and
+/// wheels, are zips that should be left whole.
+pub(crate) fn determine_file_type(path: &Path) -> Result<FileType, String> {
     if path.is_dir() {
         return Ok(FileType::Directory);
     }
@@ -119,21 +197,24 @@ fn determine_file_type(path: &Path) -> Result<FileType, String> {
                 .map_err(|e| {
                     format!("Failed to interpret file name {basename:?} as a utf-8 string: {e}")
                 })?;
-            let ext = match name.rsplitn(3, '.').collect::<Vec<_>>()[..] {
-                [_, "tar", stem] => name.trim_start_matches(stem).trim_start_matches('.'),
-                [ext, ..] => ext,
-                _ => {
-                    return Err(format!(
-                        "This archive has no type declared and it could not be guessed from \
-                            its name: {name}",
-                    ))
-                }
-            };
-            let file_type = if let Some(archive_type) = ArchiveType::from_ext(ext) {
-                FileType::Archive(archive_type)
-            } else {
-                FileType::Blob
+            let named_type = file_type_from_name(name)?;
+            let file_type = match (archive::sniff(path)?, named_type) {
+                (Some(ArchiveType::Zip), FileType::Blob) => FileType::Blob,
+                (Some(archive_type), _) => FileType::Archive(archive_type),
+                (None, named_type) => named_type,
             };
+            if let (FileType::Archive(named), FileType::Archive(detected)) = (named_type, file_type)
+            {
+                if named != detected {
+                    warn!(
+                        "{path} is named like a {named} archive but holds a {detected} archive; \
+                        treating it as a {detected} archive.",
+                        path = path.display(),
+                        named = named.as_ext(),
+                        detected = detected.as_ext()
+                    );
+                }
+            }
             return Ok(file_type);
         }
     }
@@ -143,13 +224,42 @@ fn determine_file_type(path: &Path) -> Result<FileType, String> {
     ))
 }
 
+// An existing archive declared to be of a different archive type than its name implies is
+// transcoded to the declared type by the boot-pack.
+fn transcoding(path: &Path, file_type: FileType) -> Option<(ArchiveType, ArchiveType)> {
+    if !path.is_file() {
+        return None;
+    }
+    match (determine_file_type(path), file_type) {
+        (Ok(FileType::Archive(from)), FileType::Archive(to)) if from != to => Some((from, to)),
+        _ => None,
+    }
+}
+
+/// The path of the bytes the boot-pack stores in the scie for the given file.
+pub(crate) fn packed_path(
+    resolve_base: &Path,
+    zips: &Zips,
+    file: &File,
+) -> Result<PathBuf, String> {
+    if FileType::Directory == file.file_type {
+        return archive::zip_path(resolve_base, &file.name, zips, Some(&file.hash))
+            .map(|zip_path| zip_path.expect("The hash of the zip is known."));
+    }
+    let path = resolve_base.join(&file.name);
+    Ok(match transcoding(&path, file.file_type) {
+        Some((_, to)) => archive::transcoded_path(&path, to),
+        None => path,
+    })
+}
+
 #[cfg(not(target_family = "unix"))]
-fn is_executable(_path: &Path) -> Result<bool, String> {
+pub(crate) fn is_executable(_path: &Path) -> Result<bool, String> {
     Ok(false)
 }
 
 #[cfg(target_family = "unix")]
-fn is_executable(path: &Path) -> Result<bool, String> {
+pub(crate) fn is_executable(path: &Path) -> Result<bool, String> {
     use std::os::unix::fs::PermissionsExt;
     let metadata = path.metadata().map_err(|e| {
         format!(
@@ -160,12 +270,106 @@ fn is_executable(path: &Path) -> Result<bool, String> {
     Ok(metadata.permissions().mode() & 0o111 != 0)
 }
 
+/// Caches the work of preparing files for packing.
+///
+/// When several lift manifests are packed together and share files, the shared files are only
+/// archived and fingerprinted once.
+#[derive(Default)]
+pub struct PackCache {
+    zips: HashMap<(PathBuf, Zips), PathBuf>,
+    archives: HashMap<PathBuf, PathBuf>,
+    digests: HashMap<PathBuf, (usize, String)>,
+    extracted_sizes: HashMap<PathBuf, Option<usize>>,
+}
+
+impl PackCache {
+    fn archive(
+        &mut self,
+        resolve_base: &Path,
+        name: &str,
+        unicode_normalization: UnicodeNormalization,
+        keep_junk: bool,
+        zips: &Zips,
+    ) -> Result<PathBuf, String> {
+        let key = (resolve_base.join(name), zips.clone());
+        if let Some(archive) = self.zips.get(&key) {
+            return Ok(archive.clone());
+        }
+        let (archive, digest) =
+            archive::create(resolve_base, name, unicode_normalization, keep_junk, zips)?;
+        // N.B.: Zips named after their hash can only collide when they hold the same contents.
+        if digest.is_none() {
+            if let Some(((other, _), _)) = self
+                .zips
+                .iter()
+                .find(|((other, _), zip)| zip == &&archive && other != &key.0)
+            {
+                return Err(format!(
+                    "The directories {other} and {directory} are both zipped to {zip}. Include \
+                    {{hash}} in the lift \"zips\" \"name\" to give each zip a distinct name.",
+                    other = other.display(),
+                    directory = key.0.display(),
+                    zip = archive.display()
+                ));
+            }
+        }
+        if let Some(digest) = digest {
+            self.digests.insert(archive.clone(), digest);
+        }
+        self.zips.insert(key, archive.clone());
+        Ok(archive)
+    }
+
+    fn transcode(
+        &mut self,
+        path: &Path,
+        from: ArchiveType,
+        to: ArchiveType,
+    ) -> Result<PathBuf, String> {
+        let transcoded_path = archive::transcoded_path(path, to);
+        if let Some(archive) = self.archives.get(&transcoded_path) {
+            return Ok(archive.clone());
+        }
+        let archive = archive::transcode(path, from, to)?;
+        self.archives.insert(transcoded_path, archive.clone());
+        Ok(archive)
+    }
+
+    fn digest(&mut self, path: &Path) -> Result<(usize, String), String> {
+        if let Some((size, hash)) = self.digests.get(path) {
+            return Ok((*size, hash.clone()));
+        }
+        let (size, hash) = fingerprint::digest_file(path)?;
+        self.digests
+            .insert(path.to_path_buf(), (size, hash.clone()));
+        Ok((size, hash))
+    }
+
+    fn extracted_size(
+        &mut self,
+        path: &Path,
+        file_type: FileType,
+    ) -> Result<Option<usize>, String> {
+        if let Some(extracted_size) = self.extracted_sizes.get(path) {
+            return Ok(*extracted_size);
+        }
+        let extracted_size = archive::extracted_size(path, file_type)?;
+        self.extracted_sizes
+            .insert(path.to_path_buf(), extracted_size);
+        Ok(extracted_size)
+    }
+}
+
 #[time("debug", "lift::{}")]
 fn assemble(
     resolve_base: &Path,
     config_files: Vec<crate::config::File>,
-    reconstitute: bool,
+    unicode_normalization: UnicodeNormalization,
+    keep_junk: bool,
+    zips: &Zips,
+    mut pack_cache: Option<&mut PackCache>,
 ) -> Result<Vec<File>, String> {
+    let reconstitute = pack_cache.is_some();
     let mut files = vec![];
     for file in config_files {
         let mut path = resolve_base.join(&file.name);
@@ -177,9 +381,37 @@ fn assemble(
         } else {
             return Err(format!("A file type is required. Found: {file:?}"));
         };
+        if file.execute_in_place && file_type != FileType::Blob {
+            return Err(format!(
+                "The file {name} is marked execute_in_place but it is a {file_type:?} and only \
+                blobs can be executed in place.",
+                name = file.name
+            ));
+        }
 
-        if reconstitute && file_type == FileType::Directory {
-            path = archive::create(resolve_base, &file.name)?;
+        if let (Some(cache), FileType::Directory) = (pack_cache.as_deref_mut(), file_type) {
+            path = cache.archive(
+                resolve_base,
+                &file.name,
+                unicode_normalization,
+                keep_junk,
+                zips,
+            )?;
+        }
+        if let (Some(cache), None) = (pack_cache.as_deref_mut(), &file.source) {
+            if let Some((from, to)) = transcoding(&path, file_type) {
+                if file.hash.is_some() {
+                    return Err(format!(
+                        "The file {name} is transcoded from {from} to {to} but has a hash \
+                        specified. Remove the hash so the boot-pack can record the hash of the \
+                        transcoded archive.",
+                        name = file.name,
+                        from = from.as_ext(),
+                        to = to.as_ext()
+                    ));
+                }
+                path = cache.transcode(&path, from, to)?;
+            }
         }
 
         let (size, hash) = match file {
@@ -193,12 +425,22 @@ fn assemble(
                 hash: Some(hash),
                 ..
             } => (0, hash), // A scie-tote entry.
-            _ if reconstitute => fingerprint::digest_file(&path)?,
-            file => {
-                return Err(format!(
-                    "Both file size and hash are required. Found: {file:?}"
-                ));
+            _ => match pack_cache.as_deref_mut() {
+                Some(cache) => cache.digest(&path)?,
+                None => {
+                    return Err(format!(
+                        "Both file size and hash are required. Found: {file:?}"
+                    ));
+                }
+            },
+        };
+
+        let extracted_size = match (file.extracted_size, pack_cache.as_deref_mut()) {
+            (Some(extracted_size), _) => Some(extracted_size),
+            (None, Some(cache)) if file.source.is_none() => {
+                cache.extracted_size(&path, file_type)?
             }
+            _ => None,
         };
 
         let executable = if let Some(executable) = file.executable {
@@ -213,65 +455,142 @@ fn assemble(
             name: file.name,
             key: file.key,
             size,
+            extracted_size,
+            // N.B.: The boot-pack records fresh offsets as it lays out the scie.
+            offset: if reconstitute { None } else { file.offset },
+            chunks: if reconstitute { vec![] } else { file.chunks },
             hash,
             file_type,
             executable,
             eager_extract: file.eager_extract,
+            execute_in_place: file.execute_in_place,
             source: match file.source {
                 None => Source::Scie,
-                Some(binding_name) => Source::LoadBinding(binding_name),
+                Some(FileSource::Binding(binding_name)) => Source::LoadBinding(binding_name),
+                Some(FileSource::EntryRange {
+                    url,
+                    mirrors,
+                    offset,
+                }) => Source::EntryRange {
+                    url,
+                    mirrors,
+                    offset,
+                },
             },
+            source_url: file.source_url,
+            version: file.version,
+            license: file.license,
         });
     }
     Ok(files)
 }
 
 #[time("debug", "lift::{}")]
-pub(crate) fn load_scie(scie_path: &Path, scie_data: &[u8]) -> Result<(Jump, Lift), String> {
-    let end_of_zip = crate::zip::end_of_zip(scie_data, Config::MAXIMUM_CONFIG_SIZE)?;
-    let result = load(scie_path, &scie_data[end_of_zip..], false).map_err(|e| {
-        format!(
-            "The scie at {scie_path} has missing information in its lift manifest: {e}",
-            scie_path = scie_path.display()
-        )
+pub(crate) fn load_scie(scie_path: &Path, scie_data: &[u8]) -> Result<(Jump, Lift), JumpError> {
+    let lift_manifest = crate::zip::maximum_trailer_size()
+        .and_then(|maximum_trailer_size| crate::zip::lift_manifest(scie_data, maximum_trailer_size))
+        .map_err(JumpError::Config)?;
+    let result = load(
+        scie_path,
+        &lift_manifest.data,
+        UnknownFields::Ignore.or_strict_from_env(),
+        None,
+    )
+    .map_err(|e| {
+        e.context(|message| {
+            format!(
+                "The scie at {scie_path} has missing information in its lift manifest: {message}",
+                scie_path = scie_path.display()
+            )
+        })
     })?;
     match result {
-        (Some(jump), lift) => Ok((jump, lift)),
-        _ => Err(format!(
+        (Some(jump), mut lift) => {
+            // N.B.: The lift manifest region of the scie is the whole zip holding the lift manifest
+            // when it is stored in one.
+            lift.size = scie_data.len() - lift_manifest.offset;
+            Ok((jump, lift))
+        }
+        _ => Err(JumpError::Config(format!(
             "The scie at {path} has a lift manifest with no scie-jump information.",
             path = scie_path.display()
-        )),
+        ))),
     }
 }
 
 #[time("debug", "lift::{}")]
-pub fn load_lift(manifest_path: &Path) -> Result<(Option<Jump>, Lift), String> {
+pub fn load_lift(
+    manifest_path: &Path,
+    unknown_fields: UnknownFields,
+    pack_cache: &mut PackCache,
+) -> Result<(Option<Jump>, Lift), JumpError> {
     let data = std::fs::read(manifest_path).map_err(|e| {
-        format!(
-            "Failed to open lift manifest at {manifest}: {e}",
-            manifest = manifest_path.display()
+        JumpError::io(
+            format!(
+                "Failed to open lift manifest at {manifest}",
+                manifest = manifest_path.display()
+            ),
+            e,
         )
     })?;
-    load(manifest_path, &data, true)
+    load(manifest_path, &data, unknown_fields, Some(pack_cache))
+}
+
+/// Loads a lift manifest from `data`, resolving the files it names relative to `resolve_base`.
+#[time("debug", "lift::{}")]
+pub fn load_lift_data(
+    data: &[u8],
+    resolve_base: &Path,
+    unknown_fields: UnknownFields,
+    pack_cache: &mut PackCache,
+) -> Result<(Option<Jump>, Lift), JumpError> {
+    load_from(resolve_base, data, unknown_fields, Some(pack_cache))
 }
 
 fn load(
     manifest_path: &Path,
     data: &[u8],
-    reconstitute: bool,
-) -> Result<(Option<Jump>, Lift), String> {
-    let config = Config::parse(data)?;
+    unknown_fields: UnknownFields,
+    pack_cache: Option<&mut PackCache>,
+) -> Result<(Option<Jump>, Lift), JumpError> {
     let manifest_absolute_path = manifest_path.canonicalize().map_err(|e| {
-        format!(
-            "Failed to resolve an absolute path for the lift manifest {manifest}: {e}",
-            manifest = manifest_path.display()
+        JumpError::io(
+            format!(
+                "Failed to resolve an absolute path for the lift manifest {manifest}",
+                manifest = manifest_path.display()
+            ),
+            e,
         )
     })?;
     let resolve_base = manifest_absolute_path
         .parent()
         .unwrap_or_else(|| Path::new(""));
+    load_from(resolve_base, data, unknown_fields, pack_cache)
+}
+
+fn load_from(
+    resolve_base: &Path,
+    data: &[u8],
+    unknown_fields: UnknownFields,
+    pack_cache: Option<&mut PackCache>,
+) -> Result<(Option<Jump>, Lift), JumpError> {
+    let config = Config::parse(data, unknown_fields)?;
     let lift = config.scie.lift;
-    let files = assemble(resolve_base, lift.files, reconstitute)?;
+    if pack_cache.is_some() {
+        validate(&lift).map_err(JumpError::Config)?;
+    }
+    // N.B.: The zips the boot-pack creates from directories use NFC entry names unless the lift
+    // asks otherwise; so the same logical tree fingerprints the same on macOS and Linux.
+    let files = assemble(
+        resolve_base,
+        lift.files,
+        lift.unicode_normalization
+            .unwrap_or(UnicodeNormalization::Nfc),
+        lift.keep_junk.unwrap_or(false),
+        &lift.zips.clone().unwrap_or_default(),
+        pack_cache,
+    )
+    .map_err(JumpError::Config)?;
     Ok((
         config.scie.jump,
         Lift {
@@ -279,6 +598,14 @@ fn load(
             description: lift.description,
             base: lift.base,
             load_dotenv: lift.load_dotenv.unwrap_or(false),
+            boot_timing: lift.boot_timing.unwrap_or(false),
+            fetch: lift.fetch,
+            permissions: lift.permissions,
+            integrity: lift.integrity,
+            unicode_normalization: lift.unicode_normalization,
+            keep_junk: lift.keep_junk.unwrap_or(false),
+            zips: lift.zips,
+            tmp: lift.tmp,
             boot: lift.boot,
             size: data.len(),
             hash: fingerprint::digest(data),
@@ -287,3 +614,64 @@ fn load(
         },
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::tempdir;
+
+    use super::determine_file_type;
+    use crate::config::{ArchiveType, Compression, FileType};
+
+    #[test]
+    fn file_type_detection() {
+        let tmp = tempdir().unwrap();
+        let file_type = |name: &str, contents: &[u8]| {
+            let path = tmp.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            determine_file_type(&path).unwrap()
+        };
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        zip.start_file("Main.class", Default::default()).unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+        assert_eq!(FileType::Blob, file_type("app.jar", &zip));
+        assert_eq!(
+            FileType::Archive(ArchiveType::Zip),
+            file_type("app.zip", &zip)
+        );
+        assert_eq!(
+            FileType::Archive(ArchiveType::Zip),
+            file_type("mislabeled.tar.gz", &zip)
+        );
+
+        let mut tar = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o755);
+        header.set_size(9);
+        tar.append_data(&mut header, "run.sh", b"#!/bin/sh".as_slice())
+            .unwrap();
+        let tar = tar.into_inner().unwrap();
+        assert_eq!(
+            FileType::Archive(ArchiveType::Tar),
+            file_type("plain.tar.gz", &tar)
+        );
+        let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gz.write_all(&tar).unwrap();
+        assert_eq!(
+            FileType::Archive(ArchiveType::CompressedTar(Compression::Gzip)),
+            file_type("bundle", &gz.finish().unwrap())
+        );
+
+        assert_eq!(FileType::Blob, file_type("script.sh", b"#!/bin/sh\n"));
+        assert_eq!(
+            FileType::Archive(ArchiveType::CompressedTar(Compression::Xz)),
+            file_type("empty.tar.xz", b"")
+        );
+        assert_eq!(
+            FileType::Directory,
+            determine_file_type(tmp.path()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fmt::Write;
+
+use crate::config::{Cmd, Config, EnvVar};
+use crate::user_args;
+
+/// The format of the manual generated from a scie's lift manifest.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ManualFormat {
+    /// Plain text suitable for a terminal (`--scie-help`).
+    Text,
+    /// A troff man page (`--scie-help=man`).
+    Man,
+}
+
+struct Command<'a> {
+    name: &'a str,
+    default: bool,
+    cmd: &'a Cmd,
+}
+
+// N.B.: This follows the same rules as the boot command selection help page; so disabled commands
+// are left out and commands without a description stay hidden unless there is nothing else to
+// show.
+fn documented_commands(config: &Config) -> Vec<Command> {
+    let lift = &config.scie.lift;
+    let commands = lift
+        .boot
+        .commands
+        .iter()
+        .filter(|(_, cmd)| cmd.is_enabled())
+        .map(|(name, cmd)| Command {
+            name: if name.is_empty() {
+                lift.name.as_str()
+            } else {
+                name.as_str()
+            },
+            default: name.is_empty(),
+            cmd,
+        })
+        .collect::<Vec<_>>();
+    if commands
+        .iter()
+        .any(|command| command.default || command.cmd.description.is_some())
+    {
+        commands
+            .into_iter()
+            .filter(|command| command.default || command.cmd.description.is_some())
+            .collect()
+    } else {
+        commands
+    }
+}
+
+fn describe_env(name: &EnvVar, value: &Option<String>) -> (String, String) {
+    match (name, value) {
+        (EnvVar::Default(name), Some(value)) => (
+            name.to_string(),
+            format!("Defaults to `{value}`; set {name} to override."),
+        ),
+        (EnvVar::Replace(name), Some(value)) => (name.to_string(), format!("Set to `{value}`.")),
+        (EnvVar::Default(name) | EnvVar::Replace(name), None) => (
+            name.to_string(),
+            "Removed from the environment.".to_string(),
+        ),
+    }
+}
+
+fn text(config: &Config) -> String {
+    let lift = &config.scie.lift;
+    let commands = documented_commands(config);
+    let mut manual = lift.name.clone();
+    if let Some(description) = &lift.description {
+        let _ = write!(manual, ": {description}");
+    }
+    manual.push_str("\n\nCommands:\n");
+    for command in &commands {
+        let _ = write!(
+            manual,
+            "\n  {name}{default}\n",
+            name = command.name,
+            default = if command.default {
+                " (the default when SCIE_BOOT is not set)"
+            } else {
+                ""
+            }
+        );
+        if let Some(description) = &command.cmd.description {
+            let _ = writeln!(manual, "      {description}");
+        }
+        if let Some(user_args) = &command.cmd.user_args {
+            let _ = writeln!(
+                manual,
+                "      Usage: {usage}",
+                usage = user_args::usage(command.name, user_args)
+            );
+        }
+        if !command.cmd.env.is_empty() {
+            manual.push_str("      Environment:\n");
+            for (name, value) in &command.cmd.env {
+                let (name, description) = describe_env(name, value);
+                let _ = writeln!(manual, "        {name}: {description}");
+            }
+        }
+    }
+    manual.push_str(
+        "\nSelect a command by setting the SCIE_BOOT environment variable or by passing \
+        --scie-boot=<command>.\n",
+    );
+    manual
+}
+
+fn troff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
+
+fn man(config: &Config) -> String {
+    let lift = &config.scie.lift;
+    let name = troff_escape(&lift.name);
+    let mut manual = format!(
+        ".TH \"{title}\" 1\n.SH NAME\n{name}",
+        title = troff_escape(&lift.name.to_uppercase())
+    );
+    if let Some(description) = &lift.description {
+        let _ = write!(
+            manual,
+            " \\- {description}",
+            description = troff_escape(description)
+        );
+    }
+    let _ = write!(
+        manual,
+        "\n.SH SYNOPSIS\n.B {name}\n[\\fIargs\\fR ...]\n.SH COMMANDS\n"
+    );
+    for command in documented_commands(config) {
+        let _ = writeln!(manual, ".TP\n.B {name}", name = troff_escape(command.name));
+        if command.default {
+            manual.push_str("The default command when SCIE_BOOT is not set.\n");
+        }
+        if let Some(description) = &command.cmd.description {
+            let _ = writeln!(
+                manual,
+                "{description}",
+                description = troff_escape(description)
+            );
+        }
+        if let Some(user_args) = &command.cmd.user_args {
+            let _ = writeln!(
+                manual,
+                ".br\nUsage: {usage}",
+                usage = troff_escape(&user_args::usage(command.name, user_args))
+            );
+        }
+        if !command.cmd.env.is_empty() {
+            manual.push_str(".RS\n");
+            for (name, value) in &command.cmd.env {
+                let (name, description) = describe_env(name, value);
+                let _ = writeln!(
+                    manual,
+                    ".TP\n.B {name}\n{description}",
+                    name = troff_escape(&name),
+                    description = troff_escape(&description)
+                );
+            }
+            manual.push_str(".RE\n");
+        }
+    }
+    manual.push_str(
+        ".SH ENVIRONMENT\n.TP\n.B SCIE_BOOT\nSelects the command to run; like passing \
+        \\-\\-scie\\-boot=<command>.\n",
+    );
+    manual
+}
+
+/// Generates a manual for a scie's commands from the names, descriptions and env vars recorded in
+/// its lift manifest.
+pub fn manual(config: &Config, format: ManualFormat) -> String {
+    match format {
+        ManualFormat::Text => text(config),
+        ManualFormat::Man => man(config),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{manual, ManualFormat};
+    use crate::config::{Config, UnknownFields};
+
+    fn config(commands: &str) -> Config {
+        Config::parse(
+            format!(
+                r#"{{"scie":{{"lift":{{"name":"tool","description":"Does -things.","files":[],
+                "boot":{{"commands":{commands}}}}}}}}}"#
+            )
+            .as_bytes(),
+            UnknownFields::Deny,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn text() {
+        let config = config(
+            r#"{
+                "": {"exe": "{python}", "env": {"=PATH": "/bin", "LEVEL": "info", "=TMP": null}},
+                "shell": {"exe": "/bin/sh", "description": "Start a shell.",
+                    "user_args": {"max": 1, "positional": [{"name": "SCRIPT"}]}},
+                "hidden": {"exe": "/bin/true"}
+            }"#,
+        );
+        assert_eq!(
+            "\
+tool: Does -things.
+
+Commands:
+
+  tool (the default when SCIE_BOOT is not set)
+      Environment:
+        PATH: Set to `/bin`.
+        LEVEL: Defaults to `info`; set LEVEL to override.
+        TMP: Removed from the environment.
+
+  shell
+      Start a shell.
+      Usage: shell [SCRIPT]
+
+Select a command by setting the SCIE_BOOT environment variable or by passing --scie-boot=<command>.
+",
+            manual(&config, ManualFormat::Text)
+        );
+    }
+
+    #[test]
+    fn hidden_commands_shown_when_none_documented() {
+        let config = config(r#"{"a": {"exe": "/bin/a"}, "b": {"exe": "/bin/b"}}"#);
+        let text = manual(&config, ManualFormat::Text);
+        assert!(text.contains("\n  a\n"), "{text}");
+        assert!(text.contains("\n  b\n"), "{text}");
+    }
+
+    #[test]
+    fn disabled_commands() {
+        let config = config(
+            r#"{
+                "shell": {"exe": "/bin/sh", "description": "Start a shell."},
+                "beta": {"exe": "/bin/beta", "description": "Try the beta.",
+                    "enabled_if": {"env": "__SCIE_MANUAL_BETA__"}}
+            }"#,
+        );
+        std::env::remove_var("__SCIE_MANUAL_BETA__");
+        let text = manual(&config, ManualFormat::Text);
+        assert!(text.contains("\n  shell\n"), "{text}");
+        assert!(!text.contains("beta"), "{text}");
+
+        std::env::set_var("__SCIE_MANUAL_BETA__", "0");
+        assert!(!manual(&config, ManualFormat::Text).contains("beta"));
+
+        std::env::set_var("__SCIE_MANUAL_BETA__", "1");
+        let text = manual(&config, ManualFormat::Text);
+        assert!(text.contains("\n  beta\n      Try the beta.\n"), "{text}");
+    }
+
+    #[test]
+    fn man() {
+        let config = config(
+            r#"{"shell": {"exe": "/bin/sh", "description": ".Start a shell.",
+                "env": {"LEVEL": "info"}}}"#,
+        );
+        assert_eq!(
+            "\
+.TH \"TOOL\" 1
+.SH NAME
+tool \\- Does \\-things.
+.SH SYNOPSIS
+.B tool
+[\\fIargs\\fR ...]
+.SH COMMANDS
+.TP
+.B shell
+\\&.Start a shell.
+.RS
+.TP
+.B LEVEL
+Defaults to `info`; set LEVEL to override.
+.RE
+.SH ENVIRONMENT
+.TP
+.B SCIE_BOOT
+Selects the command to run; like passing \\-\\-scie\\-boot=<command>.
+",
+            manual(&config, ManualFormat::Man)
+        );
+    }
+}
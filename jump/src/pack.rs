@@ -0,0 +1,1753 @@
+// Copyright 2022 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use logging_timer::time;
+use serde_json::json;
+use tempfile::TempDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::config::{ArchiveType, Chunk, Config, FileType, Fmt, Jump, UnknownFields};
+use crate::embed::{use_windows_gui_subsystem, Embedding};
+use crate::footer::scie_magic;
+use crate::lift::{packed_path, File, Lift, PackCache, Source};
+use crate::zip::LIFT_ZIP_ENTRY;
+use crate::{
+    check_is_zip, chunk, create_options, fingerprint, load_lift, load_lift_data, JumpError,
+};
+
+/// Loads the scie-jump binary at `path`, failing if it is not a scie-jump or is a scie.
+pub fn load_scie_jump(path: &Path) -> Result<Jump, JumpError> {
+    let file = std::fs::File::open(path).map_err(|e| {
+        JumpError::io(
+            format!("Failed to open {path} for reading", path = path.display()),
+            e,
+        )
+    })?;
+    let data = crate::map(&file)
+        .map_err(|e| JumpError::io(format!("Failed to read {path}", path = path.display()), e))?;
+    crate::jump::load(&data, path)?.ok_or_else(|| {
+        JumpError::Config(format!(
+            "The file at {path} is not a scie-jump binary.",
+            path = path.display()
+        ))
+    })
+}
+
+/// Loads the lift manifest at `path`, or at `path/lift.json` if `path` is a directory, checking it
+/// agrees with the given scie-jump.
+#[time("debug", "pack::{}")]
+pub fn load_manifest(
+    path: &Path,
+    jump: &Jump,
+    unknown_fields: UnknownFields,
+    pack_cache: &mut PackCache,
+) -> Result<(Lift, PathBuf), JumpError> {
+    let manifest_path = if path.is_dir() {
+        path.join("lift.json")
+    } else {
+        path.to_path_buf()
+    };
+    if !manifest_path.is_file() {
+        return Err(JumpError::Config(format!(
+            "The given path does not contain a lift manifest: {path}",
+            path = path.display()
+        )));
+    }
+    let (maybe_jump, lift) = load_lift(&manifest_path, unknown_fields, pack_cache)?;
+    check_jump(maybe_jump, jump, &manifest_path)?;
+    Ok((lift, manifest_path))
+}
+
+fn check_jump(
+    maybe_jump: Option<Jump>,
+    jump: &Jump,
+    manifest_path: &Path,
+) -> Result<(), JumpError> {
+    if let Some(ref configured_jump) = maybe_jump {
+        if jump != configured_jump {
+            return Err(JumpError::Config(format!(
+                "The lift manifest {manifest} specifies a scie jump binary of \
+                    {configured_jump:?} that does not match the current of {jump:?}.",
+                manifest = manifest_path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A lift manifest read by [`read_manifest`].
+pub struct ReadManifest {
+    pub lift: Lift,
+    /// The path to pass to [`pack`] as the lift manifest path.
+    pub manifest: PathBuf,
+    /// The directory the files were unpacked to if the manifest was read from a tar stream. It is
+    /// deleted when dropped; so it must be kept until the scie is packed.
+    pub files: Option<TempDir>,
+}
+
+/// Reads a lift manifest from `stream`, checking it agrees with the given scie-jump.
+///
+/// The stream can hold the lift manifest JSON itself, in which case the files it names are
+/// resolved relative to `resolve_base`. The stream can also hold a tar archive of a `lift.json`
+/// lift manifest along with the files it names, as produced by `tar -c lift.json app.zip` for
+/// example, in which case the files are taken from the archive.
+#[time("debug", "pack::{}")]
+pub fn read_manifest(
+    mut stream: impl Read,
+    resolve_base: &Path,
+    jump: &Jump,
+    unknown_fields: UnknownFields,
+    pack_cache: &mut PackCache,
+) -> Result<ReadManifest, JumpError> {
+    let mut data = vec![];
+    stream
+        .read_to_end(&mut data)
+        .map_err(|e| JumpError::io("Failed to read the lift manifest stream", e))?;
+    // N.B.: A tar archive starts with a 512 byte header whose magic is at offset 257.
+    if data.get(257..262) == Some(b"ustar") {
+        let files = TempDir::new()
+            .map_err(|e| JumpError::io("Failed to create a directory to unpack files to", e))?;
+        tar::Archive::new(Cursor::new(data))
+            .unpack(files.path())
+            .map_err(|e| JumpError::io("Failed to unpack the lift manifest tar stream", e))?;
+        let (lift, manifest) = load_manifest(files.path(), jump, unknown_fields, pack_cache)
+            .map_err(|e| {
+                e.context(|message| {
+                    format!("{message}\nThe tar stream should include a lift.json file.")
+                })
+            })?;
+        return Ok(ReadManifest {
+            lift,
+            manifest,
+            files: Some(files),
+        });
+    }
+    let manifest = resolve_base.join("-");
+    let (maybe_jump, lift) = load_lift_data(&data, resolve_base, unknown_fields, pack_cache)?;
+    check_jump(maybe_jump, jump, &manifest)?;
+    Ok(ReadManifest {
+        lift,
+        manifest,
+        files: None,
+    })
+}
+
+// The path `finalize_executable` leaves the executable written to `path` at.
+#[cfg(windows)]
+fn executable_path(path: &Path) -> Result<PathBuf, JumpError> {
+    use std::ffi::OsStr;
+    if let Some(std::env::consts::EXE_EXTENSION) = path.extension().and_then(OsStr::to_str) {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(path.with_file_name(format!(
+            "{file_name}.{ext}",
+            file_name = path.file_name().and_then(OsStr::to_str).ok_or_else(|| {
+                JumpError::Other(format!(
+                    "Failed to determine the file name of {path}",
+                    path = path.display()
+                ))
+            })?,
+            ext = std::env::consts::EXE_EXTENSION
+        )))
+    }
+}
+
+#[cfg(not(windows))]
+fn executable_path(path: &Path) -> Result<PathBuf, JumpError> {
+    Ok(path.to_path_buf())
+}
+
+#[cfg(windows)]
+pub(crate) fn finalize_executable(path: &Path) -> Result<PathBuf, JumpError> {
+    let exe = executable_path(path)?;
+    if exe != path {
+        std::fs::rename(path, &exe).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to rename executable from {path} to {exe}",
+                    path = path.display(),
+                    exe = exe.display()
+                ),
+                e,
+            )
+        })?;
+    }
+    Ok(exe)
+}
+
+#[cfg(unix)]
+pub(crate) fn finalize_executable(path: &Path) -> Result<PathBuf, JumpError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut perms = std::fs::metadata(path)
+        .map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to access permissions metadata for {binary}",
+                    binary = path.display()
+                ),
+                e,
+            )
+        })?
+        .permissions();
+    perms.set_mode(0o755);
+    std::fs::set_permissions(path, perms).map_err(|e| {
+        JumpError::io(
+            format!(
+                "Failed to mark {binary} as executable",
+                binary = path.display()
+            ),
+            e,
+        )
+    })?;
+    Ok(path.to_path_buf())
+}
+
+// N.B.: Platforms like wasm32-wasi have no notion of an executable file; so there is nothing to do.
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn finalize_executable(path: &Path) -> Result<PathBuf, JumpError> {
+    Ok(path.to_path_buf())
+}
+
+/// Serializes the lift manifest for writing to the tail of a scie.
+pub(crate) fn serialize_lift_manifest(
+    jump: &Jump,
+    lift: Lift,
+    single_line: bool,
+) -> Result<Vec<u8>, JumpError> {
+    let config = crate::config(jump.clone(), lift);
+    // We configure the lift manifest format to allow for easiest inspection via standard tools.
+    // In the single line case in particular, this configuration allows for inspection via
+    // `tail -2 scie | head -1 | jq .` on systems with these common tools; the last line being the
+    // one identifying the scie.
+    let fmt = Fmt::new()
+        .pretty(!single_line)
+        .leading_newline(true)
+        .trailing_newline(true);
+    let mut lift_manifest = vec![];
+    config.serialize(&mut lift_manifest, fmt)?;
+    Ok(lift_manifest)
+}
+
+/// Stores the lift manifest as the `__lift__.json` entry of a zip whose comment is the line
+/// identifying the scie.
+pub(crate) fn lift_zip(lift_manifest: &[u8], scie_magic: String) -> Result<Vec<u8>, JumpError> {
+    let mut zip_writer = ZipWriter::new(Cursor::new(vec![]));
+    zip_writer.set_comment(scie_magic);
+    zip_writer
+        .start_file(
+            LIFT_ZIP_ENTRY,
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+        .map_err(|e| {
+            JumpError::Other(format!(
+                "Failed to start the {LIFT_ZIP_ENTRY} zip entry: {e}"
+            ))
+        })?;
+    zip_writer
+        .write_all(lift_manifest)
+        .map_err(|e| JumpError::io(format!("Failed to write the {LIFT_ZIP_ENTRY} zip entry"), e))?;
+    let zip = zip_writer
+        .finish()
+        .map_err(|e| JumpError::Other(format!("Failed to finalize the lift manifest zip: {e}")))?;
+    Ok(zip.into_inner())
+}
+
+struct ScieTote {
+    zip_file: std::fs::File,
+    zip_writer: ZipWriter<std::fs::File>,
+}
+
+impl ScieTote {
+    fn new() -> Result<Self, JumpError> {
+        let zip_file = tempfile::tempfile().map_err(|e| {
+            JumpError::io(
+                "Failed to create a temporary file to built the scie-tote with",
+                e,
+            )
+        })?;
+        let zip_writer = ZipWriter::new(
+            zip_file
+                .try_clone()
+                .map_err(|e| JumpError::io("Failed to dup temporary file fd", e))?,
+        );
+        Ok(Self {
+            zip_file,
+            zip_writer,
+        })
+    }
+}
+
+pub(crate) struct PackedFile {
+    pub(crate) name: String,
+    pub(crate) file_type: FileType,
+    pub(crate) size: usize,
+    pub(crate) hash: String,
+    pub(crate) offset: Option<usize>,
+    pub(crate) scie_tote: bool,
+    pub(crate) chunks: usize,
+}
+
+// The lift manifest trailer a dry run would have written.
+pub(crate) struct Trailer {
+    lift_manifest: Vec<u8>,
+    scie_magic: String,
+    in_zip: bool,
+}
+
+/// A description of a scie written by [`pack`], or that would be written by [`pack_dry_run`].
+pub struct Packed {
+    pub manifest: PathBuf,
+    pub binary: PathBuf,
+    pub(crate) size: u64,
+    pub(crate) jump: Jump,
+    pub(crate) files: Vec<PackedFile>,
+    pub(crate) lift_manifest_offset: usize,
+    pub(crate) lift_manifest_size: usize,
+    pub(crate) lift_manifest_hash: String,
+    pub(crate) dry_run: Option<Trailer>,
+}
+
+impl Packed {
+    /// Describes the scie, including the offsets and fingerprints of its files and its lift
+    /// manifest, as a JSON object.
+    ///
+    /// The description of a dry run also includes the lift manifest and the line identifying the
+    /// scie that end it.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut description = json!({
+            "manifest": self.manifest,
+            "scie": self.binary,
+            "size": self.size,
+            "jump": {
+                "size": self.jump.size,
+                "version": self.jump.version,
+            },
+            "files": self.files.iter().map(|file| {
+                let mut description = json!({
+                    "name": file.name,
+                    "type": file.file_type,
+                    "size": file.size,
+                    "hash": file.hash,
+                    "offset": file.offset,
+                    "scie_tote": file.scie_tote,
+                });
+                if file.chunks > 0 {
+                    description["chunks"] = json!(file.chunks);
+                }
+                description
+            }).collect::<Vec<_>>(),
+            "lift": {
+                "offset": self.lift_manifest_offset,
+                "size": self.lift_manifest_size,
+                "hash": self.lift_manifest_hash,
+            },
+        });
+        if let Some(trailer) = &self.dry_run {
+            description["dry_run"] = json!(true);
+            description["lift"]["manifest"] = json!(trailer.lift_manifest());
+            description["lift"]["magic"] = json!(trailer.scie_magic);
+            description["lift"]["in_zip"] = json!(trailer.in_zip);
+        }
+        description
+    }
+
+    /// Returns `true` if this describes the scie a dry run would have written.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.is_some()
+    }
+
+    /// Describes the scie for humans: its size and files followed by the lift manifest trailer a
+    /// dry run would have written.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{manifest}: {binary} ({size} bytes)\n",
+            manifest = self.manifest.display(),
+            binary = self.binary.display(),
+            size = self.size
+        );
+        summary.push_str(&format!(
+            "  scie-jump {version}: {size} bytes\n",
+            version = self.jump.version,
+            size = self.jump.size
+        ));
+        for file in &self.files {
+            let location = match file.offset {
+                Some(offset) => format!("at offset {offset}"),
+                None if file.chunks > 0 => format!("in {chunks} chunks", chunks = file.chunks),
+                None if file.scie_tote => "in the scie-tote".to_string(),
+                None => "not stored in the scie".to_string(),
+            };
+            summary.push_str(&format!(
+                "  {name}: {size} bytes {location} sha256:{hash}\n",
+                name = file.name,
+                size = file.size,
+                hash = file.hash
+            ));
+        }
+        summary.push_str(&format!(
+            "  lift manifest: {size} bytes at offset {offset} sha256:{hash}\n",
+            size = self.lift_manifest_size,
+            offset = self.lift_manifest_offset,
+            hash = self.lift_manifest_hash
+        ));
+        if let Some(trailer) = &self.dry_run {
+            if trailer.in_zip {
+                summary.push_str(&format!(
+                    "  stored as the {LIFT_ZIP_ENTRY} entry of a zip:\n"
+                ));
+            }
+            summary.push_str(&trailer.lift_manifest());
+            summary.push_str(&trailer.scie_magic);
+        }
+        summary
+    }
+
+    /// Calculates the SHA-256 fingerprint of the scie.
+    pub fn digest(&self) -> Result<String, JumpError> {
+        let (_, hash) = fingerprint::digest_file(&self.binary)?;
+        Ok(hash)
+    }
+
+    fn sibling(&self, extension: &str) -> PathBuf {
+        let mut path = self.binary.as_os_str().to_os_string();
+        path.push(extension);
+        PathBuf::from(path)
+    }
+
+    fn write_sibling(&self, extension: &str, contents: &[u8]) -> Result<PathBuf, JumpError> {
+        let path = self.sibling(extension);
+        std::fs::write(&path, contents).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to write {path} for {scie}",
+                    path = path.display(),
+                    scie = self.binary.display()
+                ),
+                e,
+            )
+        })?;
+        Ok(path)
+    }
+
+    /// Writes `<scie>.sha256` in the format `sha256sum --check` expects given the scie's `hash`.
+    pub fn write_sha256(&self, hash: &str) -> Result<PathBuf, JumpError> {
+        let name = self
+            .binary
+            .file_name()
+            .unwrap_or(self.binary.as_os_str())
+            .to_string_lossy();
+        self.write_sibling(".sha256", format!("{hash}  {name}\n").as_bytes())
+    }
+
+    /// Writes `<scie>.provenance.json` describing how the scie with the given `hash` was packed.
+    ///
+    /// This is the same description [`Packed::to_json`] produces with the scie's hash added.
+    pub fn write_provenance(&self, hash: &str) -> Result<PathBuf, JumpError> {
+        let mut provenance = self.to_json();
+        provenance["hash"] = json!(hash);
+        let mut contents = serde_json::to_vec_pretty(&provenance).map_err(|e| {
+            JumpError::Other(format!("Failed to serialize the provenance of a scie: {e}"))
+        })?;
+        contents.push(b'\n');
+        self.write_sibling(".provenance.json", &contents)
+    }
+
+    /// Checks the sizes of the files stored in the scie and of the scie as a whole against the
+    /// given limits.
+    ///
+    /// Sizes over a warning limit are logged. Sizes over a maximum are an error listing all of
+    /// them unless the limits allow large scies, in which case they are only logged too.
+    pub fn check_sizes(&self, limits: &SizeLimits) -> Result<(), JumpError> {
+        // N.B.: The files in a scie-tote are checked individually; so the scie-tote that holds
+        // them, which is always the last file, is not.
+        let stored = if self.files.iter().any(|file| file.scie_tote) {
+            &self.files[..self.files.len() - 1]
+        } else {
+            &self.files[..]
+        };
+        let mut warnings = vec![];
+        let mut errors = vec![];
+        let mut check =
+            |what: String, size: u64, warn: Option<u64>, max: Option<u64>| match (warn, max) {
+                (_, Some(max)) if size > max => errors.push(format!(
+                    "{what} is {size} bytes; over the {max} byte maximum."
+                )),
+                (Some(warn), _) if size > warn => warnings.push(format!(
+                    "{what} is {size} bytes; over the {warn} byte warning limit."
+                )),
+                _ => {}
+            };
+        for file in stored
+            .iter()
+            .filter(|file| file.offset.is_some() || file.scie_tote || file.chunks > 0)
+        {
+            check(
+                format!("The file {name}", name = file.name),
+                file.size as u64,
+                limits.warn_file_size,
+                limits.max_file_size,
+            );
+        }
+        check(
+            format!("The scie {binary}", binary = self.binary.display()),
+            self.size,
+            limits.warn_scie_size,
+            limits.max_scie_size,
+        );
+        for warning in warnings {
+            warn!("{warning}");
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        if limits.allow_large {
+            for error in errors {
+                warn!("{error}");
+            }
+            return Ok(());
+        }
+        Err(JumpError::Config(format!(
+            "The scie packed from {manifest} is larger than allowed:\n{errors}\n\
+            Check that nothing was included by accident, like debug symbols, or else pass \
+            --allow-large to pack it anyway.",
+            manifest = self.manifest.display(),
+            errors = errors
+                .iter()
+                .map(|error| format!("+ {error}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )))
+    }
+}
+
+/// Limits on the sizes of the files stored in a scie and of the scie as a whole.
+///
+/// These guard against accidentally packing something huge; like a toolchain with its debug
+/// symbols. See [`Packed::check_sizes`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SizeLimits {
+    pub warn_file_size: Option<u64>,
+    pub max_file_size: Option<u64>,
+    pub warn_scie_size: Option<u64>,
+    pub max_scie_size: Option<u64>,
+    /// Downgrades exceeding a maximum to a warning.
+    pub allow_large: bool,
+}
+
+impl SizeLimits {
+    /// Parses a size given in bytes or with a binary unit suffix; e.g.: `1048576`, `512K`, `64MiB`
+    /// or `2G`.
+    pub fn parse_size(value: &str) -> Result<u64, JumpError> {
+        let digits = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(digits);
+        let shift = match unit.to_ascii_lowercase().as_str() {
+            "" | "b" => 0,
+            "k" | "kb" | "kib" => 10,
+            "m" | "mb" | "mib" => 20,
+            "g" | "gb" | "gib" => 30,
+            "t" | "tb" | "tib" => 40,
+            _ => {
+                return Err(JumpError::Config(format!(
+                    "The size {value:?} has an unknown unit {unit:?}; expected one of B, K, M, G \
+                    or T with an optional iB or B suffix."
+                )))
+            }
+        };
+        number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(1 << shift))
+            .ok_or_else(|| {
+                JumpError::Config(format!(
+                    "The size {value:?} is not a whole number of bytes that fits in 64 bits."
+                ))
+            })
+    }
+}
+
+// The page size on most platforms; aligning blobs to this boundary allows them to be mmapped
+// directly from the scie.
+pub const PAGE_ALIGNMENT: usize = 4096;
+
+fn pad(
+    binary: &mut std::fs::File,
+    offset: usize,
+    alignment: Option<usize>,
+) -> Result<usize, JumpError> {
+    let Some(alignment) = alignment else {
+        return Ok(0);
+    };
+    let padding = (alignment - offset % alignment) % alignment;
+    binary
+        .write_all(&vec![0; padding])
+        .map_err(|e| JumpError::io(format!("Failed to pad to a {alignment} byte boundary"), e))?;
+    Ok(padding)
+}
+
+// Stores the file as content-defined chunks, each starting on a page boundary and each stored only
+// once per scie. Returns `false`, having written nothing, if the file is too small to cut.
+fn write_chunks(
+    binary: &mut std::fs::File,
+    binary_path: &Path,
+    blob: &std::fs::File,
+    path: &Path,
+    file: &mut File,
+    offset: &mut usize,
+    stored_chunks: &mut HashMap<String, usize>,
+) -> Result<bool, JumpError> {
+    let data = crate::map(blob).map_err(|e| {
+        JumpError::io(
+            format!("Failed to read {path} to chunk it", path = path.display()),
+            e,
+        )
+    })?;
+    let ranges = chunk::cut(&data);
+    if ranges.len() < 2 {
+        return Ok(false);
+    }
+    for range in ranges {
+        let bytes = &data[range];
+        let hash = fingerprint::digest(bytes);
+        let chunk_offset = match stored_chunks.get(&hash) {
+            Some(chunk_offset) => *chunk_offset,
+            None => {
+                *offset += pad(binary, *offset, Some(PAGE_ALIGNMENT))?;
+                binary.write_all(bytes).map_err(|e| {
+                    JumpError::io(
+                        format!(
+                            "Failed to append a chunk of {src} / {name} to {binary}",
+                            src = path.display(),
+                            name = file.name,
+                            binary = binary_path.display()
+                        ),
+                        e,
+                    )
+                })?;
+                stored_chunks.insert(hash.clone(), *offset);
+                *offset += bytes.len();
+                *offset - bytes.len()
+            }
+        };
+        file.chunks.push(Chunk {
+            offset: chunk_offset,
+            size: bytes.len(),
+            hash,
+        });
+    }
+    file.offset = None;
+    Ok(true)
+}
+
+// Makes the offsets of the zip file that ends at `end` relative to the start of the scie. Since
+// this changes the zip's bytes, its hash is re-calculated.
+fn adjust_last_zip_offsets(
+    binary: &mut std::fs::File,
+    binary_path: &Path,
+    lift: &mut Lift,
+    packed_files: &mut [PackedFile],
+    end: usize,
+) -> Result<(), JumpError> {
+    let Some((file, start)) = lift
+        .files
+        .iter_mut()
+        .rev()
+        .filter(|file| Source::Scie == file.source)
+        .find_map(|file| file.offset.map(|offset| (file, offset)))
+        .filter(|(file, start)| start + file.size == end)
+    else {
+        return Err(JumpError::Config(format!(
+            "Found no file ending {binary} to adjust the zip offsets of.",
+            binary = binary_path.display()
+        )));
+    };
+    if !matches!(
+        file.file_type,
+        FileType::Archive(ArchiveType::Zip) | FileType::Directory
+    ) {
+        return Err(JumpError::Config(format!(
+            "Cannot adjust the zip offsets of {binary} since its last file, {name}, is not a zip \
+            the scie-jump extracts. Pack with --lift-in-zip to adjust the offsets of the lift \
+            manifest zip instead.",
+            binary = binary_path.display(),
+            name = file.name
+        )));
+    }
+    crate::zip::adjust_offsets(binary, start as u64, end as u64).map_err(|e| {
+        JumpError::Other(format!(
+            "Failed to adjust the zip offsets of {name} in {binary}: {e}",
+            name = file.name,
+            binary = binary_path.display()
+        ))
+    })?;
+    binary
+        .seek(SeekFrom::Start(start as u64))
+        .map_err(|e| JumpError::io(format!("Failed to seek to {name}", name = file.name), e))?;
+    let (_, hash) = fingerprint::digest_reader((&*binary).take(file.size as u64))?;
+    binary
+        .seek(SeekFrom::End(0))
+        .map_err(|e| JumpError::io("Failed to seek to the end of the scie", e))?;
+    if let Some(packed_file) = packed_files
+        .iter_mut()
+        .find(|packed_file| packed_file.offset == Some(start))
+    {
+        packed_file.hash = hash.clone();
+    }
+    file.hash = hash;
+    Ok(())
+}
+
+// Reads the first `jump.size` bytes of the scie-jump binary; switching it to the Windows GUI
+// subsystem if `gui` and it is a Windows scie-jump.
+fn read_scie_jump(scie_jump_path: &Path, jump: &Jump, gui: bool) -> Result<Vec<u8>, JumpError> {
+    let mut scie_jump = std::fs::read(scie_jump_path).map_err(|e| {
+        JumpError::io(
+            format!(
+                "Failed to read scie-jump binary {path}",
+                path = scie_jump_path.display()
+            ),
+            e,
+        )
+    })?;
+    scie_jump.truncate(jump.size);
+    if gui {
+        use_windows_gui_subsystem(&mut scie_jump).map_err(JumpError::Other)?;
+    }
+    Ok(scie_jump)
+}
+
+impl Trailer {
+    fn lift_manifest(&self) -> String {
+        String::from_utf8_lossy(&self.lift_manifest).into_owned()
+    }
+}
+
+/// How [`pack`] lays out a scie.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PackOptions {
+    /// Writes the lift manifest as a single line of JSON instead of pretty-printing it.
+    pub single_line: bool,
+    /// Aligns the files stored in the scie to this many bytes.
+    pub alignment: Option<usize>,
+    /// Stores the lift manifest as the `__lift__.json` entry of a zip following the files instead
+    /// of as a trailer; so the last file need not be a zip.
+    pub lift_in_zip: bool,
+    /// Stores the files and lift manifest in a section (PE) or segment (Mach-O) added to the
+    /// scie-jump executable so the scie can be code signed.
+    pub embed: bool,
+    /// Makes the offsets of the zip the scie ends with relative to the start of the scie so that
+    /// zip tools can open the scie as a whole.
+    pub adjust_zip_offsets: bool,
+    /// Stores large files as content-defined chunks on page boundaries, each stored once; so
+    /// releases of the scie dedupe and diff well. The last file is only chunked along with
+    /// `lift_in_zip`.
+    pub chunk: bool,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        Self {
+            single_line: true,
+            alignment: None,
+            lift_in_zip: false,
+            embed: false,
+            adjust_zip_offsets: false,
+            chunk: false,
+        }
+    }
+}
+
+/// Packs the lift into a scie executable named after it in `dest_dir`, using the first `jump.size`
+/// bytes of the scie-jump binary at `scie_jump_path` as its tip and laying it out as the `options`
+/// direct.
+///
+/// If all the boot commands are marked `gui`, a Windows scie-jump tip is switched to the GUI
+/// subsystem.
+#[time("debug", "pack::{}")]
+pub fn pack(
+    lift: Lift,
+    manifest_path: &Path,
+    jump: &Jump,
+    scie_jump_path: &Path,
+    dest_dir: &Path,
+    options: &PackOptions,
+) -> Result<Packed, JumpError> {
+    pack_scie(
+        lift,
+        manifest_path,
+        jump,
+        scie_jump_path,
+        dest_dir,
+        options,
+        false,
+    )
+}
+
+/// Lays the lift out as [`pack`] would without writing the scie to `dest_dir`.
+///
+/// The scie is assembled in an anonymous temporary file that is discarded; so the description
+/// returned has the exact sizes, hashes and offsets of the files and the lift manifest trailer
+/// `pack` would write.
+#[time("debug", "pack::{}")]
+pub fn pack_dry_run(
+    lift: Lift,
+    manifest_path: &Path,
+    jump: &Jump,
+    scie_jump_path: &Path,
+    dest_dir: &Path,
+    options: &PackOptions,
+) -> Result<Packed, JumpError> {
+    pack_scie(
+        lift,
+        manifest_path,
+        jump,
+        scie_jump_path,
+        dest_dir,
+        options,
+        true,
+    )
+}
+
+fn pack_scie(
+    mut lift: Lift,
+    manifest_path: &Path,
+    jump: &Jump,
+    scie_jump_path: &Path,
+    dest_dir: &Path,
+    options: &PackOptions,
+    dry_run: bool,
+) -> Result<Packed, JumpError> {
+    let PackOptions {
+        single_line,
+        alignment,
+        lift_in_zip,
+        embed,
+        adjust_zip_offsets,
+        chunk,
+    } = *options;
+    if adjust_zip_offsets && embed {
+        return Err(JumpError::Config(
+            "The zip offsets of a scie cannot be adjusted when its files are embedded in an \
+            executable section or segment."
+                .to_string(),
+        ));
+    }
+    let gui = !lift.boot.commands.is_empty() && lift.boot.commands.values().all(|cmd| cmd.gui);
+    if !gui && lift.boot.commands.values().any(|cmd| cmd.gui) {
+        warn!(
+            "Not all of the commands of {name} are marked gui; so it will keep using the console \
+            subsystem on Windows.",
+            name = lift.name
+        );
+    }
+    let embedding = if embed {
+        Some(Embedding::new(read_scie_jump(scie_jump_path, jump, gui)?).map_err(JumpError::Other)?)
+    } else {
+        None
+    };
+    let jump = &Jump {
+        size: embedding
+            .as_ref()
+            .map(Embedding::payload_offset)
+            .unwrap_or(jump.size),
+        min_version: Some(crate::jump::min_version()),
+        ..jump.clone()
+    };
+
+    let binary_path = dest_dir.join(&lift.name);
+    let mut binary = if dry_run {
+        tempfile::tempfile().map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to create a temporary file to lay out {path} in",
+                    path = binary_path.display()
+                ),
+                e,
+            )
+        })?
+    } else {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&binary_path)
+            .map_err(|e| {
+                JumpError::io(
+                    format!(
+                        "Failed to open binary {path} for writing {lift:?}",
+                        path = binary_path.display(),
+                    ),
+                    e,
+                )
+            })?
+    };
+    if let Some(embedding) = &embedding {
+        binary.write_all(embedding.tip()).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to write the scie-jump tip to {binary}",
+                    binary = binary_path.display()
+                ),
+                e,
+            )
+        })?;
+    } else if gui {
+        binary
+            .write_all(&read_scie_jump(scie_jump_path, jump, gui)?)
+            .map_err(|e| {
+                JumpError::io(
+                    format!(
+                        "Failed to write the scie-jump tip to {binary}",
+                        binary = binary_path.display()
+                    ),
+                    e,
+                )
+            })?;
+    } else {
+        let mut scie_jump = std::fs::File::open(scie_jump_path)
+            .map_err(|e| {
+                JumpError::io(
+                    format!(
+                        "Failed to open scie-jump binary {path} for writing to the tip of {binary}",
+                        path = scie_jump_path.display(),
+                        binary = binary_path.display()
+                    ),
+                    e,
+                )
+            })?
+            .take(jump.size as u64);
+        std::io::copy(&mut scie_jump, &mut binary).map_err(|e| {
+            JumpError::io(
+                format!(
+                "Failed to write first {scie_jump_size} bytes of the scie-jump binary {path} to \
+            {binary}",
+                scie_jump_size = jump.size,
+                path = scie_jump_path.display(),
+                binary = binary_path.display()
+            ),
+                e,
+            )
+        })?;
+    }
+    let mut offset = jump.size;
+    let mut packed_files = vec![];
+    let resolve_base = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    let zips = lift.zips.clone().unwrap_or_default();
+    let mut scie_tote: Option<ScieTote> = None;
+    if let Some(last_file) = lift.files.last().filter(|_| !lift_in_zip) {
+        let path = packed_path(resolve_base, &zips, last_file).map_err(JumpError::Other)?;
+        if check_is_zip(&path).is_err() {
+            if let Some(file) = lift.files.iter().find(|file| file.execute_in_place) {
+                return Err(JumpError::Config(format!(
+                    "The file {name} is marked execute_in_place but cannot be since all files \
+                    will be stored in a scie-tote. Make the last file in the lift manifest a \
+                    directory or zip to avoid the scie-tote.",
+                    name = file.name
+                )));
+            }
+            if chunk {
+                warn!(
+                    "The files of {name} are stored in a scie-tote; so they will not be chunked. \
+                    Make the last file in the lift manifest a directory or zip or pass \
+                    --lift-in-zip to chunk them.",
+                    name = lift.name
+                );
+            }
+            scie_tote = Some(ScieTote::new()?)
+        }
+    }
+    // N.B.: The last file is never chunked unless the lift manifest is in a zip of its own since
+    // the lift manifest trailer must follow the zip that file holds.
+    let file_count = lift.files.len();
+    let mut stored_chunks = HashMap::new();
+    for (index, file) in lift.files.iter_mut().enumerate() {
+        if Source::Scie != file.source {
+            packed_files.push(PackedFile {
+                name: file.name.clone(),
+                file_type: file.file_type,
+                size: file.size,
+                hash: file.hash.clone(),
+                offset: None,
+                scie_tote: false,
+                chunks: 0,
+            });
+            continue;
+        }
+        let path = packed_path(resolve_base, &zips, file).map_err(JumpError::Other)?;
+        let mut blob = std::fs::File::open(&path).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to open {src} / {file:?} for writing to {binary}",
+                    src = path.display(),
+                    binary = binary_path.display()
+                ),
+                e,
+            )
+        })?;
+        if let Some(tote) = scie_tote.as_mut() {
+            let metadata = blob.metadata().map_err(|e| {
+                JumpError::io(
+                    format!("Failed to read metadata for {path}", path = path.display()),
+                    e,
+                )
+            })?;
+            let options = create_options(&metadata)?.compression_method(CompressionMethod::Stored);
+            tote.zip_writer
+                .start_file(&file.name, options)
+                .map_err(|e| {
+                    JumpError::Other(format!(
+                        "Failed to start a scie-tote file entry for {path}: {e}",
+                        path = path.display()
+                    ))
+                })?;
+            std::io::copy(&mut blob, &mut tote.zip_writer).map_err(|e| {
+                JumpError::io(
+                    format!(
+                        "Failed to append {src} / {file:?} to {binary}",
+                        src = path.display(),
+                        binary = binary_path.display()
+                    ),
+                    e,
+                )
+            })?;
+            packed_files.push(PackedFile {
+                name: file.name.clone(),
+                file_type: file.file_type,
+                size: file.size,
+                hash: file.hash.clone(),
+                offset: None,
+                scie_tote: true,
+                chunks: 0,
+            });
+            // N.B.: The size of a file in the scie-tote is not recorded since it is not stored
+            // directly in the scie, but we still need to know how much room it takes up once
+            // extracted.
+            file.extracted_size.get_or_insert(file.size);
+            file.size = 0;
+        } else if chunk
+            && !file.execute_in_place
+            && (lift_in_zip || index + 1 < file_count)
+            && write_chunks(
+                &mut binary,
+                &binary_path,
+                &blob,
+                &path,
+                file,
+                &mut offset,
+                &mut stored_chunks,
+            )?
+        {
+            packed_files.push(PackedFile {
+                name: file.name.clone(),
+                file_type: file.file_type,
+                size: file.size,
+                hash: file.hash.clone(),
+                offset: None,
+                scie_tote: false,
+                chunks: file.chunks.len(),
+            });
+        } else {
+            offset += pad(&mut binary, offset, alignment)?;
+            std::io::copy(&mut blob, &mut binary).map_err(|e| {
+                JumpError::io(
+                    format!(
+                        "Failed to append {src} / {file:?} to {binary}",
+                        src = path.display(),
+                        binary = binary_path.display()
+                    ),
+                    e,
+                )
+            })?;
+            file.offset = Some(offset);
+            packed_files.push(PackedFile {
+                name: file.name.clone(),
+                file_type: file.file_type,
+                size: file.size,
+                hash: file.hash.clone(),
+                offset: file.offset,
+                scie_tote: false,
+                chunks: 0,
+            });
+            offset += file.size;
+        };
+    }
+    if let Some(tote) = scie_tote.as_mut() {
+        tote.zip_writer
+            .finish()
+            .map_err(|e| JumpError::Other(format!("Failed to finalize the scie-tote zip: {e}")))?;
+
+        tote.zip_file.rewind().map_err(|e| {
+            JumpError::io(
+                "Failed to re-wind the scie-tote file to make a second pass calculation of its \
+                hash",
+                e,
+            )
+        })?;
+        let (size, hash) = fingerprint::digest_reader(&tote.zip_file)?;
+        offset += pad(&mut binary, offset, alignment)?;
+        let tote_file = File {
+            name: "scie-tote".to_string(),
+            key: None,
+            size,
+            extracted_size: None,
+            offset: Some(offset),
+            chunks: vec![],
+            hash,
+            file_type: FileType::Archive(ArchiveType::Zip),
+            executable: None,
+            eager_extract: false,
+            execute_in_place: false,
+            source: Source::Scie,
+            source_url: None,
+            version: None,
+            license: None,
+        };
+
+        tote.zip_file.rewind().map_err(|e| {
+            JumpError::io(
+                "Failed to re-wind the scie-tote file to append it".to_string(),
+                e,
+            )
+        })?;
+        std::io::copy(&mut tote.zip_file, &mut binary).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to append {tote_file:?} to {binary}",
+                    binary = binary_path.display()
+                ),
+                e,
+            )
+        })?;
+        packed_files.push(PackedFile {
+            name: tote_file.name.clone(),
+            file_type: tote_file.file_type,
+            size: tote_file.size,
+            hash: tote_file.hash.clone(),
+            offset: tote_file.offset,
+            scie_tote: false,
+            chunks: 0,
+        });
+        offset += tote_file.size;
+        lift.files.push(tote_file);
+    }
+    if adjust_zip_offsets && !lift_in_zip {
+        adjust_last_zip_offsets(
+            &mut binary,
+            &binary_path,
+            &mut lift,
+            &mut packed_files,
+            offset,
+        )?;
+    }
+    let mut lift_manifest = serialize_lift_manifest(jump, lift, single_line)?;
+    let lift_manifest_hash = fingerprint::digest(&lift_manifest);
+    let scie_magic = scie_magic(jump, offset).map_err(JumpError::Config)?;
+    let trailer = dry_run.then(|| Trailer {
+        lift_manifest: lift_manifest.clone(),
+        scie_magic: scie_magic.clone(),
+        in_zip: lift_in_zip,
+    });
+    let lift_manifest = if lift_in_zip {
+        lift_zip(&lift_manifest, scie_magic)?
+    } else {
+        if lift_manifest.len() > Config::MAXIMUM_CONFIG_SIZE {
+            warn!(
+                "The {size} byte lift manifest of {binary} is larger than the {max} bytes the \
+                scie-jump searches by default; the scie will only boot with \
+                SCIE_MAXIMUM_TRAILER_SIZE={size} or more set. Pass --lift-in-zip to avoid this.",
+                size = lift_manifest.len(),
+                binary = binary_path.display(),
+                max = Config::MAXIMUM_CONFIG_SIZE
+            );
+        }
+        lift_manifest.extend(scie_magic.into_bytes());
+        lift_manifest
+    };
+    binary.write_all(&lift_manifest).map_err(|e| {
+        JumpError::io(
+            format!(
+                "Failed to write the lift manifest to {binary}",
+                binary = binary_path.display()
+            ),
+            e,
+        )
+    })?;
+    if adjust_zip_offsets && lift_in_zip {
+        crate::zip::adjust_offsets(
+            &mut binary,
+            offset as u64,
+            (offset + lift_manifest.len()) as u64,
+        )
+        .map_err(|e| {
+            JumpError::Other(format!(
+                "Failed to adjust the offsets of the lift manifest zip in {binary}: {e}",
+                binary = binary_path.display()
+            ))
+        })?;
+    }
+    let size = match &embedding {
+        Some(embedding) => embedding
+            .finish(&mut binary, offset + lift_manifest.len() - jump.size)
+            .map_err(JumpError::Other)?,
+        None => offset + lift_manifest.len(),
+    };
+    drop(binary);
+    let binary = if dry_run {
+        executable_path(&binary_path)?
+    } else {
+        finalize_executable(&binary_path)?
+    };
+    Ok(Packed {
+        manifest: manifest_path.to_path_buf(),
+        size: size as u64,
+        binary,
+        jump: jump.clone(),
+        files: packed_files,
+        lift_manifest_offset: offset,
+        lift_manifest_size: lift_manifest.len(),
+        lift_manifest_hash,
+        dry_run: trailer,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use crate::config::{Config, UnknownFields};
+    use crate::footer::scie_magic;
+    use crate::{
+        fingerprint, is_scie, load_manifest, load_scie_jump, pack, pack_dry_run, read_manifest,
+        Jump, JumpError, PackCache, PackOptions, Packed, ReadManifest, Repack, ScieFooter,
+        SizeLimits, EOF_MAGIC, PAGE_ALIGNMENT,
+    };
+
+    fn scie_jump(dir: &Path) -> (Jump, PathBuf) {
+        let mut scie_jump = b"#!/bin/sh\n".to_vec();
+        let size = scie_jump.len() as u32 + 8;
+        scie_jump.write_u32::<LittleEndian>(size).unwrap();
+        scie_jump.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+        let scie_jump_path = dir.join("scie-jump");
+        std::fs::write(&scie_jump_path, scie_jump).unwrap();
+        (load_scie_jump(&scie_jump_path).unwrap(), scie_jump_path)
+    }
+
+    fn lift_manifest(files: &[(&str, &str)]) -> String {
+        let names = files
+            .iter()
+            .map(|(name, _)| format!(r#"{{"name":"{name}"}}"#))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"scie":{{"lift":{{"name":"example","files":[{names}],
+            "boot":{{"commands":{{"":{{"exe":"{{{first}}}"}}}}}}}}}}}}"#,
+            first = files[0].0
+        )
+    }
+
+    fn pack_files(
+        dir: &Path,
+        files: &[(&str, &str)],
+        lift_in_zip: bool,
+        adjust_zip_offsets: bool,
+    ) -> Packed {
+        let (jump, scie_jump_path) = scie_jump(dir);
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        std::fs::write(dir.join("lift.json"), lift_manifest(files)).unwrap();
+        let (lift, manifest) =
+            load_manifest(dir, &jump, UnknownFields::Deny, &mut PackCache::default()).unwrap();
+        pack(
+            lift,
+            &manifest,
+            &jump,
+            &scie_jump_path,
+            dir,
+            &PackOptions {
+                lift_in_zip,
+                adjust_zip_offsets,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn stored_files(scie: &Path) -> Vec<(String, Vec<u8>)> {
+        let data = std::fs::read(scie).unwrap();
+        let config = crate::inspect(scie).unwrap();
+        config
+            .scie
+            .lift
+            .files
+            .into_iter()
+            .map(|file| {
+                let offset = file.offset.unwrap();
+                let bytes = data[offset..offset + file.size.unwrap()].to_vec();
+                assert_eq!(file.hash.unwrap(), fingerprint::digest(&bytes));
+                (file.name, bytes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extracted_sizes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("app").join("lib")).unwrap();
+        std::fs::write(dir.join("app").join("run"), "abc").unwrap();
+        std::fs::write(dir.join("app").join("lib").join("lib.so"), "defgh").unwrap();
+        let packed = pack_files(dir, &[("data.txt", "data")], false, false);
+        let (jump, scie_jump_path) = scie_jump(dir);
+        std::fs::write(
+            dir.join("lift.json"),
+            r#"{"scie":{"lift":{"name":"tool","files":[{"name":"data.txt"},{"name":"app"}],
+            "boot":{"commands":{"":{"exe":"{app}/run"}}}}}}"#,
+        )
+        .unwrap();
+        let (lift, manifest) =
+            load_manifest(dir, &jump, UnknownFields::Deny, &mut PackCache::default()).unwrap();
+        let app = pack(
+            lift,
+            &manifest,
+            &jump,
+            &scie_jump_path,
+            dir,
+            &PackOptions::default(),
+        )
+        .unwrap();
+
+        let extracted_sizes = |packed: &Packed| {
+            crate::inspect(&packed.binary)
+                .unwrap()
+                .scie
+                .lift
+                .files
+                .into_iter()
+                .map(|file| (file.name, file.extracted_size))
+                .collect::<Vec<_>>()
+        };
+        // N.B.: Blobs stored in a scie-tote record their size as their extracted size since their
+        // size is not otherwise recorded.
+        assert_eq!(
+            vec![
+                ("data.txt".to_string(), Some(4)),
+                ("scie-tote".to_string(), None)
+            ],
+            extracted_sizes(&packed)
+        );
+        assert_eq!(
+            vec![("data.txt".to_string(), None), ("app".to_string(), Some(8))],
+            extracted_sizes(&app)
+        );
+    }
+
+    #[test]
+    fn checksums() {
+        let tmp = tempfile::tempdir().unwrap();
+        let packed = pack_files(tmp.path(), &[("data.txt", "data")], false, false);
+
+        let hash = packed.digest().unwrap();
+        assert_eq!(
+            fingerprint::digest(&std::fs::read(&packed.binary).unwrap()),
+            hash
+        );
+
+        let sha256 = packed.write_sha256(&hash).unwrap();
+        assert_eq!(tmp.path().join("example.sha256"), sha256);
+        assert_eq!(
+            format!("{hash}  example\n"),
+            std::fs::read_to_string(sha256).unwrap()
+        );
+
+        let provenance = packed.write_provenance(&hash).unwrap();
+        assert_eq!(tmp.path().join("example.provenance.json"), provenance);
+        let provenance: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(provenance).unwrap()).unwrap();
+        assert_eq!(hash, provenance["hash"]);
+        assert_eq!("data.txt", provenance["files"][0]["name"]);
+    }
+
+    #[test]
+    fn dry_run() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let packed = pack_files(
+            dir,
+            &[("data.txt", "data"), ("tool.sh", "echo")],
+            false,
+            false,
+        );
+        assert!(!packed.is_dry_run());
+
+        let (jump, scie_jump_path) = scie_jump(dir);
+        let (lift, manifest) =
+            load_manifest(dir, &jump, UnknownFields::Deny, &mut PackCache::default()).unwrap();
+        let dest_dir = dir.join("dist");
+        let dry_run = pack_dry_run(
+            lift,
+            &manifest,
+            &jump,
+            &scie_jump_path,
+            &dest_dir,
+            &PackOptions::default(),
+        )
+        .unwrap();
+        assert!(dry_run.is_dry_run());
+        assert_eq!(dest_dir.join("example"), dry_run.binary);
+        assert!(!dest_dir.exists());
+
+        // The dry run describes exactly the scie that is packed.
+        let data = std::fs::read(&packed.binary).unwrap();
+        assert_eq!(data.len() as u64, dry_run.size);
+        let mut report = dry_run.to_json();
+        assert_eq!(true, report["dry_run"]);
+        let lift = report["lift"].as_object_mut().unwrap();
+        let trailer = format!(
+            "{manifest}{magic}",
+            manifest = lift.remove("manifest").unwrap().as_str().unwrap(),
+            magic = lift.remove("magic").unwrap().as_str().unwrap()
+        );
+        assert_eq!(Some(false), lift.remove("in_zip").unwrap().as_bool());
+        assert!(data.ends_with(trailer.as_bytes()));
+        report.as_object_mut().unwrap().remove("dry_run");
+        report["scie"] = packed.to_json()["scie"].clone();
+        assert_eq!(packed.to_json(), report);
+
+        let summary = dry_run.summary();
+        assert!(summary.starts_with(&format!(
+            "{manifest}: {binary} ({size} bytes)\n",
+            manifest = manifest.display(),
+            binary = dry_run.binary.display(),
+            size = data.len()
+        )));
+        assert!(summary.contains(&format!(
+            "  data.txt: 4 bytes in the scie-tote sha256:{hash}\n",
+            hash = fingerprint::digest(b"data")
+        )));
+        assert!(summary.ends_with(&trailer));
+    }
+
+    #[test]
+    fn size_limits() {
+        assert_eq!(42, SizeLimits::parse_size("42").unwrap());
+        assert_eq!(42, SizeLimits::parse_size("42B").unwrap());
+        assert_eq!(512 * 1024, SizeLimits::parse_size("512K").unwrap());
+        assert_eq!(64 * 1024 * 1024, SizeLimits::parse_size("64MiB").unwrap());
+        assert_eq!(
+            2 * 1024 * 1024 * 1024,
+            SizeLimits::parse_size("2gb").unwrap()
+        );
+        assert_eq!(1 << 40, SizeLimits::parse_size("1T").unwrap());
+        assert!(SizeLimits::parse_size("").is_err());
+        assert!(SizeLimits::parse_size("M").is_err());
+        assert!(SizeLimits::parse_size("1.5G").is_err());
+        assert!(SizeLimits::parse_size("1P").is_err());
+        assert!(SizeLimits::parse_size("16777216T").is_err());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let packed = pack_files(
+            tmp.path(),
+            &[("small.txt", "small"), ("large.txt", "large file")],
+            false,
+            false,
+        );
+        packed.check_sizes(&SizeLimits::default()).unwrap();
+
+        // The scie-tote holding the files is not itself checked against the file limits.
+        let limits = SizeLimits {
+            warn_file_size: Some(5),
+            max_file_size: Some(10),
+            ..Default::default()
+        };
+        packed.check_sizes(&limits).unwrap();
+
+        let limits = SizeLimits {
+            max_file_size: Some(5),
+            max_scie_size: Some(packed.size - 1),
+            ..Default::default()
+        };
+        let error = packed.check_sizes(&limits).unwrap_err();
+        assert!(matches!(error, JumpError::Config(_)), "{error:?}");
+        let error = error.to_string();
+        assert!(
+            error.contains("+ The file large.txt is 10 bytes; over the 5 byte maximum.\n"),
+            "{error}"
+        );
+        assert!(!error.contains("small.txt"), "{error}");
+        assert!(
+            error.contains(&format!(
+                "+ The scie {binary} is {size} bytes; over the {max} byte maximum.\n",
+                binary = packed.binary.display(),
+                size = packed.size,
+                max = packed.size - 1
+            )),
+            "{error}"
+        );
+        packed
+            .check_sizes(&SizeLimits {
+                allow_large: true,
+                ..limits
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn chunked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let (jump, scie_jump_path) = scie_jump(dir);
+        let a = crate::chunk::noise(10 * 1024 * 1024, 1);
+        let mut b = b"an edit".to_vec();
+        b.extend_from_slice(&a);
+        std::fs::write(dir.join("a.bin"), &a).unwrap();
+        std::fs::write(dir.join("b.bin"), &b).unwrap();
+        std::fs::write(dir.join("tool.sh"), "echo tool").unwrap();
+        std::fs::write(
+            dir.join("lift.json"),
+            lift_manifest(&[("tool.sh", ""), ("a.bin", ""), ("b.bin", "")]),
+        )
+        .unwrap();
+        let (lift, manifest) =
+            load_manifest(dir, &jump, UnknownFields::Deny, &mut PackCache::default()).unwrap();
+        let packed = pack(
+            lift,
+            &manifest,
+            &jump,
+            &scie_jump_path,
+            dir,
+            &PackOptions {
+                lift_in_zip: true,
+                chunk: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Each chunk is stored on a page boundary and only once; so the chunks b.bin shares with
+        // a.bin are not stored again.
+        let data = std::fs::read(&packed.binary).unwrap();
+        assert_eq!(data.len() as u64, packed.size);
+        assert!(data.len() < a.len() + 2 * 1024 * 1024);
+        let (_, lift) = crate::lift::load_scie(&packed.binary, &data).unwrap();
+        let files = lift.files;
+        assert_eq!(Some(jump.size), files[0].offset);
+        assert!(files[0].chunks.is_empty());
+        for (file, contents) in [(&files[1], &a), (&files[2], &b)] {
+            assert_eq!(None, file.offset);
+            assert!(file.chunks.len() > 1);
+            let mut reassembled = vec![];
+            for chunk in &file.chunks {
+                assert_eq!(0, chunk.offset % PAGE_ALIGNMENT);
+                let bytes = &data[chunk.offset..chunk.offset + chunk.size];
+                assert_eq!(chunk.hash, fingerprint::digest(bytes));
+                reassembled.extend_from_slice(bytes);
+            }
+            assert_eq!(contents, &reassembled);
+        }
+        let shared = files[2]
+            .chunks
+            .iter()
+            .filter(|chunk| files[1].chunks.contains(chunk))
+            .count();
+        assert!(shared >= files[1].chunks.len() - 2);
+
+        // The scie-jump reassembles chunked files when it installs them.
+        let installer = crate::installer::Installer::new(&data[jump.size..], jump.size);
+        let dst = dir.join("installed").join("b.bin");
+        installer
+            .install(&[crate::context::FileEntry::Install((
+                files[2].clone(),
+                dst.clone(),
+            ))])
+            .unwrap();
+        assert_eq!(b, std::fs::read(dst).unwrap());
+
+        // Chunked files are re-packed whole.
+        let repacked = dir.join("repacked");
+        Repack::new(&packed.binary).write(&repacked).unwrap();
+        assert_eq!(
+            vec![
+                ("tool.sh".to_string(), b"echo tool".to_vec()),
+                ("a.bin".to_string(), a),
+                ("b.bin".to_string(), b)
+            ],
+            stored_files(&repacked)
+        );
+    }
+
+    #[test]
+    fn lift_in_zip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let packed = pack_files(
+            tmp.path(),
+            &[("data.txt", "data"), ("tool.sh", "echo tool")],
+            true,
+            false,
+        );
+
+        // N.B.: The last file is not a zip, but there is no scie-tote since the lift manifest is
+        // in a zip of its own.
+        assert_eq!(
+            vec![
+                ("data.txt".to_string(), b"data".to_vec()),
+                ("tool.sh".to_string(), b"echo tool".to_vec())
+            ],
+            stored_files(&packed.binary)
+        );
+
+        let data = std::fs::read(&packed.binary).unwrap();
+        let footer = ScieFooter::parse(&data).unwrap().unwrap();
+        assert_eq!(packed.lift_manifest_offset, footer.lift_manifest_offset);
+        assert_eq!(packed.lift_manifest_size, footer.lift_manifest_size);
+        // N.B.: The line identifying the scie is the comment of the lift manifest zip; so the scie
+        // still ends in a well-formed zip.
+        assert!(data.ends_with(
+            scie_magic(&packed.jump, packed.lift_manifest_offset)
+                .unwrap()
+                .as_bytes()
+        ));
+        assert_eq!(Some(footer), is_scie(&packed.binary).unwrap());
+        let lift_manifest = crate::zip::lift_manifest(&data, Config::MAXIMUM_CONFIG_SIZE).unwrap();
+        assert!(lift_manifest.in_zip);
+
+        let new_tool = tmp.path().join("new-tool.sh");
+        std::fs::write(&new_tool, "echo new tool").unwrap();
+        let repacked: PathBuf = tmp.path().join("repacked");
+        Repack::new(&packed.binary)
+            .file("tool.sh", &new_tool)
+            .write(&repacked)
+            .unwrap();
+        assert_eq!(
+            vec![
+                ("data.txt".to_string(), b"data".to_vec()),
+                ("tool.sh".to_string(), b"echo new tool".to_vec())
+            ],
+            stored_files(&repacked)
+        );
+        let mut data = std::fs::read(&repacked).unwrap();
+        assert!(
+            crate::zip::lift_manifest(&data, Config::MAXIMUM_CONFIG_SIZE)
+                .unwrap()
+                .in_zip
+        );
+
+        // N.B.: A code signature appended to the scie should not hide the lift manifest zip.
+        data.extend([0; 64]);
+        let signed = tmp.path().join("signed");
+        std::fs::write(&signed, data).unwrap();
+        assert_eq!(stored_files(&repacked), stored_files(&signed));
+    }
+
+    #[test]
+    fn read_manifest_stream() {
+        let files = [("data.txt", "data"), ("tool.sh", "echo tool")];
+        let expected = files
+            .iter()
+            .map(|(name, contents)| (name.to_string(), contents.as_bytes().to_vec()))
+            .collect::<Vec<_>>();
+        let pack_stream = |dir: &Path, stream: &[u8]| {
+            let (jump, scie_jump_path) = scie_jump(dir);
+            let ReadManifest {
+                lift,
+                manifest,
+                files: _files,
+            } = read_manifest(
+                stream,
+                dir,
+                &jump,
+                UnknownFields::Deny,
+                &mut PackCache::default(),
+            )
+            .unwrap();
+            let packed = pack(
+                lift,
+                &manifest,
+                &jump,
+                &scie_jump_path,
+                dir,
+                &PackOptions {
+                    lift_in_zip: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            stored_files(&packed.binary)
+        };
+
+        let tmp = tempfile::tempdir().unwrap();
+        for (name, contents) in files {
+            std::fs::write(tmp.path().join(name), contents).unwrap();
+        }
+        assert_eq!(
+            expected,
+            pack_stream(tmp.path(), lift_manifest(&files).as_bytes())
+        );
+
+        let mut tar = tar::Builder::new(vec![]);
+        let manifest = lift_manifest(&files);
+        for (name, contents) in [("lift.json", manifest.as_str())].iter().chain(&files) {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        let stream = tar.into_inner().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(expected, pack_stream(tmp.path(), &stream));
+        assert!(!tmp.path().join("lift.json").exists());
+    }
+
+    #[test]
+    fn adjust_zip_offsets() {
+        let files = [("data.txt", "data"), ("tool.sh", "echo tool")];
+        let expected = vec![
+            ("data.txt".to_string(), b"data".to_vec()),
+            ("tool.sh".to_string(), b"echo tool".to_vec()),
+        ];
+
+        let tmp = tempfile::tempdir().unwrap();
+        let packed = pack_files(tmp.path(), &files, false, true);
+        let data = std::fs::read(&packed.binary).unwrap();
+        let tote = crate::inspect(&packed.binary)
+            .unwrap()
+            .scie
+            .lift
+            .files
+            .into_iter()
+            .find(|file| file.offset.is_some())
+            .unwrap();
+        let offset = tote.offset.unwrap();
+        assert_eq!(
+            tote.hash.unwrap(),
+            fingerprint::digest(&data[offset..offset + tote.size.unwrap()])
+        );
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&packed.binary).unwrap()).unwrap();
+        assert_eq!(0, zip.offset());
+        let mut contents = String::new();
+        zip.by_name("tool.sh")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!("echo tool", contents);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let packed = pack_files(tmp.path(), &files, true, true);
+        assert_eq!(expected, stored_files(&packed.binary));
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&packed.binary).unwrap()).unwrap();
+        assert_eq!(0, zip.offset());
+        assert!(zip.by_name(crate::zip::LIFT_ZIP_ENTRY).is_ok());
+    }
+}
@@ -14,16 +14,19 @@ pub(crate) enum Placeholder<'a> {
     Env(&'a str),
     FileHash(&'a str),
     FileName(&'a str),
+    FileSize(&'a str),
     UserCacheDir(&'a str),
     Scie,
     ScieBase,
     ScieBindings,
     ScieBindingCmd(&'a str),
     ScieBindingEnv(ScieBindingEnv<'a>),
+    ScieLibc,
     ScieLift,
     SciePlatform,
     SciePlatformArch,
     SciePlatformOs,
+    ScieScript,
 }
 
 #[cfg_attr(test, derive(Eq, PartialEq))]
@@ -101,9 +104,15 @@ pub(crate) fn parse(text: &str) -> Result<Parsed, String> {
                         }
                     }
                     ["scie", "env", env] => items.push(Item::Placeholder(Placeholder::Env(env))),
-                    ["scie", "files", name] => {
-                        items.push(Item::Placeholder(Placeholder::FileName(name)))
-                    }
+                    ["scie", "files", name] => items.push(Item::Placeholder(
+                        if let Some(name) = name.strip_suffix(".hash") {
+                            Placeholder::FileHash(name)
+                        } else if let Some(name) = name.strip_suffix(".size") {
+                            Placeholder::FileSize(name)
+                        } else {
+                            Placeholder::FileName(name)
+                        },
+                    )),
                     ["scie", "files:hash", name] => {
                         items.push(Item::Placeholder(Placeholder::FileHash(name)))
                     }
@@ -127,6 +136,7 @@ pub(crate) fn parse(text: &str) -> Result<Parsed, String> {
                             }
                         }
                     }
+                    ["scie", "libc"] => items.push(Item::Placeholder(Placeholder::ScieLibc)),
                     ["scie", "lift"] => items.push(Item::Placeholder(Placeholder::ScieLift)),
                     ["scie", "platform"] => {
                         items.push(Item::Placeholder(Placeholder::SciePlatform))
@@ -137,6 +147,7 @@ pub(crate) fn parse(text: &str) -> Result<Parsed, String> {
                     ["scie", "platform", "os"] => {
                         items.push(Item::Placeholder(Placeholder::SciePlatformOs))
                     }
+                    ["scie", "script"] => items.push(Item::Placeholder(Placeholder::ScieScript)),
                     _ => items.push(Item::Placeholder(Placeholder::FileName(symbol))),
                 }
                 previous_char = Some('}');
@@ -302,6 +313,42 @@ mod tests {
             vec![Item::Placeholder(Placeholder::FileHash("python"))],
             parse("{scie.files:hash.python}").unwrap().items
         );
+        assert_eq!(
+            vec![Item::Placeholder(Placeholder::FileHash("python"))],
+            parse("{scie.files.python.hash}").unwrap().items
+        );
+        assert_eq!(
+            vec![
+                Item::Text("--digest="),
+                Item::Placeholder(Placeholder::FileHash("dotted.file.name"))
+            ],
+            parse("--digest={scie.files.dotted.file.name.hash}")
+                .unwrap()
+                .items
+        );
+        assert_eq!(
+            vec![Item::Placeholder(Placeholder::FileHash(
+                "{scie.env.PYTHON}"
+            ))],
+            parse("{scie.files.{scie.env.PYTHON}.hash}").unwrap().items
+        );
+    }
+
+    #[test]
+    fn file_size() {
+        assert_eq!(
+            vec![Item::Placeholder(Placeholder::FileSize("python"))],
+            parse("{scie.files.python.size}").unwrap().items
+        );
+        assert_eq!(
+            vec![Item::Placeholder(Placeholder::FileSize("dotted.file.name"))],
+            parse("{scie.files.dotted.file.name.size}").unwrap().items
+        );
+        // The bare file name form has no attributes.
+        assert_eq!(
+            vec![Item::Placeholder(Placeholder::FileName("python.size"))],
+            parse("{python.size}").unwrap().items
+        );
     }
 
     #[test]
@@ -367,6 +414,21 @@ mod tests {
             vec![Item::Placeholder(Placeholder::SciePlatformOs)],
             parse("{scie.platform.os}").unwrap().items,
         );
+        assert_eq!(
+            vec![Item::Placeholder(Placeholder::ScieLibc)],
+            parse("{scie.libc}").unwrap().items,
+        );
+    }
+
+    #[test]
+    fn scie_script() {
+        assert_eq!(
+            vec![
+                Item::Text("--script="),
+                Item::Placeholder(Placeholder::ScieScript)
+            ],
+            parse("--script={scie.script}").unwrap().items
+        );
     }
 
     #[test]
@@ -1,17 +1,19 @@
 // Copyright 2022 Science project contributors.
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::collections::HashSet;
 use std::env;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::Duration;
 
+use indexmap::IndexSet;
 use logging_timer::time;
 use os_str_bytes::OsStrBytes;
 use sha2::{Digest, Sha256};
 
 use crate::comparable_regex::ComparableRegex;
 use crate::config::EnvVar as ConfigEnvVar;
+use crate::error::JumpError;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum EnvVar {
@@ -19,6 +21,54 @@ pub enum EnvVar {
     Replace((OsString, OsString)),
     Remove(OsString),
     RemoveMatching(ComparableRegex),
+    /// Removes all env vars but those in `INHERITED_ENV_VARS`.
+    Clean,
+}
+
+/// The env vars a command run with a clean environment still inherits; without these many
+/// programs cannot run at all. Any `LC_*` locale env vars are inherited as well.
+const INHERITED_ENV_VARS: &[&str] = &[
+    // Unix.
+    "HOME",
+    "LANG",
+    "LOGNAME",
+    "PATH",
+    "SHELL",
+    "TERM",
+    "TMPDIR",
+    "TZ",
+    "USER",
+    // Windows.
+    "APPDATA",
+    "COMSPEC",
+    "LOCALAPPDATA",
+    "PATHEXT",
+    "PROGRAMDATA",
+    "SYSTEMDRIVE",
+    "SYSTEMROOT",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "WINDIR",
+    // The scie-jump's own.
+    "SCIE",
+    "SCIE_ARGV0",
+    "SCIE_BOOT_START_MS",
+    "SCIE_BOOT_TIME_MS",
+    "SCIE_EXTRACT_TIME_MS",
+];
+
+fn is_inherited(name: &OsStr) -> bool {
+    // N.B.: Env var names are case-insensitive on Windows.
+    let name = if cfg!(windows) {
+        name.to_ascii_uppercase()
+    } else {
+        name.to_os_string()
+    };
+    INHERITED_ENV_VARS
+        .iter()
+        .any(|inherited| name == *inherited)
+        || name.to_raw_bytes().starts_with(b"LC_")
 }
 
 impl TryFrom<(&ConfigEnvVar, Option<String>)> for EnvVar {
@@ -46,12 +96,16 @@ pub struct EnvVars {
 }
 
 impl EnvVars {
-    // Translates this `EnvVars` into a sequence of env var set and env var remove instructions
-    // that, when carried out in order, will place the environment in the requested state.
-    fn to_env_vars(&self) -> Vec<(OsString, Option<OsString>)> {
+    /// Translates this `EnvVars` into a sequence of env var set and env var remove instructions
+    /// that, when carried out in order, will place the environment in the requested state.
+    ///
+    /// All removals come first, then all defaults and finally all replacements. Within each of
+    /// these groups, the instructions follow the order of the env vars; so when a name is set more
+    /// than once, the last setting wins.
+    pub fn to_env_vars(&self) -> Vec<(OsString, Option<OsString>)> {
         let mut defaults = vec![];
         let mut replacements = vec![];
-        let mut removals: HashSet<OsString> = HashSet::new();
+        let mut removals: IndexSet<OsString> = IndexSet::new();
         for env_var in &self.vars {
             match env_var {
                 EnvVar::Default((name, val)) => {
@@ -70,6 +124,13 @@ impl EnvVars {
                         }
                     }
                 }
+                EnvVar::Clean => {
+                    for (name, _) in env::vars_os() {
+                        if !is_inherited(&name) {
+                            removals.insert(name);
+                        }
+                    }
+                }
             }
         }
         let mut env_vars = vec![];
@@ -100,14 +161,18 @@ impl EnvVars {
     }
 }
 
-pub fn execute(exe: OsString, args: Vec<OsString>, argv_skip: usize) -> Result<ExitStatus, String> {
+pub fn execute(exe: OsString, args: Vec<OsString>) -> Result<ExitStatus, JumpError> {
     Command::new(&exe)
         .args(&args)
-        .args(env::args().skip(argv_skip))
         .spawn()
-        .map_err(|e| format!("Failed to spawn {exe:?} {args:?}: {e}"))?
+        .map_err(|e| JumpError::process(format!("Failed to spawn {exe:?} {args:?}"), e))?
         .wait()
-        .map_err(|e| format!("Spawned {exe:?} {args:?} but failed to gather its exit status: {e}"))
+        .map_err(|e| {
+            JumpError::process(
+                format!("Spawned {exe:?} {args:?} but failed to gather its exit status"),
+                e,
+            )
+        })
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -115,8 +180,16 @@ pub struct Process {
     pub env: EnvVars,
     pub exe: OsString,
     pub args: Vec<OsString>,
+    pub cwd: Option<OsString>,
+    /// A file descriptor holding the executable when it is run in place from memory; in which case
+    /// `exe` is only descriptive.
+    pub exe_fd: Option<i32>,
+    /// How long the process may run for before it should be killed.
+    pub timeout: Option<Duration>,
 }
 
+// N.B.: Process fingerprints are only used to boot scies; which wasm32-wasi builds do not support.
+#[cfg_attr(target_os = "wasi", allow(dead_code))]
 fn as_bytes(os_string: &OsString) -> Result<Vec<u8>, String> {
     let string = os_string
         .clone()
@@ -127,11 +200,15 @@ fn as_bytes(os_string: &OsString) -> Result<Vec<u8>, String> {
 
 impl Process {
     #[time("debug", "Process::{}")]
+    #[cfg_attr(target_os = "wasi", allow(dead_code))]
     pub(crate) fn fingerprint(&self) -> Result<String, String> {
         let mut hasher = Sha256::new_with_prefix(as_bytes(&self.exe)?);
         for arg in &self.args {
             hasher.update(as_bytes(arg)?);
         }
+        if let Some(cwd) = &self.cwd {
+            hasher.update(as_bytes(cwd)?);
+        }
         for (key, value) in self.env.to_env_vars() {
             if let Some(val) = value {
                 hasher.update(as_bytes(&key)?);
@@ -144,6 +221,9 @@ impl Process {
     fn as_command(&self) -> Command {
         let mut command = Command::new(&self.exe);
         command.args(&self.args);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
         for (name, value) in self.env.to_env_vars() {
             match value {
                 Some(val) => {
@@ -160,39 +240,48 @@ impl Process {
     pub fn execute(
         &self,
         extra_env: impl IntoIterator<Item = (OsString, OsString)>,
-    ) -> Result<ExitStatus, String> {
+    ) -> Result<ExitStatus, JumpError> {
         self.as_command()
             .envs(extra_env)
             .spawn()
             .map_err(|e| {
-                format!(
-                    "Failed to spawn {exe:?} {args:?}: {e}",
-                    exe = self.exe,
-                    args = self.args
+                JumpError::process(
+                    format!(
+                        "Failed to spawn {exe:?} {args:?}",
+                        exe = self.exe,
+                        args = self.args
+                    ),
+                    e,
                 )
             })?
             .wait()
             .map_err(|e| {
-                format!(
-                    "Spawned process with {exe:?} {args:?} but failed to gather its exit \
-                    status: {e}",
-                    exe = self.exe,
-                    args = self.args
+                JumpError::process(
+                    format!(
+                        "Spawned process with {exe:?} {args:?} but failed to gather its exit \
+                        status",
+                        exe = self.exe,
+                        args = self.args
+                    ),
+                    e,
                 )
             })
     }
 
-    pub fn spawn_stdout(&self, args: &[&str]) -> Result<Child, String> {
+    pub fn spawn_stdout(&self, args: &[&str]) -> Result<Child, JumpError> {
         self.as_command()
             .args(args)
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .spawn()
             .map_err(|e| {
-                format!(
-                    "Failed to spawn {exe:?} {args:?}: {e}",
-                    exe = self.exe,
-                    args = self.args
+                JumpError::process(
+                    format!(
+                        "Failed to spawn {exe:?} {args:?}",
+                        exe = self.exe,
+                        args = self.args
+                    ),
+                    e,
                 )
             })
     }
@@ -218,7 +307,7 @@ mod tests {
 
     fn with_env<T>(func: T)
     where
-        T: FnOnce() -> (),
+        T: FnOnce(),
     {
         let _env_lock = ENV_LOCK
             .get_or_init(|| Arc::new(ReentrantMutex::new(())))
@@ -228,7 +317,7 @@ mod tests {
 
     fn with_extra_env<T>(extra_env: &[(OsString, OsString)], func: T)
     where
-        T: FnOnce() -> (),
+        T: FnOnce(),
     {
         with_env(|| {
             let mut original_env = vec![];
@@ -277,6 +366,70 @@ mod tests {
         assert_to_env_vars()
     }
 
+    #[test]
+    fn to_env_vars_order() {
+        with_extra_env(&[("__SCIE_SET__".into(), "ambient".into())], || {
+            assert_eq!(
+                vec![
+                    ("__SCIE_Z__".into(), None),
+                    ("__SCIE_A__".into(), None),
+                    ("__SCIE_M__".into(), None),
+                    ("__SCIE_SET__".into(), Some("ambient".into())),
+                    ("__SCIE_UNSET__".into(), Some("default".into())),
+                    ("__SCIE_R__".into(), Some("1".into())),
+                    ("__SCIE_R__".into(), Some("2".into())),
+                ],
+                EnvVars {
+                    vars: vec![
+                        EnvVar::Replace(("__SCIE_R__".into(), "1".into())),
+                        EnvVar::Remove("__SCIE_Z__".into()),
+                        EnvVar::Default(("__SCIE_SET__".into(), "default".into())),
+                        EnvVar::Remove("__SCIE_A__".into()),
+                        EnvVar::Default(("__SCIE_UNSET__".into(), "default".into())),
+                        EnvVar::Replace(("__SCIE_R__".into(), "2".into())),
+                        EnvVar::Remove("__SCIE_M__".into()),
+                        EnvVar::Remove("__SCIE_A__".into()),
+                    ]
+                }
+                .to_env_vars()
+            )
+        })
+    }
+
+    #[test]
+    fn to_env_vars_clean() {
+        with_extra_env(
+            &[
+                ("__SCIE_EXOTIC__".into(), "1".into()),
+                ("LC_SCIE_TEST".into(), "C".into()),
+            ],
+            || {
+                let env_vars = EnvVars {
+                    vars: vec![
+                        EnvVar::Default(("__SCIE_EXOTIC__".into(), "default".into())),
+                        EnvVar::Replace(("__SCIE_SET__".into(), "set".into())),
+                        EnvVar::Clean,
+                    ],
+                }
+                .to_env_vars();
+                let removed = |name: &str| env_vars.contains(&(name.into(), None));
+                assert!(!removed("PATH"));
+                assert!(!removed("LC_SCIE_TEST"));
+                assert!(removed("__SCIE_EXOTIC__"));
+                assert_eq!(
+                    vec![
+                        ("__SCIE_EXOTIC__".into(), Some("default".into())),
+                        ("__SCIE_SET__".into(), Some("set".into()))
+                    ],
+                    env_vars
+                        .into_iter()
+                        .filter(|(_, value)| value.is_some())
+                        .collect::<Vec<(OsString, Option<OsString>)>>()
+                );
+            },
+        )
+    }
+
     #[cfg(windows)]
     fn create_non_utf8_string() -> OsString {
         use std::os::windows::ffi::OsStringExt;
@@ -0,0 +1,509 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use logging_timer::time;
+
+use crate::config::{FileType, Jump};
+use crate::footer::scie_magic;
+use crate::lift::{determine_file_type, is_executable, File, Source};
+use crate::pack::{
+    finalize_executable, lift_zip, load_scie_jump, serialize_lift_manifest, Packed, PackedFile,
+};
+use crate::{archive, check_is_zip, chunk, fingerprint, JumpError};
+
+/// Re-packs an existing scie with some of its files replaced or new files added or with a new
+/// scie-jump launcher.
+///
+/// All other files are copied from the existing scie as-is; so only the files given need to be
+/// fingerprinted.
+pub struct Repack<'a> {
+    scie: &'a Path,
+    scie_jump: Option<PathBuf>,
+    files: Vec<(String, PathBuf)>,
+    single_line: bool,
+}
+
+impl<'a> Repack<'a> {
+    pub fn new(scie: &'a Path) -> Self {
+        Self {
+            scie,
+            scie_jump: None,
+            files: vec![],
+            single_line: true,
+        }
+    }
+
+    /// Stores the file at `path` under `name`; replacing the existing file with that name or
+    /// key if there is one and adding a new file otherwise.
+    pub fn file(mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.files.push((name.into(), path.into()));
+        self
+    }
+
+    /// Replaces the scie's launcher with the scie-jump binary at `path`.
+    pub fn scie_jump(mut self, path: impl Into<PathBuf>) -> Self {
+        self.scie_jump = Some(path.into());
+        self
+    }
+
+    pub fn single_line(mut self, value: bool) -> Self {
+        self.single_line = value;
+        self
+    }
+
+    /// Re-packs the scie over itself.
+    ///
+    /// The re-packed scie is written to a temporary file beside the scie first and then moved
+    /// into place; so the scie is never left partially written.
+    pub fn write_in_place(self) -> Result<Packed, JumpError> {
+        let scie = self.scie;
+        let parent = scie
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let file_name = scie.file_name().ok_or_else(|| {
+            JumpError::Config(format!(
+                "The scie path {scie} has no file name.",
+                scie = scie.display()
+            ))
+        })?;
+        let work_dir = tempfile::tempdir_in(parent).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to create a temporary directory in {parent} for re-packing {scie}",
+                    parent = parent.display(),
+                    scie = scie.display()
+                ),
+                e,
+            )
+        })?;
+        let dest = work_dir.path().join(file_name);
+        let mut packed = self.write(&dest)?;
+        std::fs::rename(&dest, scie).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to move the re-packed scie {dest} into place at {scie}",
+                    dest = dest.display(),
+                    scie = scie.display()
+                ),
+                e,
+            )
+        })?;
+        packed.binary = scie.to_path_buf();
+        Ok(packed)
+    }
+
+    /// Writes the re-packed scie to `dest`, which must not exist yet.
+    #[time("debug", "Repack::{}")]
+    pub fn write(self, dest: &Path) -> Result<Packed, JumpError> {
+        let scie = std::fs::File::open(self.scie).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to open scie at {scie} for re-packing",
+                    scie = self.scie.display()
+                ),
+                e,
+            )
+        })?;
+        let data = crate::map(&scie).map_err(|e| {
+            JumpError::io(
+                format!("Failed to read scie at {scie}", scie = self.scie.display()),
+                e,
+            )
+        })?;
+        if crate::embed::payload_end(&data).is_some() {
+            return Err(JumpError::Config(format!(
+                "The scie at {scie} has its files embedded in an executable section or segment \
+                and cannot be re-packed. Pack its lift manifest with `boot-pack --embed` instead.",
+                scie = self.scie.display()
+            )));
+        }
+        let (jump, mut lift) = crate::lift::load_scie(self.scie, &data)?;
+        let maximum_trailer_size = crate::zip::maximum_trailer_size().map_err(JumpError::Config)?;
+        let lift_in_zip = crate::zip::lift_manifest(&data, maximum_trailer_size)
+            .map_err(JumpError::Config)?
+            .in_zip;
+        let (tip, new_jump) = match self.scie_jump {
+            Some(path) => {
+                let new_jump = load_scie_jump(&path)?;
+                (Bytes::Path(path), new_jump)
+            }
+            None => (Bytes::Original(vec![&data[..jump.size]]), jump.clone()),
+        };
+        let new_jump = Jump {
+            min_version: Some(crate::jump::min_version()),
+            ..new_jump
+        };
+
+        let mut replacements = self.files;
+        let mut location = jump.size;
+        let mut entries = vec![];
+        for mut file in std::mem::take(&mut lift.files) {
+            let stored = file.size > 0 && Source::Scie == file.source;
+            let original = if stored && !file.chunks.is_empty() {
+                Some(chunk::slices(&data, 0, &file.name, &file.chunks).map_err(JumpError::Config)?)
+            } else if stored {
+                let start = file.offset.unwrap_or(location);
+                location = start + file.size;
+                data.get(start..location).map(|bytes| vec![bytes])
+            } else {
+                None
+            };
+            let index = replacements
+                .iter()
+                .position(|(name, _)| name == &file.name || Some(name) == file.key.as_ref());
+            let Some((_, path)) = index.map(|index| replacements.remove(index)) else {
+                let bytes = if stored {
+                    Some(Bytes::Original(original.ok_or_else(|| {
+                        JumpError::Config(format!(
+                            "The file {name} lies outside the scie {scie}.",
+                            name = file.name,
+                            scie = self.scie.display()
+                        ))
+                    })?))
+                } else {
+                    None
+                };
+                entries.push((file, bytes));
+                continue;
+            };
+            if let Source::LoadBinding(binding) = &file.source {
+                return Err(JumpError::Config(format!(
+                    "The file {name} is loaded by the {binding} binding and is not stored in the \
+                    scie; so it cannot be replaced.",
+                    name = file.name
+                )));
+            }
+            if let Source::EntryRange { url, .. } = &file.source {
+                return Err(JumpError::Config(format!(
+                    "The file {name} is fetched from {url} and is not stored in the scie; so it \
+                    cannot be replaced.",
+                    name = file.name
+                )));
+            }
+            if file.size == 0 {
+                return Err(JumpError::Config(format!(
+                    "The file {name} is stored in the scie-tote and cannot be replaced. Re-pack \
+                    the scie from its lift manifest instead.",
+                    name = file.name
+                )));
+            }
+            if FileType::Directory == file.file_type {
+                check_is_zip(&path).map_err(|e| {
+                    e.context(|message| {
+                        format!(
+                            "The directory {name} must be replaced with a zip of its contents: \
+                            {message}",
+                            name = file.name
+                        )
+                    })
+                })?;
+            }
+            (file.size, file.hash) = fingerprint::digest_file(&path)?;
+            file.extracted_size =
+                archive::extracted_size(&path, file.file_type).map_err(JumpError::Other)?;
+            // N.B.: The replacement need not come from where the original did.
+            file.source_url = None;
+            file.version = None;
+            file.license = None;
+            entries.push((file, Some(Bytes::Path(path))));
+        }
+
+        // N.B.: A scie's lift manifest is found just after the end of the zip that is its last
+        // stored file; so any new files go before that unless the lift manifest is stored in its
+        // own zip.
+        let last = if lift_in_zip {
+            entries.len()
+        } else {
+            let last = entries
+                .iter()
+                .rposition(|(_, bytes)| bytes.is_some())
+                .ok_or_else(|| {
+                    JumpError::Config(format!(
+                        "The scie {scie} stores no files.",
+                        scie = self.scie.display()
+                    ))
+                })?;
+            if let (file, Some(Bytes::Path(path))) = &entries[last] {
+                check_is_zip(path).map_err(|e| {
+                    e.context(|message| {
+                        format!(
+                            "The {name} file is the last in the scie; so it can only be replaced \
+                            with a zip: {message}",
+                            name = file.name
+                        )
+                    })
+                })?;
+            }
+            last
+        };
+        let mut added = vec![];
+        for (name, path) in replacements {
+            let file_type = determine_file_type(&path).map_err(JumpError::Other)?;
+            if FileType::Directory == file_type {
+                return Err(JumpError::Config(format!(
+                    "Cannot add the directory {path} as {name}; add a zip of its contents \
+                    instead.",
+                    path = path.display()
+                )));
+            }
+            let (size, hash) = fingerprint::digest_file(&path)?;
+            let file = File {
+                name,
+                key: None,
+                size,
+                extracted_size: archive::extracted_size(&path, file_type)
+                    .map_err(JumpError::Other)?,
+                offset: None,
+                chunks: vec![],
+                hash,
+                file_type,
+                executable: is_executable(&path)
+                    .map_err(JumpError::Other)?
+                    .then_some(true),
+                eager_extract: false,
+                execute_in_place: false,
+                source: Source::Scie,
+                source_url: None,
+                version: None,
+                license: None,
+            };
+            added.push((file, Some(Bytes::Path(path))));
+        }
+        entries.splice(last..last, added);
+
+        let mut binary = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dest)
+            .map_err(|e| {
+                JumpError::io(
+                    format!(
+                        "Failed to open {dest} for writing the re-packed scie",
+                        dest = dest.display()
+                    ),
+                    e,
+                )
+            })?;
+        let write_error = |e: std::io::Error| {
+            JumpError::io(
+                format!(
+                    "Failed to write the re-packed scie {dest}",
+                    dest = dest.display()
+                ),
+                e,
+            )
+        };
+        tip.write(&mut binary, dest)?;
+        let mut offset = new_jump.size;
+        let mut packed_files = vec![];
+        for (mut file, bytes) in entries {
+            if let Some(bytes) = bytes {
+                bytes.write(&mut binary, dest)?;
+            }
+            if file.size > 0 && Source::Scie == file.source {
+                // N.B.: Chunked files are re-packed whole.
+                file.offset = Some(offset);
+                file.chunks.clear();
+                offset += file.size;
+            }
+            packed_files.push(PackedFile {
+                name: file.name.clone(),
+                file_type: file.file_type,
+                size: file.size,
+                hash: file.hash.clone(),
+                offset: file.offset,
+                scie_tote: file.size == 0 && Source::Scie == file.source,
+                chunks: 0,
+            });
+            lift.files.push(file);
+        }
+        let lift_manifest = serialize_lift_manifest(&new_jump, lift, self.single_line)?;
+        let lift_manifest_hash = fingerprint::digest(&lift_manifest);
+        let scie_magic = scie_magic(&new_jump, offset).map_err(JumpError::Config)?;
+        let lift_manifest = if lift_in_zip {
+            lift_zip(&lift_manifest, scie_magic)?
+        } else {
+            [lift_manifest, scie_magic.into_bytes()].concat()
+        };
+        binary.write_all(&lift_manifest).map_err(write_error)?;
+        drop(binary);
+        let binary = finalize_executable(dest)?;
+        Ok(Packed {
+            manifest: self.scie.to_path_buf(),
+            binary,
+            size: (offset + lift_manifest.len()) as u64,
+            jump: new_jump,
+            files: packed_files,
+            lift_manifest_offset: offset,
+            lift_manifest_size: lift_manifest.len(),
+            lift_manifest_hash,
+            dry_run: None,
+        })
+    }
+}
+
+enum Bytes<'a> {
+    /// The bytes of a file in the original scie; in pieces if it was chunked.
+    Original(Vec<&'a [u8]>),
+    Path(PathBuf),
+}
+
+impl Bytes<'_> {
+    fn write(self, binary: &mut std::fs::File, dest: &Path) -> Result<(), JumpError> {
+        let write_error = |e: std::io::Error| {
+            JumpError::io(
+                format!(
+                    "Failed to write the re-packed scie {dest}",
+                    dest = dest.display()
+                ),
+                e,
+            )
+        };
+        match self {
+            Bytes::Original(pieces) => pieces
+                .into_iter()
+                .try_for_each(|bytes| binary.write_all(bytes))
+                .map_err(write_error),
+            Bytes::Path(path) => {
+                let mut src = std::fs::File::open(&path).map_err(|e| {
+                    JumpError::io(
+                        format!(
+                            "Failed to open {path} for writing to {dest}",
+                            path = path.display(),
+                            dest = dest.display()
+                        ),
+                        e,
+                    )
+                })?;
+                std::io::copy(&mut src, binary)
+                    .map(|_| ())
+                    .map_err(write_error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::Repack;
+    use crate::config::UnknownFields;
+    use crate::{fingerprint, load_manifest, pack, JumpError, PackCache, PackOptions, EOF_MAGIC};
+
+    #[test]
+    fn repack() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let mut scie_jump = b"#!/bin/sh\n".to_vec();
+        let size = scie_jump.len() as u32 + 8;
+        scie_jump.write_u32::<LittleEndian>(size).unwrap();
+        scie_jump.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+        let scie_jump_path = tmp.path().join("scie-jump");
+        std::fs::write(&scie_jump_path, scie_jump).unwrap();
+
+        std::fs::write(tmp.path().join("data.txt"), "data").unwrap();
+        std::fs::create_dir(tmp.path().join("app")).unwrap();
+        std::fs::write(tmp.path().join("app").join("run.sh"), "echo hi").unwrap();
+        std::fs::write(
+            tmp.path().join("lift.json"),
+            r#"{"scie":{"lift":{"name":"example","files":[{"name":"data.txt"},{"name":"app"}],
+                "boot":{"commands":{"":{"exe":"{app}/run.sh","args":["{data.txt}"]}}}}}}"#,
+        )
+        .unwrap();
+        let jump = crate::load_scie_jump(&scie_jump_path).unwrap();
+        let (lift, manifest) = load_manifest(
+            tmp.path(),
+            &jump,
+            UnknownFields::Deny,
+            &mut PackCache::default(),
+        )
+        .unwrap();
+        let scie = pack(
+            lift,
+            &manifest,
+            &jump,
+            &scie_jump_path,
+            tmp.path(),
+            &PackOptions::default(),
+        )
+        .unwrap()
+        .binary;
+
+        let new_data = tmp.path().join("new-data.txt");
+        std::fs::write(&new_data, "new data").unwrap();
+        let extra = tmp.path().join("extra.txt");
+        std::fs::write(&extra, "extra").unwrap();
+        let repacked = tmp.path().join("repacked");
+        Repack::new(&scie)
+            .file("data.txt", &new_data)
+            .file("extra.txt", &extra)
+            .write(&repacked)
+            .unwrap();
+
+        let config = crate::inspect(&repacked).unwrap();
+        let files = config.scie.lift.files;
+        assert_eq!(
+            vec!["data.txt", "extra.txt", "app"],
+            files
+                .iter()
+                .map(|file| file.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        let data = std::fs::read(&repacked).unwrap();
+        for (file, expected) in files.iter().zip([Some(&new_data), Some(&extra), None]) {
+            let offset = file.offset.unwrap();
+            let bytes = &data[offset..offset + file.size.unwrap()];
+            assert_eq!(
+                file.hash.as_deref(),
+                Some(fingerprint::digest(bytes).as_str())
+            );
+            if let Some(expected) = expected {
+                assert_eq!(std::fs::read(expected).unwrap(), bytes);
+            }
+        }
+
+        let mut new_scie_jump = b"#!/bin/sh\n# A newer scie-jump.\n".to_vec();
+        let size = new_scie_jump.len() as u32 + 8;
+        new_scie_jump.write_u32::<LittleEndian>(size).unwrap();
+        new_scie_jump.write_u32::<LittleEndian>(EOF_MAGIC).unwrap();
+        let new_scie_jump_path = tmp.path().join("scie-jump-new");
+        std::fs::write(&new_scie_jump_path, &new_scie_jump).unwrap();
+        let packed = Repack::new(&repacked)
+            .scie_jump(&new_scie_jump_path)
+            .write_in_place()
+            .unwrap();
+        assert_eq!(repacked, packed.binary);
+        assert_eq!(new_scie_jump.len(), packed.jump.size);
+
+        let config = crate::inspect(&repacked).unwrap();
+        assert_eq!(new_scie_jump.len(), config.scie.jump.as_ref().unwrap().size);
+        let data = std::fs::read(&repacked).unwrap();
+        assert_eq!(new_scie_jump, data[..new_scie_jump.len()]);
+        for (before, after) in files.iter().zip(&config.scie.lift.files) {
+            assert_eq!(before.hash, after.hash);
+            let offset = after.offset.unwrap();
+            let bytes = &data[offset..offset + after.size.unwrap()];
+            assert_eq!(
+                after.hash.as_deref(),
+                Some(fingerprint::digest(bytes).as_str())
+            );
+        }
+
+        let Err(error) = Repack::new(&scie)
+            .file("app", &new_data)
+            .write(&tmp.path().join("bad"))
+        else {
+            panic!("Expected replacing a directory with a blob to fail.");
+        };
+        assert!(matches!(error, JumpError::Config(_)), "{error:?}");
+        assert!(
+            error.to_string().contains("must be replaced with a zip"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Periodic re-verification of the files extracted to the `nce` cache; see the lift `"integrity"`
+//! `"reverify"` interval and `SCIE_REVERIFY`.
+//!
+//! Once its completion marker is written, an extracted blob is only re-hashed when its size or
+//! modification time changes and an extracted directory is never re-hashed at all. That is cheap
+//! but leaves long-lived caches open to bit-rot and to partial deletions. With an interval set, an
+//! entry last verified longer ago than the interval is re-hashed in full; directories against the
+//! `<dir>.sha256` listing of their files written when they were extracted.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rayon::prelude::*;
+
+use crate::atomic::{marker_path, Target};
+use crate::config::{parse_interval, Integrity};
+use crate::fingerprint;
+
+/// Returns the interval after which extracted files should be re-verified, if any.
+///
+/// The `SCIE_REVERIFY` environment variable overrides the lift manifest's interval.
+pub(crate) fn interval(integrity: Option<&Integrity>) -> Result<Option<Duration>, String> {
+    match std::env::var("SCIE_REVERIFY") {
+        Ok(value) if !value.is_empty() => parse_interval(&value)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse SCIE_REVERIFY={value}: {e}")),
+        _ => integrity.map_or(Ok(None), Integrity::reverify),
+    }
+}
+
+fn listing_path(target: &Path) -> PathBuf {
+    let mut listing = target.as_os_str().to_os_string();
+    listing.push(".sha256");
+    PathBuf::from(listing)
+}
+
+// N.B.: We follow `sha256sum`'s escaping of file names containing backslashes or newlines; so the
+// listing can also be checked by hand with `sha256sum -c`.
+fn escape(name: &str) -> (&'static str, String) {
+    if name.contains(['\\', '\n']) {
+        ("\\", name.replace('\\', "\\\\").replace('\n', "\\n"))
+    } else {
+        ("", name.to_string())
+    }
+}
+
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                chars.next();
+                unescaped.push('\n');
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                unescaped.push('\\');
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn parse_listing(listing: &str) -> Option<Vec<(&str, String)>> {
+    listing
+        .lines()
+        .map(|line| {
+            let (escaped, line) = match line.strip_prefix('\\') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (hash, name) = line.split_once("  ")?;
+            Some((
+                hash,
+                if escaped {
+                    unescape(name)
+                } else {
+                    name.to_string()
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Writes the `<dir>.sha256` listing of the hashes of the regular files in the extracted `dir`.
+pub(crate) fn record(dir: &Path) -> Result<(), String> {
+    let mut names = vec![];
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.map_err(|e| {
+            format!(
+                "Failed to walk {dir} to list its files: {e}",
+                dir = dir.display()
+            )
+        })?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative_path = entry.path().strip_prefix(dir).map_err(|e| format!("{e}"))?;
+        let name = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()
+            .map(|components| components.join("/"));
+        match name {
+            Some(name) => names.push(name),
+            None => debug!(
+                "Not listing {path} since its name is not valid UTF-8.",
+                path = entry.path().display()
+            ),
+        }
+    }
+    let hashes = names
+        .par_iter()
+        .map(|name| fingerprint::digest_file(&dir.join(name)).map(|(_, hash)| hash))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            format!(
+                "Failed to hash the files in {dir}: {e}",
+                dir = dir.display()
+            )
+        })?;
+    let mut listing = String::new();
+    for (hash, name) in hashes.iter().zip(names.iter()) {
+        let (prefix, name) = escape(name);
+        listing.push_str(&format!("{prefix}{hash}  {name}\n"));
+    }
+
+    let path = listing_path(dir);
+    let parent = path.parent().unwrap_or(dir);
+    tempfile::NamedTempFile::new_in(parent)
+        .and_then(|mut temp| {
+            temp.write_all(listing.as_bytes())?;
+            temp.persist(&path)?;
+            Ok(())
+        })
+        .map_err(|e| {
+            format!(
+                "Failed to write the listing {path} of the files in {dir}: {e}",
+                path = path.display(),
+                dir = dir.display()
+            )
+        })
+}
+
+/// Returns `true` if the extracted `target` was last verified at least `interval` ago.
+pub(crate) fn is_due(target: &Path, interval: Duration) -> bool {
+    marker_path(target)
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|verified| SystemTime::now().duration_since(verified).ok())
+        .map_or(false, |elapsed| elapsed >= interval)
+}
+
+/// The outcome of re-verifying an extracted file.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum Check {
+    Intact,
+    /// The paths, relative to an extracted directory, of the files whose contents no longer match
+    /// or that are missing; always empty for an extracted blob.
+    Mutated(Vec<String>),
+    /// The extracted directory has no listing to verify its files against; e.g.: because it was
+    /// extracted before re-verification was configured.
+    Unlisted,
+}
+
+/// Re-hashes the extracted `target` in full.
+pub(crate) fn check(target: &Path, target_type: Target, fingerprint: &str) -> Check {
+    if Target::File == target_type {
+        return match fingerprint::digest_file(target) {
+            Ok((_, hash)) if hash == fingerprint => Check::Intact,
+            _ => Check::Mutated(vec![]),
+        };
+    }
+    let Ok(listing) = std::fs::read_to_string(listing_path(target)) else {
+        return Check::Unlisted;
+    };
+    let Some(entries) = parse_listing(&listing) else {
+        return Check::Unlisted;
+    };
+    let mut mutated = entries
+        .par_iter()
+        .filter_map(
+            |(hash, name)| match fingerprint::digest_file(&target.join(name)) {
+                Ok((_, actual)) if actual == *hash => None,
+                _ => Some(name.clone()),
+            },
+        )
+        .collect::<Vec<_>>();
+    if mutated.is_empty() {
+        return Check::Intact;
+    }
+    mutated.sort();
+    Check::Mutated(mutated)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{check, escape, is_due, listing_path, parse_listing, record, Check};
+    use crate::atomic::{marker_path, Target};
+    use crate::fingerprint;
+
+    #[test]
+    fn escaping() {
+        let (prefix, escaped) = escape("a\\b\nc");
+        assert_eq!("\\", prefix);
+        let line = format!("{prefix}abc  {escaped}");
+        assert_eq!("\\abc  a\\\\b\\nc", line);
+        assert_eq!(
+            Some(vec![("abc", "a\\b\nc".to_string())]),
+            parse_listing(&line)
+        );
+        assert_eq!(("", "plain name".to_string()), escape("plain name"));
+        assert_eq!(None, parse_listing("no separator"));
+    }
+
+    #[test]
+    fn directory() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("dir");
+        std::fs::create_dir_all(target.join("sub")).unwrap();
+        std::fs::write(target.join("a"), "a").unwrap();
+        std::fs::write(target.join("sub").join("b"), "b").unwrap();
+
+        assert_eq!(Check::Unlisted, check(&target, Target::Directory, "abc"));
+        record(&target).unwrap();
+        assert_eq!(
+            format!(
+                "{a}  a\n{b}  sub/b\n",
+                a = fingerprint::digest(b"a"),
+                b = fingerprint::digest(b"b")
+            ),
+            std::fs::read_to_string(listing_path(&target)).unwrap()
+        );
+        assert_eq!(Check::Intact, check(&target, Target::Directory, "abc"));
+
+        // Files added after extraction, like byte code caches, are not mutations.
+        std::fs::write(target.join("c"), "c").unwrap();
+        assert_eq!(Check::Intact, check(&target, Target::Directory, "abc"));
+
+        std::fs::write(target.join("sub").join("b"), "rot").unwrap();
+        std::fs::remove_file(target.join("a")).unwrap();
+        assert_eq!(
+            Check::Mutated(vec!["a".to_string(), "sub/b".to_string()]),
+            check(&target, Target::Directory, "abc")
+        );
+    }
+
+    #[test]
+    fn file() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("file");
+        std::fs::write(&target, "contents").unwrap();
+        let hash = fingerprint::digest(b"contents");
+
+        assert_eq!(Check::Intact, check(&target, Target::File, &hash));
+        std::fs::write(&target, "rot").unwrap();
+        assert_eq!(Check::Mutated(vec![]), check(&target, Target::File, &hash));
+    }
+
+    #[test]
+    fn due() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("file");
+        assert!(!is_due(&target, Duration::ZERO));
+
+        std::fs::write(marker_path(&target), "abc").unwrap();
+        assert!(is_due(&target, Duration::ZERO));
+        assert!(!is_due(&target, Duration::from_secs(60 * 60)));
+    }
+}
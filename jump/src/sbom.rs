@@ -0,0 +1,266 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use serde::Serialize;
+
+use crate::config::{Config, File, FileType, Jump};
+
+const SPEC_VERSION: &str = "1.5";
+
+#[derive(Debug, Serialize)]
+pub struct Hash {
+    pub alg: &'static str,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+}
+
+impl Property {
+    fn new(name: &str, value: impl ToString) -> Self {
+        Self {
+            name: format!("scie:{name}"),
+            value: value.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct License {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LicenseChoice {
+    pub license: License,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExternalReference {
+    #[serde(rename = "type")]
+    pub reference_type: &'static str,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Component {
+    #[serde(rename = "type")]
+    pub component_type: &'static str,
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hashes: Vec<Hash>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<LicenseChoice>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub external_references: Vec<ExternalReference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Metadata {
+    pub component: Component,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Dependency {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    #[serde(rename = "dependsOn")]
+    pub depends_on: Vec<String>,
+}
+
+/// A CycloneDX software bill of materials describing the files embedded in a scie.
+///
+/// See: https://cyclonedx.org/docs/1.5/json/
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sbom {
+    pub bom_format: &'static str,
+    pub spec_version: &'static str,
+    pub version: u32,
+    pub metadata: Metadata,
+    pub components: Vec<Component>,
+    pub dependencies: Vec<Dependency>,
+}
+
+fn jump_component(jump: &Jump) -> Component {
+    Component {
+        component_type: "application",
+        bom_ref: "scie-jump".to_string(),
+        name: "scie-jump".to_string(),
+        version: Some(jump.version.clone()).filter(|version| !version.is_empty()),
+        description: None,
+        hashes: vec![],
+        licenses: vec![],
+        external_references: vec![],
+        properties: vec![Property::new("size", jump.size)],
+    }
+}
+
+fn file_component(file: &File) -> Component {
+    let mut properties = vec![];
+    if let Some(file_type) = &file.file_type {
+        let file_type = match file_type {
+            FileType::Archive(archive_type) => archive_type.as_ext(),
+            FileType::Blob => "blob",
+            FileType::Directory => "directory",
+        };
+        properties.push(Property::new("type", file_type));
+    }
+    if let Some(size) = file.size {
+        properties.push(Property::new("size", size));
+    }
+    if let Some(key) = &file.key {
+        properties.push(Property::new("key", key));
+    }
+    if let Some(binding) = &file.source {
+        properties.push(Property::new("source", binding));
+    }
+    Component {
+        component_type: "file",
+        bom_ref: format!("file:{name}", name = file.name),
+        name: file.name.clone(),
+        version: file.version.clone(),
+        description: None,
+        hashes: file
+            .hash
+            .iter()
+            .map(|hash| Hash {
+                alg: "SHA-256",
+                content: hash.clone(),
+            })
+            .collect(),
+        licenses: file
+            .license
+            .iter()
+            .map(|license| LicenseChoice {
+                license: License {
+                    name: license.clone(),
+                },
+            })
+            .collect(),
+        external_references: file
+            .source_url
+            .iter()
+            .map(|url| ExternalReference {
+                reference_type: "distribution",
+                url: url.clone(),
+            })
+            .collect(),
+        properties,
+    }
+}
+
+/// Generates a CycloneDX SBOM for the scie with the given lift manifest.
+///
+/// The scie itself is the subject of the SBOM and it is described as depending on its scie-jump
+/// and on each of its files.
+pub fn sbom(config: &Config) -> Sbom {
+    let lift = &config.scie.lift;
+    let mut components = vec![];
+    if let Some(jump) = &config.scie.jump {
+        components.push(jump_component(jump));
+    }
+    components.extend(lift.files.iter().map(file_component));
+    let component = Component {
+        component_type: "application",
+        bom_ref: lift.name.clone(),
+        name: lift.name.clone(),
+        version: None,
+        description: lift.description.clone(),
+        hashes: vec![],
+        licenses: vec![],
+        external_references: vec![],
+        properties: vec![],
+    };
+    let dependencies = vec![Dependency {
+        reference: component.bom_ref.clone(),
+        depends_on: components
+            .iter()
+            .map(|component| component.bom_ref.clone())
+            .collect(),
+    }];
+    Sbom {
+        bom_format: "CycloneDX",
+        spec_version: SPEC_VERSION,
+        version: 1,
+        metadata: Metadata { component },
+        components,
+        dependencies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sbom;
+    use crate::config::{Config, UnknownFields};
+
+    #[test]
+    fn cyclonedx() {
+        let config = Config::parse(
+            br#"{"scie":{"lift":{"name":"example","files":[
+                {"name":"cpython.tar.gz","hash":"abc","size":42,"type":"tar.gz","version":"3.12.0",
+                 "license":"PSF-2.0","source_url":"https://example.com/cpython.tar.gz"},
+                {"name":"app.pex","key":"app","hash":"def","type":"blob","source":"fetch"}],
+                "boot":{"commands":{"":{"exe":"{cpython.tar.gz}/python"}}}},
+                "jump":{"size":1024,"version":"0.11.0"}}}"#,
+            UnknownFields::Deny,
+        )
+        .unwrap();
+        let document = serde_json::to_value(sbom(&config)).unwrap();
+        assert_eq!("CycloneDX", document["bomFormat"]);
+        assert_eq!("example", document["metadata"]["component"]["name"]);
+
+        let components = document["components"].as_array().unwrap();
+        assert_eq!(3, components.len());
+        assert_eq!("scie-jump", components[0]["name"]);
+        assert_eq!("0.11.0", components[0]["version"]);
+        assert_eq!("cpython.tar.gz", components[1]["name"]);
+        assert_eq!("3.12.0", components[1]["version"]);
+        assert_eq!(
+            serde_json::json!([{"license": {"name": "PSF-2.0"}}]),
+            components[1]["licenses"]
+        );
+        assert_eq!(
+            serde_json::json!([{"type": "distribution", "url": "https://example.com/cpython.tar.gz"}]),
+            components[1]["externalReferences"]
+        );
+        assert_eq!(
+            serde_json::json!([{"alg": "SHA-256", "content": "abc"}]),
+            components[1]["hashes"]
+        );
+        assert_eq!(
+            serde_json::json!([
+                {"name": "scie:type", "value": "tar.gz"},
+                {"name": "scie:size", "value": "42"}
+            ]),
+            components[1]["properties"]
+        );
+        assert_eq!(
+            serde_json::json!([
+                {"name": "scie:type", "value": "blob"},
+                {"name": "scie:key", "value": "app"},
+                {"name": "scie:source", "value": "fetch"}
+            ]),
+            components[2]["properties"]
+        );
+        assert_eq!(
+            serde_json::json!([{
+                "ref": "example",
+                "dependsOn": ["scie-jump", "file:cpython.tar.gz", "file:app.pex"]
+            }]),
+            document["dependencies"]
+        );
+    }
+}
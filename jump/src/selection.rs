@@ -0,0 +1,259 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fmt::{Display, Formatter};
+
+use crate::SelectBoot;
+
+/// What to tell the user when the boot command to run could not be selected.
+///
+/// This is returned by `select` without printing anything or exiting; its `Display`
+/// implementation renders the message the scie-jump prints.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Selection {
+    /// The scie has no enabled boot commands at all; so it is malformed.
+    Malformed { scie: String },
+    /// The scie only has a default boot command, which could not be selected.
+    NoAlternates { scie: String, error_message: String },
+    /// The user can select one of these boot commands.
+    Choices {
+        error_message: String,
+        /// The description of the scie, if it has one.
+        description: Option<String>,
+        /// The description of the default boot command, if there is one.
+        default: Option<String>,
+        /// The names and descriptions of the boot commands that can be selected by name.
+        commands: Vec<(String, String)>,
+    },
+}
+
+/// Determines what to tell the user about the boot commands they can select from.
+pub fn select(select_boot: &SelectBoot) -> Selection {
+    let default = select_boot
+        .boots
+        .iter()
+        .find(|boot| boot.default)
+        .map(|boot| boot.description.clone().unwrap_or_default());
+    let mut commands = select_boot
+        .boots
+        .iter()
+        .filter(|boot| !boot.default)
+        .filter_map(|boot| {
+            boot.description
+                .as_ref()
+                .map(|desc| (boot.name.clone(), desc.clone()))
+        })
+        .collect::<Vec<_>>();
+
+    // Only include hidden named commands when that's all there is.
+    if commands.is_empty() && default.is_none() {
+        commands.extend(
+            select_boot
+                .boots
+                .iter()
+                .filter(|boot| !boot.default)
+                .map(|boot| (boot.name.clone(), "".to_string())),
+        );
+    }
+
+    if commands.is_empty() {
+        return match default {
+            None => Selection::Malformed {
+                scie: select_boot.scie.invoked_as(),
+            },
+            Some(_) => Selection::NoAlternates {
+                scie: select_boot.scie.invoked_as(),
+                error_message: select_boot.error_message.clone(),
+            },
+        };
+    }
+    Selection::Choices {
+        error_message: select_boot.error_message.clone(),
+        description: select_boot.description.clone(),
+        default,
+        commands,
+    }
+}
+
+impl Display for Selection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Selection::Malformed { scie } => write!(
+                f,
+                "The {scie} scie is malformed - it has no boot commands.\n\
+                \n\
+                You might begin debugging by inspecting the output of `SCIE=inspect {scie}`."
+            ),
+            Selection::NoAlternates {
+                scie,
+                error_message,
+            } => write!(
+                f,
+                "{error_message}\n\
+                \n\
+                The {scie} scie contains no alternate boot commands."
+            ),
+            Selection::Choices {
+                error_message,
+                description,
+                default,
+                commands,
+            } => {
+                let default = default.as_ref().map(|description| {
+                    (
+                        "<default> (when SCIE_BOOT is not set in the environment)",
+                        description.as_str(),
+                    )
+                });
+                let choices = default
+                    .into_iter()
+                    .chain(
+                        commands
+                            .iter()
+                            .map(|(name, description)| (name.as_str(), description.as_str())),
+                    )
+                    .collect::<Vec<_>>();
+                let max_name_width = choices
+                    .iter()
+                    .map(|(name, _)| name.len())
+                    .max()
+                    .unwrap_or_default();
+                write!(f, "{error_message}\n\n")?;
+                if let Some(description) = description {
+                    write!(f, "{description}\n\n")?;
+                }
+                writeln!(f, "Please select from the following boot commands:\n")?;
+                for (name, description) in choices {
+                    if description.is_empty() {
+                        writeln!(f, "{name}")?;
+                    } else {
+                        writeln!(f, "{name:<max_name_width$}  {description}")?;
+                    }
+                }
+                write!(
+                    f,
+                    "\nYou can select a boot command by setting the SCIE_BOOT environment \
+                    variable{or_else_by}.",
+                    or_else_by = if default.is_none() {
+                        " or else by passing it as the 1st argument"
+                    } else {
+                        ""
+                    }
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{select, Selection};
+    use crate::{CurrentExe, ScieBoot, SelectBoot};
+
+    fn select_boot(boots: &[(&str, Option<&str>, bool)]) -> SelectBoot {
+        SelectBoot {
+            scie: CurrentExe {
+                exe: PathBuf::from("/opt/tool"),
+                invoked_as: PathBuf::from("./tool"),
+            },
+            boots: boots
+                .iter()
+                .map(|(name, description, default)| ScieBoot {
+                    name: name.to_string(),
+                    description: description.map(str::to_string),
+                    default: *default,
+                })
+                .collect(),
+            description: None,
+            error_message: "No command selected.".to_string(),
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        let selection = select(&select_boot(&[]));
+        assert_eq!(
+            Selection::Malformed {
+                scie: "./tool".to_string()
+            },
+            selection
+        );
+        assert_eq!(
+            "The ./tool scie is malformed - it has no boot commands.\n\
+            \n\
+            You might begin debugging by inspecting the output of `SCIE=inspect ./tool`.",
+            selection.to_string()
+        );
+    }
+
+    #[test]
+    fn no_alternates() {
+        let selection = select(&select_boot(&[("", None, true), ("hidden", None, false)]));
+        assert_eq!(
+            Selection::NoAlternates {
+                scie: "./tool".to_string(),
+                error_message: "No command selected.".to_string()
+            },
+            selection
+        );
+        assert_eq!(
+            "No command selected.\n\nThe ./tool scie contains no alternate boot commands.",
+            selection.to_string()
+        );
+    }
+
+    #[test]
+    fn choices() {
+        let mut select_boot = select_boot(&[
+            ("", Some("Runs the tool."), true),
+            ("fmt", Some("Formats."), false),
+            ("hidden", None, false),
+        ]);
+        select_boot.description = Some("A tool.".to_string());
+        assert_eq!(
+            "No command selected.\n\
+            \n\
+            A tool.\n\
+            \n\
+            Please select from the following boot commands:\n\
+            \n\
+            <default> (when SCIE_BOOT is not set in the environment)  Runs the tool.\n\
+            fmt                                                       Formats.\n\
+            \n\
+            You can select a boot command by setting the SCIE_BOOT environment variable.",
+            select(&select_boot).to_string()
+        );
+
+        // Hidden commands are only listed when there is nothing else to select from.
+        let selection = select(&self::select_boot(&[
+            ("a", None, false),
+            ("b", None, false),
+        ]));
+        assert_eq!(
+            Selection::Choices {
+                error_message: "No command selected.".to_string(),
+                description: None,
+                default: None,
+                commands: vec![
+                    ("a".to_string(), "".to_string()),
+                    ("b".to_string(), "".to_string())
+                ],
+            },
+            selection
+        );
+        assert_eq!(
+            "No command selected.\n\
+            \n\
+            Please select from the following boot commands:\n\
+            \n\
+            a\n\
+            b\n\
+            \n\
+            You can select a boot command by setting the SCIE_BOOT environment variable or \
+            else by passing it as the 1st argument.",
+            selection.to_string()
+        );
+    }
+}
@@ -0,0 +1,693 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Verification of a scie against a Sigstore bundle before it boots.
+//!
+//! Verification is opt-in and configured by whoever runs the scie via `SCIE_SIGSTORE_*` env vars;
+//! a policy carried in the scie's own lift manifest could simply be stripped along with the
+//! signature by anyone able to tamper with the scie.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::error::JumpError;
+
+/// The certificate identity, issuer and trust root a scie's Sigstore bundle must match.
+#[cfg_attr(not(feature = "sigstore"), allow(dead_code))]
+pub(crate) struct Policy {
+    identity: String,
+    issuer: String,
+    trusted_root: PathBuf,
+    bundle: PathBuf,
+}
+
+impl Policy {
+    /// Reads the verification policy from the environment if `SCIE_SIGSTORE_IDENTITY` is set.
+    pub(crate) fn from_env(scie: &Path) -> Result<Option<Self>, JumpError> {
+        let Some(identity) = env::var_os("SCIE_SIGSTORE_IDENTITY") else {
+            return Ok(None);
+        };
+        let identity = identity.into_string().map_err(|value| {
+            JumpError::Other(format!(
+                "The SCIE_SIGSTORE_IDENTITY value is not valid UTF-8: {value:?}"
+            ))
+        })?;
+        let issuer = env::var("SCIE_SIGSTORE_ISSUER").map_err(|e| {
+            JumpError::Other(format!(
+                "Verifying this scie as signed by {identity} requires SCIE_SIGSTORE_ISSUER to name \
+                the OIDC issuer that vouched for that identity: {e}"
+            ))
+        })?;
+        let trusted_root = env::var_os("SCIE_SIGSTORE_TRUSTED_ROOT")
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                JumpError::Other(format!(
+                    "Verifying this scie as signed by {identity} requires SCIE_SIGSTORE_TRUSTED_ROOT \
+                    to point to a Sigstore trusted_root.json."
+                ))
+            })?;
+        let bundle = env::var_os("SCIE_SIGSTORE_BUNDLE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                let mut bundle = scie.as_os_str().to_os_string();
+                bundle.push(".sigstore.json");
+                PathBuf::from(bundle)
+            });
+        Ok(Some(Self {
+            identity,
+            issuer,
+            trusted_root,
+            bundle,
+        }))
+    }
+}
+
+#[cfg(not(feature = "sigstore"))]
+pub(crate) fn verify(_policy: &Policy, _data: &[u8]) -> Result<(), JumpError> {
+    Err(JumpError::Other(
+        "This scie-jump was built without sigstore support; so it cannot verify the scie as \
+        requested by SCIE_SIGSTORE_IDENTITY."
+            .to_string(),
+    ))
+}
+
+#[cfg(feature = "sigstore")]
+pub(crate) use self::verification::verify;
+
+#[cfg(feature = "sigstore")]
+mod verification {
+    use std::path::Path;
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use logging_timer::time;
+    use p256::ecdsa::signature::hazmat::PrehashVerifier;
+    use p256::ecdsa::signature::Verifier;
+    use p256::pkcs8::DecodePublicKey;
+    use serde::de::DeserializeOwned;
+    use serde::Deserialize;
+    use sha2::{Digest, Sha256};
+    use x509_cert::der::asn1::{ObjectIdentifier, Utf8StringRef};
+    use x509_cert::der::{Decode, Encode};
+    use x509_cert::ext::pkix::name::GeneralName;
+    use x509_cert::ext::pkix::SubjectAltName;
+    use x509_cert::Certificate;
+
+    use super::Policy;
+    use crate::error::JumpError;
+
+    const SUBJECT_ALT_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.17");
+    // See: https://github.com/sigstore/fulcio/blob/main/docs/oid-info.md
+    const ISSUER_V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.1");
+    const ISSUER_V2: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.8");
+    const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+    const ECDSA_WITH_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct RawBytes {
+        raw_bytes: String,
+    }
+
+    #[derive(Deserialize)]
+    struct CertificateChain {
+        certificates: Vec<RawBytes>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct LogId {
+        key_id: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct InclusionPromise {
+        signed_entry_timestamp: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TlogEntry {
+        log_index: String,
+        log_id: LogId,
+        integrated_time: String,
+        inclusion_promise: Option<InclusionPromise>,
+        canonicalized_body: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct VerificationMaterial {
+        certificate: Option<RawBytes>,
+        x509_certificate_chain: Option<CertificateChain>,
+        #[serde(default)]
+        tlog_entries: Vec<TlogEntry>,
+    }
+
+    #[derive(Deserialize)]
+    struct MessageDigest {
+        algorithm: String,
+        digest: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct MessageSignature {
+        message_digest: MessageDigest,
+        signature: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Bundle {
+        verification_material: VerificationMaterial,
+        message_signature: Option<MessageSignature>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct CertificateAuthority {
+        cert_chain: CertificateChain,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TransparencyLog {
+        public_key: RawBytes,
+        log_id: LogId,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TrustedRoot {
+        certificate_authorities: Vec<CertificateAuthority>,
+        tlogs: Vec<TransparencyLog>,
+    }
+
+    // N.B.: Only the fields of a Rekor `hashedrekord` entry that tie it to the bundle are modelled.
+    #[derive(Deserialize)]
+    struct HashedRekord {
+        spec: HashedRekordSpec,
+    }
+
+    #[derive(Deserialize)]
+    struct HashedRekordSpec {
+        data: HashedRekordData,
+        signature: HashedRekordSignature,
+    }
+
+    #[derive(Deserialize)]
+    struct HashedRekordData {
+        hash: HashedRekordHash,
+    }
+
+    #[derive(Deserialize)]
+    struct HashedRekordHash {
+        algorithm: String,
+        value: String,
+    }
+
+    #[derive(Deserialize)]
+    struct HashedRekordSignature {
+        content: String,
+    }
+
+    fn fail(message: String) -> JumpError {
+        JumpError::Integrity(format!("Sigstore verification failed: {message}"))
+    }
+
+    fn load<T: DeserializeOwned>(path: &Path, description: &str) -> Result<T, JumpError> {
+        let data = std::fs::read(path).map_err(|e| {
+            JumpError::io(
+                format!(
+                    "Failed to read the Sigstore {description} at {path}",
+                    path = path.display()
+                ),
+                e,
+            )
+        })?;
+        serde_json::from_slice(&data).map_err(|e| {
+            JumpError::Other(format!(
+                "Failed to parse the Sigstore {description} at {path}: {e}",
+                path = path.display()
+            ))
+        })
+    }
+
+    fn decode(value: &str, description: &str) -> Result<Vec<u8>, JumpError> {
+        STANDARD
+            .decode(value)
+            .map_err(|e| fail(format!("The {description} is not valid base64: {e}")))
+    }
+
+    fn certificate(raw_bytes: &RawBytes) -> Result<Certificate, JumpError> {
+        let der = decode(&raw_bytes.raw_bytes, "certificate")?;
+        Certificate::from_der(&der).map_err(|e| fail(format!("Failed to parse certificate: {e}")))
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    fn verify_issued_by(leaf: &Certificate, issuer: &Certificate) -> Result<(), JumpError> {
+        let message = leaf
+            .tbs_certificate
+            .to_der()
+            .map_err(|e| fail(format!("Failed to encode certificate: {e}")))?;
+        let signature = leaf.signature.raw_bytes();
+        let issuer_key = issuer
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|e| fail(format!("Failed to encode issuer public key: {e}")))?;
+        let verified = match leaf.signature_algorithm.oid {
+            ECDSA_WITH_SHA384 => p384::ecdsa::VerifyingKey::from_public_key_der(&issuer_key)
+                .ok()
+                .zip(p384::ecdsa::Signature::from_der(signature).ok())
+                .map(|(key, signature)| key.verify(&message, &signature).is_ok()),
+            ECDSA_WITH_SHA256 => p256::ecdsa::VerifyingKey::from_public_key_der(&issuer_key)
+                .ok()
+                .zip(p256::ecdsa::Signature::from_der(signature).ok())
+                .map(|(key, signature)| key.verify(&message, &signature).is_ok()),
+            oid => {
+                return Err(fail(format!(
+                    "The signing certificate uses an unsupported signature algorithm: {oid}"
+                )))
+            }
+        };
+        if verified == Some(true) {
+            Ok(())
+        } else {
+            Err(fail(
+                "The signing certificate was not issued by the trusted certificate authority."
+                    .to_string(),
+            ))
+        }
+    }
+
+    fn identities(leaf: &Certificate) -> Result<(Vec<String>, Option<String>), JumpError> {
+        let mut identities = vec![];
+        let mut issuer = None;
+        for extension in leaf.tbs_certificate.extensions.iter().flatten() {
+            let value = extension.extn_value.as_bytes();
+            match extension.extn_id {
+                SUBJECT_ALT_NAME => {
+                    let names = SubjectAltName::from_der(value).map_err(|e| {
+                        fail(format!("Failed to parse the certificate identity: {e}"))
+                    })?;
+                    for name in names.0 {
+                        match name {
+                            GeneralName::Rfc822Name(email) => identities.push(email.to_string()),
+                            GeneralName::UniformResourceIdentifier(uri) => {
+                                identities.push(uri.to_string())
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                ISSUER_V2 => {
+                    let value = Utf8StringRef::from_der(value).map_err(|e| {
+                        fail(format!("Failed to parse the certificate issuer: {e}"))
+                    })?;
+                    issuer = Some(value.to_string());
+                }
+                ISSUER_V1 if issuer.is_none() => {
+                    issuer = Some(String::from_utf8_lossy(value).into_owned());
+                }
+                _ => {}
+            }
+        }
+        Ok((identities, issuer))
+    }
+
+    fn verify_tlog_entry(
+        trusted_root: &TrustedRoot,
+        entry: &TlogEntry,
+        digest: &[u8],
+        signature: &str,
+    ) -> Result<u64, JumpError> {
+        let tlog = trusted_root
+            .tlogs
+            .iter()
+            .find(|tlog| tlog.log_id.key_id == entry.log_id.key_id)
+            .ok_or_else(|| {
+                fail("The bundle's transparency log is not a trusted one.".to_string())
+            })?;
+        let promise = entry.inclusion_promise.as_ref().ok_or_else(|| {
+            fail("The bundle's transparency log entry has no inclusion promise.".to_string())
+        })?;
+        let integrated_time = entry.integrated_time.parse::<u64>().map_err(|e| {
+            fail(format!(
+                "Invalid transparency log integrated time {time}: {e}",
+                time = entry.integrated_time
+            ))
+        })?;
+        let log_index = entry.log_index.parse::<u64>().map_err(|e| {
+            fail(format!(
+                "Invalid transparency log index {index}: {e}",
+                index = entry.log_index
+            ))
+        })?;
+
+        // N.B.: Rekor signs the canonical JSON (sorted keys, no whitespace) of these fields.
+        let payload = format!(
+            r#"{{"body":"{body}","integratedTime":{integrated_time},"logID":"{log_id}","logIndex":{log_index}}}"#,
+            body = entry.canonicalized_body,
+            log_id = hex(&decode(&entry.log_id.key_id, "transparency log id")?),
+        );
+        let key = p256::ecdsa::VerifyingKey::from_public_key_der(&decode(
+            &tlog.public_key.raw_bytes,
+            "transparency log key",
+        )?)
+        .map_err(|e| fail(format!("Unsupported transparency log key: {e}")))?;
+        let set = p256::ecdsa::Signature::from_der(&decode(
+            &promise.signed_entry_timestamp,
+            "signed entry timestamp",
+        )?)
+        .map_err(|e| fail(format!("Invalid signed entry timestamp: {e}")))?;
+        key.verify(payload.as_bytes(), &set).map_err(|_| {
+            fail("The transparency log's signed entry timestamp does not match.".to_string())
+        })?;
+
+        let body: HashedRekord = serde_json::from_slice(&decode(
+            &entry.canonicalized_body,
+            "transparency log entry",
+        )?)
+        .map_err(|e| fail(format!("Unsupported transparency log entry: {e}")))?;
+        if body.spec.data.hash.algorithm != "sha256"
+            || body.spec.data.hash.value != hex(digest)
+            || decode(&body.spec.signature.content, "logged signature")?
+                != decode(signature, "signature")?
+        {
+            return Err(fail(
+                "The transparency log entry is for a different signature.".to_string(),
+            ));
+        }
+        Ok(integrated_time)
+    }
+
+    /// Verifies `data`, the full contents of a scie, against the Sigstore bundle named by `policy`.
+    #[time("debug", "sigstore::{}")]
+    pub(crate) fn verify(policy: &Policy, data: &[u8]) -> Result<(), JumpError> {
+        let bundle: Bundle = load(&policy.bundle, "bundle")?;
+        let trusted_root: TrustedRoot = load(&policy.trusted_root, "trusted root")?;
+
+        let message_signature = bundle.message_signature.ok_or_else(|| {
+            fail("Only bundles with a message signature are supported.".to_string())
+        })?;
+        if message_signature.message_digest.algorithm != "SHA2_256" {
+            return Err(fail(format!(
+                "Unsupported message digest algorithm {algorithm}.",
+                algorithm = message_signature.message_digest.algorithm
+            )));
+        }
+        let digest = Sha256::digest(data);
+        if decode(&message_signature.message_digest.digest, "message digest")? != digest[..] {
+            return Err(fail(format!(
+                "The bundle at {bundle} is for different content.",
+                bundle = policy.bundle.display()
+            )));
+        }
+
+        let material = bundle.verification_material;
+        let leaf = match (&material.certificate, &material.x509_certificate_chain) {
+            (Some(raw_bytes), _) => certificate(raw_bytes)?,
+            (None, Some(chain)) if !chain.certificates.is_empty() => {
+                certificate(&chain.certificates[0])?
+            }
+            _ => {
+                return Err(fail(
+                    "The bundle contains no signing certificate.".to_string(),
+                ))
+            }
+        };
+        let key = p256::ecdsa::VerifyingKey::from_public_key_der(
+            &leaf
+                .tbs_certificate
+                .subject_public_key_info
+                .to_der()
+                .map_err(|e| fail(format!("Failed to encode the signing key: {e}")))?,
+        )
+        .map_err(|e| fail(format!("Unsupported signing key: {e}")))?;
+        let signature =
+            p256::ecdsa::Signature::from_der(&decode(&message_signature.signature, "signature")?)
+                .map_err(|e| fail(format!("Invalid signature: {e}")))?;
+        key.verify_prehash(&digest, &signature)
+            .map_err(|_| fail("The signature does not match the scie.".to_string()))?;
+
+        let mut issued = false;
+        for authority in &trusted_root.certificate_authorities {
+            if let Some(issuer) = authority.cert_chain.certificates.first() {
+                let issuer = certificate(issuer)?;
+                if issuer.tbs_certificate.subject == leaf.tbs_certificate.issuer {
+                    verify_issued_by(&leaf, &issuer)?;
+                    issued = true;
+                    break;
+                }
+            }
+        }
+        if !issued {
+            return Err(fail(
+                "The signing certificate was not issued by a trusted certificate authority."
+                    .to_string(),
+            ));
+        }
+
+        let (identities, issuer) = identities(&leaf)?;
+        if !identities.contains(&policy.identity) {
+            return Err(fail(format!(
+                "The scie was signed by {identities} and not {identity}.",
+                identities = identities.join(", "),
+                identity = policy.identity
+            )));
+        }
+        if issuer.as_deref() != Some(policy.issuer.as_str()) {
+            return Err(fail(format!(
+                "The signing identity was issued by {issuer} and not {expected}.",
+                issuer = issuer.as_deref().unwrap_or("<unknown>"),
+                expected = policy.issuer
+            )));
+        }
+
+        // N.B.: Sigstore signing certificates are short-lived; so the transparency log's record of
+        // when the signature was made is what establishes the certificate was valid at the time.
+        let entry = material
+            .tlog_entries
+            .first()
+            .ok_or_else(|| fail("The bundle contains no transparency log entry.".to_string()))?;
+        let integrated_time =
+            verify_tlog_entry(&trusted_root, entry, &digest, &message_signature.signature)?;
+        let validity = &leaf.tbs_certificate.validity;
+        let not_before = validity.not_before.to_unix_duration().as_secs();
+        let not_after = validity.not_after.to_unix_duration().as_secs();
+        if integrated_time < not_before || integrated_time > not_after {
+            return Err(fail(format!(
+                "The signature was logged at {integrated_time} which is outside the signing \
+                certificate's validity of {not_before} to {not_after}."
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "sigstore"))]
+mod tests {
+    use std::path::Path;
+    use std::str::FromStr;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use p256::ecdsa::signature::hazmat::PrehashSigner;
+    use p256::ecdsa::signature::Signer;
+    use p256::pkcs8::EncodePublicKey;
+    use sha2::{Digest, Sha256};
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::der::asn1::{Ia5String, ObjectIdentifier, Utf8StringRef};
+    use x509_cert::der::oid::AssociatedOid;
+    use x509_cert::der::{Encode, Length, Writer};
+    use x509_cert::ext::pkix::name::GeneralName;
+    use x509_cert::ext::pkix::SubjectAltName;
+    use x509_cert::ext::{AsExtension, Extension};
+    use x509_cert::name::Name;
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::spki::SubjectPublicKeyInfoOwned;
+    use x509_cert::time::Validity;
+
+    use super::{verify, Policy};
+
+    const IDENTITY: &str = "release@example.com";
+    const ISSUER: &str = "https://accounts.example.com";
+
+    struct OidcIssuer(&'static str);
+
+    impl AssociatedOid for OidcIssuer {
+        const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.8");
+    }
+
+    impl Encode for OidcIssuer {
+        fn encoded_len(&self) -> x509_cert::der::Result<Length> {
+            Utf8StringRef::new(self.0)?.encoded_len()
+        }
+
+        fn encode(&self, encoder: &mut impl Writer) -> x509_cert::der::Result<()> {
+            Utf8StringRef::new(self.0)?.encode(encoder)
+        }
+    }
+
+    impl AsExtension for OidcIssuer {
+        fn critical(&self, _subject: &Name, _extensions: &[Extension]) -> bool {
+            false
+        }
+    }
+
+    // Signs `data` the way `cosign sign-blob --bundle` would, but with a private certificate
+    // authority and transparency log that are recorded in a trusted root alongside the bundle.
+    fn sign(dir: &Path, data: &[u8]) -> Policy {
+        let ca_key = p384::ecdsa::SigningKey::from_slice(&[1; 48]).unwrap();
+        let ca_name = Name::from_str("CN=sigstore-intermediate,O=example.com").unwrap();
+        let ca = CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1u32),
+            Validity::from_now(Duration::from_secs(3600)).unwrap(),
+            ca_name.clone(),
+            SubjectPublicKeyInfoOwned::from_key(*ca_key.verifying_key()).unwrap(),
+            &ca_key,
+        )
+        .unwrap()
+        .build::<p384::ecdsa::DerSignature>()
+        .unwrap();
+
+        let leaf_key = p256::ecdsa::SigningKey::from_slice(&[2; 32]).unwrap();
+        let mut builder = CertificateBuilder::new(
+            Profile::Leaf {
+                issuer: ca_name,
+                enable_key_agreement: false,
+                enable_key_encipherment: false,
+            },
+            SerialNumber::from(2u32),
+            Validity::from_now(Duration::from_secs(600)).unwrap(),
+            Name::default(),
+            SubjectPublicKeyInfoOwned::from_key(*leaf_key.verifying_key()).unwrap(),
+            &ca_key,
+        )
+        .unwrap();
+        builder
+            .add_extension(&SubjectAltName(vec![GeneralName::Rfc822Name(
+                Ia5String::new(IDENTITY).unwrap(),
+            )]))
+            .unwrap();
+        builder.add_extension(&OidcIssuer(ISSUER)).unwrap();
+        let leaf = builder.build::<p384::ecdsa::DerSignature>().unwrap();
+
+        let digest = Sha256::digest(data);
+        let signature: p256::ecdsa::Signature = leaf_key.sign_prehash(&digest).unwrap();
+        let signature = STANDARD.encode(signature.to_der());
+
+        let log_key = p256::ecdsa::SigningKey::from_slice(&[3; 32]).unwrap();
+        let log_public_key = log_key.verifying_key().to_public_key_der().unwrap();
+        let log_id = Sha256::digest(log_public_key.as_bytes());
+        let body = STANDARD.encode(
+            serde_json::json!({
+                "apiVersion": "0.0.1",
+                "kind": "hashedrekord",
+                "spec": {
+                    "data": {"hash": {"algorithm": "sha256", "value": format!("{digest:x}")}},
+                    "signature": {"content": signature}
+                }
+            })
+            .to_string(),
+        );
+        let integrated_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let set: p256::ecdsa::Signature = log_key.sign(
+            format!(
+                r#"{{"body":"{body}","integratedTime":{integrated_time},"logID":"{log_id:x}","logIndex":42}}"#
+            )
+            .as_bytes(),
+        );
+
+        let bundle = dir.join("scie.sigstore.json");
+        std::fs::write(
+            &bundle,
+            serde_json::json!({
+                "mediaType": "application/vnd.dev.sigstore.bundle+json;version=0.2",
+                "verificationMaterial": {
+                    "x509CertificateChain": {
+                        "certificates": [{"rawBytes": STANDARD.encode(leaf.to_der().unwrap())}]
+                    },
+                    "tlogEntries": [{
+                        "logIndex": "42",
+                        "logId": {"keyId": STANDARD.encode(log_id)},
+                        "kindVersion": {"kind": "hashedrekord", "version": "0.0.1"},
+                        "integratedTime": integrated_time.to_string(),
+                        "inclusionPromise": {"signedEntryTimestamp": STANDARD.encode(set.to_der())},
+                        "canonicalizedBody": body
+                    }]
+                },
+                "messageSignature": {
+                    "messageDigest": {"algorithm": "SHA2_256", "digest": STANDARD.encode(digest)},
+                    "signature": signature
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let trusted_root = dir.join("trusted_root.json");
+        std::fs::write(
+            &trusted_root,
+            serde_json::json!({
+                "mediaType": "application/vnd.dev.sigstore.trustedroot+json;version=0.1",
+                "tlogs": [{
+                    "baseUrl": "https://rekor.example.com",
+                    "hashAlgorithm": "SHA2_256",
+                    "publicKey": {"rawBytes": STANDARD.encode(log_public_key.as_bytes())},
+                    "logId": {"keyId": STANDARD.encode(log_id)}
+                }],
+                "certificateAuthorities": [{
+                    "uri": "https://fulcio.example.com",
+                    "certChain": {"certificates": [{"rawBytes": STANDARD.encode(ca.to_der().unwrap())}]}
+                }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        Policy {
+            identity: IDENTITY.to_string(),
+            issuer: ISSUER.to_string(),
+            trusted_root,
+            bundle,
+        }
+    }
+
+    #[test]
+    fn verified() {
+        let tmp = tempfile::tempdir().unwrap();
+        let policy = sign(tmp.path(), b"scie");
+        verify(&policy, b"scie").unwrap();
+    }
+
+    #[test]
+    fn rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let policy = sign(tmp.path(), b"scie");
+
+        let error = verify(&policy, b"tampered scie").unwrap_err().to_string();
+        assert!(error.contains("is for different content"), "{error}");
+
+        let policy = Policy {
+            identity: "attacker@example.com".to_string(),
+            ..policy
+        };
+        let error = verify(&policy, b"scie").unwrap_err().to_string();
+        assert!(
+            error.contains("signed by release@example.com and not attacker@example.com"),
+            "{error}"
+        );
+    }
+}
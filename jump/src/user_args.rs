@@ -0,0 +1,214 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::config::UserArgs;
+
+/// The exit code when a command is given arguments its "user_args" reject; `EX_USAGE` from the BSD
+/// `sysexits.h`.
+#[cfg(not(target_os = "wasi"))]
+pub(crate) const USAGE_ERROR: i32 = 64;
+
+#[cfg(not(target_os = "wasi"))]
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+#[cfg(not(target_os = "wasi"))]
+fn check_choice(what: &str, value: &str, choices: &[String]) -> Result<(), String> {
+    if choices.is_empty() || choices.iter().any(|choice| choice == value) {
+        return Ok(());
+    }
+    Err(format!(
+        "{what} must be one of {choices} but was given {value:?}.",
+        choices = choices.join(", ")
+    ))
+}
+
+/// Renders a one line summary of the arguments a command accepts; e.g.:
+/// `tool [--verbose] [--level <value>] ACTION [TARGET]...`.
+pub(crate) fn usage(name: &str, user_args: &UserArgs) -> String {
+    let mut usage = vec![name.to_string()];
+    for (flag, spec) in &user_args.flags {
+        usage.push(if spec.value {
+            format!("[{flag} <value>]")
+        } else {
+            format!("[{flag}]")
+        });
+    }
+    let min = user_args.min.unwrap_or(0);
+    for (index, positional) in user_args.positional.iter().enumerate() {
+        if index < min {
+            usage.push(positional.name.clone());
+        } else {
+            usage.push(format!("[{name}]", name = positional.name));
+        }
+    }
+    let described = user_args.positional.len();
+    if user_args.max.map(|max| max > described).unwrap_or(true) {
+        usage.push(if described == 0 {
+            "[args]...".to_string()
+        } else {
+            "...".to_string()
+        });
+    }
+    usage.join(" ")
+}
+
+#[cfg(not(target_os = "wasi"))]
+fn check_args(user_args: &UserArgs, args: &[String]) -> Result<(), String> {
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if user_args.flags.is_empty() || !arg.starts_with('-') || arg == "-" {
+            positional.push(arg);
+            continue;
+        }
+        if arg == "--" {
+            positional.extend(args);
+            break;
+        }
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        let Some(spec) = user_args.flags.get(flag) else {
+            return Err(format!("Unrecognized flag {flag}."));
+        };
+        if !spec.value {
+            if inline_value.is_some() {
+                return Err(format!("The {flag} flag does not take a value."));
+            }
+            continue;
+        }
+        let value = match inline_value {
+            Some(value) => value,
+            None => args
+                .next()
+                .map(String::as_str)
+                .ok_or_else(|| format!("The {flag} flag needs a value."))?,
+        };
+        check_choice(&format!("The {flag} flag"), value, &spec.choices)?;
+    }
+
+    let count = positional.len();
+    if let Some(min) = user_args.min.filter(|min| count < *min) {
+        return Err(format!(
+            "Expected at least {min} argument{plural} but was given {count}.",
+            plural = plural(min)
+        ));
+    }
+    if let Some(max) = user_args.max.filter(|max| count > *max) {
+        return Err(format!(
+            "Expected at most {max} argument{plural} but was given {count}.",
+            plural = plural(max)
+        ));
+    }
+    for (spec, value) in user_args.positional.iter().zip(positional) {
+        check_choice(&spec.name, value, &spec.choices)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "wasi"))]
+/// Checks the arguments the user passed to the command named `name` against what it accepts.
+pub(crate) fn check(name: &str, user_args: &UserArgs, args: &[String]) -> Result<(), String> {
+    check_args(user_args, args)
+        .map_err(|e| format!("{e}\nUsage: {usage}", usage = usage(name, user_args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, usage};
+    use crate::config::UserArgs;
+
+    fn user_args(json: &str) -> UserArgs {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn positional() {
+        let user_args = user_args(
+            r#"{
+                "min": 1,
+                "max": 2,
+                "positional": [{"name": "ACTION", "choices": ["start", "stop"]}, {"name": "TARGET"}]
+            }"#,
+        );
+        assert_eq!("svc ACTION [TARGET]", usage("svc", &user_args));
+        check("svc", &user_args, &args(&["start"])).unwrap();
+        check("svc", &user_args, &args(&["stop", "--all"])).unwrap();
+        assert_eq!(
+            "Expected at least 1 argument but was given 0.\nUsage: svc ACTION [TARGET]",
+            check("svc", &user_args, &[]).unwrap_err()
+        );
+        assert_eq!(
+            "Expected at most 2 arguments but was given 3.\nUsage: svc ACTION [TARGET]",
+            check("svc", &user_args, &args(&["start", "a", "b"])).unwrap_err()
+        );
+        assert_eq!(
+            "ACTION must be one of start, stop but was given \"restart\".\n\
+            Usage: svc ACTION [TARGET]",
+            check("svc", &user_args, &args(&["restart"])).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn flags() {
+        let user_args = user_args(
+            r#"{
+                "max": 1,
+                "flags": {
+                    "--verbose": {},
+                    "-q": {},
+                    "--level": {"value": true, "choices": ["debug", "info"]}
+                }
+            }"#,
+        );
+        assert_eq!(
+            "tool [--verbose] [-q] [--level <value>] [args]...",
+            usage("tool", &user_args)
+        );
+        check("tool", &user_args, &args(&["--verbose", "-q", "file"])).unwrap();
+        check("tool", &user_args, &args(&["--level", "debug"])).unwrap();
+        check("tool", &user_args, &args(&["--level=info", "-"])).unwrap();
+        check("tool", &user_args, &args(&["--", "--not-a-flag"])).unwrap();
+
+        let error = |given: &[&str]| {
+            check("tool", &user_args, &args(given))
+                .unwrap_err()
+                .lines()
+                .next()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("Unrecognized flag --debug.", error(&["--debug"]));
+        assert_eq!(
+            "The --verbose flag does not take a value.",
+            error(&["--verbose=1"])
+        );
+        assert_eq!("The --level flag needs a value.", error(&["--level"]));
+        assert_eq!(
+            "The --level flag must be one of debug, info but was given \"trace\".",
+            error(&["--level=trace"])
+        );
+        assert_eq!(
+            "Expected at most 1 argument but was given 2.",
+            error(&["a", "--", "-b"])
+        );
+    }
+
+    #[test]
+    fn flags_unchecked() {
+        let user_args = user_args(r#"{"max": 1}"#);
+        check("tool", &user_args, &args(&["--anything"])).unwrap();
+        assert!(check("tool", &user_args, &args(&["--level", "info"])).is_err());
+    }
+}
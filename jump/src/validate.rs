@@ -0,0 +1,448 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashSet;
+
+use crate::archive;
+use crate::config::{Cmd, EnvVar, FileSource, Lift, Permissions, UserArgs};
+use crate::placeholders::{self, Item, Placeholder, ScieBindingEnv};
+
+struct Validator<'a> {
+    lift: &'a Lift,
+    file_names: HashSet<&'a str>,
+    problems: Vec<String>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(lift: &'a Lift) -> Self {
+        Self {
+            lift,
+            file_names: HashSet::new(),
+            problems: vec![],
+        }
+    }
+
+    fn check_files(&mut self) {
+        for file in &self.lift.files {
+            for name in [Some(&file.name), file.key.as_ref()].into_iter().flatten() {
+                if !self.file_names.insert(name.as_str()) {
+                    self.problems.push(format!(
+                        "The file name or key {name} is used by more than one file."
+                    ));
+                }
+            }
+            if let Some(source) = &file.source {
+                if file.execute_in_place {
+                    self.problems.push(format!(
+                        "The file {file} is marked execute_in_place but it has a source of \
+                        {source}; only files stored in the scie can be executed in place.",
+                        file = file.name
+                    ));
+                }
+                match source {
+                    FileSource::Binding(binding) => {
+                        if !self.lift.boot.bindings.contains_key(binding) {
+                            self.problems.push(format!(
+                                "The file {file} has a source of {binding} but there is no \
+                                binding command with that name.",
+                                file = file.name
+                            ));
+                        }
+                    }
+                    FileSource::EntryRange { url, .. } => {
+                        if file.size.is_none() || file.hash.is_none() {
+                            self.problems.push(format!(
+                                "The file {file} is fetched from {url} but does not specify \
+                                both its size and hash.",
+                                file = file.name
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_value(&mut self, location: &str, value: &str) {
+        let parsed = match placeholders::parse(value) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.problems.push(format!("{location}: {e}"));
+                return;
+            }
+        };
+        for item in parsed.items {
+            match item {
+                Item::Placeholder(Placeholder::FileName(name))
+                | Item::Placeholder(Placeholder::FileHash(name))
+                | Item::Placeholder(Placeholder::FileSize(name)) => {
+                    if name.contains('{') {
+                        // The file name is calculated at runtime; so we can only check the
+                        // placeholders used to calculate it.
+                        self.check_value(location, name);
+                    } else if !self.file_names.contains(name) {
+                        self.problems.push(format!(
+                            "{location}: The placeholder {{{name}}} does not refer to any file \
+                            name or key in the lift manifest."
+                        ));
+                    }
+                }
+                Item::Placeholder(Placeholder::Env(env))
+                | Item::Placeholder(Placeholder::UserCacheDir(env)) => {
+                    self.check_value(location, env)
+                }
+                Item::Placeholder(Placeholder::ScieBindingCmd(binding)) => {
+                    // N.B.: This can also name an output of a binding as `<binding>.<key>`.
+                    if self.lift.boot.binding_output(binding).is_none() {
+                        self.check_binding(location, binding)
+                    }
+                }
+                Item::Placeholder(Placeholder::ScieBindingEnv(ScieBindingEnv { binding, env })) => {
+                    self.check_binding(location, binding);
+                    self.check_value(location, env);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_binding(&mut self, location: &str, binding: &str) {
+        if !self.lift.boot.bindings.contains_key(binding) {
+            self.problems.push(format!(
+                "{location}: There is no binding command named {binding}."
+            ));
+        }
+    }
+
+    fn check_cmd(&mut self, kind: &str, name: &str, cmd: &Cmd) {
+        let location = format!("The {kind} {name:?}");
+        self.check_value(&format!("{location} exe"), &cmd.exe);
+        for arg in &cmd.args {
+            self.check_value(&format!("{location} args"), arg);
+        }
+        for (env_var, value) in &cmd.env {
+            if let Some(val) = value {
+                let name = match env_var {
+                    EnvVar::Default(name) => name.to_string(),
+                    EnvVar::Replace(name) => format!("={name}"),
+                };
+                self.check_value(&format!("{location} env {name}"), val);
+            }
+        }
+        if let Some(cwd) = &cmd.cwd {
+            self.check_value(&format!("{location} cwd"), cwd);
+        }
+        for entry in &cmd.prepend_path {
+            self.check_value(&format!("{location} prepend_path"), entry);
+        }
+        if let Some(enabled_if) = &cmd.enabled_if {
+            if kind != "command" {
+                self.problems.push(format!(
+                    "{location} enabled_if: Only boot commands can be enabled conditionally."
+                ));
+            } else if enabled_if.env.is_empty() || enabled_if.env.contains('=') {
+                self.problems.push(format!(
+                    "{location} enabled_if: {env:?} is not a valid environment variable name.",
+                    env = enabled_if.env
+                ));
+            }
+        }
+        if let Some(user_args) = &cmd.user_args {
+            self.check_user_args(&location, kind, user_args);
+        }
+        if let Some(timeout) = cmd.timeout {
+            if kind != "command" {
+                self.problems.push(format!(
+                    "{location} timeout: Only boot commands can be timed out."
+                ));
+            } else if timeout == 0 {
+                self.problems.push(format!(
+                    "{location} timeout: The timeout must be at least 1 second."
+                ));
+            }
+        }
+        if cmd.shebang && kind != "command" {
+            self.problems.push(format!(
+                "{location} shebang: Only boot commands can run scripts."
+            ));
+        }
+        if cmd.gui && kind != "command" {
+            self.problems.push(format!(
+                "{location} gui: Only boot commands launch GUI applications."
+            ));
+        }
+        if cmd.retry.is_some() && kind == "command" {
+            self.problems.push(format!(
+                "{location} retry: Only binding commands can be retried."
+            ));
+        }
+        for additional_file in &cmd.additional_files {
+            if additional_file.contains('{') {
+                self.check_value(&format!("{location} additional_files"), additional_file);
+            } else if !self.file_names.contains(additional_file.as_str())
+                && !self.lift.boot.commands.contains_key(additional_file)
+                && !self.lift.boot.bindings.contains_key(additional_file)
+            {
+                self.problems.push(format!(
+                    "{location} additional_files: {additional_file} is neither a file name or \
+                    key nor a command name."
+                ));
+            }
+        }
+    }
+
+    fn check_shebang(&mut self) {
+        let shebang_commands = self
+            .lift
+            .boot
+            .commands
+            .iter()
+            .filter(|(_, cmd)| cmd.shebang)
+            .map(|(name, _)| format!("{name:?}"))
+            .collect::<Vec<_>>();
+        if shebang_commands.len() > 1 {
+            self.problems.push(format!(
+                "Only one command can be the shebang command but {commands} all are.",
+                commands = shebang_commands.join(", ")
+            ));
+        }
+    }
+
+    fn check_user_args(&mut self, location: &str, kind: &str, user_args: &UserArgs) {
+        if kind != "command" {
+            self.problems.push(format!(
+                "{location} user_args: Only boot commands are passed arguments by the user."
+            ));
+            return;
+        }
+        match (user_args.min, user_args.max) {
+            (Some(min), Some(max)) if min > max => self.problems.push(format!(
+                "{location} user_args: The min of {min} is greater than the max of {max}."
+            )),
+            (_, Some(max)) if user_args.positional.len() > max => self.problems.push(format!(
+                "{location} user_args: {count} positional arguments are described but at most \
+                {max} are accepted.",
+                count = user_args.positional.len()
+            )),
+            _ => {}
+        }
+        for (flag, spec) in &user_args.flags {
+            if !flag.starts_with('-') || flag.contains('=') {
+                self.problems.push(format!(
+                    "{location} user_args: The flag {flag:?} must start with a - and cannot \
+                    contain an =."
+                ));
+            }
+            if !spec.value && !spec.choices.is_empty() {
+                self.problems.push(format!(
+                    "{location} user_args: The flag {flag} has choices but does not take a \
+                    value."
+                ));
+            }
+        }
+    }
+
+    fn check_permissions(&mut self) {
+        if let Some(Err(e)) = self.lift.permissions.as_ref().map(Permissions::umask) {
+            self.problems.push(e);
+        }
+        if let Some(Permissions {
+            quarantine: true,
+            strip_quarantine: true,
+            ..
+        }) = self.lift.permissions
+        {
+            self.problems.push(
+                "The permissions quarantine and strip_quarantine cannot both be set; quarantine \
+                flags are either restored or stripped."
+                    .to_string(),
+            );
+        }
+    }
+
+    fn check_zips(&mut self) {
+        if let Some(template) = self
+            .lift
+            .zips
+            .as_ref()
+            .and_then(|zips| zips.name.as_deref())
+        {
+            if let Err(e) = archive::zip_name(template, "name", Some("hash")) {
+                self.problems.push(e);
+            }
+        }
+    }
+
+    fn validate(mut self) -> Result<(), String> {
+        self.check_files();
+        self.check_permissions();
+        self.check_zips();
+        for (name, cmd) in &self.lift.boot.commands {
+            self.check_cmd("command", name, cmd);
+        }
+        for (name, cmd) in &self.lift.boot.bindings {
+            self.check_cmd("binding", name, cmd);
+        }
+        self.check_shebang();
+        if self.problems.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "The lift manifest for {name} has {count} problem{plural}:\n{problems}",
+            name = self.lift.name,
+            count = self.problems.len(),
+            plural = if self.problems.len() == 1 { "" } else { "s" },
+            problems = self
+                .problems
+                .iter()
+                .map(|problem| format!("+ {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+}
+
+/// Checks the lift manifest for internal consistency, reporting all problems found at once.
+pub(crate) fn validate(lift: &Lift) -> Result<(), String> {
+    Validator::new(lift).validate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+    use crate::config::Lift;
+
+    fn lift(json: &str) -> Lift {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn valid() {
+        validate(&lift(
+            r#"
+            {
+                "name": "valid",
+                "permissions": {"private": true, "umask": "022"},
+                "files": [
+                    {"name": "python.tar.gz", "key": "python"},
+                    {"name": "app.pex"},
+                    {"name": "extra", "source": "fetch"}
+                ],
+                "boot": {
+                    "commands": {
+                        "": {
+                            "exe": "{python}/bin/python",
+                            "args": [
+                                "{app.pex}",
+                                "{scie.files.{scie.env.EXTRA=extra}}",
+                                "{scie.bindings.install.venv.path}"
+                            ],
+                            "env": {"=PATH": "{scie.bindings.install:BIN}:{scie.env.PATH}"},
+                            "additional_files": ["extra", "fetch"]
+                        },
+                        "svc": {
+                            "exe": "{python}/bin/python",
+                            "enabled_if": {"env": "SVC"},
+                            "shebang": true,
+                            "args": ["{scie.script}"],
+                            "timeout": 600,
+                            "user_args": {
+                                "min": 1,
+                                "max": 2,
+                                "positional": [{"name": "ACTION", "choices": ["start", "stop"]}],
+                                "flags": {"--level": {"value": true, "choices": ["info"]}}
+                            }
+                        }
+                    },
+                    "bindings": {
+                        "fetch": {"exe": "{python}/bin/python", "retry": {"retries": 3}},
+                        "install": {"exe": "{python}/bin/python", "args": ["{scie.bindings}"]}
+                    }
+                }
+            }
+            "#,
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn problems() {
+        let error = validate(&lift(
+            r#"
+            {
+                "name": "invalid",
+                "permissions": {"umask": "0700", "quarantine": true, "strip_quarantine": true},
+                "zips": {"name": "{scie.base}/{name}.zip"},
+                "files": [
+                    {"name": "python.tar.gz", "key": "python"},
+                    {"name": "python"},
+                    {"name": "extra", "source": "dne", "execute_in_place": true}
+                ],
+                "boot": {
+                    "commands": {
+                        "": {
+                            "exe": "{pyhton}/bin/python",
+                            "args": ["{scie.files.{scie.env.EXTRA={missing}}}", "{"],
+                            "env": {"FOO": "{scie.bindings.install}"},
+                            "enabled_if": {"env": "FEATURE=1"},
+                            "user_args": {"min": 2, "max": 1, "flags": {"v": {"choices": ["1"]}}},
+                            "additional_files": ["nope"],
+                            "timeout": 0,
+                            "retry": {"retries": 1, "backoff_ms": 100},
+                            "shebang": true
+                        },
+                        "other": {"exe": "/bin/true", "shebang": true}
+                    },
+                    "bindings": {
+                        "fetch": {
+                            "exe": "/bin/true",
+                            "enabled_if": {"env": "FETCH"},
+                            "timeout": 1,
+                            "shebang": true,
+                            "gui": true
+                        }
+                    }
+                }
+            }
+            "#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            "The lift manifest for invalid has 22 problems:\n\
+            + The file name or key python is used by more than one file.\n\
+            + The file extra is marked execute_in_place but it has a source of dne; only files \
+            stored in the scie can be executed in place.\n\
+            + The file extra has a source of dne but there is no binding command with that name.\n\
+            + The permissions umask 0700 would clear the owner's permission bits; only group and \
+            other permission bits can be cleared.\n\
+            + The permissions quarantine and strip_quarantine cannot both be set; quarantine \
+            flags are either restored or stripped.\n\
+            + The zip name {scie.base}/{name}.zip can only use the {name} and {hash} \
+            placeholders.\n\
+            + The command \"\" exe: The placeholder {pyhton} does not refer to any file name or \
+            key in the lift manifest.\n\
+            + The command \"\" args: The placeholder {missing} does not refer to any file name \
+            or key in the lift manifest.\n\
+            + The command \"\" args: Encountered text of '{'. If a literal '{' is intended, \
+            escape it like so: '{{'.\n\
+            + The command \"\" env FOO: There is no binding command named install.\n\
+            + The command \"\" enabled_if: \"FEATURE=1\" is not a valid environment variable \
+            name.\n\
+            + The command \"\" user_args: The min of 2 is greater than the max of 1.\n\
+            + The command \"\" user_args: The flag \"v\" must start with a - and cannot contain \
+            an =.\n\
+            + The command \"\" user_args: The flag v has choices but does not take a value.\n\
+            + The command \"\" timeout: The timeout must be at least 1 second.\n\
+            + The command \"\" retry: Only binding commands can be retried.\n\
+            + The command \"\" additional_files: nope is neither a file name or key nor a \
+            command name.\n\
+            + The binding \"fetch\" enabled_if: Only boot commands can be enabled \
+            conditionally.\n\
+            + The binding \"fetch\" timeout: Only boot commands can be timed out.\n\
+            + The binding \"fetch\" shebang: Only boot commands can run scripts.\n\
+            + The binding \"fetch\" gui: Only boot commands launch GUI applications.\n\
+            + Only one command can be the shebang command but \"\", \"other\" all are.",
+            error
+        );
+    }
+}
@@ -1,42 +1,139 @@
 // Copyright 2022 Science project contributors.
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::borrow::Cow;
 use std::cmp::min;
-use std::io::{Read, Seek, SeekFrom};
+use std::env;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
-use itertools::Itertools;
+use memchr::memmem::FinderRev;
+
+use crate::config::Config;
+use crate::error::JumpError;
+use crate::footer::SCIE_MAGIC_SIZE;
 
 // See "4.3.6 Overall .ZIP file format:" and "4.3.16  End of central directory record:"
 // in https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT for Zip file format facts
 // leveraged here.
 
-const EOCD_SIGNATURE: (&u8, &u8, &u8, &u8) = (&0x06, &0x05, &0x4b, &0x50);
+const EOCD_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
 const EOCD_MIN_SIZE: usize = 22;
 const EOCD_MAX_SIZE: usize = EOCD_MIN_SIZE + u16::MAX as usize;
 
+/// The name of the zip entry holding the lift manifest of a scie that stores its lift manifest in
+/// a zip appended to its files instead of as a trailer.
+pub const LIFT_ZIP_ENTRY: &str = "__lift__.json";
+
+struct EndOfCentralDirectory {
+    start: usize,
+    end: usize,
+    cd_size: usize,
+    cd_offset: usize,
+}
+
+/// The maximum number of bytes code signing tools are expected to append to a scie.
+///
+/// Both Windows `signtool` and macOS `codesign` store their signatures at the end of the file they
+/// sign, which pushes the zip end of central directory record and lift manifest trailer of a scie
+/// away from the end of the file.
+const MAXIMUM_SIGNATURE_SIZE: usize = 0x100000;
+
+/// The maximum size of the lift manifest trailer to search past for the end of the zip it follows.
+///
+/// This defaults to the maximum lift manifest size but can be raised via the
+/// `SCIE_MAXIMUM_TRAILER_SIZE` environment variable for scies with larger lift manifests.
+pub(crate) fn maximum_trailer_size() -> Result<usize, String> {
+    match env::var("SCIE_MAXIMUM_TRAILER_SIZE") {
+        Ok(value) => value.trim().parse().map_err(|e| {
+            format!(
+                "The SCIE_MAXIMUM_TRAILER_SIZE environment variable must be a number of bytes but \
+                was {value:?}: {e}"
+            )
+        }),
+        Err(_) => Ok(Config::MAXIMUM_CONFIG_SIZE),
+    }
+}
+
 pub(crate) fn end_of_zip(data: &[u8], maximum_trailer_size: usize) -> Result<usize, String> {
+    let max_scan = EOCD_MAX_SIZE + maximum_trailer_size;
+    find_end_of_central_directory(data, max_scan, |eocd| Ok(eocd.end))
+}
+
+/// Returns the result of `func` for the last end of central directory record in the last
+/// `max_scan` bytes of the data it accepts.
+///
+/// The bytes of a zip comment or of whatever follows a zip can happen to contain an end of central
+/// directory record signature; so records that are malformed or that `func` rejects are skipped in
+/// favor of earlier ones. If none are accepted, the error for the last one is returned.
+fn find_end_of_central_directory<T>(
+    data: &[u8],
+    max_scan: usize,
+    func: impl Fn(EndOfCentralDirectory) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut first_error = None;
+    for eocd_start in eocd_starts(data, max_scan)? {
+        match end_of_central_directory(data, eocd_start).and_then(&func) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| {
+        format!(
+            "Failed to find application zip end of central directory record within the last \
+            {max_scan} bytes of the file. Invalid NCE."
+        )
+    }))
+}
+
+/// Yields the start of each end of central directory record signature found in the last
+/// `max_scan` bytes of the data, from last to first.
+///
+/// A zip with nothing following it and either no comment or a scie magic line comment, which is how
+/// scies with their lift manifest in a zip end, has its end of central directory record at a fixed
+/// position; so those positions are checked directly before scanning.
+fn eocd_starts(data: &[u8], max_scan: usize) -> Result<impl Iterator<Item = usize> + '_, String> {
+    if data.len() < EOCD_MIN_SIZE {
+        return Err(format!(
+            "The file is {size} bytes which is too small to contain a zip end of central \
+            directory record. Invalid NCE.",
+            size = data.len()
+        ));
+    }
+    let canonical = [0, SCIE_MAGIC_SIZE].into_iter().find_map(|comment_size| {
+        let start = data.len().checked_sub(EOCD_MIN_SIZE + comment_size)?;
+        let comment_size_field = &data[start + EOCD_MIN_SIZE - 2..start + EOCD_MIN_SIZE];
+        (data[start..].starts_with(&EOCD_SIGNATURE)
+            && comment_size_field == (comment_size as u16).to_le_bytes())
+        .then_some(start)
+    });
+    let max_signature_position = data.len() - EOCD_MIN_SIZE + EOCD_SIGNATURE.len();
+    let scan_start = max_signature_position.saturating_sub(max_scan);
+    // N.B.: Each search ends just short of the last signature found so that the next search can
+    // only find signatures starting before it.
+    let mut scan_end = match canonical {
+        Some(start) => start + EOCD_SIGNATURE.len() - 1,
+        None => max_signature_position,
+    };
+    let finder = FinderRev::new(&EOCD_SIGNATURE);
+    let scan = std::iter::from_fn(move || {
+        let position = scan_start + finder.rfind(data.get(scan_start..scan_end)?)?;
+        scan_end = position + EOCD_SIGNATURE.len() - 1;
+        Some(position)
+    });
+    Ok(canonical.into_iter().chain(scan))
+}
+
+fn end_of_central_directory(
+    data: &[u8],
+    eocd_start: usize,
+) -> Result<EndOfCentralDirectory, String> {
     #[allow(clippy::too_many_arguments)]
     let eocd_struct = structure!("<4sHHHHIIH");
     debug_assert!(EOCD_MIN_SIZE == eocd_struct.size());
 
-    let max_scan = EOCD_MAX_SIZE + maximum_trailer_size;
-    let max_signature_position = data.len() - EOCD_MIN_SIZE + 4;
-
-    let offset_from_eof = EOCD_MIN_SIZE
-        + data[..max_signature_position]
-            .iter()
-            .rev()
-            .take(max_scan)
-            .tuple_windows::<(_, _, _, _)>()
-            .position(|chunk| EOCD_SIGNATURE == chunk)
-            .ok_or_else(|| {
-                format!(
-                "Failed to find application zip end of central directory record within the last \
-                {max_scan} bytes of the file. Invalid NCE."
-            )
-            })?;
-    let eocd_start = data.len() - offset_from_eof;
     let eocd_end = eocd_start + EOCD_MIN_SIZE;
     let (
         _signature,
@@ -44,8 +141,8 @@ pub(crate) fn end_of_zip(data: &[u8], maximum_trailer_size: usize) -> Result<usi
         _cd_disk_no,
         _disk_cd_record_count,
         _total_cd_record_count,
-        _cd_size,
-        _cd_offset,
+        cd_size,
+        cd_offset,
         zip_comment_size,
     ) = eocd_struct
         .unpack(&data[eocd_start..eocd_end])
@@ -54,40 +151,567 @@ pub(crate) fn end_of_zip(data: &[u8], maximum_trailer_size: usize) -> Result<usi
                 "Invalid end of central directory record found starting at byte {eocd_start}: {e}"
             )
         })?;
-    Ok(eocd_end + (zip_comment_size as usize))
+    let end = eocd_end + (zip_comment_size as usize);
+    if end > data.len() {
+        return Err(format!(
+            "The end of central directory record found starting at byte {eocd_start} has a \
+            comment of size {zip_comment_size} that extends {overrun} bytes past the end of the \
+            file. Invalid NCE.",
+            overrun = end - data.len()
+        ));
+    }
+    Ok(EndOfCentralDirectory {
+        start: eocd_start,
+        end,
+        cd_size: cd_size as usize,
+        cd_offset: cd_offset as usize,
+    })
 }
 
-pub fn check_is_zip(path: &Path) -> Result<(), String> {
-    let mut file = std::fs::File::open(path).map_err(|e| {
+/// A zip stream whose entry offsets may be skewed by data that preceded the zip.
+///
+/// The offsets recorded in a zip are normally relative to its start. Tools like `zip -A` instead
+/// make them relative to the start of the file the zip ends; so that zip readers can open a scie,
+/// with its scie-jump and other files ahead of its last zip, as a whole. Read on its own, such a zip
+/// has offsets skewed by the size of the data that preceded it. A `ZipStream` presents that many
+/// zero bytes ahead of the zip so its offsets resolve.
+#[derive(Clone, Debug)]
+pub struct ZipStream<R> {
+    inner: R,
+    len: u64,
+    skew: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> ZipStream<R> {
+    pub fn new(mut inner: R) -> std::io::Result<Self> {
+        let len = inner.seek(SeekFrom::End(0))?;
+        let tail_offset = len.saturating_sub(EOCD_MAX_SIZE as u64);
+        inner.seek(SeekFrom::Start(tail_offset))?;
+        let mut tail = Vec::with_capacity((len - tail_offset) as usize);
+        inner.read_to_end(&mut tail)?;
+        inner.seek(SeekFrom::Start(0))?;
+        let skew = find_end_of_central_directory(&tail, EOCD_MAX_SIZE, |eocd| {
+            // N.B.: Zip64 archives record their offsets elsewhere and we leave locating them to
+            // the zip crate.
+            if eocd.cd_size == u32::MAX as usize || eocd.cd_offset == u32::MAX as usize {
+                return Ok(0);
+            }
+            Ok(((eocd.cd_offset + eocd.cd_size) as u64)
+                .saturating_sub(tail_offset + eocd.start as u64))
+        })
+        .unwrap_or(0);
+        Ok(Self {
+            inner,
+            len,
+            skew,
+            position: 0,
+        })
+    }
+
+    /// The number of zero bytes presented ahead of the zip.
+    pub fn skew(&self) -> u64 {
+        self.skew
+    }
+}
+
+impl<R: Read + Seek> Read for ZipStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position < self.skew {
+            let count = min(buf.len() as u64, self.skew - self.position) as usize;
+            buf[..count].fill(0);
+            self.position += count as u64;
+            return Ok(count);
+        }
+        let count = self.inner.read(buf)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for ZipStream<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(position) => (position, 0),
+            SeekFrom::End(offset) => (self.skew + self.len, offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+        let position = base.checked_add_signed(offset).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Cannot seek before the start of a zip stream.",
+            )
+        })?;
+        self.inner
+            .seek(SeekFrom::Start(position.saturating_sub(self.skew)))?;
+        self.position = position;
+        Ok(position)
+    }
+}
+
+const CENTRAL_HEADER_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
+const CENTRAL_HEADER_MIN_SIZE: usize = 46;
+const ZIP64_LOCATOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
+const ZIP64_LOCATOR_SIZE: usize = 20;
+
+fn le_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn le_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+/// Rewrites the offsets recorded in the zip occupying `start..end` of the stream so that they are
+/// relative to the start of the stream instead of the start of the zip; just like `zip -A` does.
+///
+/// This lets zip tools open the whole stream; e.g.: a scie, as a zip. Zip64 archives are not
+/// supported.
+pub(crate) fn adjust_offsets<S: Read + Write + Seek>(
+    stream: &mut S,
+    start: u64,
+    end: u64,
+) -> Result<(), String> {
+    let tail_start = end.saturating_sub(EOCD_MAX_SIZE as u64).max(start);
+    let tail = read_range(stream, tail_start, end)?;
+    let eocd = find_end_of_central_directory(&tail, EOCD_MAX_SIZE, |eocd| {
+        if eocd.end == tail.len() {
+            Ok(eocd)
+        } else {
+            Err("Found no end of central directory record at the end of the zip.".to_string())
+        }
+    })?;
+    let zip64 = eocd.cd_size == u32::MAX as usize
+        || eocd.cd_offset == u32::MAX as usize
+        || le_u16(&tail, eocd.start + 8) == u16::MAX
+        || le_u16(&tail, eocd.start + 10) == u16::MAX
+        || (eocd.start >= ZIP64_LOCATOR_SIZE
+            && tail[eocd.start - ZIP64_LOCATOR_SIZE..].starts_with(&ZIP64_LOCATOR_SIGNATURE));
+    if zip64 {
+        return Err("Cannot adjust the offsets of a zip64 archive.".to_string());
+    }
+
+    // N.B.: The zip may already have its offsets adjusted for data that preceded it elsewhere; so
+    // we work out the current offset base from where the central directory actually ends.
+    let eocd_position = tail_start + eocd.start as u64;
+    let cd_position = eocd_position
+        .checked_sub(eocd.cd_size as u64)
+        .filter(|cd_position| *cd_position >= start)
+        .ok_or_else(|| {
+            "The zip's central directory extends before the start of the zip.".to_string()
+        })?;
+    let base = i128::from(cd_position) - eocd.cd_offset as i128;
+    let adjust = |offset: u32| -> Result<[u8; 4], String> {
+        let adjusted = i128::from(offset) + base;
+        u32::try_from(adjusted).map(u32::to_le_bytes).map_err(|_| {
+            format!("Adjusting the zip offset {offset} by {base} does not fit in 32 bits.")
+        })
+    };
+
+    let mut records = read_range(stream, cd_position, end)?;
+    let mut at = 0;
+    while at < eocd.cd_size {
+        if at + CENTRAL_HEADER_MIN_SIZE > eocd.cd_size
+            || !records[at..].starts_with(&CENTRAL_HEADER_SIGNATURE)
+        {
+            return Err(format!(
+                "Found no central directory file header at byte {position}.",
+                position = cd_position + at as u64
+            ));
+        }
+        let local_header_offset = le_u32(&records, at + 42);
+        if local_header_offset == u32::MAX {
+            return Err("Cannot adjust the offsets of a zip64 archive.".to_string());
+        }
+        records[at + 42..at + 46].copy_from_slice(&adjust(local_header_offset)?);
+        at += CENTRAL_HEADER_MIN_SIZE
+            + usize::from(le_u16(&records, at + 28))
+            + usize::from(le_u16(&records, at + 30))
+            + usize::from(le_u16(&records, at + 32));
+    }
+    let eocd_cd_offset = eocd.cd_size + 16;
+    records[eocd_cd_offset..eocd_cd_offset + 4].copy_from_slice(&adjust(eocd.cd_offset as u32)?);
+
+    stream
+        .seek(SeekFrom::Start(cd_position))
+        .and_then(|_| stream.write_all(&records))
+        .map_err(|e| format!("Failed to write the adjusted central directory: {e}"))
+}
+
+fn read_range<S: Read + Seek>(stream: &mut S, start: u64, end: u64) -> Result<Vec<u8>, String> {
+    let mut data = vec![0; (end - start) as usize];
+    stream
+        .seek(SeekFrom::Start(start))
+        .and_then(|_| stream.read_exact(&mut data))
+        .map_err(|e| format!("Failed to read bytes {start} to {end} of the zip: {e}"))?;
+    Ok(data)
+}
+
+/// The lift manifest of a scie.
+pub(crate) struct LiftManifest<'a> {
+    /// The offset of the lift manifest, or of the zip holding it, from the start of the scie.
+    pub(crate) offset: usize,
+    pub(crate) data: Cow<'a, [u8]>,
+    /// If the lift manifest is stored as the `__lift__.json` entry of a zip instead of a trailer.
+    pub(crate) in_zip: bool,
+}
+
+/// Finds the lift manifest of a scie; either the trailer following its last zip or else the
+/// `__lift__.json` entry of the zip that ends it.
+///
+/// Any bytes following the lift manifest, like a code signature, are ignored.
+pub(crate) fn lift_manifest(
+    data: &[u8],
+    maximum_trailer_size: usize,
+) -> Result<LiftManifest<'_>, String> {
+    let max_scan = EOCD_MAX_SIZE + maximum_trailer_size + MAXIMUM_SIGNATURE_SIZE;
+    find_end_of_central_directory(data, max_scan, |eocd| lift_manifest_after(data, eocd))
+}
+
+fn lift_manifest_after(
+    data: &[u8],
+    eocd: EndOfCentralDirectory,
+) -> Result<LiftManifest<'_>, String> {
+    let trailer = &data[eocd.end..];
+    if trailer
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .filter(|byte| **byte == b'{')
+        .is_some()
+    {
+        return Ok(LiftManifest {
+            offset: eocd.end,
+            data: Cow::Borrowed(&trailer[..lift_manifest_trailer_size(trailer)]),
+            in_zip: false,
+        });
+    }
+    let offset = eocd
+        .start
+        .checked_sub(eocd.cd_size + eocd.cd_offset)
+        .ok_or_else(|| {
+            format!(
+                "The zip ending the file has a central directory that extends {overrun} bytes \
+                before the start of the file. Invalid NCE.",
+                overrun = eocd.cd_size + eocd.cd_offset - eocd.start
+            )
+        })?;
+    let mut zip = ::zip::ZipArchive::new(Cursor::new(&data[offset..eocd.end]))
+        .map_err(|e| format!("Failed to read the zip ending the file: {e}"))?;
+    // N.B.: If the zip's offsets are relative to the start of the file instead of the start of the
+    // zip, the zip starts with its first entry instead of at the offset calculated above.
+    let offset = offset
+        + (0..zip.len())
+            .filter_map(|index| {
+                zip.by_index_raw(index)
+                    .ok()
+                    .map(|entry| entry.header_start() as usize)
+            })
+            .min()
+            .unwrap_or(0);
+    let mut entry = zip.by_name(LIFT_ZIP_ENTRY).map_err(|e| {
         format!(
-            "Failed to open zip {zip} for reading: {e}",
-            zip = path.display()
+            "The file ends in a zip with no lift manifest after it and no {LIFT_ZIP_ENTRY} entry \
+            in it: {e}"
+        )
+    })?;
+    let mut lift_manifest = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut lift_manifest).map_err(|e| {
+        format!("Failed to read the lift manifest from the {LIFT_ZIP_ENTRY} zip entry: {e}")
+    })?;
+    Ok(LiftManifest {
+        offset,
+        data: Cow::Owned(lift_manifest),
+        in_zip: true,
+    })
+}
+
+/// Returns the size of the JSON lift manifest at the start of the trailer including surrounding
+/// whitespace but excluding anything that follows it.
+///
+/// If the trailer does not start with a well-formed JSON document, the whole trailer is considered
+/// the lift manifest so that parsing it reports the problem.
+fn lift_manifest_trailer_size(trailer: &[u8]) -> usize {
+    let mut documents =
+        serde_json::Deserializer::from_slice(trailer).into_iter::<serde::de::IgnoredAny>();
+    match documents.next() {
+        Some(Ok(_)) => {
+            let end = documents.byte_offset();
+            end + trailer[end..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_whitespace())
+                .count()
+        }
+        _ => trailer.len(),
+    }
+}
+
+pub fn check_is_zip(path: &Path) -> Result<(), JumpError> {
+    let mut file = std::fs::File::open(path).map_err(|e| {
+        JumpError::io(
+            format!("Failed to open zip {zip} for reading", zip = path.display()),
+            e,
         )
     })?;
     let file_size = file
         .metadata()
         .map_err(|e| {
-            format!(
-                "Failed to determine the size of the file at {path}: {e}",
-                path = path.display()
+            JumpError::io(
+                format!(
+                    "Failed to determine the size of the file at {path}",
+                    path = path.display()
+                ),
+                e,
             )
         })?
         .len();
     let seek = min(EOCD_MAX_SIZE, file_size as usize);
     file.seek(SeekFrom::End(-(seek as i64))).map_err(|e| {
-        format!(
-            "Failed to reset stream pointer for {file_size} byte file {path} to position \
-                {seek} from the end: {e}",
-            path = path.display()
+        JumpError::io(
+            format!(
+                "Failed to reset stream pointer for {file_size} byte file {path} to position \
+                {seek} from the end",
+                path = path.display()
+            ),
+            e,
         )
     })?;
     let mut buffer = Vec::with_capacity(seek);
     file.read_to_end(&mut buffer).map_err(|e| {
-        format!(
-            "Failed to read last {seek} bytes of {path} to check for a zip end of central \
-            directory record: {e}",
-            path = path.display()
+        JumpError::io(
+            format!(
+                "Failed to read last {seek} bytes of {path} to check for a zip end of central \
+                directory record",
+                path = path.display()
+            ),
+            e,
         )
     })?;
-    end_of_zip(&buffer, 0).map(|_| ())
+    end_of_zip(&buffer, 0)
+        .map(|_| ())
+        .map_err(JumpError::Config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::{
+        adjust_offsets, end_of_zip, eocd_starts, lift_manifest, ZipStream, EOCD_SIGNATURE,
+        LIFT_ZIP_ENTRY,
+    };
+
+    fn zip(comment: &str) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        zip.set_comment(comment);
+        zip.start_file("file", FileOptions::default()).unwrap();
+        zip.write_all(b"contents").unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    // Writes a zip after the prefix with offsets relative to the start of the prefix.
+    fn prefixed_zip(prefix: &[u8], entry: &str, contents: &str) -> Vec<u8> {
+        let mut buffer = Cursor::new(prefix.to_vec());
+        buffer.set_position(prefix.len() as u64);
+        let mut zip = ZipWriter::new(buffer);
+        zip.start_file(entry, FileOptions::default()).unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn signatures_at(size: usize, positions: &[usize]) -> Vec<u8> {
+        let mut data = vec![0; size];
+        for position in positions {
+            data[*position..*position + 4].copy_from_slice(&EOCD_SIGNATURE);
+        }
+        data
+    }
+
+    fn eocd_positions(data: &[u8], max_scan: usize) -> Vec<usize> {
+        eocd_starts(data, max_scan).unwrap().collect()
+    }
+
+    #[test]
+    fn eocd_starts_order() {
+        // The last 22 bytes of 100 can hold an end of central directory record at 78; so the
+        // signature there is found without scanning.
+        let data = signatures_at(100, &[10, 50, 78]);
+        assert_eq!(vec![78, 50, 10], eocd_positions(&data, 100));
+
+        let data = signatures_at(100, &[10, 50, 77]);
+        assert_eq!(vec![77, 50, 10], eocd_positions(&data, 100));
+
+        // Signatures too close to the end to start a record are not candidates.
+        let data = signatures_at(100, &[50, 79]);
+        assert_eq!(vec![50], eocd_positions(&data, 100));
+    }
+
+    #[test]
+    fn eocd_starts_window_edge() {
+        // With a 40 byte scan the window is bytes 42 up to 82.
+        assert_eq!(vec![42], eocd_positions(&signatures_at(100, &[42]), 40));
+        assert!(eocd_positions(&signatures_at(100, &[41]), 40).is_empty());
+        assert!(eocd_positions(&signatures_at(100, &[40]), 40).is_empty());
+        assert_eq!(
+            vec![60, 42],
+            eocd_positions(&signatures_at(100, &[30, 41, 42, 60]), 40)
+        );
+        assert_eq!(vec![78], eocd_positions(&signatures_at(100, &[78]), 0));
+        assert!(eocd_positions(&signatures_at(100, &[77]), 0).is_empty());
+        assert!(eocd_starts(&[0; 21], 100).is_err());
+    }
+
+    #[test]
+    fn eocd_starts_scie_magic_comment() {
+        // A record at 14 with a 64 byte comment ends exactly at 100; so it is found without
+        // scanning and any signatures in its comment are skipped.
+        let mut data = signatures_at(100, &[10, 14, 50]);
+        data[34] = 64;
+        assert_eq!(vec![14], eocd_positions(&data, 0));
+        assert_eq!(vec![14, 10], eocd_positions(&data, 100));
+
+        // The comment size must match for the record to be found without scanning.
+        data[34] = 63;
+        assert!(eocd_positions(&data, 0).is_empty());
+    }
+
+    #[test]
+    fn end_of_zip_canonical() {
+        let data = zip("");
+        assert_eq!(data.len(), end_of_zip(&data, 0).unwrap());
+    }
+
+    #[test]
+    fn end_of_zip_comments() {
+        for size in [1, 3, 4, 21, 22, 23, 1000, u16::MAX as usize] {
+            let data = zip(&"c".repeat(size));
+            assert_eq!(
+                data.len(),
+                end_of_zip(&data, 0).unwrap(),
+                "Failed to find the end of a zip with a {size} byte comment."
+            );
+
+            let mut scie = data.clone();
+            scie.extend(b"\n{\"scie\": {}}\n");
+            assert_eq!(
+                data.len(),
+                end_of_zip(&scie, 100).unwrap(),
+                "Failed to find the end of a zip with a {size} byte comment and a trailer."
+            );
+            let lift_manifest = lift_manifest(&scie, 100).unwrap();
+            assert_eq!(data.len(), lift_manifest.offset);
+            assert!(!lift_manifest.in_zip);
+        }
+    }
+
+    #[test]
+    fn end_of_zip_signature_in_comment() {
+        // A comment holding what looks like an end of central directory record whose own comment
+        // would run past the end of the file.
+        let mut comment = String::from("PK\x05\x06");
+        comment.push_str(&"\0".repeat(16));
+        comment.push_str("\x7f\x7f");
+        comment.push_str(" and then some.");
+        let data = zip(&comment);
+        assert_eq!(data.len(), end_of_zip(&data, 0).unwrap());
+
+        // A comment ending in a signature too close to the end to start a record.
+        let data = zip("PK\x05\x06");
+        assert_eq!(data.len(), end_of_zip(&data, 0).unwrap());
+    }
+
+    #[test]
+    fn end_of_zip_trailer() {
+        let mut data = zip("");
+        let end = data.len();
+        let trailer = format!(
+            "{{\"trailer\": \"{padding}\"}}\n",
+            padding = "x".repeat(70_000)
+        );
+        data.extend(trailer.bytes());
+        assert_eq!(end, end_of_zip(&data, trailer.len()).unwrap());
+        assert_eq!(
+            trailer.as_bytes(),
+            lift_manifest(&data, trailer.len()).unwrap().data.as_ref()
+        );
+
+        let error = end_of_zip(&data, 0).unwrap_err();
+        assert!(error.starts_with("Failed to find application zip end of central directory"));
+    }
+
+    #[test]
+    fn zip_stream() {
+        let data = zip("");
+        assert_eq!(0, ZipStream::new(Cursor::new(&data)).unwrap().skew());
+
+        let prefix = [b'j'; 1000];
+        let data = prefixed_zip(&prefix, "file", "contents");
+        let stream = ZipStream::new(Cursor::new(&data[prefix.len()..])).unwrap();
+        assert_eq!(1000, stream.skew());
+        let mut zip = zip::ZipArchive::new(stream).unwrap();
+        let mut contents = String::new();
+        zip.by_name("file")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!("contents", contents);
+
+        // Zips read with their prefix need no skew.
+        assert_eq!(0, ZipStream::new(Cursor::new(&data)).unwrap().skew());
+    }
+
+    #[test]
+    fn lift_manifest_in_prefixed_zip() {
+        let prefix = [b'j'; 1000];
+        let lift = r#"{"scie": {}}"#;
+
+        let mut data = prefix.to_vec();
+        data.extend(prefixed_zip(&[], LIFT_ZIP_ENTRY, lift));
+        let manifest = lift_manifest(&data, 0).unwrap();
+        assert!(manifest.in_zip);
+        assert_eq!(prefix.len(), manifest.offset);
+        assert_eq!(lift.as_bytes(), manifest.data.as_ref());
+
+        let data = prefixed_zip(&prefix, LIFT_ZIP_ENTRY, lift);
+        let manifest = lift_manifest(&data, 0).unwrap();
+        assert!(manifest.in_zip);
+        assert_eq!(prefix.len(), manifest.offset);
+        assert_eq!(lift.as_bytes(), manifest.data.as_ref());
+    }
+
+    #[test]
+    fn adjusted_offsets() {
+        let prefix = [b'j'; 1000];
+        let mut data = prefix.to_vec();
+        data.extend(zip("comment"));
+        data.extend(b"trailer");
+        let end = (data.len() - b"trailer".len()) as u64;
+
+        let mut stream = Cursor::new(data);
+        adjust_offsets(&mut stream, prefix.len() as u64, end).unwrap();
+        let data = stream.into_inner();
+        let zip_data = &data[..end as usize];
+        assert_eq!(0, ZipStream::new(Cursor::new(zip_data)).unwrap().skew());
+        let mut zip = zip::ZipArchive::new(Cursor::new(zip_data)).unwrap();
+        assert_eq!(0, zip.offset());
+        assert_eq!("comment".as_bytes(), zip.comment());
+        let mut contents = String::new();
+        zip.by_name("file")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!("contents", contents);
+
+        // Adjusting again is a no-op.
+        let mut stream = Cursor::new(data.clone());
+        adjust_offsets(&mut stream, prefix.len() as u64, end).unwrap();
+        assert_eq!(data, stream.into_inner());
+
+        // The zip must end exactly where we are told it does.
+        let mut stream = Cursor::new(data);
+        assert!(adjust_offsets(&mut stream, prefix.len() as u64, end - 1).is_err());
+        assert!(adjust_offsets(&mut stream, 0, prefix.len() as u64).is_err());
+    }
 }
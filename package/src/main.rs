@@ -9,7 +9,7 @@ use std::process::Command;
 
 use byteorder::{LittleEndian, WriteBytesExt};
 use clap::Parser;
-use jump::EOF_MAGIC;
+use jump::{EOF_MAGIC, JUMP_TRAILER_SIZE};
 use proc_exit::{Code, Exit, ExitResult};
 use sha2::{Digest, Sha256};
 
@@ -44,7 +44,7 @@ fn add_magic(path: &Path) -> ExitResult {
         ))
     })?;
     binary
-        .write_u32::<LittleEndian>(size + 8)
+        .write_u32::<LittleEndian>(size + JUMP_TRAILER_SIZE as u32)
         .and_then(|()| binary.write_u32::<LittleEndian>(EOF_MAGIC))
         .map_err(|e| {
             Code::FAILURE.with_message(format!("Problem writing {BINARY} trailer magic bytes: {e}"))
@@ -115,6 +115,18 @@ impl Display for SpecifiedPath {
 struct Args {
     #[arg(long, help = "Override the default --target for this platform.")]
     target: Option<String>,
+    #[arg(
+        long,
+        default_value = "release",
+        help = "The cargo profile to build with; `minimal` trades speed for size."
+    )]
+    profile: String,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Build with just these features (e.g. codecs like `zstd`) instead of the defaults."
+    )]
+    features: Option<Vec<String>>,
     #[arg(
         help = "The destination directory for the scie-jump binary and checksum file.",
         default_value_t = SpecifiedPath::new("dist")
@@ -145,17 +157,23 @@ fn main() -> ExitResult {
     let workspace_root = PathBuf::from(cargo_manifest_dir).join("..");
     let output_root = PathBuf::from(out_dir).join("dist");
     let output_bin_dir = output_root.join("bin");
+    let mut command = Command::new(cargo);
+    command.args([
+        "install",
+        "--path",
+        path_as_str(&workspace_root)?,
+        "--target",
+        &target,
+        "--profile",
+        &args.profile,
+        "--root",
+        path_as_str(&output_root)?,
+    ]);
+    if let Some(features) = args.features {
+        command.args(["--no-default-features", "--features", &features.join(",")]);
+    }
     execute(
-        Command::new(cargo)
-            .args([
-                "install",
-                "--path",
-                path_as_str(&workspace_root)?,
-                "--target",
-                &target,
-                "--root",
-                path_as_str(&output_root)?,
-            ])
+        command
             // N.B.: This just suppresses a warning about adding this bin dir to your PATH.
             .env(
                 "PATH",
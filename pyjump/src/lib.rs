@@ -0,0 +1,127 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Python bindings for packing and inspecting scies.
+
+use std::path::{Path, PathBuf};
+
+use jump::config::{Fmt, UnknownFields};
+use jump::{load_manifest, load_scie_jump, PackCache, PackOptions, PAGE_ALIGNMENT};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    pyjump,
+    JumpError,
+    PyException,
+    "Raised when a scie cannot be packed or inspected."
+);
+
+fn json_loads(py: Python<'_>, value: &str) -> PyResult<PyObject> {
+    Ok(py.import("json")?.call_method1("loads", (value,))?.into())
+}
+
+fn pack_scie(
+    lift_manifest: &Path,
+    out_path: &Path,
+    scie_jump: &Path,
+    options: &PackOptions,
+) -> Result<String, String> {
+    let jump = load_scie_jump(scie_jump)?;
+    let (lift, manifest) = load_manifest(
+        lift_manifest,
+        &jump,
+        UnknownFields::Warn.or_strict_from_env(),
+        &mut PackCache::default(),
+    )?;
+    // N.B.: Scies are packed to a file named after the lift; so we pack in a scratch directory
+    // alongside the requested path and then move the scie into place.
+    let out_dir = match out_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let scratch = tempfile::tempdir_in(out_dir).map_err(|e| {
+        format!(
+            "Failed to create a scratch directory in {out_dir}: {e}",
+            out_dir = out_dir.display()
+        )
+    })?;
+    let mut packed = jump::pack(lift, &manifest, &jump, scie_jump, scratch.path(), options)?;
+    std::fs::rename(&packed.binary, out_path).map_err(|e| {
+        format!(
+            "Failed to move the scie {binary} to {out_path}: {e}",
+            binary = packed.binary.display(),
+            out_path = out_path.display()
+        )
+    })?;
+    packed.binary = out_path.to_path_buf();
+    Ok(packed.to_json().to_string())
+}
+
+/// Packs the lift manifest into a scie at `out_path` using the given scie-jump binary as its tip.
+///
+/// The `lift_manifest` can be a lift manifest file or a directory containing a `lift.json`.
+/// Returns a report describing the scie; just like `SCIE=boot-pack --json`.
+#[pyfunction]
+#[pyo3(signature = (
+    lift_manifest,
+    out_path,
+    *,
+    scie_jump,
+    single_line = true,
+    align = false,
+    lift_in_zip = false,
+    embed = false
+))]
+// N.B.: These are the arguments of the Python function; so there is one per keyword.
+#[allow(clippy::too_many_arguments)]
+fn pack(
+    py: Python<'_>,
+    lift_manifest: PathBuf,
+    out_path: PathBuf,
+    scie_jump: PathBuf,
+    single_line: bool,
+    align: bool,
+    lift_in_zip: bool,
+    embed: bool,
+) -> PyResult<PyObject> {
+    let report = py
+        .allow_threads(|| {
+            pack_scie(
+                &lift_manifest,
+                &out_path,
+                &scie_jump,
+                &PackOptions {
+                    single_line,
+                    alignment: align.then_some(PAGE_ALIGNMENT),
+                    lift_in_zip,
+                    embed,
+                    ..Default::default()
+                },
+            )
+        })
+        .map_err(JumpError::new_err)?;
+    json_loads(py, &report)
+}
+
+/// Returns the lift manifest of the scie at `scie_path`.
+#[pyfunction]
+fn inspect(py: Python<'_>, scie_path: PathBuf) -> PyResult<PyObject> {
+    let config = py
+        .allow_threads(|| {
+            let mut config = vec![];
+            jump::inspect(&scie_path)?.serialize(&mut config, Fmt::new())?;
+            Ok::<_, jump::JumpError>(String::from_utf8_lossy(&config).into_owned())
+        })
+        .map_err(|e| JumpError::new_err(e.to_string()))?;
+    json_loads(py, &config)
+}
+
+#[pymodule]
+fn pyjump(py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add("JumpError", py.get_type::<JumpError>())?;
+    module.add_function(wrap_pyfunction!(pack, module)?)?;
+    module.add_function(wrap_pyfunction!(inspect, module)?)?;
+    Ok(())
+}
@@ -5,13 +5,22 @@ use std::env;
 use std::path::{Path, PathBuf};
 
 use jump::config::Fmt;
-use jump::{Jump, Lift, ScieBoot, SelectBoot};
+use jump::{Diagnosis, Health, Jump, Launch, Launcher, Lift, ScieBoot, SelectBoot, Selection};
 use log::warn;
 use proc_exit::{Code, ExitResult};
 
+use crate::diagnostics::{paint, stdout_color, Color};
+use crate::exit;
+
+mod delta;
+mod diff;
 mod pack;
+mod repack;
 mod split;
+pub(crate) use delta::{delta, patch};
+pub(crate) use diff::diff;
 pub(crate) use pack::set as pack;
+pub(crate) use repack::repack;
 pub(crate) use split::split;
 
 pub(crate) fn help(message: String, exit_code: i32) -> ExitResult {
@@ -32,95 +41,78 @@ pub(crate) fn inspect(jump: Jump, lift: Lift) -> ExitResult {
         .map_err(|e| Code::FAILURE.with_message(format!("Failed to serialize lift manifest: {e}")))
 }
 
-pub(crate) fn select(select_boot: SelectBoot) -> ExitResult {
-    let default_cmd = select_boot
-        .boots
-        .iter()
-        .find(|boot| boot.default)
-        .map(|boot| {
-            (
-                "<default> (when SCIE_BOOT is not set in the environment)".to_string(),
-                boot.description.as_ref().cloned().unwrap_or_default(),
-            )
-        });
-    let mut selectable_cmds = select_boot
-        .boots
-        .iter()
-        .filter(|boot| !boot.default)
-        .filter_map(|boot| {
-            boot.description
-                .as_ref()
-                .map(|desc| (boot.name.clone(), desc.clone()))
-        })
-        .collect::<Vec<_>>();
-
-    // Only include hidden named commands when that's all there is.
-    if selectable_cmds.is_empty() && default_cmd.is_none() {
-        selectable_cmds.extend(
-            select_boot
-                .boots
-                .iter()
-                .filter(|boot| !boot.default)
-                .map(|boot| (boot.name.clone(), "".to_string())),
+pub(crate) fn sbom(jump: Jump, lift: Lift) -> ExitResult {
+    let sbom = jump::sbom(&jump::config(jump, lift));
+    serde_json::to_writer_pretty(std::io::stdout(), &sbom)
+        .map_err(|e| Code::FAILURE.with_message(format!("Failed to write the SBOM: {e}")))?;
+    println!();
+    Code::SUCCESS.ok()
+}
+
+pub(crate) fn doctor(diagnosis: Diagnosis) -> ExitResult {
+    println!("Diagnosis of {scie}:", scie = diagnosis.scie.display());
+    for check in &diagnosis.checks {
+        let mut lines = check.detail.lines();
+        let color = match check.health {
+            Health::Ok => Color::Green,
+            Health::Warning => Color::Yellow,
+            Health::Problem => Color::Red,
+        };
+        println!(
+            "[{health}] {name}: {first}",
+            health = paint(stdout_color(), color, &check.health.to_string()),
+            name = check.name,
+            first = lines.next().unwrap_or_default()
         );
+        for line in lines {
+            println!("    {line}");
+        }
     }
-
-    if selectable_cmds.is_empty() && default_cmd.is_none() {
-        return Err(Code::FAILURE.with_message(format!(
-            "The {scie} scie is malformed - it has no boot commands.\n\
-                \n\
-                You might begin debugging by inspecting the output of `SCIE=inspect {scie}`.",
-            scie = select_boot.scie.invoked_as()
-        )));
+    if diagnosis.healthy() {
+        Code::SUCCESS.ok()
+    } else {
+        Err(Code::FAILURE.with_message("Problems were found that will prevent the scie booting."))
     }
+}
+
+/// Prints the command a scie would boot into instead of running it.
+pub(crate) struct DryRun;
+
+impl Launcher for DryRun {
+    type Output = ExitResult;
 
-    if default_cmd.is_some() && selectable_cmds.is_empty() {
-        return Err(Code::FAILURE.with_message(format!(
-            "{error_message}\n\
-                \n\
-                The {scie} scie contains no alternate boot commands.",
-            scie = select_boot.scie.invoked_as(),
-            error_message = select_boot.error_message
-        )));
+    fn launch(&mut self, launch: Launch) -> ExitResult {
+        if let Some(cwd) = &launch.cwd {
+            println!("cwd: {cwd}", cwd = Path::new(cwd).display());
+        }
+        if let Some(timeout) = launch.timeout {
+            println!("timeout: {timeout}s", timeout = timeout.as_secs());
+        }
+        for (name, value) in &launch.env {
+            match value {
+                Some(value) => println!("env: {name:?}={value:?}"),
+                None => println!("env: unset {name:?}"),
+            }
+        }
+        let command_line = std::iter::once(&launch.exe)
+            .chain(launch.args.iter())
+            .map(|arg| format!("{arg:?}"))
+            .collect::<Vec<_>>();
+        println!(
+            "exec: {command_line}",
+            command_line = command_line.join(" ")
+        );
+        Code::SUCCESS.ok()
     }
+}
 
-    let maybe_scie_description = select_boot
-        .description
-        .map(|description| format!("{description}\n\n"))
-        .unwrap_or_default();
-    let max_name_width = default_cmd
-        .iter()
-        .chain(selectable_cmds.iter())
-        .map(|(name, _)| name.len())
-        .max()
-        .expect("We verified we have at least one boot command earlier");
-    Err(Code::FAILURE.with_message(format!(
-        "{error_message}\n\
-            \n\
-            {maybe_scie_description}\
-            Please select from the following boot commands:\n\
-            \n\
-            {boot_commands}\n\
-            \n\
-            You can select a boot command by setting the SCIE_BOOT environment variable\
-            {or_else_by}.",
-        boot_commands = default_cmd
-            .iter()
-            .chain(selectable_cmds.iter())
-            .map(|(name, description)| if description.is_empty() {
-                name.to_string()
-            } else {
-                format!("{name:<max_name_width$}  {description}")
-            })
-            .collect::<Vec<_>>()
-            .join("\n"),
-        or_else_by = if default_cmd.is_none() {
-            " or else by passing it as the 1st argument"
-        } else {
-            ""
-        },
-        error_message = select_boot.error_message
-    )))
+pub(crate) fn select(select_boot: SelectBoot) -> ExitResult {
+    let selection = jump::select(&select_boot);
+    let code = match selection {
+        Selection::Malformed { .. } => exit::BAD_MANIFEST,
+        Selection::NoAlternates { .. } | Selection::Choices { .. } => exit::NO_SUCH_COMMAND,
+    };
+    Err(code.with_message(selection.to_string()))
 }
 
 #[cfg(target_family = "windows")]
@@ -153,12 +145,91 @@ fn symlink_file(src: &Path, dst: &Path) -> ExitResult {
     })
 }
 
+fn remove_existing(path: &Path) -> ExitResult {
+    if path.symlink_metadata().is_ok() {
+        std::fs::remove_file(path).map_err(|e| {
+            Code::FAILURE.with_message(format!(
+                "Failed to remove existing {path}: {e}",
+                path = path.display()
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(target_family = "windows")]
+fn install_shim(dest_dir: &Path, scie_name: &std::ffi::OsStr, command: &str) -> ExitResult {
+    // N.B.: A batch file cannot control the argv0 the scie sees; so it selects the command via
+    // SCIE_BOOT instead.
+    let dest = dest_dir.join(command).with_extension("cmd");
+    let shim = format!(
+        "@echo off\r\n\
+        setlocal\r\n\
+        set \"SCIE_BOOT={command}\"\r\n\
+        \"%~dp0{scie_name}\" %*\r\n",
+        scie_name = Path::new(scie_name).display()
+    );
+    std::fs::write(&dest, shim).map_err(|e| {
+        Code::FAILURE.with_message(format!(
+            "Failed to write shim {dest}: {e}",
+            dest = dest.display()
+        ))
+    })
+}
+
+#[cfg(target_family = "unix")]
+fn install_shim(dest_dir: &Path, scie_name: &std::ffi::OsStr, command: &str) -> ExitResult {
+    use std::os::unix::fs::symlink;
+    // N.B.: The link is relative so that the dest dir can be moved as a whole.
+    let dest = dest_dir.join(command);
+    remove_existing(&dest)?;
+    symlink(scie_name, &dest).map_err(|e| {
+        Code::FAILURE.with_message(format!(
+            "Failed to symlink {scie} -> {dest}: {e}",
+            scie = Path::new(scie_name).display(),
+            dest = dest.display()
+        ))
+    })
+}
+
+fn install_shims(scie: &Path, commands: &[ScieBoot], dest_dir: &Path) -> ExitResult {
+    let scie_name = scie.file_name().ok_or_else(|| {
+        Code::FAILURE.with_message(format!(
+            "Failed to determine the file name of the scie at {scie}.",
+            scie = scie.display()
+        ))
+    })?;
+    let dest = dest_dir.join(scie_name);
+    if dest != scie {
+        remove_existing(&dest)?;
+        std::fs::copy(scie, &dest).map_err(|e| {
+            Code::FAILURE.with_message(format!(
+                "Failed to copy {src} to {dst}: {e}",
+                src = scie.display(),
+                dst = dest.display()
+            ))
+        })?;
+    }
+    for command in commands {
+        if dest_dir
+            .join(command.name.as_str())
+            .with_extension(env::consts::EXE_EXTENSION)
+            != dest
+        {
+            install_shim(dest_dir, scie_name, command.name.as_str())?;
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn install(scie: PathBuf, commands: Vec<ScieBoot>) -> ExitResult {
     let mut symlink = false;
+    let mut shims = false;
     let mut dest_dirs = vec![];
-    for arg in env::args().skip(1) {
+    for arg in jump::scie_args().args().skip(1) {
         match arg.as_str() {
             "-s" | "--symlink" => symlink = true,
+            "--shims" => shims = true,
             path => dest_dirs.push(PathBuf::from(path)),
         }
     }
@@ -176,6 +247,10 @@ pub(crate) fn install(scie: PathBuf, commands: Vec<ScieBoot>) -> ExitResult {
                 dest_dir = dest_dir.display()
             ))
         })?;
+        if shims {
+            install_shims(&scie, &commands, &dest_dir)?;
+            continue;
+        }
         let mut hardlink = true;
         for command in &commands {
             let dest = dest_dir
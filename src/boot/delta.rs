@@ -0,0 +1,109 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::PathBuf;
+
+use jump::Patch;
+use proc_exit::{Code, ExitResult};
+
+fn output_arg(arg: &str, value: Option<String>, what: &str) -> Result<PathBuf, proc_exit::Exit> {
+    value.map(PathBuf::from).ok_or_else(|| {
+        Code::FAILURE.with_message(format!(
+            "The {arg} flag requires an argument pointing to the path to write the {what} to."
+        ))
+    })
+}
+
+pub(crate) fn delta(scie: PathBuf) -> ExitResult {
+    let mut output = None;
+    let mut json_output = false;
+    let mut new = None;
+    let mut args = jump::scie_args().args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            "-o" | "--output" => output = Some(output_arg(&arg, args.next(), "patch")?),
+            _ if new.is_none() => new = Some(PathBuf::from(arg)),
+            _ => {
+                return Err(Code::FAILURE.with_message(format!(
+                    "The delta command accepts just one scie to make a patch to; found {arg} as \
+                    well as {new}.",
+                    new = new.unwrap_or_default().display()
+                )))
+            }
+        }
+    }
+    let new = new.ok_or_else(|| {
+        Code::FAILURE
+            .with_message("The delta command requires a path to a scie to make a patch to.")
+    })?;
+    let output = output.ok_or_else(|| {
+        Code::FAILURE
+            .with_message("The delta command requires an --output path to write the patch to.")
+    })?;
+    let delta = jump::delta(&scie, &new, &output).map_err(|e| Code::FAILURE.with_message(e))?;
+    if json_output {
+        serde_json::to_writer_pretty(std::io::stdout(), &delta).map_err(|e| {
+            Code::FAILURE.with_message(format!("Failed to write the delta report: {e}"))
+        })?;
+        println!();
+    } else {
+        println!(
+            "{patch}: {size} bytes; {copied} of the {new_size} bytes of {new} are copied from \
+            {scie}",
+            patch = delta.patch.display(),
+            size = delta.size,
+            copied = delta.copied,
+            new_size = delta.new_size,
+            new = new.display(),
+            scie = scie.display()
+        );
+    }
+    Code::SUCCESS.ok()
+}
+
+pub(crate) fn patch(scie: PathBuf) -> ExitResult {
+    let mut output = None;
+    let mut in_place = false;
+    let mut patch = None;
+    let mut args = jump::scie_args().args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--in-place" => in_place = true,
+            "-o" | "--output" => output = Some(output_arg(&arg, args.next(), "patched scie")?),
+            _ if patch.is_none() => patch = Some(PathBuf::from(arg)),
+            _ => {
+                return Err(Code::FAILURE.with_message(format!(
+                    "The patch command accepts just one patch to apply; found {arg} as well as \
+                    {patch}.",
+                    patch = patch.unwrap_or_default().display()
+                )))
+            }
+        }
+    }
+    let patch = patch.ok_or_else(|| {
+        Code::FAILURE.with_message("The patch command requires a path to a patch to apply.")
+    })?;
+    let patch = Patch::new(&scie, &patch);
+    let binary = match (output, in_place) {
+        (Some(_), true) => {
+            return Err(Code::FAILURE
+                .with_message("The --output and --in-place flags are mutually exclusive."))
+        }
+        (Some(output), false) => patch.write(&output),
+        (None, true) => patch.write_in_place(),
+        (None, false) => {
+            return Err(Code::FAILURE.with_message(
+                "The patch command requires either an --output path to write the patched scie \
+                to or the --in-place flag.",
+            ))
+        }
+    }
+    .map_err(|e| Code::FAILURE.with_message(e))?;
+    println!(
+        "{scie}: {binary}",
+        scie = scie.display(),
+        binary = binary.display()
+    );
+    Code::SUCCESS.ok()
+}
@@ -0,0 +1,98 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::PathBuf;
+
+use jump::config::File;
+use jump::{Change, Jump, Lift};
+use proc_exit::{Code, ExitResult};
+
+fn describe_file(file: &File) -> String {
+    let mut description = file.hash.clone().unwrap_or_default();
+    if let Some(size) = file.size {
+        description.push_str(format!(" ({size} bytes)").as_str());
+    }
+    description
+}
+
+fn describe_jump(jump: &Jump) -> String {
+    format!(
+        "{version} ({size} bytes)",
+        version = jump.version,
+        size = jump.size
+    )
+}
+
+fn print_changes<T, F>(title: &str, changes: &[Change<T>], describe: F)
+where
+    F: Fn(&T) -> String,
+{
+    if changes.is_empty() {
+        return;
+    }
+    println!("{title}:");
+    for change in changes {
+        let name = if change.name.is_empty() {
+            "<default>"
+        } else {
+            change.name.as_str()
+        };
+        match (&change.before, &change.after) {
+            (None, Some(after)) => println!("  + {name}: {after}", after = describe(after)),
+            (Some(before), None) => println!("  - {name}: {before}", before = describe(before)),
+            (Some(before), Some(after)) => println!(
+                "  ~ {name}: {before} -> {after}",
+                before = describe(before),
+                after = describe(after)
+            ),
+            (None, None) => {}
+        }
+    }
+}
+
+pub(crate) fn diff(jump: Jump, lift: Lift) -> ExitResult {
+    let mut json_output = false;
+    let mut other = None;
+    for arg in jump::scie_args().args().skip(1) {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            _ if other.is_none() => other = Some(PathBuf::from(arg)),
+            _ => {
+                return Err(Code::FAILURE.with_message(format!(
+                    "The diff command accepts just one scie to compare against; found {arg} \
+                    as well as {other}.",
+                    other = other.unwrap_or_default().display()
+                )))
+            }
+        }
+    }
+    let other = other.ok_or_else(|| {
+        Code::FAILURE.with_message("The diff command requires a path to a scie to compare against.")
+    })?;
+    let other_config = jump::inspect(&other).map_err(|e| {
+        Code::FAILURE.with_message(format!(
+            "Failed to load the lift manifest of {other}: {e}",
+            other = other.display()
+        ))
+    })?;
+    let scie_diff = jump::diff(jump::config(jump, lift), other_config);
+
+    if json_output {
+        serde_json::to_writer_pretty(std::io::stdout(), &scie_diff).map_err(|e| {
+            Code::FAILURE.with_message(format!("Failed to write the diff report: {e}"))
+        })?;
+        println!();
+    } else {
+        if let Some(change) = &scie_diff.jump {
+            print_changes("scie-jump", std::slice::from_ref(change), describe_jump);
+        }
+        print_changes("files", &scie_diff.files, describe_file);
+        print_changes("commands", &scie_diff.commands, |cmd| cmd.exe.clone());
+        print_changes("bindings", &scie_diff.bindings, |cmd| cmd.exe.clone());
+    }
+    if scie_diff.is_empty() {
+        Code::SUCCESS.ok()
+    } else {
+        Code::FAILURE.ok()
+    }
+}
@@ -2,268 +2,89 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
 use std::env;
-use std::io::{Read, Seek};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
-use jump::config::{ArchiveType, FileType, Fmt};
-use jump::{check_is_zip, create_options, fingerprint, load_lift, File, Jump, Lift, Source};
-use logging_timer::time;
+use jump::config::UnknownFields;
+use jump::{
+    load_manifest, pack, pack_dry_run, read_manifest, Jump, Lift, PackCache, PackOptions, Packed,
+    ReadManifest, SizeLimits, PAGE_ALIGNMENT,
+};
 use proc_exit::{Code, ExitResult};
-use zip::{CompressionMethod, ZipWriter};
 
-#[time("debug", "pack::{}")]
-fn load_manifest(path: &Path, jump: &Jump) -> Result<(Lift, PathBuf), String> {
-    let manifest_path = if path.is_dir() {
-        path.join("lift.json")
-    } else {
-        path.to_path_buf()
-    };
-    if !manifest_path.is_file() {
-        return Err(format!(
-            "The given path does not contain a lift manifest: {path}",
-            path = path.display()
-        ));
-    }
-    let (maybe_jump, lift) = load_lift(&manifest_path)?;
-    if let Some(ref configured_jump) = maybe_jump {
-        if jump != configured_jump {
-            return Err(format!(
-                "The lift manifest {manifest} specifies a scie jump binary of \
-                    {configured_jump:?} that does not match the current of {jump:?}.",
-                manifest = manifest_path.display()
-            ));
-        }
-    }
-    Ok((lift, manifest_path))
-}
-
-#[cfg(windows)]
-fn finalize_executable(path: &Path) -> Result<PathBuf, String> {
-    use std::ffi::OsStr;
-    if let Some(env::consts::EXE_EXTENSION) = path.extension().and_then(OsStr::to_str) {
-        Ok(path.to_path_buf())
-    } else {
-        let exe = path.with_file_name(format!(
-            "{file_name}.{ext}",
-            file_name = path
-                .file_name()
-                .and_then(OsStr::to_str)
-                .ok_or_else(|| format!(
-                    "Failed to determine the file name of {path}",
-                    path = path.display()
-                ))?,
-            ext = env::consts::EXE_EXTENSION
-        ));
-        std::fs::rename(path, &exe).map_err(|e| {
-            format!(
-                "Failed to rename executable from {path} to {exe}: {e}",
+fn is_scie(paths: Vec<PathBuf>) -> ExitResult {
+    let mut all_scies = !paths.is_empty();
+    for path in paths {
+        match jump::is_scie(&path).map_err(|e| Code::FAILURE.with_message(e))? {
+            Some(footer) => println!(
+                "{path}: scie built by scie-jump {version}",
                 path = path.display(),
-                exe = exe.display()
-            )
-        })?;
-        Ok(exe)
-    }
-}
-
-#[cfg(unix)]
-fn finalize_executable(path: &Path) -> Result<PathBuf, String> {
-    use std::os::unix::fs::PermissionsExt;
-    let mut perms = std::fs::metadata(path)
-        .map_err(|e| {
-            format!(
-                "Failed to access permissions metadata for {binary}: {e}",
-                binary = path.display()
-            )
-        })?
-        .permissions();
-    perms.set_mode(0o755);
-    std::fs::set_permissions(path, perms).map_err(|e| {
-        format!(
-            "Failed to mark {binary} as executable: {e}",
-            binary = path.display()
-        )
-    })?;
-    Ok(path.to_path_buf())
-}
-
-struct ScieTote {
-    zip_file: std::fs::File,
-    zip_writer: ZipWriter<std::fs::File>,
-}
-
-impl ScieTote {
-    fn new() -> Result<Self, String> {
-        let zip_file = tempfile::tempfile().map_err(|e| {
-            format!("Failed to create a temporary file to built the scie-tote with: {e}")
-        })?;
-        let zip_writer = ZipWriter::new(
-            zip_file
-                .try_clone()
-                .map_err(|e| format!("Failed to dup temporary file fd: {e}"))?,
-        );
-        Ok(Self {
-            zip_file,
-            zip_writer,
-        })
-    }
-}
-
-#[time("debug", "pack::{}")]
-fn pack(
-    mut lift: Lift,
-    manifest_path: &Path,
-    jump: &Jump,
-    scie_jump_path: &Path,
-    single_line: bool,
-) -> Result<PathBuf, String> {
-    let binary_path = env::current_dir()
-        .map(|cwd| cwd.join(&lift.name))
-        .map_err(|e| format!("Failed to determine the output directory for scies: {e}"))?;
-    let mut binary = std::fs::OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(&binary_path)
-        .map_err(|e| {
-            format!(
-                "Failed to open binary {path} for writing {lift:?}: {e}",
-                path = binary_path.display(),
-            )
-        })?;
-    let mut scie_jump = std::fs::File::open(scie_jump_path)
-        .map_err(|e| {
-            format!(
-                "Failed to open scie-jump binary {path} for writing to the tip of {binary}: {e}",
-                path = scie_jump_path.display(),
-                binary = binary_path.display()
-            )
-        })?
-        .take(jump.size as u64);
-    std::io::copy(&mut scie_jump, &mut binary).map_err(|e| {
-        format!(
-            "Failed to write first {scie_jump_size} bytes of the scie-jump binary {path} to \
-            {binary}: {e}",
-            scie_jump_size = jump.size,
-            path = scie_jump_path.display(),
-            binary = binary_path.display()
-        )
-    })?;
-    let resolve_base = manifest_path.parent().unwrap_or_else(|| Path::new(""));
-    let mut scie_tote: Option<ScieTote> = None;
-    if let Some(last_file) = lift.files.last() {
-        let mut path = resolve_base.join(&last_file.name);
-        if FileType::Directory == last_file.file_type {
-            path = path.with_extension("zip");
-        }
-        if check_is_zip(&path).is_err() {
-            scie_tote = Some(ScieTote::new()?)
+                version = footer.jump.version
+            ),
+            None => {
+                all_scies = false;
+                println!("{path}: not a scie", path = path.display())
+            }
         }
     }
-    for file in lift.files.iter_mut() {
-        if Source::Scie != file.source {
-            continue;
-        }
-        let mut path = resolve_base.join(&file.name);
-        if FileType::Directory == file.file_type {
-            path = path.with_extension("zip");
-        }
-        let mut blob = std::fs::File::open(&path).map_err(|e| {
-            format!(
-                "Failed to open {src} / {file:?} for writing to {binary}: {e}",
-                src = path.display(),
-                binary = binary_path.display()
-            )
-        })?;
-        if let Some(tote) = scie_tote.as_mut() {
-            let metadata = blob.metadata().map_err(|e| {
-                format!(
-                    "Failed to read metadata for {path}: {e}",
-                    path = path.display()
-                )
-            })?;
-            let options = create_options(&metadata)?.compression_method(CompressionMethod::Stored);
-            tote.zip_writer
-                .start_file(&file.name, options)
-                .map_err(|e| {
-                    format!(
-                        "Failed to start a scie-tote file entry for {path}: {e}",
-                        path = path.display()
-                    )
-                })?;
-            std::io::copy(&mut blob, &mut tote.zip_writer).map_err(|e| {
-                format!(
-                    "Failed to append {src} / {file:?} to {binary}: {e}",
-                    src = path.display(),
-                    binary = binary_path.display()
-                )
-            })?;
-            file.size = 0;
-        } else {
-            std::io::copy(&mut blob, &mut binary).map_err(|e| {
-                format!(
-                    "Failed to append {src} / {file:?} to {binary}: {e}",
-                    src = path.display(),
-                    binary = binary_path.display()
-                )
-            })?;
-        };
-    }
-    if let Some(tote) = scie_tote.as_mut() {
-        tote.zip_writer
-            .finish()
-            .map_err(|e| format!("Failed to finalize the scie-tote zip: {e}"))?;
-
-        tote.zip_file.rewind().map_err(|e| {
-            format!(
-                "Failed to re-wind the scie-tote file to make a second pass calculation of \
-                    its hash: {e}"
-            )
-        })?;
-        let (size, hash) = fingerprint::digest_reader(&tote.zip_file)?;
-        let tote_file = File {
-            name: "scie-tote".to_string(),
-            key: None,
-            size,
-            hash,
-            file_type: FileType::Archive(ArchiveType::Zip),
-            executable: None,
-            eager_extract: false,
-            source: Source::Scie,
-        };
-
-        tote.zip_file.rewind().map_err(|e| format!("{e}"))?;
-        std::io::copy(&mut tote.zip_file, &mut binary).map_err(|e| {
-            format!(
-                "Failed to append {tote_file:?} to {binary}: {e}",
-                binary = binary_path.display()
-            )
-        })?;
-        lift.files.push(tote_file);
+    if all_scies {
+        Code::SUCCESS.ok()
+    } else {
+        Code::FAILURE.ok()
     }
-    let config = jump::config(jump.clone(), lift);
-    // We configure the lift manifest format to allow for easiest inspection via standard tools.
-    // In the single line case in particular, this configuration allows for inspection via
-    // `tail -1 scie` or `tail -1 scie | jq .` on systems with these common tools.
-    let fmt = Fmt::new()
-        .pretty(!single_line)
-        .leading_newline(true)
-        .trailing_newline(true);
-    config.serialize(binary, fmt).map_err(|e| {
-        format!(
-            "Failed to serialize the lift manifest to {binary}: {e}",
-            binary = binary_path.display()
-        )
-    })?;
-    finalize_executable(&binary_path)
 }
 
 pub(crate) fn set(mut jump: Jump, mut scie_jump_path: PathBuf) -> ExitResult {
     let mut lifts = vec![];
-    let mut single_line = true;
-    let mut args = env::args().skip(1);
+    let mut manifests = vec![];
+    let mut options = PackOptions::default();
+    let mut json_output = false;
+    let mut sha256 = false;
+    let mut provenance = false;
+    let mut dest_dir = None;
+    let mut dry_run = false;
+    let mut size_limits = SizeLimits::default();
+    let mut unknown_fields = UnknownFields::Warn;
+    let mut args = jump::scie_args().args().skip(1);
     while let Some(arg) = args.next() {
         match arg.as_str() {
-            "-1" | "--single-lift-line" => single_line = true,
-            "--no-single-lift-line" => single_line = false,
+            "-1" | "--single-lift-line" => options.single_line = true,
+            "--no-single-lift-line" => options.single_line = false,
+            "--strict" => unknown_fields = UnknownFields::Deny,
+            "--json" => json_output = true,
+            "--align" => options.alignment = Some(PAGE_ALIGNMENT),
+            "--lift-in-zip" => options.lift_in_zip = true,
+            "--embed" => options.embed = true,
+            "--adjust-zip-offsets" => options.adjust_zip_offsets = true,
+            "--chunk" => options.chunk = true,
+            "--sha256" => sha256 = true,
+            "--provenance" => provenance = true,
+            "--dry-run" => dry_run = true,
+            "--allow-large" => size_limits.allow_large = true,
+            "--warn-file-size" | "--max-file-size" | "--warn-scie-size" | "--max-scie-size" => {
+                let size = args.next().ok_or_else(|| {
+                    Code::FAILURE.with_message(format!(
+                        "The {arg} flag requires a size argument; e.g.: 512MiB."
+                    ))
+                })?;
+                let size = SizeLimits::parse_size(&size).map_err(|e| {
+                    Code::FAILURE.with_message(format!("Invalid {arg} argument: {e}"))
+                })?;
+                *match arg.as_str() {
+                    "--warn-file-size" => &mut size_limits.warn_file_size,
+                    "--max-file-size" => &mut size_limits.max_file_size,
+                    "--warn-scie-size" => &mut size_limits.warn_scie_size,
+                    _ => &mut size_limits.max_scie_size,
+                } = Some(size);
+            }
+            "--is-scie" => return is_scie(args.map(PathBuf::from).collect()),
+            "-o" | "--dest-dir" => {
+                dest_dir = Some(PathBuf::from(args.next().ok_or_else(|| {
+                    Code::FAILURE.with_message(format!(
+                        "The {arg} flag requires an argument pointing to the directory to write \
+                        scies to."
+                    ))
+                })?));
+            }
             "-sj" | "--jump" | "--scie-jump" => {
                 scie_jump_path = PathBuf::from(args.next().ok_or_else(|| {
                     Code::FAILURE.with_message(format!(
@@ -281,19 +102,67 @@ pub(crate) fn set(mut jump: Jump, mut scie_jump_path: PathBuf) -> ExitResult {
                     })?
                     .len() as usize;
             }
-            _ => {
-                let (lift, path) = load_manifest(Path::new(arg.as_str()), &jump)
-                    .map_err(|e| Code::FAILURE.with_message(e))?;
-                lifts.push((lift, path));
-            }
+            _ => manifests.push(PathBuf::from(arg)),
         }
     }
-    if lifts.is_empty() {
+    if dry_run && (sha256 || provenance) {
+        return Err(Code::FAILURE.with_message(
+            "The --dry-run flag cannot be combined with --sha256 or --provenance since no scie is \
+            written to describe.",
+        ));
+    }
+    if manifests.is_empty() {
         if let Ok(cwd) = env::current_dir() {
-            let (lift, path) =
-                load_manifest(&cwd, &jump).map_err(|e| Code::FAILURE.with_message(e))?;
-            lifts.push((lift, path));
+            manifests.push(cwd);
+        }
+    }
+    let unknown_fields = unknown_fields.or_strict_from_env();
+    // Manifests packed together often share files; so we share the work of archiving and
+    // fingerprinting those files across all of them.
+    let mut pack_cache = PackCache::default();
+    let mut unpacked = None;
+    for manifest in manifests {
+        let (lift, path) = if manifest.as_os_str() == "-" {
+            if unpacked.is_some() {
+                return Err(Code::FAILURE
+                    .with_message("Only one lift manifest can be read from stdin.".to_string()));
+            }
+            let cwd = env::current_dir().map_err(|e| {
+                Code::FAILURE.with_message(format!(
+                    "Failed to determine the directory to resolve files named by the lift \
+                    manifest on stdin against: {e}"
+                ))
+            })?;
+            let ReadManifest {
+                lift,
+                manifest,
+                files,
+            } = read_manifest(
+                std::io::stdin().lock(),
+                &cwd,
+                &jump,
+                unknown_fields,
+                &mut pack_cache,
+            )
+            .map_err(|e| Code::FAILURE.with_message(e))?;
+            unpacked = Some(files);
+            (lift, manifest)
+        } else {
+            load_manifest(&manifest, &jump, unknown_fields, &mut pack_cache)
+                .map_err(|e| Code::FAILURE.with_message(e))?
+        };
+        if let Some((_, other)) = lifts
+            .iter()
+            .find(|(other_lift, _): &&(Lift, PathBuf)| other_lift.name == lift.name)
+        {
+            return Err(Code::FAILURE.with_message(format!(
+                "The lift manifests {other} and {path} both produce a scie named {name}.",
+                other = other.display(),
+                path = path.display(),
+                name = lift.name
+            )));
         }
+        lifts.push((lift, path));
     }
 
     if lifts.is_empty() {
@@ -303,20 +172,71 @@ pub(crate) fn set(mut jump: Jump, mut scie_jump_path: PathBuf) -> ExitResult {
                 named `lift.json`.",
         ));
     }
+    let dest_dir = match dest_dir {
+        Some(dest_dir) if dry_run => dest_dir,
+        Some(dest_dir) => {
+            std::fs::create_dir_all(&dest_dir).map_err(|e| {
+                Code::FAILURE.with_message(format!(
+                    "Failed to create the scie output directory {dest_dir}: {e}",
+                    dest_dir = dest_dir.display()
+                ))
+            })?;
+            dest_dir
+        }
+        None => env::current_dir().map_err(|e| {
+            Code::FAILURE.with_message(format!(
+                "Failed to determine the output directory for scies: {e}"
+            ))
+        })?,
+    };
     let results = lifts
         .into_iter()
         .map(|(lift, manifest)| {
-            pack(lift, &manifest, &jump, &scie_jump_path, single_line)
-                .map(|binary| (manifest, binary))
+            let pack = if dry_run { pack_dry_run } else { pack };
+            let packed = pack(lift, &manifest, &jump, &scie_jump_path, &dest_dir, &options)?;
+            if let Err(e) = packed.check_sizes(&size_limits) {
+                if !packed.is_dry_run() {
+                    let _ = std::fs::remove_file(&packed.binary);
+                }
+                return Err(e);
+            }
+            Ok(packed)
         })
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| Code::FAILURE.with_message(e))?;
-    for (manifest, binary) in results {
-        println!(
-            "{manifest}: {binary}",
-            manifest = manifest.display(),
-            binary = binary.display()
-        );
+    if sha256 || provenance {
+        for packed in &results {
+            let hash = packed.digest().map_err(|e| Code::FAILURE.with_message(e))?;
+            if sha256 {
+                packed
+                    .write_sha256(&hash)
+                    .map_err(|e| Code::FAILURE.with_message(e))?;
+            }
+            if provenance {
+                packed
+                    .write_provenance(&hash)
+                    .map_err(|e| Code::FAILURE.with_message(e))?;
+            }
+        }
+    }
+    if json_output {
+        let report = results.iter().map(Packed::to_json).collect::<Vec<_>>();
+        serde_json::to_writer_pretty(std::io::stdout(), &report).map_err(|e| {
+            Code::FAILURE.with_message(format!("Failed to write the boot-pack report: {e}"))
+        })?;
+        println!();
+    } else if dry_run {
+        for packed in results {
+            print!("{summary}", summary = packed.summary());
+        }
+    } else {
+        for packed in results {
+            println!(
+                "{manifest}: {binary}",
+                manifest = packed.manifest.display(),
+                binary = packed.binary.display()
+            );
+        }
     }
     Code::SUCCESS.ok()
 }
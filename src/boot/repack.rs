@@ -0,0 +1,75 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::PathBuf;
+
+use jump::Repack;
+use proc_exit::{Code, ExitResult};
+
+pub(crate) fn repack(scie: PathBuf) -> ExitResult {
+    let mut repack = Repack::new(&scie);
+    let mut output = None;
+    let mut in_place = false;
+    let mut json_output = false;
+    let mut args = jump::scie_args().args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-1" | "--single-lift-line" => repack = repack.single_line(true),
+            "--no-single-lift-line" => repack = repack.single_line(false),
+            "--json" => json_output = true,
+            "--in-place" => in_place = true,
+            "-sj" | "--jump" | "--scie-jump" => {
+                repack = repack.scie_jump(args.next().ok_or_else(|| {
+                    Code::FAILURE.with_message(format!(
+                        "The {arg} flag requires an argument pointing to the new scie-jump \
+                        binary to use as the scie tip."
+                    ))
+                })?);
+            }
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(args.next().ok_or_else(|| {
+                    Code::FAILURE.with_message(format!(
+                        "The {arg} flag requires an argument pointing to the path to write the \
+                        re-packed scie to."
+                    ))
+                })?));
+            }
+            _ => {
+                let (name, path) = arg.split_once('=').ok_or_else(|| {
+                    Code::FAILURE.with_message(format!(
+                        "Expected files to re-pack to be given as <name>=<path>; found: {arg}"
+                    ))
+                })?;
+                repack = repack.file(name, path);
+            }
+        }
+    }
+    let packed = match (output, in_place) {
+        (Some(_), true) => {
+            return Err(Code::FAILURE
+                .with_message("The --output and --in-place flags are mutually exclusive."))
+        }
+        (Some(output), false) => repack.write(&output),
+        (None, true) => repack.write_in_place(),
+        (None, false) => {
+            return Err(Code::FAILURE.with_message(
+                "The repack command requires either an --output path to write the re-packed \
+                scie to or the --in-place flag.",
+            ))
+        }
+    }
+    .map_err(|e| Code::FAILURE.with_message(e))?;
+    if json_output {
+        serde_json::to_writer_pretty(std::io::stdout(), &packed.to_json()).map_err(|e| {
+            Code::FAILURE.with_message(format!("Failed to write the repack report: {e}"))
+        })?;
+        println!();
+    } else {
+        println!(
+            "{scie}: {binary}",
+            scie = scie.display(),
+            binary = packed.binary.display()
+        );
+    }
+    Code::SUCCESS.ok()
+}
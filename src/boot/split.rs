@@ -3,11 +3,11 @@
 
 use std::env;
 use std::fs::Permissions;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use jump::config::{FileType, Fmt};
-use jump::{File, Jump, Lift, Source};
+use jump::{File, Jump, Lift, Source, ZipStream};
 use log::debug;
 use proc_exit::{Code, Exit, ExitResult};
 use zip::ZipArchive;
@@ -36,8 +36,22 @@ fn executable_permissions() -> Option<Permissions> {
     Some(Permissions::from_mode(0o755))
 }
 
+// Copies the bytes of the file from the scie; either from where the scie is positioned or else, if
+// the file is stored in chunks, from each of those in turn.
+fn copy_file(mut scie: &std::fs::File, file: &File, out: &mut impl Write) -> std::io::Result<u64> {
+    if file.chunks.is_empty() {
+        return std::io::copy(&mut scie.try_clone()?.take(file.size as u64), out);
+    }
+    let mut copied = 0;
+    for chunk in &file.chunks {
+        scie.seek(SeekFrom::Start(chunk.offset as u64))?;
+        copied += std::io::copy(&mut scie.try_clone()?.take(chunk.size as u64), out)?;
+    }
+    Ok(copied)
+}
+
 pub(crate) fn split(jump: Jump, mut lift: Lift, scie_path: PathBuf) -> ExitResult {
-    let base = if let Some(base) = env::args().nth(1) {
+    let base = if let Some(base) = jump::scie_args().args().nth(1) {
         PathBuf::from(base)
     } else {
         env::current_dir().map_err(|e| {
@@ -92,26 +106,36 @@ pub(crate) fn split(jump: Jump, mut lift: Lift, scie_path: PathBuf) -> ExitResul
             continue;
         } else if file.size == 0 {
             scie_tote.push(file);
-        } else if file.file_type == FileType::Directory
+            continue;
+        }
+        if let Some(offset) = file.offset {
+            (&scie).seek(SeekFrom::Start(offset as u64)).map_err(|e| {
+                Code::FAILURE.with_message(format!(
+                    "Failed to seek to {file} at offset {offset}: {e}",
+                    file = file.name
+                ))
+            })?;
+        }
+        if file.file_type == FileType::Directory
             || (index == scie_tote_index && !scie_tote.is_empty())
         {
-            let mut src = scie
-                .try_clone()
-                .map_err(|e| Code::FAILURE.with_message(format!("Failed to dup scie handle: {e}")))?
-                .take(file.size as u64);
             let mut zip_file = tempfile::tempfile().map_err(|e| {
                 Code::FAILURE.with_message(format!(
                     "Failed to create a temporary file to extract {file} to: {e}",
                     file = file.name
                 ))
             })?;
-            std::io::copy(&mut src, &mut zip_file).map_err(|e| {
+            copy_file(&scie, file, &mut zip_file).map_err(|e| {
                 Code::FAILURE.with_message(format!(
                     "Failed to extract {file} zip to a temp file: {e}",
                     file = file.name
                 ))
             })?;
-            let mut zip_archive = ZipArchive::new(zip_file).map_err(|e| {
+            let zip_stream = ZipStream::new(zip_file).map_err(|e| {
+                Code::FAILURE
+                    .with_message(format!("Failed to open {file} zip: {e}", file = file.name))
+            })?;
+            let mut zip_archive = ZipArchive::new(zip_stream).map_err(|e| {
                 Code::FAILURE
                     .with_message(format!("Failed to open {file} zip: {e}", file = file.name))
             })?;
@@ -139,12 +163,7 @@ pub(crate) fn split(jump: Jump, mut lift: Lift, scie_path: PathBuf) -> ExitResul
                         dst = dst.display()
                     ))
                 })?;
-            let file_size = file.size as u64;
-            let mut src = scie
-                .try_clone()
-                .map_err(|e| Code::FAILURE.with_message(format!("Failed to dup scie handle: {e}")))?
-                .take(file_size);
-            std::io::copy(&mut src, &mut out).map_err(|e| {
+            copy_file(&scie, file, &mut out).map_err(|e| {
                 Code::FAILURE.with_message(format!(
                     "Failed to extract {file:?} to {dst}: {e}",
                     dst = dst.display()
@@ -153,7 +172,13 @@ pub(crate) fn split(jump: Jump, mut lift: Lift, scie_path: PathBuf) -> ExitResul
         }
     }
 
-    if !scie_tote.is_empty() {
+    let has_scie_tote = !scie_tote.is_empty();
+    // The split files are laid out anew when re-packed.
+    for file in lift.files.iter_mut() {
+        file.offset = None;
+        file.chunks.clear();
+    }
+    if has_scie_tote {
         lift.files.remove(lift.files.len() - 1);
         for file in lift.files.iter_mut() {
             if file.source == Source::Scie {
@@ -0,0 +1,149 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! The scie-jump's own diagnostics: the errors, warnings and notes it writes to stderr.
+//!
+//! These all share a `<Level>: ` prefix that is colored when the stream is a terminal that wants
+//! color. Color is off when `NO_COLOR` is set (see https://no-color.org/) or `TERM` is `dumb`.
+//! Setting `SCIE_QUIET` hides everything but errors.
+
+use std::env;
+use std::io::{IsTerminal, Write};
+use std::sync::OnceLock;
+
+use log::{Level as LogLevel, LevelFilter};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Level {
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "Error",
+            Level::Warning => "Warning",
+            Level::Note => "Note",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Level::Error => Color::Red,
+            Level::Warning => Color::Yellow,
+            Level::Note => Color::Cyan,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Color {
+    Green,
+    Red,
+    Yellow,
+    Cyan,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Green => "32",
+            Color::Red => "31",
+            Color::Yellow => "33",
+            Color::Cyan => "36",
+        }
+    }
+}
+
+fn wants_color(is_terminal: bool) -> bool {
+    if !is_terminal || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    match env::var_os("TERM") {
+        Some(term) => term != "dumb",
+        // N.B.: Older Windows consoles print escape codes literally; so we only color there when a
+        // terminal that understands them, like Windows Terminal, announces itself.
+        None => !cfg!(windows) || env::var_os("WT_SESSION").is_some(),
+    }
+}
+
+/// Returns `true` if diagnostics written to stderr should be colored.
+pub(crate) fn stderr_color() -> bool {
+    static COLOR: OnceLock<bool> = OnceLock::new();
+    *COLOR.get_or_init(|| wants_color(std::io::stderr().is_terminal()))
+}
+
+/// Returns `true` if reports written to stdout should be colored.
+pub(crate) fn stdout_color() -> bool {
+    static COLOR: OnceLock<bool> = OnceLock::new();
+    *COLOR.get_or_init(|| wants_color(std::io::stdout().is_terminal()))
+}
+
+/// Returns `true` if `SCIE_QUIET` asks for just errors to be reported.
+pub(crate) fn quiet() -> bool {
+    env::var_os("SCIE_QUIET").is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Renders `text` in bold `color` if color is `wanted`.
+pub(crate) fn paint(wanted: bool, color: Color, text: &str) -> String {
+    if wanted {
+        format!("\x1b[1;{code}m{text}\x1b[0m", code = color.code())
+    } else {
+        text.to_string()
+    }
+}
+
+fn render(color: bool, level: Level, message: &str) -> String {
+    format!(
+        "{label}: {message}",
+        label = paint(color, level.color(), level.label())
+    )
+}
+
+/// Writes `message` to stderr at the given `level` unless `SCIE_QUIET` hides it.
+pub(crate) fn report(level: Level, message: &str) {
+    if level != Level::Error && quiet() {
+        return;
+    }
+    eprintln!("{}", render(stderr_color(), level, message));
+}
+
+/// Routes log records through the diagnostics format.
+///
+/// Warnings and errors are shown by default (just errors under `SCIE_QUIET`) and `RUST_LOG` or
+/// `--scie-log` can adjust that as usual.
+pub(crate) fn init_logging(filter: Option<&str>) {
+    let mut logger = env_logger::Builder::new();
+    logger.filter_level(if quiet() {
+        LevelFilter::Error
+    } else {
+        LevelFilter::Warn
+    });
+    logger.parse_env("RUST_LOG");
+    if let Some(filter) = filter {
+        logger.parse_filters(filter);
+    }
+    logger.format(|buf, record| {
+        let level = match record.level() {
+            LogLevel::Error => Level::Error,
+            LogLevel::Warn => Level::Warning,
+            LogLevel::Info => Level::Note,
+            level => {
+                return writeln!(
+                    buf,
+                    "[{level:<5} {target}] {args}",
+                    target = record.target(),
+                    args = record.args()
+                )
+            }
+        };
+        writeln!(
+            buf,
+            "{}",
+            render(stderr_color(), level, &record.args().to_string())
+        )
+    });
+    logger.init();
+}
@@ -0,0 +1,40 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Exit codes for failures of the scie-jump itself.
+//!
+//! These are drawn from the BSD `sysexits.h` range so that wrappers can tell the scie-jump failing
+//! to launch a command apart from the command itself failing; although a command is of course free
+//! to exit with any of these codes too.
+
+use jump::JumpError;
+use proc_exit::{sysexits, Code};
+
+/// The scie's lift manifest is malformed or inconsistent.
+pub(crate) const BAD_MANIFEST: Code = sysexits::CONFIG_ERR;
+
+/// Content in the scie or the `nce` cache did not match its recorded fingerprint.
+pub(crate) const INTEGRITY_FAILURE: Code = sysexits::DATA_ERR;
+
+/// A file could not be extracted into the `nce` cache.
+pub(crate) const EXTRACTION_FAILURE: Code = sysexits::CANT_CREAT;
+
+/// No boot command could be selected; e.g.: the one named via `SCIE_BOOT` does not exist.
+pub(crate) const NO_SUCH_COMMAND: Code = sysexits::USAGE_ERR;
+
+/// The selected command could not be spawned or executed.
+pub(crate) const SPAWN_FAILURE: Code = sysexits::OS_ERR;
+
+/// The selected command ran past its timeout and was killed. This is the code coreutils `timeout`
+/// uses and lies outside the `sysexits.h` range.
+pub(crate) const TIMED_OUT: Code = Code::new(124);
+
+pub(crate) fn for_error(error: &JumpError) -> Code {
+    match error {
+        JumpError::Config(_) => BAD_MANIFEST,
+        JumpError::Integrity(_) => INTEGRITY_FAILURE,
+        JumpError::Extraction(_) => EXTRACTION_FAILURE,
+        JumpError::Process { .. } => SPAWN_FAILURE,
+        _ => Code::FAILURE,
+    }
+}
@@ -3,73 +3,173 @@
 
 use std::ffi::OsString;
 
-use proc_exit::{Code, ExitResult};
+use proc_exit::ExitResult;
 
 mod boot;
+mod diagnostics;
+mod exit;
+mod supervise;
 
-use jump::BootAction;
+use jump::{BootAction, Launch, Launcher};
 
 #[cfg(windows)]
-fn exec(exe: OsString, args: Vec<OsString>, argv_skip: usize) -> ExitResult {
-    let result = jump::execute(exe, args, argv_skip);
+fn exec(exe: OsString, args: Vec<OsString>, _exe_fd: Option<i32>) -> ExitResult {
+    let result = jump::execute(exe, args);
     match result {
-        Ok(exit_status) => Code::from(exit_status).ok(),
-        Err(message) => Err(Code::FAILURE.with_message(message)),
+        Ok(exit_status) => proc_exit::Code::from(exit_status).ok(),
+        Err(error) => Err(exit::for_error(&error).with_message(error)),
     }
 }
 
+/// Converts a command line to the C strings `exec` takes; starting with the executable.
 #[cfg(unix)]
-fn exec(exe: OsString, args: Vec<OsString>, argv_skip: usize) -> ExitResult {
+fn c_args(exe: OsString, args: Vec<OsString>) -> Result<Vec<std::ffi::CString>, String> {
     use std::ffi::CString;
     use std::os::unix::ffi::OsStringExt;
 
-    use nix::unistd::execv;
+    let c_exe = CString::new(exe.into_vec())
+        .map_err(|e| format!("Failed to convert executable to a C string: {e}"))?;
+    let mut c_args = vec![c_exe];
+    for arg in args {
+        c_args.push(
+            CString::new(arg.into_vec())
+                .map_err(|e| format!("Failed to convert argument to a C string: {e}"))?,
+        );
+    }
+    Ok(c_args)
+}
 
-    let c_exe = CString::new(exe.into_vec()).map_err(|e| {
-        Code::FAILURE.with_message(format!("Failed to convert executable to a C string: {e}",))
-    })?;
+/// Converts the current environment to the `NAME=value` C strings `fexecve` takes.
+#[cfg(target_os = "linux")]
+fn c_env() -> Result<Vec<std::ffi::CString>, String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStringExt;
+
+    std::env::vars_os()
+        .map(|(name, value)| {
+            let mut entry = name.into_vec();
+            entry.push(b'=');
+            entry.extend(value.into_vec());
+            CString::new(entry)
+                .map_err(|e| format!("Failed to convert environment variable to a C string: {e}"))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn fexec(fd: i32, c_args: &[std::ffi::CString]) -> ExitResult {
+    use nix::unistd::fexecve;
+
+    let c_env = c_env().map_err(|e| exit::SPAWN_FAILURE.with_message(e))?;
+    fexecve(fd, c_args, &c_env)
+        .map_err(|e| {
+            exit::SPAWN_FAILURE.with_message(format!(
+                "Failed to fexecve fd {fd} with argv {c_args:?}: {e}"
+            ))
+        })
+        .map(|_| ())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn fexec(fd: i32, _c_args: &[std::ffi::CString]) -> ExitResult {
+    Err(exit::SPAWN_FAILURE.with_message(format!(
+        "Cannot execute fd {fd}: executing in place is only supported on Linux."
+    )))
+}
 
-    let mut c_args = vec![c_exe.clone()];
-    c_args.extend(
-        args.into_iter()
-            .chain(std::env::args().skip(argv_skip).map(OsString::from))
-            .map(|arg| {
-                CString::new(arg.into_vec()).map_err(|e| {
-                    Code::FAILURE
-                        .with_message(format!("Failed to convert argument to a C string: {e}",))
-                })
-            })
-            .collect::<Result<Vec<_>, _>>()?,
-    );
-
-    execv(&c_exe, &c_args)
+#[cfg(unix)]
+fn exec(exe: OsString, args: Vec<OsString>, exe_fd: Option<i32>) -> ExitResult {
+    use nix::unistd::execv;
+
+    let c_args = c_args(exe, args).map_err(|e| exit::SPAWN_FAILURE.with_message(e))?;
+    if let Some(fd) = exe_fd {
+        return fexec(fd, &c_args);
+    }
+    execv(&c_args[0], &c_args)
         .map_err(|e| {
-            Code::new(e as i32).with_message(format!(
-                "Failed to exec {c_exe:?} with argv {c_args:?}: {e}"
+            exit::SPAWN_FAILURE.with_message(format!(
+                "Failed to exec {c_exe:?} with argv {c_args:?}: {e}",
+                c_exe = c_args[0]
             ))
         })
         .map(|_| ())
 }
 
-fn main() -> ExitResult {
-    env_logger::init();
+/// Execs the command a scie boots into in place of the scie-jump, or runs it as a child when it
+/// has a timeout.
+struct Exec;
+
+impl Launcher for Exec {
+    type Output = ExitResult;
+
+    fn launch(&mut self, launch: Launch) -> ExitResult {
+        for (name, value) in launch.env {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+        if let Some(cwd) = launch.cwd {
+            std::env::set_current_dir(&cwd).map_err(|e| {
+                exit::SPAWN_FAILURE.with_message(format!(
+                    "Failed to change to the working directory {cwd:?} for {exe:?}: {e}",
+                    exe = launch.exe
+                ))
+            })?;
+        }
+        if let Some(timeout) = launch.timeout {
+            return supervise::run(launch.exe, launch.args, launch.exe_fd, timeout);
+        }
+        exec(launch.exe, launch.args, launch.exe_fd)
+    }
+}
+
+fn run() -> ExitResult {
+    diagnostics::init_logging(jump::scie_args().log.as_deref());
 
     let action = jump::prepare_boot().map_err(|e| {
-        Code::FAILURE.with_message(format!("Failed to prepare a scie jump action: {e}"))
+        exit::for_error(&e).with_message(format!("Failed to prepare a scie jump action: {e}"))
     })?;
 
     match action {
+        BootAction::Delta(scie) => boot::delta(scie),
+        BootAction::Diff((jump, lift)) => boot::diff(jump, lift),
+        BootAction::Doctor(diagnosis) => boot::doctor(diagnosis),
         BootAction::Execute((process, argv1_consumed)) => {
-            process.env.export();
             let argv_skip = if argv1_consumed { 2 } else { 1 };
-            exec(process.exe, process.args, argv_skip)
+            let launch = Launch::new(
+                process,
+                jump::scie_args().args_os().skip(argv_skip).cloned(),
+            );
+            if jump::scie_args().dry_run {
+                return boot::DryRun.launch(launch);
+            }
+            Exec.launch(launch)
         }
         BootAction::Help((message, exit_code)) => boot::help(message, exit_code),
         BootAction::Inspect((jump, lift)) => boot::inspect(jump, lift),
         BootAction::Install((scie, commands)) => boot::install(scie, commands),
         BootAction::List(commands) => boot::list(commands),
         BootAction::Pack((jump, scie_jump_path)) => boot::pack(jump, scie_jump_path),
+        BootAction::Patch(scie) => boot::patch(scie),
+        BootAction::Repack(scie) => boot::repack(scie),
+        BootAction::Sbom((jump, lift)) => boot::sbom(jump, lift),
         BootAction::Select(select_boot) => boot::select(select_boot),
         BootAction::Split((jump, lift, scie_path)) => boot::split(jump, lift, scie_path),
     }
 }
+
+fn main() -> std::process::ExitCode {
+    // N.B.: Returning `ExitResult` from `main` directly would exit with 1 for any error; so we
+    // report the error ourselves to preserve its exit code.
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(exit) => {
+            let message = exit.to_string();
+            if !message.is_empty() {
+                diagnostics::report(diagnostics::Level::Error, &message);
+            }
+            std::process::Termination::report(exit)
+        }
+    }
+}
@@ -0,0 +1,404 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Runs a command that has a timeout as a child of the scie-jump so that it can be killed should
+//! it run too long, instead of exec'ing it.
+
+use std::ffi::OsString;
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use proc_exit::{Code, ExitResult};
+
+use crate::exit;
+
+// N.B.: This bounds how long after the command exits we notice; so it is kept short.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+enum Wait {
+    Running,
+    /// The command was stopped by job control and has since been resumed; after the given time.
+    #[cfg_attr(windows, allow(dead_code))]
+    Resumed(Duration),
+    Exited(ExitStatus),
+}
+
+#[cfg(unix)]
+mod process_group {
+    use std::io::IsTerminal;
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::process::{Command, ExitStatus};
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::time::Instant;
+
+    use nix::libc::{self, c_int};
+    use nix::sys::signal::{
+        killpg, raise, sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal,
+    };
+    use nix::unistd::{getpgrp, setpgid, tcgetpgrp, tcsetpgrp, Pid};
+
+    use super::Wait;
+
+    const TERMINAL: c_int = libc::STDIN_FILENO;
+
+    static GROUP: AtomicI32 = AtomicI32::new(0);
+
+    extern "C" fn forward(signal: c_int) {
+        let group = GROUP.load(Ordering::SeqCst);
+        if group > 0 {
+            if let Ok(signal) = Signal::try_from(signal) {
+                let _ = killpg(Pid::from_raw(group), signal);
+            }
+        }
+    }
+
+    fn set_action(signal: Signal, handler: SigHandler, flags: SaFlags) -> nix::Result<()> {
+        // SAFETY: The only handler installed just reads an atomic and calls kill(2), both of which
+        // are async-signal-safe.
+        unsafe { sigaction(signal, &SigAction::new(handler, flags, SigSet::empty())) }.map(|_| ())
+    }
+
+    /// Makes the child's process group the terminal's foreground group; run in the child between
+    /// fork and exec, so it only makes async-signal-safe system calls.
+    fn take_terminal() -> std::io::Result<()> {
+        // N.B.: This is also done via `Command::process_group`, but we need the group to exist
+        // here regardless of the order the two are applied in.
+        setpgid(Pid::from_raw(0), Pid::from_raw(0))?;
+        // N.B.: A background group is sent SIGTTOU for setting the foreground group unless it
+        // ignores that signal, which the child inherited from the scie-jump; so it is safe to
+        // restore the default afterwards for the command's own use of the terminal.
+        tcsetpgrp(TERMINAL, getpgrp())?;
+        set_action(Signal::SIGTTOU, SigHandler::SigDfl, SaFlags::empty())?;
+        Ok(())
+    }
+
+    /// A null-terminated array of C strings as `exec` takes, built before forking since the child
+    /// may not allocate.
+    #[cfg(target_os = "linux")]
+    struct CStrings {
+        _strings: Vec<std::ffi::CString>,
+        pointers: Vec<*const libc::c_char>,
+    }
+
+    #[cfg(target_os = "linux")]
+    impl CStrings {
+        fn new(strings: Vec<std::ffi::CString>) -> Self {
+            let mut pointers = strings.iter().map(|s| s.as_ptr()).collect::<Vec<_>>();
+            pointers.push(std::ptr::null());
+            Self {
+                _strings: strings,
+                pointers,
+            }
+        }
+
+        fn as_ptr(&self) -> *const *const libc::c_char {
+            self.pointers.as_ptr()
+        }
+    }
+
+    // SAFETY: The pointers only point into the strings they are kept alongside, which are never
+    // mutated.
+    #[cfg(target_os = "linux")]
+    unsafe impl Send for CStrings {}
+    #[cfg(target_os = "linux")]
+    unsafe impl Sync for CStrings {}
+
+    /// Has the child execute the file open as `fd` in place of the command's program.
+    #[cfg(target_os = "linux")]
+    fn exec_fd(command: &mut Command, fd: i32) -> Result<(), String> {
+        let argv = CStrings::new(crate::c_args(
+            command.get_program().to_owned(),
+            command.get_args().map(ToOwned::to_owned).collect(),
+        )?);
+        let envp = CStrings::new(crate::c_env()?);
+        // SAFETY: fexecve(3) is async-signal-safe and the strings it takes were allocated up
+        // front.
+        unsafe {
+            command.pre_exec(move || {
+                libc::fexecve(fd, argv.as_ptr(), envp.as_ptr());
+                Err(std::io::Error::last_os_error())
+            })
+        };
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn exec_fd(_command: &mut Command, fd: i32) -> Result<(), String> {
+        Err(format!(
+            "Cannot execute fd {fd}: executing in place is only supported on Linux."
+        ))
+    }
+
+    /// A command running in a process group of its own so that it can be killed along with any
+    /// processes it spawns.
+    pub(super) struct Group {
+        pid: Pid,
+        /// Whether the command's group was made the terminal's foreground group.
+        foreground: bool,
+    }
+
+    impl Group {
+        /// Spawns the command in a new process group.
+        ///
+        /// Only the terminal's foreground group may read from it; so when the scie-jump holds the
+        /// terminal it hands it to the command's group, just like a shell does for a job it runs.
+        /// The hangup, interrupt, quit and terminate signals the scie-jump receives are forwarded
+        /// to the group.
+        pub(super) fn spawn(command: &mut Command, exe_fd: Option<i32>) -> Result<Self, String> {
+            let foreground = std::io::stdin().is_terminal()
+                && tcgetpgrp(TERMINAL).is_ok_and(|group| group == getpgrp());
+            command.process_group(0);
+            if foreground {
+                // N.B.: The scie-jump is in the background once the command has the terminal; so
+                // it ignores SIGTTOU to be able to take the terminal back and report errors.
+                set_action(Signal::SIGTTOU, SigHandler::SigIgn, SaFlags::empty())
+                    .map_err(|e| format!("Failed to hand the terminal to the command: {e}"))?;
+                // SAFETY: See take_terminal.
+                unsafe { command.pre_exec(take_terminal) };
+            }
+            if let Some(fd) = exe_fd {
+                exec_fd(command, fd)?;
+            }
+            let child = command.spawn().map_err(|e| {
+                format!(
+                    "Failed to spawn {program:?} {args:?}: {e}",
+                    program = command.get_program(),
+                    args = command.get_args().collect::<Vec<_>>()
+                )
+            })?;
+            let mut group = Self {
+                pid: Pid::from_raw(child.id() as i32),
+                foreground,
+            };
+            GROUP.store(group.pid.as_raw(), Ordering::SeqCst);
+            for signal in [
+                Signal::SIGHUP,
+                Signal::SIGINT,
+                Signal::SIGQUIT,
+                Signal::SIGTERM,
+            ] {
+                if let Err(e) =
+                    set_action(signal, SigHandler::Handler(forward), SaFlags::SA_RESTART)
+                {
+                    group.kill();
+                    return Err(format!("Failed to forward {signal} to the command: {e}"));
+                }
+            }
+            Ok(group)
+        }
+
+        fn wait(&self, options: c_int) -> std::io::Result<Option<c_int>> {
+            let mut status = 0;
+            loop {
+                // SAFETY: The status is a valid place for waitpid(2) to write to.
+                match unsafe { libc::waitpid(self.pid.as_raw(), &mut status, options) } {
+                    0 => return Ok(None),
+                    -1 => {
+                        let error = std::io::Error::last_os_error();
+                        if error.kind() != std::io::ErrorKind::Interrupted {
+                            return Err(error);
+                        }
+                    }
+                    _ => return Ok(Some(status)),
+                }
+            }
+        }
+
+        fn restore_terminal(&self) {
+            if self.foreground && tcgetpgrp(TERMINAL).is_ok_and(|group| group == self.pid) {
+                let _ = tcsetpgrp(TERMINAL, getpgrp());
+            }
+        }
+
+        pub(super) fn try_wait(&mut self) -> std::io::Result<Wait> {
+            // N.B.: The terminal only stops the command's group for job control when it is in the
+            // foreground.
+            let options = if self.foreground {
+                libc::WNOHANG | libc::WUNTRACED
+            } else {
+                libc::WNOHANG
+            };
+            let Some(status) = self.wait(options)? else {
+                return Ok(Wait::Running);
+            };
+            if !libc::WIFSTOPPED(status) {
+                self.restore_terminal();
+                return Ok(Wait::Exited(ExitStatus::from_raw(status)));
+            }
+
+            // The command was stopped; e.g.: by Ctrl-Z. The shell only sees that the scie-jump
+            // runs; so we stop too and resume the command once the shell resumes us.
+            let stopped = Instant::now();
+            self.restore_terminal();
+            let _ = raise(Signal::SIGTSTP);
+            // N.B.: A job resumed in the background must not take the terminal from the shell.
+            if tcgetpgrp(TERMINAL).is_ok_and(|group| group == getpgrp()) {
+                let _ = tcsetpgrp(TERMINAL, self.pid);
+            }
+            let _ = killpg(self.pid, Signal::SIGCONT);
+            Ok(Wait::Resumed(stopped.elapsed()))
+        }
+
+        pub(super) fn kill(&mut self) {
+            let _ = killpg(self.pid, Signal::SIGKILL);
+            let _ = self.wait(0);
+            self.restore_terminal();
+        }
+    }
+}
+
+#[cfg(windows)]
+mod process_group {
+    use std::os::windows::io::AsRawHandle;
+    use std::os::windows::process::CommandExt;
+    use std::process::{Child, Command};
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenThread, ResumeThread, CREATE_SUSPENDED, THREAD_SUSPEND_RESUME,
+    };
+
+    use super::Wait;
+
+    /// Resumes the main thread of a process that was created suspended.
+    fn resume(pid: u32) -> std::io::Result<()> {
+        // SAFETY: The snapshot and thread handles are checked before use and closed after.
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return Err(std::io::Error::last_os_error());
+            }
+            let mut entry: THREADENTRY32 = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+            let mut result = Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+            let mut more = Thread32First(snapshot, &mut entry) != 0;
+            while more {
+                if entry.th32OwnerProcessID == pid {
+                    let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                    result = if thread == 0 || ResumeThread(thread) == u32::MAX {
+                        Err(std::io::Error::last_os_error())
+                    } else {
+                        Ok(())
+                    };
+                    if thread != 0 {
+                        CloseHandle(thread);
+                    }
+                    break;
+                }
+                more = Thread32Next(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+            result
+        }
+    }
+
+    /// A command running in a job object of its own so that it can be killed along with any
+    /// processes it spawns.
+    pub(super) struct Group {
+        child: Child,
+        job: HANDLE,
+    }
+
+    impl Group {
+        /// Spawns the command in a new job object.
+        ///
+        /// Console control events like Ctrl-C are delivered to every process attached to the
+        /// console; so there is nothing to forward.
+        pub(super) fn spawn(command: &mut Command, _exe_fd: Option<i32>) -> Result<Self, String> {
+            // N.B.: The command starts suspended so that it cannot start any processes before it
+            // is in the job.
+            command.creation_flags(CREATE_SUSPENDED);
+            let mut child = command.spawn().map_err(|e| {
+                format!(
+                    "Failed to spawn {program:?} {args:?}: {e}",
+                    program = command.get_program(),
+                    args = command.get_args().collect::<Vec<_>>()
+                )
+            })?;
+            // SAFETY: The job handle is checked before use and closed when the group is dropped.
+            let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if job == 0 {
+                let _ = child.kill();
+                return Err(format!(
+                    "Failed to create a job object for the command: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+            let mut group = Self { child, job };
+            // SAFETY: Both handles are open.
+            if unsafe { AssignProcessToJobObject(job, group.child.as_raw_handle() as HANDLE) } == 0
+            {
+                let error = std::io::Error::last_os_error();
+                let _ = group.child.kill();
+                let _ = group.child.wait();
+                return Err(format!(
+                    "Failed to add the command to a job object: {error}"
+                ));
+            }
+            if let Err(e) = resume(group.child.id()) {
+                group.kill();
+                return Err(format!("Failed to resume the command: {e}"));
+            }
+            Ok(group)
+        }
+
+        pub(super) fn try_wait(&mut self) -> std::io::Result<Wait> {
+            Ok(self.child.try_wait()?.map_or(Wait::Running, Wait::Exited))
+        }
+
+        pub(super) fn kill(&mut self) {
+            // SAFETY: The job handle is open.
+            unsafe { TerminateJobObject(self.job, 1) };
+            let _ = self.child.wait();
+        }
+    }
+
+    impl Drop for Group {
+        fn drop(&mut self) {
+            // SAFETY: The job handle is open and is not used after this.
+            unsafe { CloseHandle(self.job) };
+        }
+    }
+}
+
+pub(crate) fn run(
+    exe: OsString,
+    args: Vec<OsString>,
+    exe_fd: Option<i32>,
+    timeout: Duration,
+) -> ExitResult {
+    let mut command = Command::new(&exe);
+    command.args(&args);
+    let mut group = process_group::Group::spawn(&mut command, exe_fd)
+        .map_err(|e| exit::SPAWN_FAILURE.with_message(e))?;
+
+    let mut deadline = Instant::now() + timeout;
+    loop {
+        let wait = group.try_wait().map_err(|e| {
+            exit::SPAWN_FAILURE.with_message(format!(
+                "Spawned {exe:?} {args:?} but failed to gather its exit status: {e}"
+            ))
+        })?;
+        match wait {
+            Wait::Running => {}
+            // N.B.: The command makes no progress while stopped; so that time does not count.
+            Wait::Resumed(stopped) => deadline += stopped,
+            Wait::Exited(status) => return Code::from(status).ok(),
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            group.kill();
+            return Err(exit::TIMED_OUT.with_message(format!(
+                "Killed {exe:?} {args:?} after it ran past its timeout of {timeout}s.",
+                timeout = timeout.as_secs()
+            )));
+        }
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
@@ -0,0 +1,501 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! End-to-end tests that boot-pack a tiny multi-call scie with the scie-jump under test and then
+//! boot its commands.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::json;
+
+use crate::common::{make_executable, stderr, stdout, Scie};
+
+const NAME: &str = "multicall";
+
+/// Copies the scie-jump under test, adding the trailer the release packaging adds, so that it acts
+/// as a boot-pack when run bare.
+fn scie_jump(dir: &Path) -> PathBuf {
+    let mut scie_jump = std::fs::read(env!("CARGO_BIN_EXE_scie-jump")).unwrap();
+    let size = u32::try_from(scie_jump.len() + 8).unwrap();
+    scie_jump.extend(size.to_le_bytes());
+    scie_jump.extend(jump::EOF_MAGIC.to_le_bytes());
+    let path = dir
+        .join("scie-jump")
+        .with_extension(std::env::consts::EXE_EXTENSION);
+    std::fs::write(&path, scie_jump).unwrap();
+    make_executable(&path);
+    path
+}
+
+/// The multi-call payload: a script dispatching on its first argument like busybox does.
+#[cfg(unix)]
+fn payload(dir: &Path) -> serde_json::Value {
+    std::fs::write(
+        dir.join("busybox.sh"),
+        r#"
+cmd="$1"
+shift
+case "${cmd}" in
+  echo) echo "$@" ;;
+  cat) cat "$@" ;;
+  printenv) printenv "$1" ;;
+  pwd) pwd ;;
+  exit) exit "$1" ;;
+  sleep) sleep "$1" ;;
+  spawn) (trap '' HUP; sleep 2; echo survived > "$1") & wait ;;
+  *) echo >&2 "Unknown command ${cmd}"; exit 1 ;;
+esac
+"#,
+    )
+    .unwrap();
+    let busybox = |args: &[&str]| {
+        json!({
+            "exe": "/bin/sh",
+            "args": ([&["{busybox.sh}"][..], args].concat()),
+        })
+    };
+    let mut printenv = busybox(&["printenv", "GREETING"]);
+    printenv["env"] = json!({"=GREETING": "hello from {scie.env.__MULTICALL_DNE__=multicall}"});
+    let mut pwd = busybox(&["pwd"]);
+    pwd["cwd"] = json!("{data}");
+    let mut sleep = busybox(&["sleep", "10"]);
+    sleep["timeout"] = json!(1);
+    let mut spawn = busybox(&["spawn", "survived.txt"]);
+    spawn["timeout"] = json!(1);
+    let mut script = busybox(&["echo", "interpreting", "{scie.script}"]);
+    script["shebang"] = json!(true);
+    json!({
+        "files": [{"name": "busybox.sh"}, {"name": "data"}],
+        "commands": {
+            "echo": busybox(&["echo"]),
+            "cat": busybox(&["cat", "{data}/a.txt"]),
+            "printenv": printenv,
+            "pwd": pwd,
+            "exit": busybox(&["exit", "42"]),
+            "sleep": sleep,
+            "spawn": spawn,
+            "script": script,
+        }
+    })
+}
+
+/// The multi-call payload: `cmd.exe` builtins standing in for a busybox.
+#[cfg(windows)]
+fn payload(_dir: &Path) -> serde_json::Value {
+    let cmd = |args: &[&str]| {
+        json!({
+            "exe": "cmd.exe",
+            "args": ([&["/c"][..], args].concat()),
+        })
+    };
+    let mut printenv = cmd(&["echo", "%GREETING%"]);
+    printenv["env"] = json!({"=GREETING": "hello from {scie.env.__MULTICALL_DNE__=multicall}"});
+    let mut pwd = cmd(&["cd"]);
+    pwd["cwd"] = json!("{data}");
+    json!({
+        "files": [{"name": "data"}],
+        "commands": {
+            "echo": cmd(&["echo"]),
+            "cat": cmd(&["type", "{data}\\a.txt"]),
+            "printenv": printenv,
+            "pwd": pwd,
+            "exit": cmd(&["exit", "42"]),
+        }
+    })
+}
+
+fn pack() -> Scie {
+    pack_with(json!({}))
+}
+
+/// Packs the multi-call scie with the given `fields` added to its lift manifest.
+fn pack_with(fields: serde_json::Value) -> Scie {
+    let dir = tempfile::tempdir().unwrap();
+    let scie_jump = scie_jump(dir.path());
+
+    std::fs::create_dir(dir.path().join("data")).unwrap();
+    std::fs::write(dir.path().join("data").join("a.txt"), "a file\n").unwrap();
+    let payload = payload(dir.path());
+    let mut lift = json!({
+        "scie": {
+            "lift": {
+                "name": NAME,
+                "files": payload["files"],
+                "boot": {"commands": payload["commands"]}
+            }
+        }
+    });
+    for (name, value) in fields.as_object().unwrap() {
+        lift["scie"]["lift"][name] = value.clone();
+    }
+    std::fs::write(
+        dir.path().join("lift.json"),
+        serde_json::to_vec_pretty(&lift).unwrap(),
+    )
+    .unwrap();
+
+    let output = Command::new(scie_jump)
+        .current_dir(dir.path())
+        .env_remove("SCIE")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let path = dir
+        .path()
+        .join(NAME)
+        .with_extension(std::env::consts::EXE_EXTENSION);
+    assert!(path.is_file(), "Expected boot-pack to produce {path:?}.");
+    Scie { dir, path }
+}
+
+#[test]
+fn boot_commands() {
+    let scie = pack();
+
+    // With no default command, the command can be selected by SCIE_BOOT or by the 1st argument.
+    assert_eq!(
+        "hello world",
+        stdout(&scie.run(|command| command.env("SCIE_BOOT", "echo").args(["hello", "world"])))
+    );
+    assert_eq!(
+        "hello world",
+        stdout(&scie.run(|command| command.args(["echo", "hello", "world"])))
+    );
+    assert_eq!("a file", stdout(&scie.run(|command| command.arg("cat"))));
+    assert_eq!(
+        format!("hello from {NAME}"),
+        stdout(&scie.run(|command| command.arg("printenv")))
+    );
+
+    let data = PathBuf::from(stdout(&scie.run(|command| command.arg("pwd"))));
+    assert_eq!(
+        scie.base()
+            .join(scie.file_hash("data"))
+            .join("data")
+            .canonicalize()
+            .unwrap(),
+        data.canonicalize().unwrap()
+    );
+
+    let list = stdout(&scie.run(|command| command.env("SCIE", "list")));
+    let mut commands = list.lines().collect::<Vec<_>>();
+    commands.sort();
+    let mut expected = payload(scie.dir.path())["commands"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(expected, commands);
+}
+
+#[cfg(unix)]
+#[test]
+fn shebang() {
+    let scie = pack();
+    for name in ["tool.sh", "echo"] {
+        std::fs::write(
+            scie.dir.path().join(name),
+            format!("#!{path}\n", path = scie.path.display()),
+        )
+        .unwrap();
+    }
+
+    let script = scie.dir.path().join("tool.sh").canonicalize().unwrap();
+    assert_eq!(
+        format!("interpreting {script}", script = script.display()),
+        stdout(&scie.run(|command| command.arg("./tool.sh")))
+    );
+
+    // A 1st argument naming a command selects it even when it also names a script.
+    assert_eq!(
+        "hello",
+        stdout(&scie.run(|command| command.args(["echo", "hello"])))
+    );
+}
+
+#[test]
+fn extraction_layout() {
+    let scie = pack();
+    assert!(!scie.base().exists());
+
+    // Files are only extracted when a command needs them; each under a directory named for its
+    // hash.
+    stdout(&scie.run(|command| command.arg("echo")));
+    let data = scie.base().join(scie.file_hash("data"));
+    assert!(!data.exists());
+
+    stdout(&scie.run(|command| command.arg("cat")));
+    assert_eq!(
+        "a file\n",
+        std::fs::read_to_string(data.join("data").join("a.txt")).unwrap()
+    );
+    #[cfg(unix)]
+    assert_eq!(
+        std::fs::read(scie.dir.path().join("busybox.sh")).unwrap(),
+        std::fs::read(
+            scie.base()
+                .join(scie.file_hash("busybox.sh"))
+                .join("busybox.sh")
+        )
+        .unwrap()
+    );
+
+    // A second boot re-uses the extracted files.
+    std::fs::write(data.join("data").join("a.txt"), "cached\n").unwrap();
+    assert_eq!("cached", stdout(&scie.run(|command| command.arg("cat"))));
+}
+
+#[test]
+fn exit_codes() {
+    let scie = pack();
+
+    // The command's exit code is passed through.
+    let output = scie.run(|command| command.arg("exit"));
+    assert_eq!(Some(42), output.status.code(), "{output:?}");
+
+    // Failing to select a command is a usage error.
+    let output = scie.run(|command| command.env("SCIE_BOOT", "dne"));
+    assert_eq!(Some(64), output.status.code(), "{output:?}");
+    assert!(
+        stderr(&output).contains("Please select from the following boot commands:"),
+        "{output:?}"
+    );
+
+    // A scie whose contents do not match their recorded fingerprints is an integrity failure. The
+    // first file starts right after the scie-jump and is needed by the cat command on all
+    // platforms.
+    let mut data = std::fs::read(&scie.path).unwrap();
+    let first_file = scie.inspect()["scie"]["jump"]["size"].as_u64().unwrap() as usize;
+    data[first_file + 16] ^= 0xff;
+    let corrupt = scie
+        .dir
+        .path()
+        .join("corrupt")
+        .with_extension(std::env::consts::EXE_EXTENSION);
+    std::fs::write(&corrupt, data).unwrap();
+    make_executable(&corrupt);
+    let output = scie.run_copy(&corrupt, |command| command.arg("cat"));
+    assert_eq!(Some(65), output.status.code(), "{output:?}");
+    assert!(stderr(&output).contains("unexpected hash"), "{output:?}");
+}
+
+#[cfg(unix)]
+#[test]
+fn timeout() {
+    let scie = pack();
+    let output = scie.run(|command| command.arg("sleep"));
+    assert_eq!(Some(124), output.status.code(), "{output:?}");
+
+    // The processes the command started are killed along with it.
+    let output = scie.run(|command| command.arg("spawn"));
+    assert_eq!(Some(124), output.status.code(), "{output:?}");
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    assert!(!scie.dir.path().join("survived.txt").exists());
+}
+
+/// Like `timeout` but with a terminal for the scie-jump to hand to the command; via util-linux's
+/// `script`.
+#[cfg(target_os = "linux")]
+#[test]
+fn timeout_in_terminal() {
+    let scie = pack();
+    let output = Command::new("script")
+        .args(["--quiet", "--return", "--command"])
+        .arg(format!("{scie} spawn", scie = scie.path.display()))
+        .arg("/dev/null")
+        .current_dir(scie.dir.path())
+        .env("SCIE_BASE", scie.base())
+        .env_remove("SCIE")
+        .env_remove("SCIE_BOOT")
+        .output()
+        .unwrap();
+    assert_eq!(Some(124), output.status.code(), "{output:?}");
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    assert!(!scie.dir.path().join("survived.txt").exists());
+}
+
+#[test]
+fn diagnostics() {
+    let scie = pack();
+
+    // Errors are always reported, with no color when stderr is not a terminal.
+    for quiet in ["", "1"] {
+        let output = scie.run(|command| command.env("SCIE_BOOT", "dne").env("SCIE_QUIET", quiet));
+        assert_eq!(Some(64), output.status.code(), "{output:?}");
+        assert!(stderr(&output).starts_with("Error: "), "{output:?}");
+        assert!(!stderr(&output).contains('\x1b'), "{output:?}");
+    }
+
+    // Warnings are reported unless SCIE_QUIET is set.
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "a file\n").unwrap();
+    for (name, quiet) in [("warned", ""), ("quiet", "1")] {
+        let lift = json!({
+            "scie": {
+                "lift": {
+                    "name": name,
+                    "nmae": "typo",
+                    "files": [{"name": "a.txt"}],
+                    "boot": {"commands": {"": {"exe": "{a.txt}"}}}
+                }
+            }
+        });
+        let manifest = dir.path().join(name).with_extension("json");
+        std::fs::write(&manifest, serde_json::to_vec(&lift).unwrap()).unwrap();
+        let output = Command::new(scie_jump(dir.path()))
+            .arg(&manifest)
+            .current_dir(dir.path())
+            .env_remove("SCIE")
+            .env_remove("RUST_LOG")
+            .env("SCIE_QUIET", quiet)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        if quiet.is_empty() {
+            assert_eq!(
+                "Warning: The lift manifest contains unrecognized fields:\n+ scie.lift.nmae\n",
+                stderr(&output)
+            );
+        } else {
+            assert_eq!("", stderr(&output));
+        }
+    }
+}
+
+#[test]
+fn audit() {
+    let scie = pack();
+    let audit_log = scie.base().join("audit.jsonl");
+
+    stdout(&scie.run(|command| command.arg("cat")));
+    assert!(!audit_log.exists());
+
+    let boot = |name: &str| stdout(&scie.run(|command| command.arg(name).env("SCIE_AUDIT", "1")));
+    boot("cat");
+    boot("cat");
+    boot("echo");
+    let records = std::fs::read_to_string(&audit_log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(3, records.len(), "{records:?}");
+
+    // The hash identifies the scie's contents via its lift manifest.
+    let hash = records[0]["hash"].as_str().unwrap();
+    assert_eq!(64, hash.len(), "{hash}");
+    for (record, command) in records.iter().zip(["cat", "cat", "echo"]) {
+        assert_eq!(command, record["command"], "{record}");
+        assert_eq!(NAME, record["lift"], "{record}");
+        assert_eq!(hash, record["hash"], "{record}");
+        assert_eq!(
+            scie.path.canonicalize().unwrap(),
+            PathBuf::from(record["scie"].as_str().unwrap())
+                .canonicalize()
+                .unwrap()
+        );
+        assert!(record["timestamp_ms"].as_u64().unwrap() > 0, "{record}");
+        assert!(record["duration_ms"].is_u64(), "{record}");
+    }
+
+    // The unaudited boot already extracted the files the audited boots needed; so only a boot that
+    // finds a file missing from the cache records extracting it.
+    for record in &records {
+        assert_eq!(Some(&vec![]), record["extractions"].as_array(), "{record}");
+    }
+    std::fs::remove_dir_all(scie.base().join(scie.file_hash("data"))).unwrap();
+    boot("cat");
+    let last = std::fs::read_to_string(&audit_log)
+        .unwrap()
+        .lines()
+        .last()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .unwrap();
+    let extractions = last["extractions"].as_array().unwrap();
+    assert_eq!(1, extractions.len(), "{last}");
+    assert_eq!("data", extractions[0]["name"]);
+    assert_eq!(scie.file_hash("data"), extractions[0]["hash"]);
+}
+
+#[test]
+fn reverify() {
+    let scie = pack();
+    let data = scie.base().join(scie.file_hash("data"));
+    let listing = data.join("data.sha256");
+    let cat = |reverify: Option<&str>| {
+        stdout(&scie.run(|command| match reverify {
+            Some(interval) => command.arg("cat").env("SCIE_REVERIFY", interval),
+            None => command.arg("cat").env_remove("SCIE_REVERIFY"),
+        }))
+    };
+
+    // Directories extracted with re-verification off are not listed; so the first re-verification
+    // of them re-extracts them to list their files.
+    assert_eq!("a file", cat(None));
+    assert!(!listing.exists());
+    std::fs::write(data.join("data").join("a.txt"), "rot\n").unwrap();
+    assert_eq!("rot", cat(None));
+    assert_eq!("a file", cat(Some("0")));
+    assert!(listing.is_file());
+
+    // Mutations go unnoticed until the extracted files are due re-verification.
+    std::fs::write(data.join("data").join("a.txt"), "rot\n").unwrap();
+    assert_eq!("rot", cat(Some("1w")));
+    assert_eq!("a file", cat(Some("0")));
+
+    // As do deletions.
+    std::fs::remove_file(data.join("data").join("a.txt")).unwrap();
+    assert_eq!("a file", cat(Some("0s")));
+}
+
+#[test]
+fn fail_on_mutation() {
+    let scie = pack_with(json!({"integrity": {"on_mutation": "fail"}}));
+    let data = scie.base().join(scie.file_hash("data")).join("data");
+    let cat =
+        |reverify: &str| scie.run(|command| command.arg("cat").env("SCIE_REVERIFY", reverify));
+
+    assert_eq!("a file", stdout(&cat("0")));
+    std::fs::write(data.join("a.txt"), "rot\n").unwrap();
+    assert_eq!("rot", stdout(&cat("1w")));
+
+    let output = cat("0");
+    assert_eq!(Some(65), output.status.code(), "{output:?}");
+    let error = stderr(&output);
+    assert!(
+        error.contains(&format!(
+            "+ data at {data}\n  - a.txt\n",
+            data = data.display()
+        )),
+        "{error}"
+    );
+
+    std::fs::remove_dir_all(&data).unwrap();
+    assert_eq!("a file", stdout(&cat("0")));
+
+    // Blobs are checked for mutations whenever their size or modification time changes.
+    #[cfg(unix)]
+    {
+        let busybox = scie
+            .base()
+            .join(scie.file_hash("busybox.sh"))
+            .join("busybox.sh");
+        std::fs::write(&busybox, "echo tampered\n").unwrap();
+        let output = scie.run(|command| command.arg("echo").env_remove("SCIE_REVERIFY"));
+        assert_eq!(Some(65), output.status.code(), "{output:?}");
+        let error = stderr(&output);
+        assert!(
+            error.contains(&format!(
+                "+ busybox.sh at {busybox}\n",
+                busybox = busybox.display()
+            )),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Helpers shared by the end-to-end tests that boot scies with the scie-jump under test.
+
+// N.B.: Each test crate includes this module but uses only some of it.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+/// A scie under test along with the directory it lives in, which also holds its `nce` cache.
+pub struct Scie {
+    pub dir: TempDir,
+    pub path: PathBuf,
+}
+
+impl Scie {
+    pub fn nce(&self) -> PathBuf {
+        self.dir.path().join("nce")
+    }
+
+    /// The `nce` cache the scie itself extracts its files to.
+    pub fn base(&self) -> PathBuf {
+        self.nce().join(self.path.file_name().unwrap())
+    }
+
+    pub fn run(&self, configure: impl FnOnce(&mut Command) -> &mut Command) -> Output {
+        self.run_copy(&self.path, configure)
+    }
+
+    /// Runs a copy of the scie, which gets an `nce` cache of its own.
+    pub fn run_copy(
+        &self,
+        path: &Path,
+        configure: impl FnOnce(&mut Command) -> &mut Command,
+    ) -> Output {
+        let mut command = Command::new(path);
+        command
+            .current_dir(self.dir.path())
+            .env("SCIE_BASE", self.nce().join(path.file_name().unwrap()))
+            .env_remove("SCIE")
+            .env_remove("SCIE_BOOT");
+        configure(&mut command).output().unwrap()
+    }
+
+    pub fn inspect(&self) -> serde_json::Value {
+        let output = self.run(|command| command.env("SCIE", "inspect"));
+        assert!(output.status.success(), "{output:?}");
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    pub fn file_hash(&self, name: &str) -> String {
+        self.inspect()["scie"]["lift"]["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|file| file["name"] == name)
+            .and_then(|file| file["hash"].as_str())
+            .unwrap()
+            .to_string()
+    }
+}
+
+/// Returns the trimmed stdout of a command that must have succeeded.
+pub fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .trim_end()
+        .to_string()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+pub fn make_executable(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
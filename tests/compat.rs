@@ -0,0 +1,84 @@
+// Copyright 2023 Science project contributors.
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Compatibility tests that boot scies packed by released scie-jumps with the scie-jump under test.
+//!
+//! The fixtures under `tests/fixtures/compat` hold the bytes following the scie-jump of each
+//! scie; see `generate.sh` there for how they are made.
+
+mod common;
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::common::{make_executable, stdout, Scie};
+
+/// The size of the stand-in scie-jump the fixtures were packed with.
+const JUMP_SIZE: usize = 256 * 1024 * 1024;
+
+/// Loads the fixture packed by the given release as a scie whose scie-jump is the scie-jump under
+/// test padded out to `JUMP_SIZE`.
+fn fixture(release: &str) -> Scie {
+    let tail = std::fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("compat")
+            .join(format!("{release}.tail")),
+    )
+    .unwrap();
+    let scie_jump = std::fs::read(env!("CARGO_BIN_EXE_scie-jump")).unwrap();
+    assert!(
+        scie_jump.len() <= JUMP_SIZE,
+        "The scie-jump under test is {size} bytes but the compat fixtures only have room for \
+        {JUMP_SIZE} bytes; they need to be re-generated with a larger stand-in scie-jump.",
+        size = scie_jump.len()
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir
+        .path()
+        .join("compat")
+        .with_extension(std::env::consts::EXE_EXTENSION);
+    let mut scie = std::fs::File::create(&path).unwrap();
+    scie.write_all(&scie_jump).unwrap();
+    scie.set_len(JUMP_SIZE as u64).unwrap();
+    std::io::Seek::seek(&mut scie, std::io::SeekFrom::End(0)).unwrap();
+    scie.write_all(&tail).unwrap();
+    drop(scie);
+    make_executable(&path);
+    Scie { dir, path }
+}
+
+fn check(release: &str, version: &str) {
+    let scie = fixture(release);
+
+    let footer = jump::is_scie(&scie.path).unwrap().unwrap();
+    assert_eq!(JUMP_SIZE, footer.jump.size);
+    assert_eq!(version, footer.jump.version);
+    assert!(footer.lift_manifest_offset > JUMP_SIZE);
+
+    assert_eq!("compat", scie.inspect()["scie"]["lift"]["name"]);
+    assert_eq!(
+        "echo\ncat\nprintenv",
+        stdout(&scie.run(|command| command.env("SCIE", "list")))
+    );
+
+    if cfg!(unix) {
+        assert_eq!(
+            "hello world",
+            stdout(&scie.run(|command| command.args(["echo", "hello", "world"])))
+        );
+        assert_eq!("a file", stdout(&scie.run(|command| command.arg("cat"))));
+        assert_eq!(
+            "hello from compat",
+            stdout(&scie.run(|command| command.arg("printenv")))
+        );
+    }
+}
+
+/// The lift manifest trailer format of the scie-jump 0.13.1 release.
+#[test]
+fn release_0_13_1() {
+    check("v0.13.1", "0.13.1");
+}
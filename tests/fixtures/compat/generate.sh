@@ -0,0 +1,55 @@
+#!/usr/bin/env bash
+# Copyright 2023 Science project contributors.
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+# Regenerates a compatibility fixture by boot-packing src/lift.json with the scie-jump of the given
+# release tag.
+#
+# Only the bytes following the scie-jump are kept. The scie is packed with a stand-in scie-jump of
+# JUMP_SIZE zero bytes; so tests/compat.rs can boot the fixture by padding the scie-jump under test
+# to that size.
+
+set -euo pipefail
+
+JUMP_SIZE=$((256 * 1024 * 1024))
+
+if (( $# < 1 )) || [[ "$1" != v* ]]; then
+  echo >&2 "Usage: $0 <release tag> [boot-pack option]*"
+  exit 1
+fi
+tag="$1"
+shift
+# N.B.: Fixtures packed with options are named after them; e.g.: v0.14.0-lift-in-zip.tail.
+name="${tag}"
+for option in "$@"; do
+  name="${name}-${option#--}"
+done
+
+here="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+root="$(git -C "${here}" rev-parse --show-toplevel)"
+work="$(mktemp -d)"
+trap 'git -C "${root}" worktree remove --force "${work}/repo"; rm -rf "${work}"' EXIT
+
+git -C "${root}" worktree add --detach "${work}/repo" "refs/tags/${tag}"
+if [[ -f "${root}/Cargo.lock" ]]; then
+  cp "${root}/Cargo.lock" "${work}/repo/"
+fi
+cargo build --manifest-path "${work}/repo/Cargo.toml" --target-dir "${work}/target"
+
+# A scie-jump only acts as a boot-pack when it ends with its size and magic number.
+python3 - "${work}/target/debug/scie-jump" "${work}/boot-pack" <<'EOF'
+import struct
+import sys
+
+with open(sys.argv[1], "rb") as fp:
+    scie_jump = fp.read()
+with open(sys.argv[2], "wb") as fp:
+    fp.write(scie_jump)
+    fp.write(struct.pack("<II", len(scie_jump) + 8, 0x534A7219))
+EOF
+chmod +x "${work}/boot-pack"
+truncate -s "${JUMP_SIZE}" "${work}/scie-jump"
+
+cp -R "${here}/src" "${work}/lift"
+(cd "${work}/lift" && "${work}/boot-pack" --scie-jump "${work}/scie-jump" "$@" lift.json)
+tail -c +$((JUMP_SIZE + 1)) "${work}/lift/compat" > "${here}/${name}.tail"
@@ -0,0 +1,8 @@
+cmd="$1"
+shift
+case "${cmd}" in
+  echo) echo "$@" ;;
+  cat) cat "$@" ;;
+  printenv) printenv "$1" ;;
+  *) echo >&2 "Unknown command ${cmd}"; exit 1 ;;
+esac